@@ -0,0 +1,95 @@
+//! Generates the audit data from a build script, for use with [`embed!`](crate::embed).
+
+use std::{env, error::Error, path::PathBuf};
+
+use auditable_serde::{MetadataOptions, VersionInfo};
+use cargo_metadata::{CargoOpt, MetadataCommand};
+
+/// Name of the file in `OUT_DIR` that [`embed!`](crate::embed) includes
+pub const AUDIT_DATA_FILE: &str = "auditable_data.bin";
+
+/// Writes the compressed audit data for the package being built into `OUT_DIR`.
+///
+/// Call it from the build script of the binary crate:
+///
+/// ```rust,ignore
+/// fn main() {
+///     auditable_embed::build::write_audit_data().unwrap();
+/// }
+/// ```
+///
+/// This runs `cargo metadata` for the features and target the package is being built with,
+/// just like `cargo auditable` does.
+pub fn write_audit_data() -> Result<(), Box<dyn Error>> {
+    let manifest_dir =
+        PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").ok_or("CARGO_MANIFEST_DIR is not set")?);
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").ok_or("OUT_DIR is not set")?);
+    let target = env::var("TARGET")?;
+    let manifest_path = manifest_dir.join("Cargo.toml");
+
+    let mut metadata_command = metadata_command(&manifest_dir);
+    let mut features = enabled_features(&manifest_path)?;
+    if let Some(index) = features.iter().position(|f| f == "default") {
+        features.remove(index);
+    } else {
+        metadata_command.features(CargoOpt::NoDefaultFeatures);
+    }
+    metadata_command.features(CargoOpt::SomeFeatures(features));
+    metadata_command.other_options(vec!["--filter-platform".to_owned(), target]);
+    let metadata = metadata_command.exec()?;
+
+    let info = VersionInfo::from_metadata(&metadata, &MetadataOptions::default())?;
+    std::fs::write(out_dir.join(AUDIT_DATA_FILE), info.to_compressed_payload())?;
+
+    println!("cargo:rerun-if-changed={}", manifest_path.display());
+    println!(
+        "cargo:rerun-if-changed={}",
+        metadata.workspace_root.join("Cargo.lock")
+    );
+    Ok(())
+}
+
+fn metadata_command(manifest_dir: &std::path::Path) -> MetadataCommand {
+    let mut command = MetadataCommand::new();
+    command.current_dir(manifest_dir);
+    // Use the same Cargo that is running the build script
+    if let Some(path) = env::var_os("CARGO") {
+        command.cargo_path(path);
+    }
+    command
+}
+
+/// Build scripts only see the enabled features as `CARGO_FEATURE_*` variables,
+/// which are uppercased and have `-` replaced with `_`,
+/// so match them against the features the package declares
+fn enabled_features(manifest_path: &std::path::Path) -> Result<Vec<String>, Box<dyn Error>> {
+    let metadata = metadata_command(manifest_path.parent().unwrap())
+        .no_deps()
+        .exec()?;
+    let package = metadata
+        .packages
+        .iter()
+        .find(|p| p.manifest_path.as_std_path() == manifest_path)
+        .ok_or("the package being built was not found in 'cargo metadata' output")?;
+    Ok(package
+        .features
+        .keys()
+        .filter(|feature| env::var_os(feature_env_var(feature)).is_some())
+        .cloned()
+        .collect())
+}
+
+fn feature_env_var(feature: &str) -> String {
+    format!("CARGO_FEATURE_{}", feature.to_uppercase().replace('-', "_"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feature_env_vars() {
+        assert_eq!(feature_env_var("default"), "CARGO_FEATURE_DEFAULT");
+        assert_eq!(feature_env_var("serde-json"), "CARGO_FEATURE_SERDE_JSON");
+    }
+}
@@ -0,0 +1,117 @@
+#![cfg_attr(not(any(feature = "build", feature = "object")), no_std)]
+#![forbid(unsafe_code)]
+
+//! Embeds the dependency tree information produced by
+//! [`cargo auditable`](https://github.com/rust-secure-code/cargo-auditable)
+//! through a static in your own code.
+//!
+//! `cargo auditable` normally passes an object file with the audit data to the linker,
+//! along with a flag that keeps it from being discarded. That does not work when the linker
+//! cannot be passed extra arguments, or when a custom linker script discards the section.
+//! In those cases, invoke [`embed_audit_data!`] once in the binary crate
+//! and build with `CARGO_AUDITABLE_EMBED_MACRO=1` set:
+//!
+//! ```rust,ignore
+//! #![no_std]
+//! #![no_main]
+//!
+//! auditable_embed::embed_audit_data!();
+//! ```
+//!
+//! ```bash
+//! CARGO_AUDITABLE_EMBED_MACRO=1 cargo auditable build --release
+//! ```
+//!
+//! `cargo auditable` then no longer links the audit data itself, and instead sets the
+//! `cargo_auditable_payload` cfg and points the `CARGO_AUDITABLE_PAYLOAD` environment variable
+//! at the compressed audit data while compiling the binary crate. Without it, e.g. in a plain
+//! `cargo build`, the macro expands to nothing.
+//!
+//! On recent toolchains declare the cfg to silence the `unexpected_cfgs` lint:
+//!
+//! ```toml
+//! [lints.rust]
+//! unexpected_cfgs = { level = "warn", check-cfg = ["cfg(cargo_auditable_payload)"] }
+//! ```
+//!
+//! ## Without `cargo auditable`
+//!
+//! [`embed!`] does not need `cargo auditable` at all. Instead, the audit data is generated
+//! by the build script of the binary crate, with this crate as a build-dependency
+//! with the `build` feature enabled:
+//!
+//! ```toml
+//! [dependencies]
+//! auditable-embed = "0.1"
+//!
+//! [build-dependencies]
+//! auditable-embed = { version = "0.1", features = ["build"] }
+//! ```
+//!
+//! ```rust,ignore
+//! // build.rs
+//! fn main() {
+//!     auditable_embed::build::write_audit_data().unwrap();
+//! }
+//! ```
+//!
+//! ```rust,ignore
+//! // main.rs
+//! auditable_embed::embed!();
+//! ```
+//!
+//! Do not combine it with `cargo auditable`, or the binary will contain the audit data twice.
+//!
+//! ## Without a Rust build
+//!
+//! With the `object` feature, [`object_file::create_object_file`] returns a complete relocatable
+//! object file holding the audit data for a given target triple, for build systems
+//! that invoke the linker themselves, such as Bazel or Buck. Link it into the binary
+//! like any other object file.
+//!
+//! ## Placement
+//!
+//! The audit data is placed into the same section as when it is injected at link time,
+//! so all the usual tools can read it. If your linker script lists sections explicitly,
+//! make sure it keeps `.dep-v0`.
+
+#[cfg(feature = "build")]
+pub mod build;
+#[cfg(feature = "object")]
+pub mod object_file;
+
+/// Defines the `AUDITABLE_VERSION_INFO` static holding the audit data for the binary.
+///
+/// Invoke it exactly once, at the top level of the binary crate.
+/// See the [crate documentation](crate) for how to enable it.
+#[macro_export]
+macro_rules! embed_audit_data {
+    () => {
+        #[cfg(cargo_auditable_payload)]
+        $crate::__audit_data_static!(env!("CARGO_AUDITABLE_PAYLOAD"));
+    };
+}
+
+/// Defines the `AUDITABLE_VERSION_INFO` static holding the audit data written by
+/// [`build::write_audit_data`](crate::build) in the build script.
+///
+/// Invoke it exactly once, at the top level of the binary crate.
+/// See the [crate documentation](crate#without-cargo-auditable) for the setup.
+#[macro_export]
+macro_rules! embed {
+    () => {
+        $crate::__audit_data_static!(concat!(env!("OUT_DIR"), "/auditable_data.bin"));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __audit_data_static {
+    ($path:expr) => {
+        #[used]
+        #[no_mangle]
+        #[cfg_attr(target_vendor = "apple", link_section = "__DATA,.dep-v0")]
+        #[cfg_attr(not(target_vendor = "apple"), link_section = ".dep-v0")]
+        static AUDITABLE_VERSION_INFO: [u8; include_bytes!($path).len()] = *include_bytes!($path);
+    };
+}
@@ -0,0 +1,458 @@
+//! Creates relocatable object files holding the audit data, enabled by the `object` feature.
+//!
+//! Build systems other than Cargo can link the object file returned by [`create_object_file`]
+//! into the binary without any knowledge of object file formats: it contains the `.dep-v0`
+//! section that tools reading the audit data look for, and a symbol that keeps the linker
+//! from discarding it.
+//!
+//! ```rust,ignore
+//! let payload = version_info.to_compressed_payload();
+//! let object = auditable_embed::object_file::create_object_file("x86_64-unknown-linux-gnu", &payload)
+//!     .expect("unsupported target");
+//! std::fs::write("audit_data.o", object)?;
+//! ```
+//!
+//! On ELF targets the section is not loaded into memory, so pass `--undefined=AUDITABLE_VERSION_INFO`
+//! to the linker to keep it, as `cargo auditable` does. Mach-O and COFF linkers keep it anyway.
+//!
+//! The object file writer is shamelessly copied from rustc codebase:
+//! https://github.com/rust-lang/rust/blob/dcca6a375bd4eddb3deea7038ebf29d02af53b48/compiler/rustc_codegen_ssa/src/back/metadata.rs#L97-L206
+//! and butchered ever so slightly
+
+use object::write::{self, StandardSegment, Symbol, SymbolSection};
+use object::{
+    elf, Architecture, BinaryFormat, Endianness, FileFlags, SectionFlags, SectionKind, SymbolFlags,
+    SymbolKind, SymbolScope,
+};
+
+/// Name of the section holding the audit data, the same as `auditable_extract::SECTION_NAME`
+const SECTION_NAME: &str = ".dep-v0";
+
+/// Name of the symbol pointing at the audit data, the same as the static defined by the macros
+pub const SYMBOL_NAME: &str = "AUDITABLE_VERSION_INFO";
+
+/// The properties of the compilation target that determine the format of the object file,
+/// named after the `cfg` values reported by `rustc --print=cfg --target=<triple>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    /// The target triple, e.g. `x86_64-unknown-linux-gnu`
+    pub triple: String,
+    /// `target_arch`, e.g. `x86_64`
+    pub arch: String,
+    /// `target_endian`, `little` or `big`
+    pub endian: String,
+    /// `target_pointer_width`, e.g. `64`
+    pub pointer_width: String,
+    /// `target_os`, e.g. `linux`
+    pub os: String,
+}
+
+impl Target {
+    /// Derives the target properties from the target triple alone, for callers that cannot
+    /// query `rustc`. Returns `None` if the architecture is not recognized.
+    ///
+    /// Only the properties that matter for the object file are derived reliably:
+    /// `os` is `unknown` for all operating systems that do not affect it.
+    pub fn from_triple(triple: &str) -> Option<Target> {
+        let components: Vec<&str> = triple.split('-').collect();
+        let arch_component = components[0];
+        let env = components.last().copied().unwrap_or_default();
+        let (arch, endian) = match arch_component {
+            "x86_64" | "x86_64h" => ("x86_64", "little"),
+            "i386" | "i486" | "i586" | "i686" => ("x86", "little"),
+            "aarch64" | "arm64" | "arm64e" | "arm64_32" => ("aarch64", "little"),
+            "aarch64_be" => ("aarch64", "big"),
+            "s390x" => ("s390x", "big"),
+            "powerpc" => ("powerpc", "big"),
+            "powerpc64" => ("powerpc64", "big"),
+            "powerpc64le" => ("powerpc64", "little"),
+            "sparc64" | "sparcv9" => ("sparc64", "big"),
+            "wasm32" => ("wasm32", "little"),
+            "wasm64" => ("wasm64", "little"),
+            a if a.starts_with("riscv32") => ("riscv32", "little"),
+            a if a.starts_with("riscv64") => ("riscv64", "little"),
+            a if a.starts_with("mips64") || a.starts_with("mipsisa64") => {
+                ("mips64", if a.ends_with("el") { "little" } else { "big" })
+            }
+            a if a.starts_with("mips") => {
+                ("mips", if a.ends_with("el") { "little" } else { "big" })
+            }
+            a if a.starts_with("armeb") || a.starts_with("thumbeb") => ("arm", "big"),
+            a if a.starts_with("arm") || a.starts_with("thumb") => ("arm", "little"),
+            _ => return None,
+        };
+        let pointer_width = match arch {
+            "x86_64" if env.ends_with("x32") => "32",
+            "aarch64" if arch_component == "arm64_32" || env.ends_with("ilp32") => "32",
+            "x86_64" | "aarch64" | "s390x" | "powerpc64" | "sparc64" | "wasm64" | "riscv64"
+            | "mips64" => "64",
+            _ => "32",
+        };
+        let os = ["freebsd", "solaris", "hermit"]
+            .iter()
+            .copied()
+            .find(|os| components[1..].contains(os))
+            .unwrap_or("unknown");
+        Some(Target {
+            triple: triple.to_owned(),
+            arch: arch.to_owned(),
+            endian: endian.to_owned(),
+            pointer_width: pointer_width.to_owned(),
+            os: os.to_owned(),
+        })
+    }
+}
+
+/// Returns a relocatable object file for the target holding `payload`, which is the
+/// compressed audit data, e.g. from `VersionInfo::to_compressed_payload`, in the section
+/// that tools reading the audit data look for. The symbol pointing at it is [`SYMBOL_NAME`].
+///
+/// Returns `None` if the target architecture is not supported.
+pub fn create_object_file(target_triple: &str, payload: &[u8]) -> Option<Vec<u8>> {
+    let target = Target::from_triple(target_triple)?;
+    create_object_file_for_target(&target, payload, SYMBOL_NAME, false)
+}
+
+/// Like [`create_object_file`], but for a target described by the `cfg` values reported by `rustc`,
+/// with the given symbol name.
+///
+/// If `loadable` is set, the section is loaded into memory, which is required for it to survive
+/// conversion into a raw memory image such as firmware. It is only meaningful for ELF.
+///
+/// Returns `None` if the target architecture is not supported.
+pub fn create_object_file_for_target(
+    // formerly `create_compressed_metadata_file` in the rustc codebase
+    target: &Target,
+    contents: &[u8],
+    symbol_name: &str,
+    // Not present in the rustc codebase.
+    loadable: bool,
+) -> Option<Vec<u8>> {
+    if matches!(target.arch.as_str(), "wasm32" | "wasm64") {
+        // The `object` crate cannot write WebAssembly, but a custom section is all we need
+        return Some(create_wasm_object_file(contents));
+    }
+    let mut file = new_object_file(target)?;
+    let section = file.add_section(
+        file.segment_name(StandardSegment::Data).to_vec(),
+        SECTION_NAME.as_bytes().to_vec(),
+        SectionKind::ReadOnlyData,
+    );
+    if let BinaryFormat::Elf = file.format() {
+        if !loadable {
+            // Explicitly set no flags to avoid SHF_ALLOC default for data section.
+            file.section_mut(section).flags = SectionFlags::Elf { sh_flags: 0 };
+        }
+    };
+    let offset = file.append_section_data(section, contents, 1);
+
+    // For MachO and probably PE this is necessary to prevent the linker from throwing away the
+    // .rustc section. For ELF this isn't necessary, but it also doesn't harm.
+    file.add_symbol(Symbol {
+        name: symbol_name.as_bytes().to_vec(),
+        value: offset,
+        size: contents.len() as u64,
+        kind: SymbolKind::Data,
+        scope: SymbolScope::Dynamic,
+        weak: false,
+        section: SymbolSection::Section(section),
+        flags: SymbolFlags::None,
+    });
+
+    Some(file.write().unwrap())
+}
+
+/// Not present in the rustc codebase. Creates a relocatable WebAssembly object that only holds
+/// a custom section with the audit data. `wasm-ld` copies custom sections from all inputs
+/// into the output module, so unlike other formats no symbol is needed to keep it alive.
+fn create_wasm_object_file(contents: &[u8]) -> Vec<u8> {
+    let mut file = b"\0asm".to_vec();
+    file.extend_from_slice(&1u32.to_le_bytes());
+    write_wasm_custom_section(&mut file, SECTION_NAME, contents);
+    // `wasm-ld` only accepts modules with a "linking" section as object files.
+    // Version 2 of the linking metadata with no subsections: no symbols, segments or init functions.
+    write_wasm_custom_section(&mut file, "linking", &[2]);
+    file
+}
+
+fn write_wasm_custom_section(file: &mut Vec<u8>, name: &str, contents: &[u8]) {
+    let mut payload = Vec::with_capacity(name.len() + contents.len() + 5);
+    write_leb128(&mut payload, name.len());
+    payload.extend_from_slice(name.as_bytes());
+    payload.extend_from_slice(contents);
+    file.push(0); // custom section id
+    write_leb128(file, payload.len());
+    file.extend_from_slice(&payload);
+}
+
+fn write_leb128(out: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn new_object_file(target: &Target) -> Option<write::Object<'static>> {
+    let target_triple = target.triple.as_str();
+    // This conversion evolves over time, and has some subtle logic for MIPS and RISC-V later on, that also evolves.
+    // If/when uplifiting this into Cargo, we will need to extract this code from rustc and put it in the `object` crate
+    // so that it could be shared between rustc and Cargo.
+    let endianness = match target.endian.as_str() {
+        "little" => Endianness::Little,
+        "big" => Endianness::Big,
+        _ => unreachable!(),
+    };
+    let architecture = match target.arch.as_str() {
+        "arm" => Architecture::Arm,
+        "aarch64" => {
+            if target.pointer_width == "32" {
+                Architecture::Aarch64_Ilp32
+            } else {
+                Architecture::Aarch64
+            }
+        }
+        "x86" => Architecture::I386,
+        "s390x" => Architecture::S390x,
+        "mips" => Architecture::Mips,
+        "mips64" => Architecture::Mips64,
+        "x86_64" => {
+            if target.pointer_width == "32" {
+                Architecture::X86_64_X32
+            } else {
+                Architecture::X86_64
+            }
+        }
+        "powerpc" => Architecture::PowerPc,
+        "powerpc64" => Architecture::PowerPc64,
+        "riscv32" => Architecture::Riscv32,
+        "riscv64" => Architecture::Riscv64,
+        "sparc64" => Architecture::Sparc64,
+        // Unsupported architecture.
+        _ => return None,
+    };
+    let binary_format = if target_triple.contains("-apple-") {
+        BinaryFormat::MachO
+    } else if target_triple.contains("-windows-") {
+        BinaryFormat::Coff
+    } else {
+        BinaryFormat::Elf
+    };
+
+    let mut file = write::Object::new(binary_format, architecture, endianness);
+    let e_flags = match architecture {
+        Architecture::Mips => {
+            // the original code matches on info we don't have to support pre-1999 MIPS variants:
+            // https://github.com/rust-lang/rust/blob/dcca6a375bd4eddb3deea7038ebf29d02af53b48/compiler/rustc_codegen_ssa/src/back/metadata.rs#L144C3-L153
+            // We can't support them, so this part was was modified significantly.
+            let arch = if target_triple.contains("r6") {
+                elf::EF_MIPS_ARCH_32R6
+            } else {
+                elf::EF_MIPS_ARCH_32R2
+            };
+            // end of modified part
+
+            // The only ABI LLVM supports for 32-bit MIPS CPUs is o32.
+            let mut e_flags = elf::EF_MIPS_CPIC | elf::EF_MIPS_ABI_O32 | arch;
+            // commented out: insufficient info to support this outside rustc
+            // if sess.target.options.relocation_model != RelocModel::Static {
+            //     e_flags |= elf::EF_MIPS_PIC;
+            // }
+            if target_triple.contains("r6") {
+                e_flags |= elf::EF_MIPS_NAN2008;
+            }
+            e_flags
+        }
+        Architecture::Mips64 => {
+            // copied from `mips64el-linux-gnuabi64-gcc foo.c -c`
+            #[allow(clippy::let_and_return)] // for staying as close to upstream as possible
+            let e_flags = elf::EF_MIPS_CPIC
+                | elf::EF_MIPS_PIC
+                | if target_triple.contains("r6") {
+                    elf::EF_MIPS_ARCH_64R6 | elf::EF_MIPS_NAN2008
+                } else {
+                    elf::EF_MIPS_ARCH_64R2
+                };
+            e_flags
+        }
+        Architecture::Riscv32 | Architecture::Riscv64 => {
+            // Source: https://github.com/riscv-non-isa/riscv-elf-psabi-doc/blob/079772828bd10933d34121117a222b4cc0ee2200/riscv-elf.adoc
+            let mut e_flags: u32 = 0x0;
+            let features = riscv_features(target_triple);
+            // Check if compressed is enabled
+            if features.contains('c') {
+                e_flags |= elf::EF_RISCV_RVC;
+            }
+
+            // Select the appropriate floating-point ABI
+            if features.contains('d') {
+                e_flags |= elf::EF_RISCV_FLOAT_ABI_DOUBLE;
+            } else if features.contains('f') {
+                e_flags |= elf::EF_RISCV_FLOAT_ABI_SINGLE;
+            } else {
+                e_flags |= elf::EF_RISCV_FLOAT_ABI_SOFT;
+            }
+            e_flags
+        }
+        _ => 0,
+    };
+    // adapted from LLVM's `MCELFObjectTargetWriter::getOSABI`
+    let os_abi = match target.os.as_str() {
+        "hermit" => elf::ELFOSABI_STANDALONE,
+        "freebsd" => elf::ELFOSABI_FREEBSD,
+        "solaris" => elf::ELFOSABI_SOLARIS,
+        _ => elf::ELFOSABI_NONE,
+    };
+    let abi_version = 0;
+    file.flags = FileFlags::Elf {
+        os_abi,
+        abi_version,
+        e_flags,
+    };
+    Some(file)
+}
+
+// This function was not present in the original rustc code, which simply used
+// `sess.target.options.features`
+// We do not have access to compiler internals, so we have to reimplement this function.
+fn riscv_features(target_triple: &str) -> String {
+    let arch = target_triple.split('-').next().unwrap();
+    assert_eq!(&arch[..5], "riscv");
+    let mut extensions = arch[7..].to_owned();
+    if extensions.contains('g') {
+        extensions.push_str("imadf");
+    }
+    extensions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_riscv_abi_detection() {
+        // real-world target with double floats
+        let features = riscv_features("riscv64gc-unknown-linux-gnu");
+        assert!(features.contains('c'));
+        assert!(features.contains('d'));
+        assert!(features.contains('f'));
+        // real-world target without floats
+        let features = riscv_features("riscv32imac-unknown-none-elf");
+        assert!(features.contains('c'));
+        assert!(!features.contains('d'));
+        assert!(!features.contains('f'));
+        // real-world target without floats or compression
+        let features = riscv_features("riscv32i-unknown-none-elf");
+        assert!(!features.contains('c'));
+        assert!(!features.contains('d'));
+        assert!(!features.contains('f'));
+        // made-up target without compression and with single floats
+        let features = riscv_features("riscv32if-unknown-none-elf");
+        assert!(!features.contains('c'));
+        assert!(!features.contains('d'));
+        assert!(features.contains('f'));
+    }
+
+    fn object_file(triple: &str) -> write::Object<'static> {
+        new_object_file(&Target::from_triple(triple).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_create_object_file() {
+        let cases = [
+            (
+                "x86_64-unknown-linux-gnu",
+                BinaryFormat::Elf,
+                Architecture::X86_64,
+            ),
+            (
+                "x86_64-unknown-linux-gnux32",
+                BinaryFormat::Elf,
+                Architecture::X86_64_X32,
+            ),
+            (
+                "x86_64-pc-windows-msvc",
+                BinaryFormat::Coff,
+                Architecture::X86_64,
+            ),
+            (
+                "x86_64-pc-windows-gnu",
+                BinaryFormat::Coff,
+                Architecture::X86_64,
+            ),
+            (
+                "i686-pc-windows-msvc",
+                BinaryFormat::Coff,
+                Architecture::I386,
+            ),
+            (
+                "x86_64-apple-darwin",
+                BinaryFormat::MachO,
+                Architecture::X86_64,
+            ),
+            (
+                "aarch64-apple-darwin",
+                BinaryFormat::MachO,
+                Architecture::Aarch64,
+            ),
+            (
+                "aarch64-unknown-linux-gnu",
+                BinaryFormat::Elf,
+                Architecture::Aarch64,
+            ),
+            (
+                "thumbv7em-none-eabihf",
+                BinaryFormat::Elf,
+                Architecture::Arm,
+            ),
+            (
+                "riscv32imac-unknown-none-elf",
+                BinaryFormat::Elf,
+                Architecture::Riscv32,
+            ),
+            (
+                "mipsel-unknown-linux-gnu",
+                BinaryFormat::Elf,
+                Architecture::Mips,
+            ),
+            (
+                "powerpc64le-unknown-linux-gnu",
+                BinaryFormat::Elf,
+                Architecture::PowerPc64,
+            ),
+        ];
+        for &(triple, format, architecture) in cases.iter() {
+            let file = object_file(triple);
+            assert_eq!(file.format(), format, "{triple}");
+            assert_eq!(file.architecture(), architecture, "{triple}");
+        }
+        assert!(create_object_file("unknownarch-unknown-linux-gnu", b"data").is_none());
+    }
+
+    #[test]
+    fn test_target_from_triple() {
+        let target = Target::from_triple("mips64-unknown-linux-gnuabi64").unwrap();
+        assert_eq!(target.arch, "mips64");
+        assert_eq!(target.endian, "big");
+        assert_eq!(target.pointer_width, "64");
+        let target = Target::from_triple("x86_64-unknown-freebsd").unwrap();
+        assert_eq!(target.os, "freebsd");
+        assert_eq!(target.pointer_width, "64");
+    }
+
+    #[test]
+    fn test_create_object_file_wasm() {
+        // long enough for the section size to need several LEB128 bytes
+        let contents = vec![b'x'; 1000];
+        let result = create_object_file("wasm32-unknown-unknown", &contents).unwrap();
+        assert!(result.starts_with(b"\0asm\x01\0\0\0"));
+        // the custom section is named `.dep-v0` and holds the contents as-is
+        let name = &result[11..19];
+        assert_eq!(name, b"\x07.dep-v0");
+        assert_eq!(&result[19..19 + contents.len()], &contents[..]);
+    }
+}
@@ -0,0 +1,292 @@
+//! Unix `ar` archives, which static libraries (`.a`, and `.lib` on Windows) and Rust libraries
+//! (`.rlib`) are stored as. They bundle object files that are later linked into an executable.
+//!
+//! The archive starts with [`AR_MAGIC`] and is followed by members, each with a 60-byte header:
+//!
+//! ```text
+//! | name (16) | mtime (12) | uid (6) | gid (6) | mode (8) | size (10, decimal) | "`\n" |
+//! ```
+//!
+//! Members are padded to an even offset. Names longer than the field are stored differently
+//! depending on the flavor of the archive: GNU and Windows archives store them in the `//` member
+//! and refer to them as `/offset`, while BSD and macOS archives store them at the start of the
+//! member data and use `#1/length` as the name.
+//!
+//! Members holding the symbol table (`/`, `/SYM64/` or `__.SYMDEF`) and the long name table
+//! are skipped, since they are never object files.
+
+use crate::Error;
+
+/// Magic at the start of every `ar` archive.
+/// Thin archives, which only refer to the object files on disk, are not supported.
+pub const AR_MAGIC: &[u8; 8] = b"!<arch>\n";
+
+pub(crate) const MEMBER_HEADER_LEN: usize = 60;
+
+const NAME_LEN: usize = 16;
+const SIZE_RANGE: core::ops::Range<usize> = 48..58;
+const HEADER_END: &[u8] = b"`\n";
+/// Prefix of BSD names stored at the start of the member data
+const BSD_LONG_NAME: &[u8] = b"#1/";
+
+/// A file stored in an archive, typically an object file
+#[derive(Debug, Clone, Copy)]
+pub struct ArchiveMember<'a> {
+    /// The name of the member, e.g. `foo.o`, without the padding or the trailing `/`
+    pub name: &'a [u8],
+    /// The contents of the member
+    pub data: &'a [u8],
+}
+
+/// Returns `true` if the data starts with the magic of an `ar` archive
+pub(crate) fn is_archive(data: &[u8]) -> bool {
+    data.starts_with(AR_MAGIC)
+}
+
+/// Lists the members of an `ar` archive, such as a static library or an rlib.
+///
+/// Returns [`Error::NotAnExecutable`] if the data is not an archive.
+/// This function does not allocate any memory on the heap and can be safely given untrusted input.
+pub fn archive_members(data: &[u8]) -> Result<ArchiveMembers<'_>, Error> {
+    if !is_archive(data) {
+        return Err(Error::NotAnExecutable);
+    }
+    Ok(ArchiveMembers {
+        data,
+        offset: AR_MAGIC.len(),
+        long_names: None,
+    })
+}
+
+/// Iterator over the members of an archive, created by [`archive_members`]
+#[derive(Debug, Clone)]
+pub struct ArchiveMembers<'a> {
+    data: &'a [u8],
+    offset: usize,
+    /// The contents of the GNU long name table, which precedes the members referring to it
+    long_names: Option<&'a [u8]>,
+}
+
+impl<'a> Iterator for ArchiveMembers<'a> {
+    type Item = Result<ArchiveMember<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.offset >= self.data.len() {
+                return None;
+            }
+            match self.member_at(self.offset) {
+                Ok((next_offset, member)) => {
+                    self.offset = next_offset;
+                    if let Some(member) = member {
+                        return Some(Ok(member));
+                    }
+                }
+                Err(e) => {
+                    // The rest of the archive cannot be located without a valid header
+                    self.offset = self.data.len();
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+impl<'a> ArchiveMembers<'a> {
+    /// Parses the member at `offset`, returning the offset of the next one
+    /// and the member itself unless it is a special member
+    fn member_at(&mut self, offset: usize) -> Result<(usize, Option<ArchiveMember<'a>>), Error> {
+        let header_end = offset
+            .checked_add(MEMBER_HEADER_LEN)
+            .ok_or(Error::MalformedFile)?;
+        let header = self
+            .data
+            .get(offset..header_end)
+            .ok_or(Error::UnexpectedEof)?;
+        let (raw_name, size) = member_header(header)?;
+        let end = header_end.checked_add(size).ok_or(Error::MalformedFile)?;
+        let data = self.data.get(header_end..end).ok_or(Error::UnexpectedEof)?;
+        // Members start at even offsets
+        let next_offset = end.checked_add(end % 2).ok_or(Error::MalformedFile)?;
+
+        let raw_name = trim_end(raw_name, b' ');
+        if raw_name == b"//" {
+            self.long_names = Some(data);
+            return Ok((next_offset, None));
+        }
+        if is_special_name(raw_name) {
+            return Ok((next_offset, None));
+        }
+        let (name, data) = if let Some(len) = raw_name.strip_prefix(BSD_LONG_NAME) {
+            let len = parse_decimal(len).ok_or(Error::MalformedFile)?;
+            if len > data.len() {
+                return Err(Error::MalformedFile);
+            }
+            let (name, data) = data.split_at(len);
+            // The name is padded with NUL bytes to keep the data aligned
+            let name = trim_end(name, 0);
+            // macOS stores the name of the symbol table this way too
+            if is_special_name(name) {
+                return Ok((next_offset, None));
+            }
+            (name, data)
+        } else if let Some(name_offset) = raw_name.strip_prefix(b"/") {
+            let name_offset = parse_decimal(name_offset).ok_or(Error::MalformedFile)?;
+            let names = self.long_names.ok_or(Error::MalformedFile)?;
+            let name = names.get(name_offset..).ok_or(Error::MalformedFile)?;
+            // GNU terminates the names with "/\n", Windows with a NUL byte
+            let name_end = name
+                .iter()
+                .position(|&b| b == b'\n' || b == 0)
+                .unwrap_or(name.len());
+            (trim_end(&name[..name_end], b'/'), data)
+        } else {
+            (trim_end(raw_name, b'/'), data)
+        };
+        Ok((next_offset, Some(ArchiveMember { name, data })))
+    }
+}
+
+/// Returns the raw name field and the size of the member data from its header
+pub(crate) fn member_header(header: &[u8]) -> Result<(&[u8], usize), Error> {
+    if header.len() < MEMBER_HEADER_LEN {
+        return Err(Error::UnexpectedEof);
+    }
+    if &header[SIZE_RANGE.end..MEMBER_HEADER_LEN] != HEADER_END {
+        return Err(Error::MalformedFile);
+    }
+    let size = parse_decimal(trim_end(&header[SIZE_RANGE], b' ')).ok_or(Error::MalformedFile)?;
+    Ok((&header[..NAME_LEN], size))
+}
+
+/// Returns the length of a BSD long name stored at the start of the member data, if any
+pub(crate) fn bsd_name_len(raw_name: &[u8]) -> Result<usize, Error> {
+    match trim_end(raw_name, b' ').strip_prefix(BSD_LONG_NAME) {
+        Some(len) => parse_decimal(len).ok_or(Error::MalformedFile),
+        None => Ok(0),
+    }
+}
+
+/// The symbol tables and the long name table, which are not object files
+pub(crate) fn is_special_name(raw_name: &[u8]) -> bool {
+    let raw_name = trim_end(raw_name, b' ');
+    let is_gnu_long_name =
+        raw_name.len() > 1 && raw_name[0] == b'/' && raw_name[1..].iter().all(u8::is_ascii_digit);
+    (raw_name.starts_with(b"/") && !is_gnu_long_name) || raw_name.starts_with(b"__.SYMDEF")
+}
+
+fn trim_end(mut data: &[u8], byte: u8) -> &[u8] {
+    while let [rest @ .., last] = data {
+        if *last != byte {
+            break;
+        }
+        data = rest;
+    }
+    data
+}
+
+fn parse_decimal(digits: &[u8]) -> Option<usize> {
+    if digits.is_empty() {
+        return None;
+    }
+    digits.iter().try_fold(0usize, |value, &digit| {
+        if !digit.is_ascii_digit() {
+            return None;
+        }
+        value
+            .checked_mul(10)?
+            .checked_add(usize::from(digit - b'0'))
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Builds an archive in the GNU flavor, storing the names that do not fit in the header
+    /// in the long name table
+    pub(crate) fn archive(members: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut result = AR_MAGIC.to_vec();
+        let mut long_names = Vec::new();
+        let mut names = Vec::new();
+        for (name, _) in members {
+            if name.len() < NAME_LEN {
+                names.push(format!("{name}/"));
+            } else {
+                names.push(format!("/{}", long_names.len()));
+                long_names.extend_from_slice(name.as_bytes());
+                long_names.extend_from_slice(b"/\n");
+            }
+        }
+        let mut add = |name: &str, data: &[u8]| {
+            result.extend_from_slice(
+                format!(
+                    "{name:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+                    0,
+                    0,
+                    0,
+                    644,
+                    data.len()
+                )
+                .as_bytes(),
+            );
+            result.extend_from_slice(data);
+            if result.len() % 2 == 1 {
+                result.push(b'\n');
+            }
+        };
+        // an empty symbol table, as written by `ar` without `s`
+        add("/", &[0; 4]);
+        if !long_names.is_empty() {
+            add("//", &long_names);
+        }
+        for (name, (_, data)) in names.iter().zip(members) {
+            add(name, data);
+        }
+        result
+    }
+
+    #[test]
+    fn gnu_members() {
+        let data = archive(&[("a.o", b"odd"), ("a_rather_long_member_name.o", b"long")]);
+        let members: Vec<ArchiveMember<'_>> = archive_members(&data)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].name, b"a.o");
+        assert_eq!(members[0].data, b"odd");
+        assert_eq!(members[1].name, b"a_rather_long_member_name.o");
+        assert_eq!(members[1].data, b"long");
+    }
+
+    #[test]
+    fn bsd_long_names() {
+        let mut data = AR_MAGIC.to_vec();
+        let header = format!(
+            "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+            "#1/20", 0, 0, 0, 644, 24
+        );
+        data.extend_from_slice(header.as_bytes());
+        data.extend_from_slice(b"__.SYMDEF SORTED\0\0\0\0data");
+        data.extend_from_slice(header.as_bytes());
+        data.extend_from_slice(b"long_member_name.o\0\0data");
+        let members: Vec<ArchiveMember<'_>> = archive_members(&data)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        // The BSD symbol table has a long name too, but it is still the symbol table
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0].name, b"long_member_name.o");
+        assert_eq!(members[0].data, b"data");
+    }
+
+    #[test]
+    fn truncated() {
+        let data = archive(&[("a.o", b"object file")]);
+        let mut members = archive_members(&data[..data.len() - 2]).unwrap();
+        assert!(matches!(members.next(), Some(Err(Error::UnexpectedEof))));
+        assert!(members.next().is_none());
+        assert!(archive_members(b"\x7fELF").is_err());
+    }
+}
@@ -0,0 +1,91 @@
+//! Locates the GNU build ID of ELF executables, which symbolication and crash reporting
+//! infrastructure uses to identify binaries.
+
+use binfarce::ByteOrder;
+
+/// Name of the section the linker places the build ID note into when passed `--build-id`
+pub(crate) const BUILD_ID_SECTION: &str = ".note.gnu.build-id";
+
+/// Note type of the build ID, as defined in `elf.h`
+const NT_GNU_BUILD_ID: u32 = 3;
+
+/// Finds the `NT_GNU_BUILD_ID` note among the notes in the section and returns its contents.
+/// Returns `None` if there is no such note or the section is malformed.
+pub(crate) fn gnu_build_id(notes: &[u8], byte_order: ByteOrder) -> Option<&[u8]> {
+    let mut rest = notes;
+    // Each note is a 12-byte header followed by the name and the description,
+    // each padded to a multiple of 4 bytes
+    while rest.len() >= 12 {
+        let name_size = read_u32(&rest[0..4], byte_order) as usize;
+        let desc_size = read_u32(&rest[4..8], byte_order) as usize;
+        let note_type = read_u32(&rest[8..12], byte_order);
+        let desc_start = 12usize.checked_add(align4(name_size)?)?;
+        let desc_end = desc_start.checked_add(desc_size)?;
+        let name = rest.get(12..12usize.checked_add(name_size)?)?;
+        let desc = rest.get(desc_start..desc_end)?;
+        if note_type == NT_GNU_BUILD_ID && name == b"GNU\0" {
+            return Some(desc);
+        }
+        // the padding after the last note may be missing
+        rest = rest
+            .get(desc_start.checked_add(align4(desc_size)?)?..)
+            .unwrap_or(&[]);
+    }
+    None
+}
+
+fn read_u32(bytes: &[u8], byte_order: ByteOrder) -> u32 {
+    let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    match byte_order {
+        ByteOrder::LittleEndian => u32::from_le_bytes(bytes),
+        ByteOrder::BigEndian => u32::from_be_bytes(bytes),
+    }
+}
+
+fn align4(size: usize) -> Option<usize> {
+    Some(size.checked_add(3)? & !3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUILD_ID_NOTE: &[u8] = &[
+        4, 0, 0, 0, // name size
+        4, 0, 0, 0, // description size
+        3, 0, 0, 0, // NT_GNU_BUILD_ID
+        b'G', b'N', b'U', 0, // name
+        0xde, 0xad, 0xbe, 0xef, // build ID
+    ];
+
+    #[test]
+    fn finds_build_id() {
+        let build_id = gnu_build_id(BUILD_ID_NOTE, ByteOrder::LittleEndian);
+        assert_eq!(build_id, Some(&[0xde, 0xad, 0xbe, 0xef][..]));
+    }
+
+    #[test]
+    fn skips_other_notes() {
+        let mut notes = vec![
+            8, 0, 0, 0, // name size
+            5, 0, 0, 0, // description size, padded to 8
+            1, 0, 0, 0, // NT_GNU_ABI_TAG
+        ];
+        notes.extend_from_slice(b"Android\0");
+        notes.extend_from_slice(&[1, 2, 3, 4, 5, 0, 0, 0]);
+        notes.extend_from_slice(BUILD_ID_NOTE);
+        let build_id = gnu_build_id(&notes, ByteOrder::LittleEndian);
+        assert_eq!(build_id, Some(&[0xde, 0xad, 0xbe, 0xef][..]));
+    }
+
+    #[test]
+    fn malformed_notes() {
+        assert_eq!(
+            gnu_build_id(&BUILD_ID_NOTE[..18], ByteOrder::LittleEndian),
+            None
+        );
+        assert_eq!(gnu_build_id(BUILD_ID_NOTE, ByteOrder::BigEndian), None);
+        let huge = [0xff; 16];
+        assert_eq!(gnu_build_id(&huge, ByteOrder::LittleEndian), None);
+    }
+}
@@ -0,0 +1,62 @@
+//! Locates the machine code of an executable, so that it can be compared with another file
+//! to tell whether both were built from the same code.
+
+use crate::{fat, header_limits, wasm, Error};
+use binfarce::Format;
+
+/// Name of the section holding the machine code in ELF and PE executables
+const TEXT_SECTION_NAME: &str = ".text";
+
+/// Segment and section holding the machine code in Mach-O executables
+const MACHO_TEXT_SECTION: (&str, &str) = ("__TEXT", "__text");
+
+/// ID of the code section in WebAssembly modules
+const WASM_CODE_SECTION_ID: u8 = 10;
+
+/// Returns the contents of the section holding the machine code of an executable:
+/// `.text` for ELF and PE, `__TEXT,__text` for Mach-O and the code section for WebAssembly.
+///
+/// Post-processing such as code signing, editing resources or adding a watermark leaves
+/// this section intact, so comparing it tells whether two files contain the same program.
+///
+/// Mach-O universal ("fat") binaries are rejected with [`Error::NotAnExecutable`];
+/// use [`fat_slices`](crate::fat_slices) and pass each slice to this function instead.
+///
+/// This function does not allocate any memory on the heap and can be safely given untrusted input.
+pub fn code_section(data: &[u8]) -> Result<&[u8], Error> {
+    if wasm::is_wasm(data) {
+        return wasm::section_with_id(data, WASM_CODE_SECTION_ID)?.ok_or(Error::SectionIsMissing);
+    }
+    if fat::is_fat(data) {
+        return Err(Error::NotAnExecutable);
+    }
+    let range = match binfarce::detect_format(data) {
+        Format::Elf32 { byte_order } => {
+            header_limits::check_elf(data, false, byte_order)?;
+            let elf = binfarce::elf32::parse(data, byte_order)?;
+            let section = elf.section_with_name(TEXT_SECTION_NAME)?;
+            section.ok_or(Error::SectionIsMissing)?.range()?
+        }
+        Format::Elf64 { byte_order } => {
+            header_limits::check_elf(data, true, byte_order)?;
+            let elf = binfarce::elf64::parse(data, byte_order)?;
+            let section = elf.section_with_name(TEXT_SECTION_NAME)?;
+            section.ok_or(Error::SectionIsMissing)?.range()?
+        }
+        Format::Macho => {
+            header_limits::check_macho(data)?;
+            let parsed = binfarce::macho::parse(data)?;
+            let (segment, name) = MACHO_TEXT_SECTION;
+            let section = parsed.section_with_name(segment, name)?;
+            section.ok_or(Error::SectionIsMissing)?.range()?
+        }
+        Format::PE => {
+            header_limits::check_pe(data)?;
+            let parsed = binfarce::pe::parse(data)?;
+            let section = parsed.section_with_name(TEXT_SECTION_NAME)?;
+            section.ok_or(Error::SectionIsMissing)?.range()?
+        }
+        _ => return Err(Error::NotAnExecutable),
+    };
+    data.get(range).ok_or(Error::UnexpectedEof)
+}
@@ -0,0 +1,164 @@
+//! Containers holding several named payloads in place of the bare audit data.
+//!
+//! So that the dependency list can be accompanied by other data, such as build provenance
+//! or a signature, without adding a new section for each, the audit data may be a container:
+//!
+//! ```text
+//! | CONTAINER_MAGIC (8 bytes) | entry | entry | ...
+//! ```
+//!
+//! where each entry is
+//!
+//! ```text
+//! | name length (u8) | name (UTF-8) | payload length (u32, little-endian) | payload |
+//! ```
+//!
+//! The dependency list is stored under [`DEPENDENCIES_PAYLOAD`]. Audit data that does not start
+//! with the magic is the dependency list alone, as written by all earlier versions.
+//! Containers may be framed or stored in a section like the bare dependency list.
+
+use crate::Error;
+use std::convert::TryFrom;
+
+/// Marks audit data holding several named payloads
+pub const CONTAINER_MAGIC: [u8; 8] = *b"\x7fDEP-BOX";
+
+/// Name of the payload holding the Zlib-compressed dependency list
+pub const DEPENDENCIES_PAYLOAD: &str = "dependencies";
+
+/// A named payload stored in the audit data
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Payload<'a> {
+    pub name: &'a str,
+    pub data: &'a [u8],
+}
+
+/// Lists the payloads stored in the audit data.
+///
+/// Use [`raw_payloads`](crate::raw_payloads) to list the payloads embedded in an executable.
+///
+/// Audit data that is not a container yields a single [`DEPENDENCIES_PAYLOAD`].
+///
+/// This function does not allocate any memory on the heap and can be safely given untrusted input.
+pub fn payloads(audit_data: &[u8]) -> Payloads<'_> {
+    match audit_data.strip_prefix(&CONTAINER_MAGIC[..]) {
+        Some(entries) => Payloads {
+            remaining: entries,
+            bare: None,
+        },
+        None => Payloads {
+            remaining: &[],
+            bare: Some(audit_data),
+        },
+    }
+}
+
+/// Iterator over the payloads in the audit data, created by [`payloads`]
+///
+/// Yields an error and stops if an entry is malformed.
+#[derive(Debug, Clone)]
+pub struct Payloads<'a> {
+    remaining: &'a [u8],
+    /// Set for audit data that is not a container
+    bare: Option<&'a [u8]>,
+}
+
+impl<'a> Iterator for Payloads<'a> {
+    type Item = Result<Payload<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(data) = self.bare.take() {
+            return Some(Ok(Payload {
+                name: DEPENDENCIES_PAYLOAD,
+                data,
+            }));
+        }
+        if self.remaining.is_empty() {
+            return None;
+        }
+        let result = next_entry(self.remaining);
+        match result {
+            Ok((payload, rest)) => {
+                self.remaining = rest;
+                Some(Ok(payload))
+            }
+            Err(e) => {
+                self.remaining = &[];
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+fn next_entry(data: &[u8]) -> Result<(Payload<'_>, &[u8]), Error> {
+    let (&name_len, rest) = data.split_first().ok_or(Error::UnexpectedEof)?;
+    let name = rest
+        .get(..usize::from(name_len))
+        .ok_or(Error::UnexpectedEof)?;
+    let name = core::str::from_utf8(name).map_err(|_| Error::MalformedFile)?;
+    let rest = &rest[usize::from(name_len)..];
+    let len_bytes = rest.get(..4).ok_or(Error::UnexpectedEof)?;
+    let len = u32::from_le_bytes([len_bytes[0], len_bytes[1], len_bytes[2], len_bytes[3]]);
+    let len = usize::try_from(len).map_err(|_| Error::UnexpectedEof)?;
+    let rest = &rest[4..];
+    let data = rest.get(..len).ok_or(Error::UnexpectedEof)?;
+    Ok((Payload { name, data }, &rest[len..]))
+}
+
+/// Returns the dependency list from audit data that may be a container
+pub(crate) fn dependency_list(audit_data: &[u8]) -> Result<&[u8], Error> {
+    for payload in payloads(audit_data) {
+        let payload = payload?;
+        if payload.name == DEPENDENCIES_PAYLOAD {
+            return Ok(payload.data);
+        }
+    }
+    Err(Error::NoAuditData)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn container(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut result = CONTAINER_MAGIC.to_vec();
+        for (name, payload) in entries {
+            result.push(name.len() as u8);
+            result.extend_from_slice(name.as_bytes());
+            result.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            result.extend_from_slice(payload);
+        }
+        result
+    }
+
+    #[test]
+    fn enumerate() {
+        let data = container(&[("provenance", b"{}"), (DEPENDENCIES_PAYLOAD, b"deps")]);
+        let names: Vec<&str> = payloads(&data).map(|p| p.unwrap().name).collect();
+        assert_eq!(names, ["provenance", DEPENDENCIES_PAYLOAD]);
+        assert_eq!(dependency_list(&data).unwrap(), b"deps");
+    }
+
+    #[test]
+    fn bare_dependency_list() {
+        let all: Vec<Payload> = payloads(b"deps").map(Result::unwrap).collect();
+        assert_eq!(
+            all,
+            [Payload {
+                name: DEPENDENCIES_PAYLOAD,
+                data: b"deps"
+            }]
+        );
+        assert_eq!(dependency_list(b"deps").unwrap(), b"deps");
+    }
+
+    #[test]
+    fn malformed() {
+        let data = container(&[("provenance", b"{}")]);
+        assert!(matches!(dependency_list(&data), Err(Error::NoAuditData)));
+        let truncated = &data[..data.len() - 1];
+        let mut entries = payloads(truncated);
+        assert!(matches!(entries.next(), Some(Err(Error::UnexpectedEof))));
+        assert!(entries.next().is_none());
+    }
+}
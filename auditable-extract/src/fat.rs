@@ -0,0 +1,170 @@
+//! Mach-O universal ("fat") binaries, which bundle executables for several architectures,
+//! such as x86_64 and aarch64 macOS, into a single file.
+//!
+//! The file starts with a big-endian header listing the slices, each of which is a regular
+//! Mach-O executable with its own audit data:
+//!
+//! ```text
+//! | magic (u32) | slice count (u32) | per slice: cpu type, cpu subtype, offset, size, alignment |
+//! ```
+//!
+//! With [`FAT_MAGIC_64`] the offset and size are 64-bit and each entry has a reserved `u32` at the end.
+
+use crate::Error;
+use std::convert::TryFrom;
+
+/// Magic of universal binaries with 32-bit slice offsets
+pub const FAT_MAGIC: u32 = 0xcafe_babe;
+/// Magic of universal binaries with 64-bit slice offsets
+pub const FAT_MAGIC_64: u32 = 0xcafe_babf;
+
+/// Universal binaries bundle a handful of architectures.
+/// Java class files share [`FAT_MAGIC`] but have their version in place of the slice count,
+/// which is at least 45, so a low cap also tells the two apart.
+const MAX_SLICES: u32 = 20;
+
+const HEADER_LEN: usize = 8;
+
+/// A single-architecture executable inside a universal binary
+#[derive(Debug, Clone, Copy)]
+pub struct FatSlice<'a> {
+    /// The `cputype` field, e.g. `0x0100000c` for arm64
+    pub cpu_type: u32,
+    /// The `cpusubtype` field
+    pub cpu_subtype: u32,
+    /// The contents of the slice, itself a Mach-O executable
+    pub data: &'a [u8],
+}
+
+/// Returns `true` if the data looks like a Mach-O universal binary
+pub(crate) fn is_fat(data: &[u8]) -> bool {
+    match (read_u32(data, 0), read_u32(data, 4)) {
+        (Some(magic), Some(count)) => {
+            (magic == FAT_MAGIC || magic == FAT_MAGIC_64) && (1..=MAX_SLICES).contains(&count)
+        }
+        _ => false,
+    }
+}
+
+/// Lists the slices of a Mach-O universal binary.
+///
+/// Returns [`Error::NotAnExecutable`] if the data is not a universal binary.
+/// This function does not allocate any memory on the heap and can be safely given untrusted input.
+pub fn fat_slices(data: &[u8]) -> Result<FatSlices<'_>, Error> {
+    if !is_fat(data) {
+        return Err(Error::NotAnExecutable);
+    }
+    let is_64 = read_u32(data, 0) == Some(FAT_MAGIC_64);
+    let count = read_u32(data, 4).ok_or(Error::UnexpectedEof)?;
+    Ok(FatSlices {
+        data,
+        is_64,
+        index: 0,
+        count,
+    })
+}
+
+/// Iterator over the slices of a universal binary, created by [`fat_slices`]
+#[derive(Debug, Clone)]
+pub struct FatSlices<'a> {
+    data: &'a [u8],
+    is_64: bool,
+    index: u32,
+    count: u32,
+}
+
+impl<'a> Iterator for FatSlices<'a> {
+    type Item = Result<FatSlice<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.count {
+            return None;
+        }
+        let entry_len = if self.is_64 { 32 } else { 20 };
+        let entry = HEADER_LEN + self.index as usize * entry_len;
+        self.index += 1;
+        Some(self.slice_at(entry))
+    }
+}
+
+impl<'a> FatSlices<'a> {
+    fn slice_at(&self, entry: usize) -> Result<FatSlice<'a>, Error> {
+        let field = |offset: usize| read_u32(self.data, entry + offset).ok_or(Error::UnexpectedEof);
+        let cpu_type = field(0)?;
+        let cpu_subtype = field(4)?;
+        let (offset, size) = if self.is_64 {
+            (
+                u64::from(field(8)?) << 32 | u64::from(field(12)?),
+                u64::from(field(16)?) << 32 | u64::from(field(20)?),
+            )
+        } else {
+            (u64::from(field(8)?), u64::from(field(12)?))
+        };
+        let start = usize::try_from(offset).map_err(|_| Error::MalformedFile)?;
+        let len = usize::try_from(size).map_err(|_| Error::MalformedFile)?;
+        let end = start.checked_add(len).ok_or(Error::MalformedFile)?;
+        let data = self.data.get(start..end).ok_or(Error::UnexpectedEof)?;
+        Ok(FatSlice {
+            cpu_type,
+            cpu_subtype,
+            data,
+        })
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARM64: u32 = 0x0100_000c;
+    const X86_64: u32 = 0x0100_0007;
+
+    fn universal(slices: &[(u32, &[u8])]) -> Vec<u8> {
+        let mut result = FAT_MAGIC.to_be_bytes().to_vec();
+        result.extend_from_slice(&(slices.len() as u32).to_be_bytes());
+        let mut offset = HEADER_LEN + slices.len() * 20;
+        for (cpu_type, contents) in slices {
+            for field in [*cpu_type, 0, offset as u32, contents.len() as u32, 0] {
+                result.extend_from_slice(&field.to_be_bytes());
+            }
+            offset += contents.len();
+        }
+        for (_, contents) in slices {
+            result.extend_from_slice(contents);
+        }
+        result
+    }
+
+    #[test]
+    fn slices() {
+        let data = universal(&[(X86_64, b"intel"), (ARM64, b"apple silicon")]);
+        let slices: Vec<FatSlice<'_>> = fat_slices(&data).unwrap().map(Result::unwrap).collect();
+        assert_eq!(slices.len(), 2);
+        assert_eq!(slices[0].cpu_type, X86_64);
+        assert_eq!(slices[0].data, b"intel");
+        assert_eq!(slices[1].cpu_type, ARM64);
+        assert_eq!(slices[1].data, b"apple silicon");
+    }
+
+    #[test]
+    fn truncated() {
+        let data = universal(&[(ARM64, b"apple silicon")]);
+        let mut slices = fat_slices(&data[..data.len() - 1]).unwrap();
+        assert!(matches!(slices.next(), Some(Err(Error::UnexpectedEof))));
+        assert!(slices.next().is_none());
+    }
+
+    #[test]
+    fn not_fat() {
+        // A Java class file for Java 8: minor version 0, major version 52
+        let class_file = [0xca, 0xfe, 0xba, 0xbe, 0, 0, 0, 52];
+        assert!(!is_fat(&class_file));
+        assert!(fat_slices(&class_file).is_err());
+        assert!(!is_fat(b"\x7fELF"));
+    }
+}
@@ -0,0 +1,366 @@
+//! Framing for audit data that is not stored in a dedicated section of an executable.
+//!
+//! When the audit data is placed into a raw memory image (e.g. a firmware image
+//! produced by `objcopy -O binary`), there are no section headers to locate it by,
+//! so the compressed data is prefixed with a magic value, its length and its checksum:
+//!
+//! ```text
+//! | FRAME_MAGIC (8 bytes) | payload length (u32, little-endian) | CRC-32 of the payload (u32, little-endian) | payload |
+//! ```
+//!
+//! The checksum is the common CRC-32 used by zlib, gzip and PNG. It is verified before
+//! the payload is returned, so that flash bit-rot or a truncated image is reported as
+//! [`Error::PayloadCorrupted`] rather than as a confusing decompression failure.
+//!
+//! This is the "v2" framing. The data in the `.dep-v0` section of regular executables is not framed.
+
+use crate::{Error, CONTAINER_MAGIC, UNCOMPRESSED_MARKER, ZSTD_MARKER};
+use std::convert::TryFrom;
+
+/// Marks the start of framed audit data
+pub const FRAME_MAGIC: [u8; 8] = *b"\x7fDEP-V2\0";
+
+/// Size of the magic, the length and the checksum preceding the payload
+pub const FRAME_HEADER_LEN: usize = FRAME_MAGIC.len() + 8;
+
+/// Maximum total size of the frames whose checksum is verified in a single search
+/// for framed audit data of unknown location.
+///
+/// Every occurrence of the magic may claim a frame extending to the end of the input,
+/// so without a limit, input crafted to contain many of them would take quadratic time to search.
+/// The search gives up once it is exceeded.
+pub const CANDIDATE_CHECK_BUDGET: usize = 64 * 1024 * 1024;
+
+/// Returns the header to write before `payload` to frame it.
+///
+/// Returns `None` if the payload is larger than 4 GiB, which cannot be framed.
+pub fn frame_header(payload: &[u8]) -> Option<[u8; FRAME_HEADER_LEN]> {
+    let len = u32::try_from(payload.len()).ok()?;
+    let mut header = [0u8; FRAME_HEADER_LEN];
+    header[..FRAME_MAGIC.len()].copy_from_slice(&FRAME_MAGIC);
+    header[FRAME_MAGIC.len()..FRAME_MAGIC.len() + 4].copy_from_slice(&len.to_le_bytes());
+    header[FRAME_MAGIC.len() + 4..].copy_from_slice(&crc32(payload).to_le_bytes());
+    Some(header)
+}
+
+/// Extracts the Zlib-compressed dependency info from framed data.
+///
+/// `data` must start at the beginning of the frame, e.g. at the location of the
+/// `__auditable_start` symbol defined by the linker script fragment generated by
+/// `cargo auditable emit-object --linker-script`. Any data after the end of the frame is ignored.
+///
+/// This function does not allocate any memory on the heap and can be safely given untrusted input.
+pub fn framed_auditable_data(data: &[u8]) -> Result<&[u8], Error> {
+    crate::container::dependency_list(frame_contents(data)?)
+}
+
+/// Returns the contents of the frame, which may be a container of several payloads,
+/// after verifying their checksum
+pub(crate) fn frame_contents(data: &[u8]) -> Result<&[u8], Error> {
+    let (contents, checksum) = unverified_frame_contents(data)?;
+    if crc32(contents) != checksum {
+        return Err(Error::PayloadCorrupted);
+    }
+    Ok(contents)
+}
+
+/// Returns the contents of the frame and the checksum recorded for them
+fn unverified_frame_contents(data: &[u8]) -> Result<(&[u8], u32), Error> {
+    if !is_framed(data) {
+        return Err(Error::NoAuditData);
+    }
+    let header = data.get(..FRAME_HEADER_LEN).ok_or(Error::UnexpectedEof)?;
+    let field = |offset: usize| {
+        u32::from_le_bytes([
+            header[offset],
+            header[offset + 1],
+            header[offset + 2],
+            header[offset + 3],
+        ])
+    };
+    let len = field(FRAME_MAGIC.len());
+    let checksum = field(FRAME_MAGIC.len() + 4);
+    let end = FRAME_HEADER_LEN
+        .checked_add(len as usize)
+        .ok_or(Error::MalformedFile)?;
+    let contents = data
+        .get(FRAME_HEADER_LEN..end)
+        .ok_or(Error::UnexpectedEof)?;
+    Ok((contents, checksum))
+}
+
+/// Returns `true` if the data starts with the magic of framed audit data, followed by
+/// a header and the first byte of what could be audit data: a container, or a payload
+/// compressed with Zlib or Zstandard, or not compressed at all.
+///
+/// The length and the checksum are not checked, so this is a cheap way to skip most
+/// occurrences of the magic by chance before reading the rest of the frame.
+pub fn is_plausible_frame(data: &[u8]) -> bool {
+    let first = match data.get(FRAME_HEADER_LEN) {
+        Some(&first) => first,
+        None => return false,
+    };
+    // The low four bits of the first byte of a Zlib stream are the compression method, always 8
+    is_framed(data)
+        && (first == CONTAINER_MAGIC[0]
+            || first == ZSTD_MARKER
+            || first == UNCOMPRESSED_MARKER
+            || first & 0x0f == 8)
+}
+
+/// The CRC-32 used by zlib, gzip and PNG, with the reflected polynomial `0xEDB88320`
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc = CRC32_TABLE[usize::from(crc as u8 ^ byte)] ^ (crc >> 8);
+    }
+    !crc
+}
+
+/// The CRC-32 of every byte value, so that [`crc32`] processes a byte at a time rather than a bit
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut byte = 0;
+    while byte < table.len() {
+        let mut crc = byte as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+            bit += 1;
+        }
+        table[byte] = crc;
+        byte += 1;
+    }
+    table
+}
+
+/// Returns the offset of the first occurrence of [`FRAME_MAGIC`] in the data.
+///
+/// With the `memchr` feature enabled this uses the SIMD-accelerated search from the
+/// [`memchr`](https://docs.rs/memchr/) crate, which contains `unsafe` code.
+/// Without it, a slower search in safe Rust is used.
+pub fn find_frame_magic(data: &[u8]) -> Option<usize> {
+    #[cfg(feature = "memchr")]
+    {
+        memchr::memmem::find(data, &FRAME_MAGIC)
+    }
+    #[cfg(not(feature = "memchr"))]
+    {
+        // Looking for the first byte of the magic is much faster than
+        // comparing the whole magic at every position
+        let mut position = 0;
+        loop {
+            let remaining = data.get(position..)?;
+            let candidate = position + remaining.iter().position(|&b| b == FRAME_MAGIC[0])?;
+            if data[candidate..].starts_with(&FRAME_MAGIC) {
+                return Some(candidate);
+            }
+            position = candidate + 1;
+        }
+    }
+}
+
+/// Searches the data for framed audit data and returns the payloads of all plausible frames,
+/// in the order they appear in the input.
+///
+/// This is meant for raw memory images such as firmware images, where the location
+/// of the audit data is not known. The magic may occur in the input by chance,
+/// so the caller should try the candidates in order until one of them decompresses successfully.
+///
+/// Candidates that fail [`is_plausible_frame`] are skipped without verifying their checksum,
+/// and the search ends once the checked frames add up to [`CANDIDATE_CHECK_BUDGET`].
+///
+/// This function does not allocate any memory on the heap and can be safely given untrusted input.
+pub fn framed_auditable_data_candidates(data: &[u8]) -> FrameCandidates<'_> {
+    FrameCandidates {
+        data,
+        position: 0,
+        checked: 0,
+    }
+}
+
+/// Iterator over the plausible frames in the input, created by [`framed_auditable_data_candidates`]
+#[derive(Debug, Clone)]
+pub struct FrameCandidates<'a> {
+    data: &'a [u8],
+    position: usize,
+    /// Total size of the frames whose checksum has been verified so far
+    checked: usize,
+}
+
+impl<'a> Iterator for FrameCandidates<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(remaining) = self.data.get(self.position..) {
+            let offset = find_frame_magic(remaining)?;
+            let frame_start = self.position + offset;
+            // Resume the search right after this magic, in case it was a false positive
+            self.position = frame_start + 1;
+            let candidate = &self.data[frame_start..];
+            if !is_plausible_frame(candidate) {
+                continue;
+            }
+            let (contents, checksum) = match unverified_frame_contents(candidate) {
+                Ok(frame) => frame,
+                Err(_) => continue,
+            };
+            self.checked = self.checked.saturating_add(contents.len());
+            if self.checked > CANDIDATE_CHECK_BUDGET {
+                self.position = self.data.len();
+                return None;
+            }
+            if crc32(contents) == checksum {
+                if let Ok(payload) = crate::container::dependency_list(contents) {
+                    return Some(payload);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Returns the frame that ends exactly at the end of the data, including its header.
+///
+/// This is where audit data copied into an executable that lacks the audit data section
+/// is stored, e.g. by `auditable_info::copy_audit_data`. Requiring the frame to end
+/// at the end of the file rules out false positives from the magic occurring by chance.
+pub(crate) fn trailing_frame(data: &[u8]) -> Option<&[u8]> {
+    let mut position = 0;
+    while let Some(remaining) = data.get(position..) {
+        let frame_start = position + find_frame_magic(remaining)?;
+        position = frame_start + 1;
+        // The checksum is verified when the frame is read, so that corruption is reported as such
+        if let Ok((contents, _)) = unverified_frame_contents(&data[frame_start..]) {
+            if frame_start + FRAME_HEADER_LEN + contents.len() == data.len() {
+                return Some(&data[frame_start..]);
+            }
+        }
+    }
+    None
+}
+
+/// Returns `true` if the data starts with the magic of framed audit data
+pub(crate) fn is_framed(data: &[u8]) -> bool {
+    data.starts_with(&FRAME_MAGIC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(payload: &[u8]) -> Vec<u8> {
+        let mut result = frame_header(payload).unwrap().to_vec();
+        result.extend_from_slice(payload);
+        result
+    }
+
+    #[test]
+    fn checksum() {
+        // The check value of CRC-32
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(CRC32_TABLE[1], 0x7707_3096);
+        assert_eq!(crc32(b""), 0);
+        let mut data = frame(b"payload");
+        let last = data.len() - 1;
+        data[last] ^= 0x10;
+        assert!(matches!(
+            framed_auditable_data(&data),
+            Err(Error::PayloadCorrupted)
+        ));
+        // a corrupted frame at the end of an executable is still found, and reported as corrupted
+        assert!(trailing_frame(&data).is_some());
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut data = frame(b"payload");
+        // trailing data such as the rest of the flash image is ignored
+        data.extend_from_slice(&[0xff; 16]);
+        assert_eq!(framed_auditable_data(&data).unwrap(), b"payload");
+    }
+
+    #[test]
+    fn trailing() {
+        let mut data = b"executable".to_vec();
+        // a frame in the middle of the file is not trailing
+        data.extend_from_slice(&frame(b"stray"));
+        data.extend_from_slice(b"more code");
+        assert!(trailing_frame(&data).is_none());
+        data.extend_from_slice(&frame(b"payload"));
+        let trailing = trailing_frame(&data).unwrap();
+        assert_eq!(frame_contents(trailing).unwrap(), b"payload");
+    }
+
+    #[test]
+    fn truncated() {
+        let data = frame(b"payload");
+        assert!(framed_auditable_data(&data[..data.len() - 1]).is_err());
+        assert!(framed_auditable_data(&data[..FRAME_HEADER_LEN - 1]).is_err());
+    }
+
+    #[test]
+    fn scan() {
+        let mut data = vec![0u8; 100];
+        // a stray magic without a valid length must not prevent finding the real frame
+        data.extend_from_slice(&FRAME_MAGIC);
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&frame(b"{first}"));
+        data.extend_from_slice(&[0u8; 3]);
+        data.extend_from_slice(&frame(b"{second}"));
+        // a frame that cannot hold audit data is skipped without verifying its checksum
+        data.extend_from_slice(&frame(b"implausible"));
+        let candidates: Vec<&[u8]> = framed_auditable_data_candidates(&data).collect();
+        assert_eq!(candidates, vec![&b"{first}"[..], &b"{second}"[..]]);
+        assert_eq!(framed_auditable_data_candidates(&[0u8; 100]).count(), 0);
+    }
+
+    #[test]
+    fn many_stray_magics() {
+        // Each stray frame claims to extend to the end of the data,
+        // so verifying all of them would take quadratic time
+        let count = 20_000;
+        let stray_len = FRAME_HEADER_LEN + 1;
+        let mut data = Vec::with_capacity(count * stray_len);
+        for i in 0..count {
+            let len = (count - i) * stray_len - FRAME_HEADER_LEN;
+            data.extend_from_slice(&FRAME_MAGIC);
+            data.extend_from_slice(&(len as u32).to_le_bytes());
+            data.extend_from_slice(&0u32.to_le_bytes());
+            data.push(UNCOMPRESSED_MARKER);
+        }
+        let mut candidates = framed_auditable_data_candidates(&data);
+        assert_eq!(candidates.next(), None);
+        assert!(candidates.checked <= CANDIDATE_CHECK_BUDGET + data.len());
+        // implausible frames cost nothing to skip
+        for stray in data.chunks_mut(stray_len) {
+            stray[FRAME_HEADER_LEN] = 0;
+        }
+        let mut candidates = framed_auditable_data_candidates(&data);
+        assert_eq!(candidates.next(), None);
+        assert_eq!(candidates.checked, 0);
+    }
+
+    #[test]
+    fn magic_search() {
+        let mut data = vec![FRAME_MAGIC[0]; 10];
+        // a partial magic at the end of the data must not be reported
+        data.extend_from_slice(&FRAME_MAGIC);
+        data.extend_from_slice(&FRAME_MAGIC[..4]);
+        assert_eq!(find_frame_magic(&data), Some(10));
+        assert_eq!(find_frame_magic(&data[11..]), None);
+        assert_eq!(find_frame_magic(&[]), None);
+    }
+
+    #[test]
+    fn not_framed() {
+        assert!(matches!(
+            framed_auditable_data(b"definitely not audit data"),
+            Err(Error::NoAuditData)
+        ));
+    }
+}
@@ -0,0 +1,140 @@
+//! Caps on the header structures of executables, checked before the executable is parsed.
+//!
+//! Parsing does not allocate, but looking up a section by name walks every section header
+//! and compares names from the section name table. A crafted file of a few hundred bytes
+//! could claim tens of thousands of sections or an enormous name table and make every lookup
+//! that much slower, which matters for services that scan untrusted uploads.
+//! Real executables are nowhere near these limits, so such files are rejected
+//! with [`Error::ImplausibleBinary`] up front.
+//!
+//! Symbol tables are never read, so they need no cap.
+//! Fields that lie outside the input are not checked here; the parser reports those.
+
+use crate::Error;
+use binfarce::ByteOrder;
+use std::convert::TryFrom;
+
+/// Linkers emit a few dozen sections; even with `-ffunction-sections` and without `--gc-sections`
+/// executables rarely have more than a few thousand.
+pub(crate) const MAX_SECTIONS: u64 = 16 * 1024;
+
+/// Each section name is short, so this is plenty even for [`MAX_SECTIONS`] sections.
+pub(crate) const MAX_SECTION_NAMES_SIZE: u64 = 1024 * 1024;
+
+/// Mach-O executables have a few dozen load commands.
+pub(crate) const MAX_LOAD_COMMANDS: u64 = 16 * 1024;
+
+/// ELF section headers are 40 or 64 bytes long. The format allows longer ones, but no linker
+/// emits them. Only checked when reading through `Seek`, where the whole table is read at once.
+pub(crate) const MAX_SECTION_HEADER_SIZE: u64 = 256;
+
+/// The load commands of real Mach-O executables take up tens of KiB.
+/// Only checked when reading through `Seek`, where all of them are read at once.
+pub(crate) const MAX_LOAD_COMMANDS_SIZE: u64 = 4 * 1024 * 1024;
+
+pub(crate) fn check_elf(data: &[u8], is_64: bool, byte_order: ByteOrder) -> Result<(), Error> {
+    // Offsets of e_shoff, e_shentsize, e_shnum, e_shstrndx in the ELF header
+    // and of sh_size in the section header, along with the size of the address fields
+    let (shoff, shentsize, shnum, shstrndx, sh_size, word) = if is_64 {
+        (0x28, 0x3A, 0x3C, 0x3E, 0x20, 8)
+    } else {
+        (0x20, 0x2E, 0x30, 0x32, 0x14, 4)
+    };
+    let section_count = match read_uint(data, shnum, 2, byte_order) {
+        Some(count) => count,
+        None => return Ok(()),
+    };
+    if section_count > MAX_SECTIONS {
+        return Err(Error::ImplausibleBinary);
+    }
+    let names_size = read_uint(data, shoff, word, byte_order).and_then(|table_offset| {
+        let entry_size = read_uint(data, shentsize, 2, byte_order)?;
+        let names_index = read_uint(data, shstrndx, 2, byte_order)?;
+        let header = table_offset.checked_add(names_index.checked_mul(entry_size)?)?;
+        let field = usize::try_from(header.checked_add(sh_size)?).ok()?;
+        read_uint(data, field, word, byte_order)
+    });
+    match names_size {
+        Some(size) if size > MAX_SECTION_NAMES_SIZE => Err(Error::ImplausibleBinary),
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn check_pe(data: &[u8]) -> Result<(), Error> {
+    // The DOS header points to the PE signature, followed by the COFF header
+    // where the number of sections follows the 2-byte machine type
+    let section_count = read_uint(data, 0x3C, 4, ByteOrder::LittleEndian)
+        .and_then(|pe_offset| usize::try_from(pe_offset.checked_add(6)?).ok())
+        .and_then(|field| read_uint(data, field, 2, ByteOrder::LittleEndian));
+    match section_count {
+        Some(count) if count > MAX_SECTIONS => Err(Error::ImplausibleBinary),
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn check_macho(data: &[u8]) -> Result<(), Error> {
+    // The magic is written in the byte order of the file
+    let byte_order = match data.get(0..4) {
+        Some([0xfe, 0xed, 0xfa, _]) => ByteOrder::BigEndian,
+        _ => ByteOrder::LittleEndian,
+    };
+    match read_uint(data, 16, 4, byte_order) {
+        Some(count) if count > MAX_LOAD_COMMANDS => Err(Error::ImplausibleBinary),
+        _ => Ok(()),
+    }
+}
+
+/// Reads an unsigned integer of `size` bytes at `offset`, or returns `None` if it is out of bounds
+pub(crate) fn read_uint(
+    data: &[u8],
+    offset: usize,
+    size: usize,
+    byte_order: ByteOrder,
+) -> Option<u64> {
+    let bytes = data.get(offset..offset.checked_add(size)?)?;
+    let fold = |value: u64, byte: &u8| (value << 8) | u64::from(*byte);
+    Some(match byte_order {
+        ByteOrder::BigEndian => bytes.iter().fold(0, fold),
+        ByteOrder::LittleEndian => bytes.iter().rev().fold(0, fold),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elf_section_count() {
+        let mut header = [0u8; 64];
+        // e_shnum of a 64-bit little-endian ELF
+        header[0x3C..0x3E].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert!(matches!(
+            check_elf(&header, true, ByteOrder::LittleEndian),
+            Err(Error::ImplausibleBinary)
+        ));
+        header[0x3C..0x3E].copy_from_slice(&30u16.to_le_bytes());
+        assert!(check_elf(&header, true, ByteOrder::LittleEndian).is_ok());
+    }
+
+    #[test]
+    fn elf_section_names_size() {
+        let mut data = [0u8; 128];
+        // e_shoff = 64, e_shentsize = 64, e_shnum = 1, e_shstrndx = 0
+        data[0x28..0x30].copy_from_slice(&64u64.to_le_bytes());
+        data[0x3A..0x3C].copy_from_slice(&64u16.to_le_bytes());
+        data[0x3C..0x3E].copy_from_slice(&1u16.to_le_bytes());
+        // sh_size of the section name table
+        data[64 + 0x20..64 + 0x28].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(
+            check_elf(&data, true, ByteOrder::LittleEndian),
+            Err(Error::ImplausibleBinary)
+        ));
+    }
+
+    #[test]
+    fn truncated_headers_are_left_to_the_parser() {
+        assert!(check_elf(&[0u8; 16], false, ByteOrder::BigEndian).is_ok());
+        assert!(check_pe(&[0u8; 16]).is_ok());
+        assert!(check_macho(&[0u8; 16]).is_ok());
+    }
+}
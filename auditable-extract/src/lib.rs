@@ -8,7 +8,10 @@
 //! [Mach-O](https://en.wikipedia.org/wiki/Mach-O)) and obtains the compressed audit data.
 //!
 //! Unlike other binary parsing crates, it is specifically designed to be resilient to malicious input.
-//! It 100% safe Rust (including all dependencies) and performs no heap allocations.
+//! It 100% safe Rust (including all dependencies) and performs no heap allocations,
+//! except in [`raw_auditable_data_from_reader`].
+//! The optional `memchr` feature speeds up searching raw memory images for audit data
+//! at the cost of pulling in a dependency that uses `unsafe`.
 //!
 //! ## Usage
 //!
@@ -42,43 +45,310 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! Audit data placed into raw memory images such as firmware images, where there are no
+//! section headers to locate it by, can be extracted with [`framed_auditable_data`] instead
+//! if its location is known, or found with [`framed_auditable_data_candidates`] if it is not.
+//!
+//! If the binary was built with the audit data written to a separate content-addressed store,
+//! the section only contains a pointer to it. Use [`payload_pointer`] to check for that.
+//!
+//! The section is looked up under every name listed in [`KNOWN_SECTION_NAMES`] and
+//! [`KNOWN_MACHO_SECTIONS`]. WebAssembly modules are supported too; in them the audit data
+//! is stored in a custom section.
+//!
+//! Mach-O universal ("fat") binaries are searched slice by slice, and the audit data of the first
+//! slice that has it is returned. The slices are built separately for each architecture,
+//! so their dependency trees may differ slightly; use [`fat_slices`] to inspect each of them.
+//!
+//! Besides the dependency list, the audit data may hold other named payloads.
+//! [`raw_payloads`] lists all of them.
+//!
+//! Audit data copied into a repackaged copy of a binary that lost the audit data section
+//! is stored in a frame at the very end of the file, and is found there if the section is missing.
+//! [`raw_audit_section`] returns the audit data exactly as stored, for copying it,
+//! and [`code_section`] locates the machine code to verify that both files contain the same program.
+//!
+//! Static libraries and rlibs are `ar` archives of object files. They are searched member by member,
+//! and the audit data of the first object file that has it is returned, such as the one
+//! `cargo auditable` bundles into `staticlib` crates. Use [`archive_members`] to inspect
+//! each member. Only ELF and Mach-O object files are supported, so COFF object files
+//! in MSVC `.lib` archives are skipped.
+//!
+//! [`raw_auditable_data_from_reader`] reads only the headers and the audit data from a file
+//! through [`Seek`](std::io::Seek), so even multi-GiB executables need not be loaded into memory.
+//!
+//! [`is_executable`] recognizes the supported formats by their magic bytes,
+//! for skipping other files cheaply when scanning a directory tree.
+//!
+//! [`raw_auditable_data_from_minidump`] extracts the audit data of the executable of a crashed
+//! process from a Windows minidump, provided the dump contains the memory the executable was loaded into.
+//!
+//! [`raw_auditable_data_and_build_id`] also returns the GNU build ID of ELF executables,
+//! for correlating the audit data with crash reports and debug symbols.
 
 use binfarce::Format;
 
+mod ar;
+mod build_id;
+mod code;
+mod container;
+mod fat;
+mod framing;
+mod header_limits;
+mod minidump;
+mod pointer;
+mod section_names;
+mod seek;
+mod wasm;
+
+pub use crate::ar::{archive_members, ArchiveMember, ArchiveMembers, AR_MAGIC};
+
+pub use crate::code::code_section;
+
+pub use crate::container::{payloads, Payload, Payloads, CONTAINER_MAGIC, DEPENDENCIES_PAYLOAD};
+
+pub use crate::fat::{fat_slices, FatSlice, FatSlices, FAT_MAGIC, FAT_MAGIC_64};
+
+pub use crate::minidump::MINIDUMP_MAGIC;
+
+pub use crate::pointer::{payload_pointer, PayloadPointer, POINTER_MAGIC};
+
+pub use crate::seek::{raw_auditable_data_from_reader, ReadError};
+
+pub use crate::section_names::{
+    KNOWN_MACHO_SECTIONS, KNOWN_SECTION_NAMES, MACHO_SEGMENT_NAME, SECTION_NAME,
+};
+
+pub use crate::framing::{
+    find_frame_magic, frame_header, framed_auditable_data, framed_auditable_data_candidates,
+    is_plausible_frame, FrameCandidates, CANDIDATE_CHECK_BUDGET, FRAME_HEADER_LEN, FRAME_MAGIC,
+};
+
+/// First byte of audit data compressed with [Zstandard](https://facebook.github.io/zstd/)
+/// rather than Zlib, which is followed by a single Zstandard frame.
+/// Zlib streams never start with it, so it tells the two apart.
+pub const ZSTD_MARKER: u8 = b'Z';
+
+/// First byte of audit data stored as plain JSON without any compression,
+/// which is the start of the JSON object. Zlib streams never start with it either.
+pub const UNCOMPRESSED_MARKER: u8 = b'{';
+
 /// Extracts the Zlib-compressed dependency info from an executable.
 ///
 /// This function does not allocate any memory on the heap and can be safely given untrusted input.
+/// Files with implausibly large headers are rejected with [`Error::ImplausibleBinary`]
+/// to bound the time spent parsing them.
 pub fn raw_auditable_data(data: &[u8]) -> Result<&[u8], Error> {
+    let (section, _) = audit_data_section(data, false)?;
+    container::dependency_list(unframe(section)?)
+}
+
+/// Lists all the payloads embedded in an executable, such as the Zlib-compressed
+/// dependency list returned by [`raw_auditable_data`] under the name [`DEPENDENCIES_PAYLOAD`].
+///
+/// This function does not allocate any memory on the heap and can be safely given untrusted input.
+pub fn raw_payloads(data: &[u8]) -> Result<Payloads<'_>, Error> {
+    let (section, _) = audit_data_section(data, false)?;
+    Ok(payloads(unframe(section)?))
+}
+
+/// Extracts the Zlib-compressed dependency info from an executable, along with the
+/// GNU build ID (the contents of the `NT_GNU_BUILD_ID` note) if it is an ELF executable that has one.
+///
+/// The executable is only parsed once. The build ID is `None` for other formats,
+/// or if the note is missing or malformed.
+///
+/// This function does not allocate any memory on the heap and can be safely given untrusted input.
+pub fn raw_auditable_data_and_build_id(data: &[u8]) -> Result<(&[u8], Option<&[u8]>), Error> {
+    let (section, build_id) = audit_data_section(data, true)?;
+    Ok((container::dependency_list(unframe(section)?)?, build_id))
+}
+
+/// Extracts the Zlib-compressed dependency info of the main executable of the process
+/// captured in a Windows minidump, for triaging crash reports.
+///
+/// The executable is not stored in the dump as a file, so the audit data is read from the memory
+/// the executable was loaded into. Minidumps only contain that memory if they were written
+/// with e.g. `MiniDumpWithFullMemory`, and [`Error::ModuleNotCaptured`] is returned otherwise.
+///
+/// This function does not allocate any memory on the heap and can be safely given untrusted input.
+pub fn raw_auditable_data_from_minidump(data: &[u8]) -> Result<&[u8], Error> {
+    let section = minidump::main_module_audit_data(data)?;
+    container::dependency_list(unframe(section)?)
+}
+
+/// Number of bytes from the start of a file that [`is_executable`] needs to recognize it
+pub const EXECUTABLE_MAGIC_LEN: usize = 8;
+
+/// Returns `true` if `header`, the first [`EXECUTABLE_MAGIC_LEN`] bytes of a file,
+/// are the magic bytes of an executable format this crate can parse.
+///
+/// Only the magic is checked, so the file may still turn out to be malformed.
+/// This is meant for quickly skipping other files when scanning a directory tree.
+pub fn is_executable(header: &[u8]) -> bool {
+    if fat::is_fat(header) || wasm::is_wasm(header) || ar::is_archive(header) {
+        return true;
+    }
+    matches!(
+        header,
+        [0x7f, b'E', b'L', b'F', ..]
+            | [0xfe, 0xed, 0xfa, 0xce | 0xcf, ..]
+            | [0xce | 0xcf, 0xfa, 0xed, 0xfe, ..]
+            | [b'M', b'Z', ..]
+    )
+}
+
+/// Returns the audit data exactly as it is stored in an executable, without the framing if any.
+///
+/// It may be a container of several payloads or a pointer to a detached payload, so it is
+/// not necessarily Zlib-compressed. This is meant for copying the audit data to another file verbatim.
+///
+/// This function does not allocate any memory on the heap and can be safely given untrusted input.
+pub fn raw_audit_section(data: &[u8]) -> Result<&[u8], Error> {
+    let (section, _) = audit_data_section(data, false)?;
+    unframe(section)
+}
+
+fn unframe(section: &[u8]) -> Result<&[u8], Error> {
+    // The section contents are framed if the audit data was meant to end up in a raw memory image
+    if framing::is_framed(section) {
+        framing::frame_contents(section)
+    } else {
+        Ok(section)
+    }
+}
+
+/// Returns the audit data section, and the GNU build ID if `want_build_id` is set
+fn audit_data_section(data: &[u8], want_build_id: bool) -> Result<(&[u8], Option<&[u8]>), Error> {
+    match sectioned_audit_data(data, want_build_id) {
+        // The audit data may have been copied to the end of a repackaged binary
+        Err(Error::NoAuditData) => match framing::trailing_frame(data) {
+            Some(frame) => Ok((frame, None)),
+            None => Err(Error::NoAuditData),
+        },
+        result => result,
+    }
+}
+
+/// Like [`audit_data_section`], but only looks in the dedicated section
+fn sectioned_audit_data(data: &[u8], want_build_id: bool) -> Result<(&[u8], Option<&[u8]>), Error> {
+    // binfarce does not know about universal binaries either
+    if fat::is_fat(data) {
+        for slice in fat_slices(data)? {
+            // Universal binaries are not nested, so the slices are parsed as regular executables
+            match executable_audit_data_section(slice?.data, want_build_id) {
+                Err(Error::NoAuditData) => continue,
+                result => return result,
+            }
+        }
+        return Err(Error::NoAuditData);
+    }
+    if ar::is_archive(data) {
+        for member in archive_members(data)? {
+            // Archives also hold members that are not object files, such as the metadata of rlibs,
+            // and object files in formats we cannot parse, so failing to parse one is not an error
+            if let Ok(result) = executable_audit_data_section(member?.data, want_build_id) {
+                return Ok(result);
+            }
+        }
+        return Err(Error::NoAuditData);
+    }
+    executable_audit_data_section(data, want_build_id)
+}
+
+/// Like [`audit_data_section`], but for a single-architecture executable
+fn executable_audit_data_section(
+    data: &[u8],
+    want_build_id: bool,
+) -> Result<(&[u8], Option<&[u8]>), Error> {
+    // binfarce does not know about WebAssembly
+    if wasm::is_wasm(data) {
+        let section = find_section(|name| wasm::custom_section(data, name))?;
+        return Ok((section, None));
+    }
     match binfarce::detect_format(data) {
         Format::Elf32 { byte_order } => {
-            let section = binfarce::elf32::parse(data, byte_order)?
-                .section_with_name(".dep-v0")?
-                .ok_or(Error::NoAuditData)?;
-            Ok(data.get(section.range()?).ok_or(Error::UnexpectedEof)?)
+            header_limits::check_elf(data, false, byte_order)?;
+            let elf = binfarce::elf32::parse(data, byte_order)?;
+            let section = find_section(|name| Ok(elf.section_with_name(name)?))?;
+            let section = data.get(section.range()?).ok_or(Error::UnexpectedEof)?;
+            let build_id = if want_build_id {
+                let notes = elf.section_with_name(build_id::BUILD_ID_SECTION);
+                notes
+                    .ok()
+                    .flatten()
+                    .and_then(|notes| notes.range().ok())
+                    .and_then(|range| data.get(range))
+                    .and_then(|notes| build_id::gnu_build_id(notes, byte_order))
+            } else {
+                None
+            };
+            Ok((section, build_id))
         }
         Format::Elf64 { byte_order } => {
-            let section = binfarce::elf64::parse(data, byte_order)?
-                .section_with_name(".dep-v0")?
-                .ok_or(Error::NoAuditData)?;
-            Ok(data.get(section.range()?).ok_or(Error::UnexpectedEof)?)
+            header_limits::check_elf(data, true, byte_order)?;
+            let elf = binfarce::elf64::parse(data, byte_order)?;
+            let section = find_section(|name| Ok(elf.section_with_name(name)?))?;
+            let section = data.get(section.range()?).ok_or(Error::UnexpectedEof)?;
+            let build_id = if want_build_id {
+                let notes = elf.section_with_name(build_id::BUILD_ID_SECTION);
+                notes
+                    .ok()
+                    .flatten()
+                    .and_then(|notes| notes.range().ok())
+                    .and_then(|range| data.get(range))
+                    .and_then(|notes| build_id::gnu_build_id(notes, byte_order))
+            } else {
+                None
+            };
+            Ok((section, build_id))
         }
         Format::Macho => {
+            header_limits::check_macho(data)?;
             let parsed = binfarce::macho::parse(data)?;
-            let section = parsed.section_with_name("__DATA", ".dep-v0")?;
-            let section = section.ok_or(Error::NoAuditData)?;
-            Ok(data.get(section.range()?).ok_or(Error::UnexpectedEof)?)
+            let section =
+                find_macho_section(|segment, name| parsed.section_with_name(segment, name))?;
+            Ok((
+                data.get(section.range()?).ok_or(Error::UnexpectedEof)?,
+                None,
+            ))
         }
         Format::PE => {
+            header_limits::check_pe(data)?;
             let parsed = binfarce::pe::parse(data)?;
-            let section = parsed
-                .section_with_name(".dep-v0")?
-                .ok_or(Error::NoAuditData)?;
-            Ok(data.get(section.range()?).ok_or(Error::UnexpectedEof)?)
+            let section = find_section(|name| Ok(parsed.section_with_name(name)?))?;
+            Ok((
+                data.get(section.range()?).ok_or(Error::UnexpectedEof)?,
+                None,
+            ))
         }
         _ => Err(Error::NotAnExecutable),
     }
 }
 
+/// Returns the first section found under any of the [`KNOWN_SECTION_NAMES`]
+fn find_section<S>(mut lookup: impl FnMut(&str) -> Result<Option<S>, Error>) -> Result<S, Error> {
+    for name in KNOWN_SECTION_NAMES {
+        if let Some(section) = lookup(name)? {
+            return Ok(section);
+        }
+    }
+    Err(Error::NoAuditData)
+}
+
+fn find_macho_section<S>(
+    mut lookup: impl FnMut(&str, &str) -> Result<Option<S>, binfarce::ParseError>,
+) -> Result<S, Error> {
+    for (segment, name) in KNOWN_MACHO_SECTIONS {
+        if let Some(section) = lookup(segment, name)? {
+            return Ok(section);
+        }
+    }
+    Err(Error::NoAuditData)
+}
+
+#[non_exhaustive]
 #[derive(Debug, Copy, Clone)]
 pub enum Error {
     NoAuditData,
@@ -88,6 +358,13 @@ pub enum Error {
     SymbolsSectionIsMissing,
     SectionIsMissing,
     UnexpectedSectionType,
+    /// The headers describe far more sections or load commands than any real executable has.
+    /// The file is most likely corrupted or crafted to slow down parsing.
+    ImplausibleBinary,
+    /// The checksum of framed audit data does not match, e.g. because of flash bit-rot
+    PayloadCorrupted,
+    /// The minidump does not contain the memory the executable was loaded into
+    ModuleNotCaptured,
 }
 
 impl std::error::Error for Error {}
@@ -102,6 +379,13 @@ impl std::fmt::Display for Error {
             Error::SymbolsSectionIsMissing => "Symbols section missing from executable",
             Error::SectionIsMissing => "Section is missing from executable",
             Error::UnexpectedSectionType => "Unexpected executable section type",
+            Error::ImplausibleBinary => {
+                "Implausibly large executable headers, the file is likely corrupted"
+            }
+            Error::PayloadCorrupted => "The audit data is corrupted, its checksum does not match",
+            Error::ModuleNotCaptured => {
+                "The minidump does not contain the memory of the executable, write it with full memory"
+            }
         };
         write!(f, "{message}")
     }
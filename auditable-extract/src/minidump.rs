@@ -0,0 +1,387 @@
+//! Locates the audit data of the main executable in a Windows minidump.
+//!
+//! Minidumps list the modules loaded into the crashed process, and may contain
+//! the memory they were loaded into if the dump was written with e.g. `MiniDumpWithFullMemory`.
+//! The first module is the executable of the process. Its PE headers are read from that memory
+//! to find the audit data section, which is then read from memory as well.
+//! Unlike in the file, sections are located by their virtual address, not their file offset.
+//!
+//! Every step is bounded by the size of the dump, so this needs no limits
+//! beyond the cap on the number of sections shared with the other formats.
+
+use crate::header_limits::{read_uint, MAX_SECTIONS};
+use crate::Error;
+use binfarce::ByteOrder;
+use std::convert::TryFrom;
+
+/// The signature at the start of every minidump
+pub const MINIDUMP_MAGIC: [u8; 4] = *b"MDMP";
+
+const HEADER_LEN: u64 = 32;
+const DIRECTORY_ENTRY_LEN: u64 = 12;
+
+const MODULE_LIST_STREAM: u64 = 4;
+const MEMORY_LIST_STREAM: u64 = 5;
+const MEMORY64_LIST_STREAM: u64 = 9;
+
+/// `MINIDUMP_MODULE` starts with the 8-byte base address and the 4-byte size of the image
+const MODULE_HEADER_LEN: u64 = 12;
+/// Both `MINIDUMP_MEMORY_DESCRIPTOR` and `MINIDUMP_MEMORY_DESCRIPTOR64` are 16 bytes long
+const MEMORY_DESCRIPTOR_LEN: u64 = 16;
+const PE_SECTION_HEADER_LEN: u64 = 40;
+
+pub(crate) fn is_minidump(data: &[u8]) -> bool {
+    data.starts_with(&MINIDUMP_MAGIC)
+}
+
+/// Returns the contents of the audit data section of the main module of the dumped process
+pub(crate) fn main_module_audit_data(data: &[u8]) -> Result<&[u8], Error> {
+    if !is_minidump(data) {
+        return Err(Error::NotAnExecutable);
+    }
+    let streams = Streams::parse(data)?;
+    let module_list = streams.module_list.ok_or(Error::NoAuditData)?;
+    let module_list = location(data, module_list)?;
+    if read_le(module_list, 0, 4)? == 0 {
+        return Err(Error::NoAuditData);
+    }
+    // The module list starts with the number of modules, and the main executable comes first
+    let module = slice(module_list, 4, MODULE_HEADER_LEN)?;
+    let base = read_le(module, 0, 8)?;
+    let image_size = read_le(module, 8, 4)?;
+    let image = Image {
+        data,
+        streams: &streams,
+        base,
+        size: image_size,
+    };
+
+    // The DOS header points to the PE signature, followed by the 20-byte COFF header
+    let dos_header = image.read(0, 0x40)?;
+    if !dos_header.starts_with(b"MZ") {
+        return Err(Error::NotAnExecutable);
+    }
+    let pe_offset = read_le(dos_header, 0x3C, 4)?;
+    let headers = image.read(pe_offset, 24)?;
+    if !headers.starts_with(b"PE\0\0") {
+        return Err(Error::NotAnExecutable);
+    }
+    let section_count = read_le(headers, 6, 2)?;
+    if section_count > MAX_SECTIONS {
+        return Err(Error::ImplausibleBinary);
+    }
+    let optional_header_size = read_le(headers, 20, 2)?;
+    let table_offset = pe_offset + 24 + optional_header_size;
+    let table = image.read(table_offset, section_count * PE_SECTION_HEADER_LEN)?;
+    let (address, size) = crate::find_section(|name| section_with_name(table, name))?;
+    image.read(address, size)
+}
+
+/// Returns the virtual address and size of the section with the given name
+fn section_with_name(table: &[u8], name: &str) -> Result<Option<(u64, u64)>, Error> {
+    // Section headers are always a multiple of the header length, as read in `main_module_audit_data`
+    for header in table.chunks_exact(PE_SECTION_HEADER_LEN as usize) {
+        // Names are padded with zeroes to 8 bytes; longer names are truncated in executables
+        let padded_name = &header[..8];
+        let len = padded_name.iter().position(|&b| b == 0).unwrap_or(8);
+        if padded_name[..len] != *name.as_bytes() {
+            continue;
+        }
+        let virtual_size = read_le(header, 8, 4)?;
+        let address = read_le(header, 12, 4)?;
+        // The virtual size is the exact size of the data, while the size of the raw data
+        // is rounded up to the file alignment. Some linkers leave the former unset.
+        let size = match virtual_size {
+            0 => read_le(header, 16, 4)?,
+            size => size,
+        };
+        return Ok(Some((address, size)));
+    }
+    Ok(None)
+}
+
+/// The memory a module was loaded into, as captured in the dump
+struct Image<'a> {
+    data: &'a [u8],
+    streams: &'a Streams,
+    base: u64,
+    size: u64,
+}
+
+impl<'a> Image<'a> {
+    /// Reads `len` bytes at `offset` from the start of the image
+    fn read(&self, offset: u64, len: u64) -> Result<&'a [u8], Error> {
+        match offset.checked_add(len) {
+            Some(end) if end <= self.size => (),
+            _ => return Err(Error::MalformedFile),
+        }
+        let address = self.base.checked_add(offset).ok_or(Error::MalformedFile)?;
+        self.streams
+            .memory(self.data, address, len)?
+            .ok_or(Error::ModuleNotCaptured)
+    }
+}
+
+/// Locations of the streams we need, as listed in the stream directory
+#[derive(Default)]
+struct Streams {
+    module_list: Option<(u64, u64)>,
+    memory_list: Option<(u64, u64)>,
+    memory64_list: Option<(u64, u64)>,
+}
+
+impl Streams {
+    fn parse(data: &[u8]) -> Result<Self, Error> {
+        let header = slice(data, 0, HEADER_LEN)?;
+        let stream_count = read_le(header, 8, 4)?;
+        let directory_offset = read_le(header, 12, 4)?;
+        let directory = slice(data, directory_offset, stream_count * DIRECTORY_ENTRY_LEN)?;
+        let mut streams = Streams::default();
+        for entry in directory.chunks_exact(DIRECTORY_ENTRY_LEN as usize) {
+            // Each entry is the stream type followed by the size and offset of the stream
+            let location = Some((read_le(entry, 8, 4)?, read_le(entry, 4, 4)?));
+            let stream = match read_le(entry, 0, 4)? {
+                MODULE_LIST_STREAM => &mut streams.module_list,
+                MEMORY_LIST_STREAM => &mut streams.memory_list,
+                MEMORY64_LIST_STREAM => &mut streams.memory64_list,
+                _ => continue,
+            };
+            // Only one stream of each type is allowed, so keep the first one
+            if stream.is_none() {
+                *stream = location;
+            }
+        }
+        Ok(streams)
+    }
+
+    /// Returns the captured memory at `address`, or `None` if it is not in the dump
+    fn memory<'a>(
+        &self,
+        data: &'a [u8],
+        address: u64,
+        len: u64,
+    ) -> Result<Option<&'a [u8]>, Error> {
+        let end = address.checked_add(len).ok_or(Error::MalformedFile)?;
+        let lists = [
+            self.memory_list.map(|list| (list, false)),
+            self.memory64_list.map(|list| (list, true)),
+        ];
+        for (list, is_64) in lists.iter().flatten() {
+            // Sections may span several ranges; that is fine as long as they are contiguous
+            // both in memory and in the file, which is always the case in `Memory64ListStream`
+            let mut run: Option<MemoryRange> = None;
+            for range in MemoryRanges::new(location(data, *list)?, *is_64)? {
+                let range = range?;
+                let current = match run {
+                    Some(run) if run.is_followed_by(&range) => MemoryRange {
+                        size: run.size + range.size,
+                        ..run
+                    },
+                    _ => range,
+                };
+                if current.address <= address && end <= current.address + current.size {
+                    let offset = current.file_offset + (address - current.address);
+                    return slice(data, offset, len).map(Some);
+                }
+                run = Some(current);
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A range of the process memory and where it is stored in the dump
+#[derive(Debug, Clone, Copy)]
+struct MemoryRange {
+    address: u64,
+    size: u64,
+    file_offset: u64,
+}
+
+impl MemoryRange {
+    fn is_followed_by(&self, next: &MemoryRange) -> bool {
+        self.address + self.size == next.address && self.file_offset + self.size == next.file_offset
+    }
+}
+
+/// Walks the descriptors of `MemoryListStream` or `Memory64ListStream`
+struct MemoryRanges<'a> {
+    descriptors: &'a [u8],
+    is_64: bool,
+    /// In `Memory64ListStream` the memory is stored back to back after a common base offset
+    next_offset: u64,
+}
+
+impl<'a> MemoryRanges<'a> {
+    fn new(list: &'a [u8], is_64: bool) -> Result<Self, Error> {
+        let (count, descriptors_offset, base_offset) = if is_64 {
+            (read_le(list, 0, 8)?, 16, read_le(list, 8, 8)?)
+        } else {
+            (read_le(list, 0, 4)?, 4, 0)
+        };
+        let descriptors_len = count
+            .checked_mul(MEMORY_DESCRIPTOR_LEN)
+            .ok_or(Error::UnexpectedEof)?;
+        Ok(MemoryRanges {
+            descriptors: slice(list, descriptors_offset, descriptors_len)?,
+            is_64,
+            next_offset: base_offset,
+        })
+    }
+
+    fn parse(&mut self, descriptor: &[u8]) -> Result<MemoryRange, Error> {
+        let address = read_le(descriptor, 0, 8)?;
+        let (size, file_offset) = if self.is_64 {
+            let size = read_le(descriptor, 8, 8)?;
+            let file_offset = self.next_offset;
+            self.next_offset = file_offset.checked_add(size).ok_or(Error::MalformedFile)?;
+            (size, file_offset)
+        } else {
+            (read_le(descriptor, 8, 4)?, read_le(descriptor, 12, 4)?)
+        };
+        // Ranges wrapping around the address space would break the arithmetic in `Streams::memory`
+        address.checked_add(size).ok_or(Error::MalformedFile)?;
+        file_offset.checked_add(size).ok_or(Error::MalformedFile)?;
+        Ok(MemoryRange {
+            address,
+            size,
+            file_offset,
+        })
+    }
+}
+
+impl Iterator for MemoryRanges<'_> {
+    type Item = Result<MemoryRange, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let len = MEMORY_DESCRIPTOR_LEN as usize;
+        if self.descriptors.len() < len {
+            return None;
+        }
+        let (descriptor, rest) = self.descriptors.split_at(len);
+        self.descriptors = rest;
+        Some(self.parse(descriptor))
+    }
+}
+
+/// Returns the stream at the given location, a pair of its offset and size
+fn location(data: &[u8], (offset, size): (u64, u64)) -> Result<&[u8], Error> {
+    slice(data, offset, size)
+}
+
+fn slice(data: &[u8], offset: u64, len: u64) -> Result<&[u8], Error> {
+    let start = usize::try_from(offset).map_err(|_| Error::UnexpectedEof)?;
+    let len = usize::try_from(len).map_err(|_| Error::UnexpectedEof)?;
+    data.get(start..start.checked_add(len).ok_or(Error::UnexpectedEof)?)
+        .ok_or(Error::UnexpectedEof)
+}
+
+/// Minidumps are always little-endian
+fn read_le(data: &[u8], offset: usize, size: usize) -> Result<u64, Error> {
+    read_uint(data, offset, size, ByteOrder::LittleEndian).ok_or(Error::UnexpectedEof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: u64 = 0x1_4000_0000;
+    const SECTION_ADDRESS: usize = 0x200;
+    const AUDIT_DATA: &[u8] = b"\x78\x9c compressed audit data";
+
+    /// A PE image as it is laid out in memory, with the audit data section at `SECTION_ADDRESS`
+    fn pe_image() -> Vec<u8> {
+        let mut image = vec![0u8; 0x400];
+        image[..2].copy_from_slice(b"MZ");
+        image[0x3C..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        image[0x80..0x84].copy_from_slice(b"PE\0\0");
+        // one section and no optional header
+        image[0x86..0x88].copy_from_slice(&1u16.to_le_bytes());
+        let section = 0x80 + 24;
+        image[section..section + 7].copy_from_slice(b".dep-v0");
+        image[section + 8..section + 12].copy_from_slice(&(AUDIT_DATA.len() as u32).to_le_bytes());
+        image[section + 12..section + 16].copy_from_slice(&(SECTION_ADDRESS as u32).to_le_bytes());
+        image[SECTION_ADDRESS..SECTION_ADDRESS + AUDIT_DATA.len()].copy_from_slice(AUDIT_DATA);
+        image
+    }
+
+    /// A minidump with the module list and a `Memory64ListStream` holding `ranges` of `image`,
+    /// given as offsets and sizes relative to the start of the image
+    fn minidump(image: &[u8], ranges: &[(usize, usize)]) -> Vec<u8> {
+        let module_list_offset = HEADER_LEN + 2 * DIRECTORY_ENTRY_LEN;
+        let module_list_len = 4 + 108;
+        let memory_list_offset = module_list_offset + module_list_len;
+        let memory_list_len = 16 + MEMORY_DESCRIPTOR_LEN * ranges.len() as u64;
+        let memory_offset = memory_list_offset + memory_list_len;
+
+        let mut dump = MINIDUMP_MAGIC.to_vec();
+        dump.extend_from_slice(&0xA793u32.to_le_bytes());
+        dump.extend_from_slice(&2u32.to_le_bytes());
+        dump.extend_from_slice(&(HEADER_LEN as u32).to_le_bytes());
+        dump.resize(HEADER_LEN as usize, 0);
+        for (stream_type, len, offset) in [
+            (MODULE_LIST_STREAM, module_list_len, module_list_offset),
+            (MEMORY64_LIST_STREAM, memory_list_len, memory_list_offset),
+        ] {
+            dump.extend_from_slice(&(stream_type as u32).to_le_bytes());
+            dump.extend_from_slice(&(len as u32).to_le_bytes());
+            dump.extend_from_slice(&(offset as u32).to_le_bytes());
+        }
+        dump.extend_from_slice(&1u32.to_le_bytes());
+        dump.extend_from_slice(&BASE.to_le_bytes());
+        dump.extend_from_slice(&(image.len() as u32).to_le_bytes());
+        dump.resize((module_list_offset + module_list_len) as usize, 0);
+        dump.extend_from_slice(&(ranges.len() as u64).to_le_bytes());
+        dump.extend_from_slice(&memory_offset.to_le_bytes());
+        for (start, len) in ranges {
+            dump.extend_from_slice(&(BASE + *start as u64).to_le_bytes());
+            dump.extend_from_slice(&(*len as u64).to_le_bytes());
+        }
+        for (start, len) in ranges {
+            dump.extend_from_slice(&image[*start..*start + *len]);
+        }
+        dump
+    }
+
+    #[test]
+    fn audit_data_of_main_module() {
+        let image = pe_image();
+        let dump = minidump(&image, &[(0, image.len())]);
+        assert_eq!(main_module_audit_data(&dump).unwrap(), AUDIT_DATA);
+    }
+
+    #[test]
+    fn section_spanning_adjacent_ranges() {
+        let image = pe_image();
+        let split = SECTION_ADDRESS + 4;
+        let dump = minidump(&image, &[(0, split), (split, image.len() - split)]);
+        assert_eq!(main_module_audit_data(&dump).unwrap(), AUDIT_DATA);
+    }
+
+    #[test]
+    fn module_memory_not_captured() {
+        let image = pe_image();
+        let dump = minidump(&image, &[]);
+        assert!(matches!(
+            main_module_audit_data(&dump),
+            Err(Error::ModuleNotCaptured)
+        ));
+        // only the headers were captured
+        let dump = minidump(&image, &[(0, SECTION_ADDRESS)]);
+        assert!(matches!(
+            main_module_audit_data(&dump),
+            Err(Error::ModuleNotCaptured)
+        ));
+    }
+
+    #[test]
+    fn truncated_minidump() {
+        let image = pe_image();
+        let dump = minidump(&image, &[(0, image.len())]);
+        assert!(main_module_audit_data(&dump[..40]).is_err());
+        assert!(matches!(
+            main_module_audit_data(&image),
+            Err(Error::NotAnExecutable)
+        ));
+    }
+}
@@ -0,0 +1,91 @@
+//! Pointers to audit data stored outside of the binary.
+//!
+//! When binary size budgets are extremely tight, `cargo auditable` can write the compressed
+//! audit data to a content-addressed store and only embed a pointer to it:
+//!
+//! ```text
+//! | POINTER_MAGIC (8 bytes) | "sha256:" + hex digest | optionally "\n" + URL of the store |
+//! ```
+//!
+//! The digest is the SHA-256 of the compressed audit data stored in the store.
+
+use crate::Error;
+
+/// Marks audit data that is stored outside of the binary
+pub const POINTER_MAGIC: [u8; 8] = *b"\x7fDEP-PTR";
+
+/// Refers to compressed audit data stored outside of the binary
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PayloadPointer<'a> {
+    /// Lowercase hex-encoded SHA-256 digest of the compressed audit data
+    pub sha256: &'a str,
+    /// Location of the store the audit data was published to, if it was recorded
+    pub url: Option<&'a str>,
+}
+
+/// Parses the pointer to audit data stored outside of the binary.
+///
+/// Returns `Ok(None)` if the audit data is stored in the binary itself.
+///
+/// This function does not allocate any memory on the heap and can be safely given untrusted input.
+pub fn payload_pointer(audit_data: &[u8]) -> Result<Option<PayloadPointer<'_>>, Error> {
+    let data = match audit_data.strip_prefix(&POINTER_MAGIC[..]) {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+    let text = core::str::from_utf8(data).map_err(|_| Error::MalformedFile)?;
+    let (digest, url) = match text.split_once('\n') {
+        Some((digest, url)) => (digest, Some(url)),
+        None => (text, None),
+    };
+    let sha256 = digest.strip_prefix("sha256:").ok_or(Error::MalformedFile)?;
+    let is_valid_digest = sha256.len() == 64
+        && sha256
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+    if !is_valid_digest || url == Some("") {
+        return Err(Error::MalformedFile);
+    }
+    Ok(Some(PayloadPointer { sha256, url }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIGEST: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    fn pointer(text: &str) -> Vec<u8> {
+        let mut result = POINTER_MAGIC.to_vec();
+        result.extend_from_slice(text.as_bytes());
+        result
+    }
+
+    #[test]
+    fn with_url() {
+        let data = pointer(&format!("sha256:{DIGEST}\nhttps://example.com/audit-data"));
+        let parsed = payload_pointer(&data).unwrap().unwrap();
+        assert_eq!(parsed.sha256, DIGEST);
+        assert_eq!(parsed.url, Some("https://example.com/audit-data"));
+    }
+
+    #[test]
+    fn without_url() {
+        let data = pointer(&format!("sha256:{DIGEST}"));
+        let parsed = payload_pointer(&data).unwrap().unwrap();
+        assert_eq!(parsed.sha256, DIGEST);
+        assert_eq!(parsed.url, None);
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(payload_pointer(&pointer("sha256:abc")).is_err());
+        assert!(payload_pointer(&pointer(&format!("md5:{DIGEST}"))).is_err());
+        assert!(payload_pointer(&pointer(&DIGEST.to_uppercase())).is_err());
+    }
+
+    #[test]
+    fn inline_data() {
+        assert_eq!(payload_pointer(&[0x78, 0x9c, 0x01]).unwrap(), None);
+    }
+}
@@ -0,0 +1,18 @@
+//! Names of the section holding the audit data on every platform.
+//!
+//! Extraction tries every known name, so that if a name is ever changed,
+//! binaries built by earlier versions of `cargo auditable` are still recognized.
+//! So far only one name has been used on each platform.
+
+/// Name of the section holding the audit data in ELF and PE executables,
+/// and of the custom section holding it in WebAssembly modules
+pub const SECTION_NAME: &str = ".dep-v0";
+
+/// Name of the Mach-O segment the audit data section belongs to
+pub const MACHO_SEGMENT_NAME: &str = "__DATA";
+
+/// All names the audit data section has been written under, most recent first
+pub const KNOWN_SECTION_NAMES: &[&str] = &[SECTION_NAME];
+
+/// All Mach-O segment and section names the audit data has been written under, most recent first
+pub const KNOWN_MACHO_SECTIONS: &[(&str, &str)] = &[(MACHO_SEGMENT_NAME, SECTION_NAME)];
@@ -0,0 +1,611 @@
+//! Locates the audit data through [`Seek`], reading only the headers and the audit data section
+//! instead of the whole executable.
+//!
+//! Binaries with debug info can be several GiB in size, while the audit data takes up a few KiB.
+//! Parsing them in memory would require loading all of it, so this module reimplements
+//! the section lookup on top of a reader. The header tables are read in one piece each,
+//! so their sizes are capped like in `header_limits`, and the audit data is only read
+//! if it fits within the limit given by the caller. Peak memory use is therefore
+//! a few MiB plus that limit, regardless of the size of the executable.
+
+use crate::header_limits::{
+    read_uint, MAX_LOAD_COMMANDS, MAX_LOAD_COMMANDS_SIZE, MAX_SECTIONS, MAX_SECTION_HEADER_SIZE,
+    MAX_SECTION_NAMES_SIZE,
+};
+use crate::{ar, fat, framing, wasm, Error, KNOWN_MACHO_SECTIONS, KNOWN_SECTION_NAMES};
+use binfarce::ByteOrder;
+use std::io::{Read, Seek, SeekFrom};
+
+/// Long enough for the file header of every supported format, the longest being 64-bit ELF
+const FILE_HEADER_LEN: u64 = 64;
+
+/// Extracts the Zlib-compressed dependency info from an executable like
+/// [`raw_auditable_data`](crate::raw_auditable_data), but reads only the headers
+/// and the audit data through `reader` instead of requiring the whole file in memory.
+///
+/// Audit data larger than `max_size` bytes is rejected with [`ReadError::SectionTooLarge`]
+/// without reading it. Apart from that, at most a few MiB of headers are held in memory
+/// however large the executable is, so it can be safely given untrusted input.
+/// The headers are read in small pieces, so wrap files in a [`BufReader`](std::io::BufReader).
+///
+/// Audit data stored in a frame at the very end of the file is only found
+/// if the whole frame fits within `max_size`.
+pub fn raw_auditable_data_from_reader<R: Read + Seek>(
+    reader: &mut R,
+    max_size: u64,
+) -> Result<Vec<u8>, ReadError> {
+    let section = audit_data_section(reader, max_size)?;
+    let audit_data = crate::container::dependency_list(crate::unframe(&section)?)?;
+    Ok(audit_data.to_vec())
+}
+
+/// Error returned by [`raw_auditable_data_from_reader`]
+#[derive(Debug)]
+pub enum ReadError {
+    /// Reading from or seeking in the input failed
+    Io(std::io::Error),
+    /// The executable is malformed or contains no audit data
+    Parse(Error),
+    /// The audit data is larger than the limit, so it was not read
+    SectionTooLarge,
+}
+
+impl std::error::Error for ReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReadError::Io(e) => Some(e),
+            ReadError::Parse(e) => Some(e),
+            ReadError::SectionTooLarge => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadError::Io(e) => write!(f, "Failed to read the executable: {e}"),
+            ReadError::Parse(e) => write!(f, "{e}"),
+            ReadError::SectionTooLarge => write!(f, "The audit data is over the size limit"),
+        }
+    }
+}
+
+impl From<std::io::Error> for ReadError {
+    fn from(e: std::io::Error) -> Self {
+        ReadError::Io(e)
+    }
+}
+
+impl From<Error> for ReadError {
+    fn from(e: Error) -> Self {
+        ReadError::Parse(e)
+    }
+}
+
+/// Reads the audit data section, or the frame at the end of the file if there is no section
+fn audit_data_section<R: Read + Seek>(reader: &mut R, max_size: u64) -> Result<Vec<u8>, ReadError> {
+    match section_location(reader) {
+        Ok((offset, size)) => {
+            if size > max_size {
+                return Err(ReadError::SectionTooLarge);
+            }
+            read_exact_at(reader, offset, size)
+        }
+        // The audit data may have been copied to the end of a repackaged binary
+        Err(ReadError::Parse(Error::NoAuditData)) => trailing_frame(reader, max_size),
+        Err(e) => Err(e),
+    }
+}
+
+fn trailing_frame<R: Read + Seek>(reader: &mut R, max_size: u64) -> Result<Vec<u8>, ReadError> {
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    let tail_size = file_size.min(max_size.saturating_add(framing::FRAME_HEADER_LEN as u64));
+    let tail = read_exact_at(reader, file_size - tail_size, tail_size)?;
+    match framing::trailing_frame(&tail) {
+        Some(frame) => Ok(frame.to_vec()),
+        None => Err(Error::NoAuditData.into()),
+    }
+}
+
+/// Returns the offset and size of the audit data section in the file
+fn section_location<R: Read + Seek>(reader: &mut R) -> Result<(u64, u64), ReadError> {
+    let header = read_at_most(reader, 0, FILE_HEADER_LEN)?;
+    if ar::is_archive(&header) {
+        return archive_section_location(reader);
+    }
+    if !fat::is_fat(&header) {
+        return executable_section_location(reader, 0, &header);
+    }
+    let is_64 = header.starts_with(&fat::FAT_MAGIC_64.to_be_bytes());
+    let count = field(&header, 4, 4, ByteOrder::BigEndian)?;
+    let entry_len = if is_64 { 32 } else { 20 };
+    let entries = read_exact_at(reader, 8, count * entry_len as u64)?;
+    for entry in entries.chunks_exact(entry_len) {
+        let word = if is_64 { 8 } else { 4 };
+        let offset = field(entry, 8, word, ByteOrder::BigEndian)?;
+        let size = field(entry, 8 + word, word, ByteOrder::BigEndian)?;
+        let slice_end = offset.checked_add(size).ok_or(Error::MalformedFile)?;
+        // Universal binaries are not nested, so the slices are parsed as regular executables
+        let header = read_at_most(reader, offset, FILE_HEADER_LEN.min(size))?;
+        match executable_section_location(reader, offset, &header) {
+            Ok((start, len)) => {
+                return match start.checked_add(len) {
+                    Some(end) if end <= slice_end => Ok((start, len)),
+                    _ => Err(Error::UnexpectedEof.into()),
+                };
+            }
+            Err(ReadError::Parse(Error::NoAuditData)) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(Error::NoAuditData.into())
+}
+
+/// Like [`section_location`], but for the object files in an `ar` archive.
+/// Only the member headers are read until an object file with the audit data is found.
+fn archive_section_location<R: Read + Seek>(reader: &mut R) -> Result<(u64, u64), ReadError> {
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    let mut offset = ar::AR_MAGIC.len() as u64;
+    while offset < file_size {
+        let header = read_exact_at(reader, offset, ar::MEMBER_HEADER_LEN as u64)?;
+        let (raw_name, size) = ar::member_header(&header)?;
+        let data_start = offset + ar::MEMBER_HEADER_LEN as u64;
+        let member_end = data_start
+            .checked_add(size as u64)
+            .ok_or(Error::MalformedFile)?;
+        if member_end > file_size {
+            return Err(Error::UnexpectedEof.into());
+        }
+        // Members start at even offsets
+        offset = member_end + member_end % 2;
+        if ar::is_special_name(raw_name) {
+            continue;
+        }
+        // BSD archives store long names at the start of the member data
+        let base = data_start
+            .checked_add(ar::bsd_name_len(raw_name)? as u64)
+            .filter(|base| *base <= member_end)
+            .ok_or(Error::MalformedFile)?;
+        let header = read_at_most(reader, base, FILE_HEADER_LEN.min(member_end - base))?;
+        // Like in memory, members that are not object files or cannot be parsed are skipped
+        match executable_section_location(reader, base, &header) {
+            Ok((start, len)) => match start.checked_add(len) {
+                Some(end) if end <= member_end => return Ok((start, len)),
+                _ => continue,
+            },
+            Err(ReadError::Io(e)) => return Err(ReadError::Io(e)),
+            Err(_) => continue,
+        }
+    }
+    Err(Error::NoAuditData.into())
+}
+
+/// Like [`section_location`], but for a single-architecture executable starting at `base`
+fn executable_section_location<R: Read + Seek>(
+    reader: &mut R,
+    base: u64,
+    header: &[u8],
+) -> Result<(u64, u64), ReadError> {
+    if wasm::is_wasm(header) {
+        for name in KNOWN_SECTION_NAMES {
+            if let Some(location) = wasm_custom_section(reader, base, name)? {
+                return Ok(location);
+            }
+        }
+        return Err(Error::NoAuditData.into());
+    }
+    match header {
+        [0x7f, b'E', b'L', b'F', class @ (1 | 2), order @ (1 | 2), ..] => {
+            let byte_order = if *order == 1 {
+                ByteOrder::LittleEndian
+            } else {
+                ByteOrder::BigEndian
+            };
+            elf_section(reader, base, header, *class == 2, byte_order)
+        }
+        [0xfe, 0xed, 0xfa, last @ (0xce | 0xcf), ..] => {
+            macho_section(reader, base, header, *last == 0xcf, ByteOrder::BigEndian)
+        }
+        [first @ (0xce | 0xcf), 0xfa, 0xed, 0xfe, ..] => macho_section(
+            reader,
+            base,
+            header,
+            *first == 0xcf,
+            ByteOrder::LittleEndian,
+        ),
+        [b'M', b'Z', ..] => pe_section(reader, base, header),
+        _ => Err(Error::NotAnExecutable.into()),
+    }
+}
+
+fn elf_section<R: Read + Seek>(
+    reader: &mut R,
+    base: u64,
+    header: &[u8],
+    is_64: bool,
+    byte_order: ByteOrder,
+) -> Result<(u64, u64), ReadError> {
+    // Offsets of e_shoff, e_shentsize, e_shnum, e_shstrndx in the ELF header,
+    // of sh_offset and sh_size in the section header, the size of the address fields
+    // and the size of the section header
+    let (shoff, shentsize, shnum, shstrndx, sh_offset, sh_size, word, min_entry_size) = if is_64 {
+        (0x28, 0x3A, 0x3C, 0x3E, 0x18, 0x20, 8, 0x40)
+    } else {
+        (0x20, 0x2E, 0x30, 0x32, 0x10, 0x14, 4, 0x28)
+    };
+    let table_offset = field(header, shoff, word, byte_order)?;
+    let entry_size = field(header, shentsize, 2, byte_order)?;
+    let count = field(header, shnum, 2, byte_order)?;
+    let names_index = field(header, shstrndx, 2, byte_order)?;
+    if count > MAX_SECTIONS || entry_size > MAX_SECTION_HEADER_SIZE {
+        return Err(Error::ImplausibleBinary.into());
+    }
+    if entry_size < min_entry_size {
+        return Err(Error::MalformedFile.into());
+    }
+    let table = read_exact_at(reader, offset(base, table_offset)?, count * entry_size)?;
+    let entries = table.chunks_exact(entry_size as usize);
+    let names_header = entries.clone().nth(names_index as usize);
+    let names_header = names_header.ok_or(Error::MalformedFile)?;
+    let names_size = field(names_header, sh_size, word, byte_order)?;
+    if names_size > MAX_SECTION_NAMES_SIZE {
+        return Err(Error::ImplausibleBinary.into());
+    }
+    let names_offset = field(names_header, sh_offset, word, byte_order)?;
+    let names = read_exact_at(reader, offset(base, names_offset)?, names_size)?;
+    for name in KNOWN_SECTION_NAMES {
+        for entry in entries.clone() {
+            let name_offset = field(entry, 0, 4, byte_order)? as usize;
+            let entry_name = names.get(name_offset..).map(nul_terminated);
+            if entry_name == Some(name.as_bytes()) {
+                let section_offset = field(entry, sh_offset, word, byte_order)?;
+                let size = field(entry, sh_size, word, byte_order)?;
+                return Ok((offset(base, section_offset)?, size));
+            }
+        }
+    }
+    Err(Error::NoAuditData.into())
+}
+
+fn pe_section<R: Read + Seek>(
+    reader: &mut R,
+    base: u64,
+    header: &[u8],
+) -> Result<(u64, u64), ReadError> {
+    const SECTION_HEADER_LEN: usize = 40;
+    // The DOS header points to the PE signature, followed by the COFF header
+    // holding the number of sections and the size of the optional header
+    let pe_offset = offset(base, field(header, 0x3C, 4, ByteOrder::LittleEndian)?)?;
+    let coff_header = read_exact_at(reader, pe_offset, 24)?;
+    if !coff_header.starts_with(b"PE\0\0") {
+        return Err(Error::MalformedFile.into());
+    }
+    let count = field(&coff_header, 6, 2, ByteOrder::LittleEndian)?;
+    if count > MAX_SECTIONS {
+        return Err(Error::ImplausibleBinary.into());
+    }
+    let optional_header_size = field(&coff_header, 20, 2, ByteOrder::LittleEndian)?;
+    let table_offset = offset(pe_offset, 24 + optional_header_size)?;
+    let table = read_exact_at(reader, table_offset, count * SECTION_HEADER_LEN as u64)?;
+    for name in KNOWN_SECTION_NAMES {
+        // Names longer than 8 bytes are stored elsewhere, but none of the known ones are
+        for entry in table.chunks_exact(SECTION_HEADER_LEN) {
+            if nul_terminated(&entry[..8]) == name.as_bytes() {
+                let virtual_size = field(entry, 8, 4, ByteOrder::LittleEndian)?;
+                let raw_size = field(entry, 16, 4, ByteOrder::LittleEndian)?;
+                let raw_offset = field(entry, 20, 4, ByteOrder::LittleEndian)?;
+                // The raw data is padded to the file alignment,
+                // while the virtual size is the size of the actual contents
+                let size = match virtual_size {
+                    0 => raw_size,
+                    _ => virtual_size.min(raw_size),
+                };
+                return Ok((offset(base, raw_offset)?, size));
+            }
+        }
+    }
+    Err(Error::NoAuditData.into())
+}
+
+fn macho_section<R: Read + Seek>(
+    reader: &mut R,
+    base: u64,
+    header: &[u8],
+    is_64: bool,
+    byte_order: ByteOrder,
+) -> Result<(u64, u64), ReadError> {
+    // The LC_SEGMENT(_64) command, the offsets of the section count and of the first section in it,
+    // the size of a section header and the offset and width of its size field, followed by the offset
+    let (segment_command, nsects, first_section, section_len, size_field, word) = if is_64 {
+        (0x19, 64, 72, 80, 40, 8)
+    } else {
+        (0x1, 48, 56, 68, 36, 4)
+    };
+    let count = field(header, 16, 4, byte_order)?;
+    let commands_size = field(header, 20, 4, byte_order)?;
+    if count > MAX_LOAD_COMMANDS || commands_size > MAX_LOAD_COMMANDS_SIZE {
+        return Err(Error::ImplausibleBinary.into());
+    }
+    let header_len = if is_64 { 32 } else { 28 };
+    let commands = read_exact_at(reader, offset(base, header_len)?, commands_size)?;
+    for (segment, name) in KNOWN_MACHO_SECTIONS {
+        let mut rest = &commands[..];
+        for _ in 0..count {
+            let command_size = field(rest, 4, 4, byte_order)? as usize;
+            let command = rest.get(..command_size).ok_or(Error::UnexpectedEof)?;
+            if field(command, 0, 4, byte_order)? == segment_command {
+                let section_count = field(command, nsects, 4, byte_order)? as usize;
+                let sections = command.get(first_section..).ok_or(Error::MalformedFile)?;
+                for section in sections.chunks_exact(section_len).take(section_count) {
+                    if nul_terminated(&section[..16]) == name.as_bytes()
+                        && nul_terminated(&section[16..32]) == segment.as_bytes()
+                    {
+                        let size = field(section, size_field, word, byte_order)?;
+                        let section_offset = field(section, size_field + word, 4, byte_order)?;
+                        return Ok((offset(base, section_offset)?, size));
+                    }
+                }
+            }
+            rest = &rest[command_size..];
+        }
+    }
+    Err(Error::NoAuditData.into())
+}
+
+/// Walks the sections of a WebAssembly module and returns the location of the contents
+/// of the first custom section with the given name, or `None` if there is none
+fn wasm_custom_section<R: Read + Seek>(
+    reader: &mut R,
+    base: u64,
+    name: &str,
+) -> Result<Option<(u64, u64)>, ReadError> {
+    // Skip the magic and the 4-byte version
+    let mut position = offset(base, 8)?;
+    loop {
+        // The ID is followed by the size, which takes at most 5 bytes
+        let start = read_at_most(reader, position, 6)?;
+        let (&id, mut after_id) = match start.split_first() {
+            Some(split) => split,
+            None => return Ok(None),
+        };
+        let size = wasm::read_leb128_u32(&mut after_id)? as u64;
+        let contents = offset(position, (start.len() - after_id.len()) as u64)?;
+        if id == wasm::CUSTOM_SECTION_ID {
+            // Only the beginning of the section is needed to compare the name
+            let name_len = name.len() as u64;
+            let prefix = read_at_most(reader, contents, size.min(5 + name_len))?;
+            let mut after_len = &prefix[..];
+            let section_name_len = wasm::read_leb128_u32(&mut after_len)? as u64;
+            if section_name_len == name_len && after_len.starts_with(name.as_bytes()) {
+                let header_len = (prefix.len() - after_len.len()) as u64 + name_len;
+                let data_size = size.checked_sub(header_len).ok_or(Error::MalformedFile)?;
+                return Ok(Some((offset(contents, header_len)?, data_size)));
+            }
+        }
+        position = offset(contents, size)?;
+    }
+}
+
+/// Returns the part of a name field or name table entry before the terminating NUL, if any
+fn nul_terminated(name: &[u8]) -> &[u8] {
+    match name.iter().position(|&byte| byte == 0) {
+        Some(end) => &name[..end],
+        None => name,
+    }
+}
+
+/// Reads an unsigned integer of `size` bytes from a header that has already been read
+fn field(data: &[u8], offset: usize, size: usize, byte_order: ByteOrder) -> Result<u64, Error> {
+    read_uint(data, offset, size, byte_order).ok_or(Error::UnexpectedEof)
+}
+
+fn offset(base: u64, relative: u64) -> Result<u64, Error> {
+    base.checked_add(relative).ok_or(Error::MalformedFile)
+}
+
+fn read_exact_at<R: Read + Seek>(
+    reader: &mut R,
+    offset: u64,
+    len: u64,
+) -> Result<Vec<u8>, ReadError> {
+    let data = read_at_most(reader, offset, len)?;
+    if (data.len() as u64) < len {
+        return Err(Error::UnexpectedEof.into());
+    }
+    Ok(data)
+}
+
+/// Reads up to `len` bytes at `offset`, fewer if the input ends first
+fn read_at_most<R: Read + Seek>(
+    reader: &mut R,
+    offset: u64,
+    len: u64,
+) -> Result<Vec<u8>, ReadError> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut data = Vec::new();
+    reader.by_ref().take(len).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const AUDIT_DATA: &[u8] = b"compressed audit data";
+
+    fn extract(data: Vec<u8>, max_size: u64) -> Result<Vec<u8>, ReadError> {
+        raw_auditable_data_from_reader(&mut Cursor::new(data), max_size)
+    }
+
+    /// A 64-bit little-endian ELF file with a null section, the names table and the audit data,
+    /// separated from the headers by `padding` bytes that must never be read
+    fn elf(padding: usize) -> Vec<u8> {
+        let names = b"\0.shstrtab\0.dep-v0\0";
+        let names_offset = 64 + padding;
+        let data_offset = names_offset + names.len();
+        let table_offset = data_offset + AUDIT_DATA.len();
+        let mut data = vec![0u8; table_offset + 3 * 64];
+        data[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        data[0x28..0x30].copy_from_slice(&(table_offset as u64).to_le_bytes());
+        data[0x3A..0x3C].copy_from_slice(&64u16.to_le_bytes());
+        data[0x3C..0x3E].copy_from_slice(&3u16.to_le_bytes());
+        data[0x3E..0x40].copy_from_slice(&1u16.to_le_bytes());
+        data[names_offset..data_offset].copy_from_slice(names);
+        data[data_offset..table_offset].copy_from_slice(AUDIT_DATA);
+        let sections = [
+            (1, names_offset, names.len()),
+            (11, data_offset, AUDIT_DATA.len()),
+        ];
+        for (index, (name, offset, size)) in sections.iter().enumerate() {
+            let header = table_offset + (index + 1) * 64;
+            data[header..header + 4].copy_from_slice(&(*name as u32).to_le_bytes());
+            data[header + 0x18..header + 0x20].copy_from_slice(&(*offset as u64).to_le_bytes());
+            data[header + 0x20..header + 0x28].copy_from_slice(&(*size as u64).to_le_bytes());
+        }
+        data
+    }
+
+    /// Fails the test if anything outside of the given ranges is read
+    struct Guarded {
+        inner: Cursor<Vec<u8>>,
+        forbidden: std::ops::Range<u64>,
+    }
+
+    impl Read for Guarded {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let position = self.inner.position();
+            let end = position + buf.len() as u64;
+            assert!(end <= self.forbidden.start || position >= self.forbidden.end);
+            self.inner.read(buf)
+        }
+    }
+
+    impl Seek for Guarded {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn elf_headers_and_section_only() {
+        let padding = 1024 * 1024;
+        let mut reader = Guarded {
+            inner: Cursor::new(elf(padding)),
+            forbidden: 64..64 + padding as u64,
+        };
+        let audit_data = raw_auditable_data_from_reader(&mut reader, 1024).unwrap();
+        assert_eq!(audit_data, AUDIT_DATA);
+    }
+
+    #[test]
+    fn size_limit() {
+        assert!(matches!(
+            extract(elf(0), AUDIT_DATA.len() as u64 - 1),
+            Err(ReadError::SectionTooLarge)
+        ));
+        assert_eq!(
+            extract(elf(0), AUDIT_DATA.len() as u64).unwrap(),
+            AUDIT_DATA
+        );
+    }
+
+    #[test]
+    fn pe() {
+        let mut data = vec![0u8; 0x200];
+        data[..2].copy_from_slice(b"MZ");
+        data[0x3C..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        data[0x80..0x84].copy_from_slice(b"PE\0\0");
+        data[0x86..0x88].copy_from_slice(&1u16.to_le_bytes());
+        // no optional header, so the section table follows the COFF header
+        let section = 0x80 + 24;
+        data[section..section + 7].copy_from_slice(b".dep-v0");
+        data[section + 8..section + 12].copy_from_slice(&(AUDIT_DATA.len() as u32).to_le_bytes());
+        // the raw data is padded to the file alignment
+        data[section + 16..section + 20].copy_from_slice(&0x100u32.to_le_bytes());
+        data[section + 20..section + 24].copy_from_slice(&0x100u32.to_le_bytes());
+        data[0x100..0x100 + AUDIT_DATA.len()].copy_from_slice(AUDIT_DATA);
+        assert_eq!(extract(data, 1024).unwrap(), AUDIT_DATA);
+    }
+
+    #[test]
+    fn macho_in_fat_binary() {
+        let mut macho = vec![0u8; 32 + 72 + 80];
+        macho[..4].copy_from_slice(&[0xcf, 0xfa, 0xed, 0xfe]);
+        macho[16..20].copy_from_slice(&1u32.to_le_bytes());
+        macho[20..24].copy_from_slice(&(72u32 + 80).to_le_bytes());
+        let command = 32;
+        macho[command..command + 4].copy_from_slice(&0x19u32.to_le_bytes());
+        macho[command + 4..command + 8].copy_from_slice(&(72u32 + 80).to_le_bytes());
+        macho[command + 64..command + 68].copy_from_slice(&1u32.to_le_bytes());
+        let section = command + 72;
+        macho[section..section + 7].copy_from_slice(b".dep-v0");
+        macho[section + 16..section + 22].copy_from_slice(b"__DATA");
+        macho[section + 40..section + 48].copy_from_slice(&(AUDIT_DATA.len() as u64).to_le_bytes());
+        let data_offset = macho.len() as u32;
+        macho[section + 48..section + 52].copy_from_slice(&data_offset.to_le_bytes());
+        macho.extend_from_slice(AUDIT_DATA);
+        assert_eq!(extract(macho.clone(), 1024).unwrap(), AUDIT_DATA);
+
+        let mut fat = fat::FAT_MAGIC.to_be_bytes().to_vec();
+        fat.extend_from_slice(&1u32.to_be_bytes());
+        // cpu type and subtype, offset, size and alignment
+        for value in &[0x0100000c, 0, 0x1000, macho.len() as u32, 12] {
+            fat.extend_from_slice(&u32::to_be_bytes(*value));
+        }
+        fat.resize(0x1000, 0);
+        fat.extend_from_slice(&macho);
+        assert_eq!(extract(fat, 1024).unwrap(), AUDIT_DATA);
+    }
+
+    #[test]
+    fn wasm_custom_section() {
+        let mut data = b"\0asm\x01\0\0\0".to_vec();
+        data.extend_from_slice(b"\x00\x07\x04name\x01\x02");
+        data.push(0);
+        data.push((8 + AUDIT_DATA.len()) as u8);
+        data.extend_from_slice(b"\x07.dep-v0");
+        data.extend_from_slice(AUDIT_DATA);
+        assert_eq!(extract(data, 1024).unwrap(), AUDIT_DATA);
+    }
+
+    #[test]
+    fn trailing_frame_fallback() {
+        let mut data = elf(0);
+        // drop the audit data section by renaming it
+        data[64 + 12] = b'x';
+        assert!(matches!(
+            extract(data.clone(), 1024),
+            Err(ReadError::Parse(Error::NoAuditData))
+        ));
+        data.extend_from_slice(&framing::frame_header(AUDIT_DATA).unwrap());
+        data.extend_from_slice(AUDIT_DATA);
+        assert_eq!(extract(data.clone(), 1024).unwrap(), AUDIT_DATA);
+        // the frame is only looked for within the size limit from the end of the file
+        assert!(extract(data, AUDIT_DATA.len() as u64 - 1).is_err());
+    }
+
+    #[test]
+    fn object_file_in_archive() {
+        let data = ar::tests::archive(&[
+            ("lib.rmeta", b"not an object file"),
+            ("staticlib_crate_audit_data.o", &elf(0)),
+        ]);
+        assert_eq!(crate::raw_audit_section(&data).unwrap(), AUDIT_DATA);
+        assert_eq!(extract(data, 1024).unwrap(), AUDIT_DATA);
+
+        let data = ar::tests::archive(&[("lib.rmeta", b"not an object file")]);
+        assert!(matches!(
+            extract(data, 1024),
+            Err(ReadError::Parse(Error::NoAuditData))
+        ));
+    }
+
+    #[test]
+    fn not_an_executable() {
+        assert!(matches!(
+            extract(b"definitely not an executable".to_vec(), 1024),
+            Err(ReadError::Parse(Error::NotAnExecutable))
+        ));
+    }
+}
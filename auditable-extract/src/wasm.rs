@@ -0,0 +1,130 @@
+//! Locates custom sections in WebAssembly modules.
+//!
+//! Unlike the other formats, WebAssembly has no section headers to look names up in,
+//! so the sections are walked one by one. Each step is constant time, so this needs no limits.
+
+use crate::Error;
+use std::convert::TryFrom;
+
+const WASM_MAGIC: &[u8] = b"\0asm";
+
+/// Custom sections have ID 0 and start with their name
+pub(crate) const CUSTOM_SECTION_ID: u8 = 0;
+
+pub(crate) fn is_wasm(data: &[u8]) -> bool {
+    data.starts_with(WASM_MAGIC)
+}
+
+/// Returns the contents of the first custom section with the given name
+pub(crate) fn custom_section<'a>(data: &'a [u8], name: &str) -> Result<Option<&'a [u8]>, Error> {
+    // Skip the magic and the 4-byte version
+    let mut rest = data.get(8..).ok_or(Error::UnexpectedEof)?;
+    while let Some((id, contents)) = next_section(&mut rest)? {
+        if id == CUSTOM_SECTION_ID {
+            let mut contents = contents;
+            let name_len = read_leb128_u32(&mut contents)?;
+            let section_name = contents.get(..name_len).ok_or(Error::MalformedFile)?;
+            if section_name == name.as_bytes() {
+                return Ok(Some(&contents[name_len..]));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the contents of the first section with the given ID
+pub(crate) fn section_with_id(data: &[u8], wanted_id: u8) -> Result<Option<&[u8]>, Error> {
+    let mut rest = data.get(8..).ok_or(Error::UnexpectedEof)?;
+    while let Some((id, contents)) = next_section(&mut rest)? {
+        if id == wanted_id {
+            return Ok(Some(contents));
+        }
+    }
+    Ok(None)
+}
+
+/// Reads the ID and contents of the next section and advances past it
+fn next_section<'a>(rest: &mut &'a [u8]) -> Result<Option<(u8, &'a [u8])>, Error> {
+    let (&id, mut after_id) = match rest.split_first() {
+        Some(split) => split,
+        None => return Ok(None),
+    };
+    let size = read_leb128_u32(&mut after_id)?;
+    let contents = after_id.get(..size).ok_or(Error::UnexpectedEof)?;
+    *rest = &after_id[size..];
+    Ok(Some((id, contents)))
+}
+
+/// Reads an unsigned LEB128-encoded 32-bit integer and advances past it
+pub(crate) fn read_leb128_u32(data: &mut &[u8]) -> Result<usize, Error> {
+    let mut result: u32 = 0;
+    // A 32-bit integer takes at most 5 bytes, and the last one only has 4 significant bits
+    for i in 0..5 {
+        let byte = *data.get(i).ok_or(Error::UnexpectedEof)?;
+        let bits = u32::from(byte & 0x7f);
+        if i == 4 && bits > 0x0f {
+            return Err(Error::MalformedFile);
+        }
+        result |= bits << (7 * i);
+        if byte & 0x80 == 0 {
+            *data = &data[i + 1..];
+            return usize::try_from(result).map_err(|_| Error::UnexpectedEof);
+        }
+    }
+    Err(Error::MalformedFile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(sections: &[(u8, &[u8])]) -> Vec<u8> {
+        let mut data = b"\0asm\x01\0\0\0".to_vec();
+        for (id, contents) in sections {
+            data.push(*id);
+            assert!(contents.len() < 16384);
+            let len = contents.len();
+            if len < 128 {
+                data.push(len as u8);
+            } else {
+                data.push((len & 0x7f) as u8 | 0x80);
+                data.push((len >> 7) as u8);
+            }
+            data.extend_from_slice(contents);
+        }
+        data
+    }
+
+    #[test]
+    fn custom_sections() {
+        // Longer than 127 bytes so that its size takes two bytes to encode
+        let mut audit_section = b"\x07.dep-v0".to_vec();
+        audit_section.extend_from_slice(&[0xaa; 200]);
+        let data = module(&[
+            (1, b"\x00"),
+            (CUSTOM_SECTION_ID, b"\x04name\x01\x02"),
+            (CUSTOM_SECTION_ID, &audit_section),
+        ]);
+        assert!(is_wasm(&data));
+        let contents = custom_section(&data, ".dep-v0").unwrap().unwrap();
+        assert_eq!(contents, &[0xaa; 200][..]);
+        assert!(custom_section(&data, ".missing").unwrap().is_none());
+        assert_eq!(section_with_id(&data, 1).unwrap().unwrap(), b"\x00");
+        assert!(section_with_id(&data, 10).unwrap().is_none());
+    }
+
+    #[test]
+    fn truncated_module() {
+        let data = module(&[(CUSTOM_SECTION_ID, b"\x07.dep-v0\x01")]);
+        let truncated = &data[..data.len() - 1];
+        assert!(matches!(
+            custom_section(truncated, ".dep-v0"),
+            Err(Error::UnexpectedEof)
+        ));
+        let overlong = b"\0asm\x01\0\0\0\x00\xff\xff\xff\xff\xff";
+        assert!(matches!(
+            custom_section(overlong, ".dep-v0"),
+            Err(Error::MalformedFile)
+        ));
+    }
+}
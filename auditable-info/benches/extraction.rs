@@ -0,0 +1,66 @@
+//! Benchmarks of the hot paths: locating the audit data in binaries of various sizes,
+//! and decompressing and parsing audit data with various numbers of packages.
+//!
+//! Run with `cargo bench --features bench`.
+
+use auditable_info::synthetic::{SyntheticBinary, SyntheticFormat};
+use auditable_info::{audit_info_from_slice, json_from_seekable_reader, json_from_slice, Limits};
+use auditable_serde::VersionInfo;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::io::Cursor;
+use std::str::FromStr;
+
+const MIB: usize = 1024 * 1024;
+
+/// Extraction from binaries of growing size with the same audit data,
+/// which should take constant time since only the headers are parsed
+fn binary_size(c: &mut Criterion) {
+    let limit = Limits::default().decompressed_json_size;
+    let mut group = c.benchmark_group("binary_size");
+    for &format in SyntheticFormat::ALL {
+        for &size in &[MIB, 16 * MIB, 256 * MIB] {
+            let data = SyntheticBinary::new(format).size(size).build();
+            let id = BenchmarkId::new(format.name(), format!("{}MiB", size / MIB));
+            group.bench_with_input(id, &data, |b, data| {
+                b.iter(|| json_from_slice(data, limit).unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Extraction through `Seek`, which reads only the headers and the audit data
+fn seekable_reader(c: &mut Criterion) {
+    let mut group = c.benchmark_group("seekable_reader");
+    for &format in SyntheticFormat::ALL {
+        let data = SyntheticBinary::new(format).size(256 * MIB).build();
+        group.bench_function(format.name(), |b| {
+            b.iter(|| {
+                json_from_seekable_reader(&mut Cursor::new(&data), Limits::default()).unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Decompression and parsing of audit data with a growing number of packages
+fn package_count(c: &mut Criterion) {
+    let limit = Limits::default().decompressed_json_size;
+    let mut group = c.benchmark_group("package_count");
+    for &packages in &[10, 100, 1000, 10_000] {
+        let binary = SyntheticBinary::new(SyntheticFormat::Elf).packages(packages);
+        let data = binary.build();
+        group.throughput(Throughput::Elements(packages as u64));
+        group.bench_with_input(BenchmarkId::new("extract", packages), &data, |b, data| {
+            b.iter(|| audit_info_from_slice(data, limit).unwrap())
+        });
+        let json = json_from_slice(&data, limit).unwrap();
+        group.bench_with_input(BenchmarkId::new("parse", packages), &json, |b, json| {
+            b.iter(|| VersionInfo::from_str(json).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, binary_size, seekable_reader, package_count);
+criterion_main!(benches);
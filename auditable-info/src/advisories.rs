@@ -0,0 +1,261 @@
+//! Checking the audit data against the [RustSec advisory database](https://rustsec.org/),
+//! enabled by the `advisories` feature.
+//!
+//! This module does not perform any network access. The caller opens a local copy of the
+//! [advisory database](https://github.com/rustsec/advisory-db) with [`rustsec::Database::open`],
+//! or fetches it with `rustsec::Database::fetch` if the `git` feature of `rustsec` is enabled.
+//!
+//! Other advisory databases, such as internal feeds, can be plugged in by implementing
+//! [`AdvisorySource`]. [`findings`] checks the audit data against several sources at once
+//! and merges their findings. The RustSec [`Database`] implements it, and so does
+//! [`OsvDatabase`] for advisories in the [OSV format](https://ossf.github.io/osv-schema/).
+
+use auditable_serde::{Package, VersionInfo};
+use rustsec::cargo_lock::{self, Lockfile};
+use rustsec::{Database, Vulnerability};
+use std::convert::TryFrom;
+
+pub use crate::osv::{OsvDatabase, OsvError};
+
+/// Error returned by an [`AdvisorySource`]
+pub type SourceError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A database of security advisories that the audit data can be checked against
+pub trait AdvisorySource {
+    /// Name of the source shown in reports, e.g. `RustSec`
+    fn name(&self) -> &str;
+
+    /// Lists the packages in the audit data affected by advisories in this source,
+    /// each with the advisory affecting it
+    fn affected<'a>(
+        &self,
+        info: &'a VersionInfo,
+    ) -> Result<Vec<(&'a Package, Advisory)>, SourceError>;
+}
+
+/// A security advisory, independent of the database it comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Advisory {
+    /// The ID of the advisory in its database, e.g. `RUSTSEC-2020-0071`
+    pub id: String,
+    /// Other IDs of the same advisory, e.g. CVE or GHSA IDs
+    pub aliases: Vec<String>,
+    pub title: String,
+}
+
+impl Advisory {
+    /// Whether both advisories describe the same issue, judging by their IDs and aliases
+    pub fn is_same_as(&self, other: &Advisory) -> bool {
+        self.ids()
+            .any(|id| other.ids().any(|other_id| other_id == id))
+    }
+
+    fn ids(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.id).chain(&self.aliases)
+    }
+}
+
+/// A package affected by an advisory, as reported by [`findings`]
+#[derive(Debug, Clone)]
+pub struct Finding<'a> {
+    pub package: &'a Package,
+    pub advisory: Advisory,
+    /// The [`AdvisorySource::name`] of the source that reported it
+    pub source: String,
+}
+
+/// Checks the audit data against every source and merges their findings.
+///
+/// An advisory reported for the same package by several sources, e.g. by RustSec and by
+/// an OSV export of RustSec, is only reported once, by the first source in `sources`.
+/// Advisories are recognized as the same if their IDs or aliases overlap.
+/// Errors are prefixed with the name of the source that failed.
+pub fn findings<'a>(
+    info: &'a VersionInfo,
+    sources: &[&dyn AdvisorySource],
+) -> Result<Vec<Finding<'a>>, SourceError> {
+    let mut result: Vec<Finding<'a>> = Vec::new();
+    for source in sources {
+        let affected = source
+            .affected(info)
+            .map_err(|e| format!("{}: {e}", source.name()))?;
+        for (package, advisory) in affected {
+            let is_duplicate = result.iter().any(|finding| {
+                std::ptr::eq(finding.package, package) && finding.advisory.is_same_as(&advisory)
+            });
+            if !is_duplicate {
+                result.push(Finding {
+                    package,
+                    advisory,
+                    source: source.name().to_owned(),
+                });
+            }
+        }
+    }
+    Ok(result)
+}
+
+impl AdvisorySource for Database {
+    fn name(&self) -> &str {
+        "RustSec"
+    }
+
+    fn affected<'a>(
+        &self,
+        info: &'a VersionInfo,
+    ) -> Result<Vec<(&'a Package, Advisory)>, SourceError> {
+        Ok(vulnerabilities(info, self)?
+            .into_iter()
+            .map(|affected| {
+                let advisory = &affected.vulnerability.advisory;
+                let advisory = Advisory {
+                    id: advisory.id.to_string(),
+                    aliases: advisory.aliases.iter().map(ToString::to_string).collect(),
+                    title: advisory.title.clone(),
+                };
+                (affected.package, advisory)
+            })
+            .collect())
+    }
+}
+
+/// A package in the audit data that is affected by a security advisory
+#[derive(Debug, Clone)]
+pub struct AffectedPackage<'a> {
+    pub package: &'a Package,
+    /// The advisory and the versions of the package that fix it
+    pub vulnerability: Vulnerability,
+}
+
+/// Lists the packages in the audit data affected by the advisories in `database`.
+///
+/// Informational advisories, such as notices of unmaintained crates, and withdrawn advisories
+/// are not reported. Build dependencies are reported too, since they can affect the binary
+/// during the build; check [`Package::kind`] to tell them apart.
+///
+/// ```rust, ignore
+/// let database = rustsec::Database::open(Path::new("advisory-db"))?;
+/// let info = audit_info_from_file(Path::new("path/to/file"), Default::default())?;
+/// for affected in vulnerabilities(&info, &database)? {
+///     let advisory = &affected.vulnerability.advisory;
+///     println!("{} {}: {} {}", affected.package.name, affected.package.version, advisory.id, advisory.title);
+/// }
+/// ```
+pub fn vulnerabilities<'a>(
+    info: &'a VersionInfo,
+    database: &Database,
+) -> Result<Vec<AffectedPackage<'a>>, cargo_lock::Error> {
+    // The database is queried with the same lockfile representation `cargo audit` uses
+    let lockfile = Lockfile::try_from(info)?;
+    let mut vulnerabilities = database.vulnerabilities(&lockfile);
+    // Packages with the same name and version but different sources are separate packages
+    // in the audit data but are reported once per entry in the lockfile, so deduplicate them
+    vulnerabilities.sort_by(|a, b| {
+        (&a.package.name, &a.package.version, &a.advisory.id).cmp(&(
+            &b.package.name,
+            &b.package.version,
+            &b.advisory.id,
+        ))
+    });
+    vulnerabilities.dedup_by(|a, b| {
+        (&a.package.name, &a.package.version, &a.advisory.id)
+            == (&b.package.name, &b.package.version, &b.advisory.id)
+    });
+    let mut affected = Vec::new();
+    for vulnerability in vulnerabilities {
+        let packages = info.packages.iter().filter(|package| {
+            package.name == vulnerability.package.name.as_str()
+                && package.version == vulnerability.package.version
+        });
+        for package in packages {
+            affected.push(AffectedPackage {
+                package,
+                vulnerability: vulnerability.clone(),
+            });
+        }
+    }
+    Ok(affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::str::FromStr;
+
+    const ADVISORY: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-2020-0001"
+package = "vulnerable"
+date = "2020-01-01"
+
+[versions]
+patched = [">= 1.0.1"]
+```
+
+# Memory corruption in `vulnerable`
+
+The description.
+"#;
+
+    #[test]
+    fn affected_packages() {
+        let root =
+            std::env::temp_dir().join(format!("auditable-advisories-{}", std::process::id()));
+        let crate_dir = root.join("crates").join("vulnerable");
+        fs::create_dir_all(&crate_dir).unwrap();
+        fs::write(crate_dir.join("RUSTSEC-2020-0001.md"), ADVISORY).unwrap();
+        let database = Database::open(&root).unwrap();
+
+        let info = VersionInfo::from_str(
+            r#"{"packages":[
+            {"name":"vulnerable","version":"1.0.0","source":"crates.io"},
+            {"name":"fixed","version":"1.0.0","source":"crates.io"},
+            {"name":"app","version":"0.1.0","source":"local","root":true,"dependencies":[0,1]}
+            ]}"#,
+        )
+        .unwrap();
+        let affected = vulnerabilities(&info, &database).unwrap();
+        assert_eq!(affected.len(), 1);
+        assert_eq!(affected[0].package.name, "vulnerable");
+        assert_eq!(
+            affected[0].vulnerability.advisory.id.as_str(),
+            "RUSTSEC-2020-0001"
+        );
+
+        let info = VersionInfo::from_str(
+            r#"{"packages":[
+            {"name":"vulnerable","version":"1.0.1","source":"crates.io"},
+            {"name":"app","version":"0.1.0","source":"local","root":true,"dependencies":[0]}
+            ]}"#,
+        )
+        .unwrap();
+        assert!(vulnerabilities(&info, &database).unwrap().is_empty());
+
+        // The same advisory from an OSV feed is merged into the finding from RustSec
+        let info = VersionInfo::from_str(
+            r#"{"packages":[
+            {"name":"vulnerable","version":"1.0.0","source":"crates.io"},
+            {"name":"internal","version":"0.3.0","source":"crates.io"},
+            {"name":"app","version":"0.1.0","source":"local","root":true,"dependencies":[0,1]}
+            ]}"#,
+        )
+        .unwrap();
+        let feed = OsvDatabase::from_json(
+            r#"[
+            {"id":"CVE-2020-0001","aliases":["RUSTSEC-2020-0001"],"affected":[{"package":{"ecosystem":"crates.io","name":"vulnerable"},"versions":["1.0.0"]}]},
+            {"id":"INTERNAL-0001","summary":"Leaks credentials","affected":[{"package":{"ecosystem":"crates.io","name":"internal"},"versions":["0.3.0"]}]}
+            ]"#,
+        )
+        .unwrap()
+        .with_name("internal");
+        let merged = findings(&info, &[&database, &feed]).unwrap();
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].advisory.id, "RUSTSEC-2020-0001");
+        assert_eq!(merged[0].source, "RustSec");
+        assert_eq!(merged[1].advisory.id, "INTERNAL-0001");
+        assert_eq!(merged[1].advisory.title, "Leaks credentials");
+        assert_eq!(merged[1].source, "internal");
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,290 @@
+//! Extracts the audit data from many binaries at once.
+//!
+//! Reading the files, decompressing the audit data and parsing the JSON run as separate stages
+//! connected by bounded channels, so that the decompression and parsing of one file overlaps
+//! with reading the next, and a slow stage stalls the ones before it instead of buffering
+//! an unbounded number of files in memory.
+
+use crate::{decompress_json, embedded_audit_data, read_input, Error, Limits};
+#[cfg(feature = "serde")]
+use auditable_serde::VersionInfo;
+use std::fs::File;
+use std::io::BufReader;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+type Item<T> = (PathBuf, Result<T, Error>);
+
+/// Loads audit info from all the specified binaries, using up to `threads` threads
+/// for decompression and the same number for parsing.
+///
+/// Results are yielded as soon as they are ready, which is not necessarily the order of `paths`.
+/// At most about `threads` files are held in memory at once in each stage,
+/// so the peak RAM usage is roughly `threads` times what [`Limits`] allows for a single file.
+///
+/// ```rust, ignore
+/// let threads = std::thread::available_parallelism()?;
+/// for (path, result) in audit_info_from_files(paths, Default::default(), threads) {
+///     println!("{}: {:?}", path.display(), result.map(|info| info.packages.len()));
+/// }
+/// ```
+///
+/// Dropping the returned iterator early stops the processing of the remaining files.
+#[cfg(feature = "serde")]
+pub fn audit_info_from_files<I>(
+    paths: I,
+    limits: Limits,
+    threads: NonZeroUsize,
+) -> impl Iterator<Item = (PathBuf, Result<VersionInfo, Error>)>
+where
+    I: IntoIterator<Item = PathBuf>,
+    I::IntoIter: Send + 'static,
+{
+    let json = json_stage(paths, limits, threads);
+    let parsed = spawn_stage(json, threads, |(path, json)| {
+        let info = json.and_then(|json| Ok(VersionInfo::from_json(&json)?));
+        (path, info)
+    });
+    parsed.into_iter()
+}
+
+/// Extracts the audit data from all the specified binaries and returns the JSON strings,
+/// using up to `threads` threads for decompression.
+///
+/// Results are yielded as soon as they are ready, which is not necessarily the order of `paths`.
+/// At most about `threads` files are held in memory at once in each stage,
+/// so the peak RAM usage is roughly `threads` times what [`Limits`] allows for a single file.
+///
+/// Dropping the returned iterator early stops the processing of the remaining files.
+pub fn json_from_files<I>(
+    paths: I,
+    limits: Limits,
+    threads: NonZeroUsize,
+) -> impl Iterator<Item = (PathBuf, Result<String, Error>)>
+where
+    I: IntoIterator<Item = PathBuf>,
+    I::IntoIter: Send + 'static,
+{
+    json_stage(paths, limits, threads).into_iter()
+}
+
+fn json_stage<I>(paths: I, limits: Limits, threads: NonZeroUsize) -> Receiver<Item<String>>
+where
+    I: IntoIterator<Item = PathBuf>,
+    I::IntoIter: Send + 'static,
+{
+    let binaries = read_stage(paths.into_iter(), limits, threads);
+    spawn_stage(binaries, threads, move |(path, binary)| {
+        let json = binary.and_then(|binary| {
+            // Copy out the compressed data so that the large allocation of the input file
+            // is dropped before we start decompressing, like `json_from_reader` does
+            let compressed_audit_data = embedded_audit_data(&binary)?.to_owned();
+            drop(binary);
+            decompress_json(&compressed_audit_data, limits.decompressed_json_size)
+        });
+        (path, json)
+    })
+}
+
+/// Something that happened while scanning files with [`scan_files`]
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum ScanEvent {
+    /// The file is about to be read. Every file gets this event before any other one.
+    FileStarted { path: PathBuf },
+    /// The audit data was extracted from the file
+    DataFound { path: PathBuf, json: String },
+    /// No audit data could be extracted from the file
+    Skipped { path: PathBuf, reason: SkipReason },
+    /// All files have been processed. This is always the last event.
+    Finished { stats: ScanStats },
+}
+
+/// Why no audit data was extracted from a file, as reported by [`ScanEvent::Skipped`]
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum SkipReason {
+    /// The file is a binary without audit data, e.g. one not built with `cargo auditable`
+    NoAuditData,
+    /// The file could not be read or parsed, or the audit data is malformed
+    Error(Error),
+}
+
+/// Totals reported by [`ScanEvent::Finished`]
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScanStats {
+    pub files: usize,
+    pub with_audit_data: usize,
+    pub without_audit_data: usize,
+    pub errors: usize,
+}
+
+/// Extracts the audit data from all the specified binaries like [`json_from_files`],
+/// but reports the progress as a stream of [`ScanEvent`]s, for user interfaces that show
+/// which file is being processed and running totals.
+///
+/// The events of a file arrive in order, but the events of different files are interleaved.
+/// The last event is always [`ScanEvent::Finished`].
+///
+/// ```rust, ignore
+/// let threads = std::thread::available_parallelism()?;
+/// for event in scan_files(paths, Default::default(), threads) {
+///     match event {
+///         ScanEvent::FileStarted { path } => eprintln!("Scanning {}", path.display()),
+///         ScanEvent::Finished { stats } => eprintln!("{} files scanned", stats.files),
+///         _ => (),
+///     }
+/// }
+/// ```
+///
+/// Dropping the returned receiver early stops the processing of the remaining files.
+pub fn scan_files<I>(paths: I, limits: Limits, threads: NonZeroUsize) -> Receiver<ScanEvent>
+where
+    I: IntoIterator<Item = PathBuf>,
+    I::IntoIter: Send + 'static,
+{
+    let (events, receiver) = sync_channel(threads.get());
+    let started = events.clone();
+    // Reported by the thread reading the files, so it precedes the results of the file
+    let paths = paths.into_iter().inspect(move |path| {
+        let _ = started.send(ScanEvent::FileStarted { path: path.clone() });
+    });
+    let results = json_stage(paths, limits, threads);
+    thread::spawn(move || {
+        let mut stats = ScanStats::default();
+        for (path, result) in results {
+            stats.files += 1;
+            let event = match result {
+                Ok(json) => {
+                    stats.with_audit_data += 1;
+                    ScanEvent::DataFound { path, json }
+                }
+                Err(Error::NoAuditData) => {
+                    stats.without_audit_data += 1;
+                    ScanEvent::Skipped {
+                        path,
+                        reason: SkipReason::NoAuditData,
+                    }
+                }
+                Err(error) => {
+                    stats.errors += 1;
+                    ScanEvent::Skipped {
+                        path,
+                        reason: SkipReason::Error(error),
+                    }
+                }
+            };
+            // The receiver is gone if the caller is no longer interested in the results
+            if events.send(event).is_err() {
+                return;
+            }
+        }
+        let _ = events.send(ScanEvent::Finished { stats });
+    });
+    receiver
+}
+
+/// Reads the files one after another on a dedicated thread.
+/// Reading them in parallel rarely helps, and it would multiply the peak memory usage.
+fn read_stage<I>(paths: I, limits: Limits, threads: NonZeroUsize) -> Receiver<Item<Vec<u8>>>
+where
+    I: Iterator<Item = PathBuf> + Send + 'static,
+{
+    let (sender, receiver) = sync_channel(threads.get());
+    thread::spawn(move || {
+        for path in paths {
+            let binary = File::open(&path)
+                .map_err(Error::from)
+                .and_then(|file| read_input(&mut BufReader::new(file), limits));
+            // The receiver is gone if the caller is no longer interested in the results
+            if sender.send((path, binary)).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+/// Applies `f` to every item from `input` on a pool of `threads` threads.
+/// The output channel is bounded, so the pool stops taking new items while the next stage is busy.
+fn spawn_stage<In, Out, F>(input: Receiver<In>, threads: NonZeroUsize, f: F) -> Receiver<Out>
+where
+    In: Send + 'static,
+    Out: Send + 'static,
+    F: Fn(In) -> Out + Send + Sync + 'static,
+{
+    let (sender, receiver) = sync_channel(threads.get());
+    let input = Arc::new(Mutex::new(input));
+    let f = Arc::new(f);
+    for _ in 0..threads.get() {
+        let input = Arc::clone(&input);
+        let sender = sender.clone();
+        let f = Arc::clone(&f);
+        thread::spawn(move || loop {
+            // The lock is only held while waiting for the next item, not while processing it
+            let item = match input.lock().unwrap().recv() {
+                Ok(item) => item,
+                Err(_) => break,
+            };
+            if sender.send(f(item)).is_err() {
+                break;
+            }
+        });
+    }
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_file_gets_a_result() {
+        let not_a_binary = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        let missing = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("no-such-file");
+        let paths = vec![not_a_binary.clone(), missing.clone(), not_a_binary.clone()];
+        let threads = NonZeroUsize::new(2).unwrap();
+        let mut results: Vec<_> = json_from_files(paths, Default::default(), threads).collect();
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].0, not_a_binary);
+        assert!(matches!(results[0].1, Err(Error::BinaryParsing(_))));
+        assert!(matches!(results[1].1, Err(Error::BinaryParsing(_))));
+        assert_eq!(results[2].0, missing);
+        assert!(matches!(results[2].1, Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn scan_events() {
+        let not_a_binary = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        let missing = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("no-such-file");
+        let paths = vec![not_a_binary.clone(), missing.clone()];
+        let threads = NonZeroUsize::new(2).unwrap();
+        let events: Vec<ScanEvent> = scan_files(paths, Default::default(), threads)
+            .into_iter()
+            .collect();
+        assert_eq!(events.len(), 5);
+        for path in [&not_a_binary, &missing].iter() {
+            let started = events
+                .iter()
+                .position(|e| matches!(e, ScanEvent::FileStarted { path: p } if &p == path))
+                .unwrap();
+            let skipped = events
+                .iter()
+                .position(|e| matches!(e, ScanEvent::Skipped { path: p, .. } if &p == path))
+                .unwrap();
+            assert!(started < skipped);
+        }
+        match &events[4] {
+            ScanEvent::Finished { stats } => {
+                assert_eq!(stats.files, 2);
+                assert_eq!(stats.errors, 2);
+            }
+            other => panic!("expected the scan to finish, got {:?}", other),
+        }
+    }
+}
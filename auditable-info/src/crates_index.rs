@@ -0,0 +1,246 @@
+//! Verification of the recorded checksums against the crates.io index.
+//!
+//! A binary built from a modified copy of a crate (e.g. an edited vendored dependency)
+//! still reports the upstream name and version. If the checksum of the `.crate` file was recorded,
+//! comparing it against the one published in the index reveals the substitution.
+//!
+//! This module does not perform any network access. The caller supplies the contents
+//! of the index files, either from a local copy of the index or by fetching them from
+//! the [sparse index](https://doc.rust-lang.org/cargo/reference/registry-index.html#sparse-protocol)
+//! at `https://index.crates.io/` followed by [`index_file_path`].
+
+use auditable_serde::{Package, Source, VersionInfo};
+use std::collections::HashMap;
+
+/// Path of the file describing all versions of the crate, relative to the root of the index.
+///
+/// The layout is shared by the git and sparse protocols:
+/// ```rust
+/// use auditable_info::crates_index::index_file_path;
+/// assert_eq!(index_file_path("a").unwrap(), "1/a");
+/// assert_eq!(index_file_path("Serde").unwrap(), "se/rd/serde");
+/// ```
+///
+/// Returns `None` if the name is not a valid crate name. The name usually comes from
+/// untrusted audit data, and must not be allowed to escape the index with `/` or `..`.
+pub fn index_file_path(crate_name: &str) -> Option<String> {
+    if !is_valid_crate_name(crate_name) {
+        return None;
+    }
+    let name = crate_name.to_ascii_lowercase();
+    Some(match name.len() {
+        1 => format!("1/{name}"),
+        2 => format!("2/{name}"),
+        3 => format!("3/{}/{name}", &name[..1]),
+        _ => format!("{}/{}/{name}", &name[..2], &name[2..4]),
+    })
+}
+
+/// Whether the name only consists of the characters allowed in crate names:
+/// ASCII letters, digits, `-` and `_`.
+///
+/// Check this before using a name from the audit data in a path or URL.
+pub fn is_valid_crate_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Computes the checksum of a `.crate` file in the form recorded in the audit data and the index:
+/// the lowercase hex-encoded SHA-256 of the file.
+///
+/// This verifies that a downloaded copy of a crate is the one the binary was built from.
+pub fn crate_file_checksum(crate_file: &[u8]) -> String {
+    crate::sha256::sha256_hex(crate_file)
+}
+
+/// A single published version of a crate, as described in the index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub version: String,
+    /// Lowercase hex-encoded SHA-256 of the `.crate` file
+    pub checksum: String,
+    pub yanked: bool,
+}
+
+/// Parses an index file, which contains one JSON object per published version.
+///
+/// Lines that cannot be parsed are skipped, since new versions of Cargo may introduce
+/// entries this function does not understand.
+pub fn parse_index_file(contents: &str) -> Vec<IndexEntry> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let entry: serde_json::Value = serde_json::from_str(line).ok()?;
+            Some(IndexEntry {
+                version: entry.get("vers")?.as_str()?.to_owned(),
+                checksum: entry.get("cksum")?.as_str()?.to_owned(),
+                yanked: entry
+                    .get("yanked")
+                    .and_then(|y| y.as_bool())
+                    .unwrap_or(false),
+            })
+        })
+        .collect()
+}
+
+/// Outcome of verifying a single package against the index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChecksumStatus {
+    /// The recorded checksum is identical to the one in the index
+    Match,
+    /// The recorded checksum differs from the one in the index.
+    /// The binary was not built from the published version of this crate.
+    Mismatch { expected: String },
+    /// No checksum was recorded for this package, so it cannot be verified
+    NotRecorded,
+    /// The index does not list this version of the crate
+    NotInIndex,
+}
+
+/// Result of the verification of a single package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecksumVerification<'a> {
+    pub package: &'a Package,
+    pub status: ChecksumStatus,
+}
+
+/// Verifies the checksums of all crates.io packages in the dependency tree against the index.
+/// Packages from other sources are not included in the output.
+///
+/// `index_file` is called with the name of each crate and should return the contents
+/// of its index file located at [`index_file_path`], or `None` if there is no such file.
+/// It is called at most once per crate name.
+pub fn verify_checksums<E>(
+    info: &VersionInfo,
+    mut index_file: impl FnMut(&str) -> Result<Option<String>, E>,
+) -> Result<Vec<ChecksumVerification<'_>>, E> {
+    let mut cache: HashMap<&str, Vec<IndexEntry>> = HashMap::new();
+    let mut result = Vec::new();
+    for package in info.packages.iter() {
+        if package.source != Source::CratesIo {
+            continue;
+        }
+        let status = match &package.checksum {
+            None => ChecksumStatus::NotRecorded,
+            // No crate by this name can be published, and looking it up could escape the index
+            Some(_) if !is_valid_crate_name(&package.name) => ChecksumStatus::NotInIndex,
+            Some(checksum) => {
+                if !cache.contains_key(package.name.as_str()) {
+                    let entries = index_file(&package.name)?
+                        .map(|contents| parse_index_file(&contents))
+                        .unwrap_or_default();
+                    cache.insert(&package.name, entries);
+                }
+                let version = package.version.to_string();
+                match cache[package.name.as_str()]
+                    .iter()
+                    .find(|entry| entry.version == version)
+                {
+                    None => ChecksumStatus::NotInIndex,
+                    Some(entry) if entry.checksum.eq_ignore_ascii_case(checksum) => {
+                        ChecksumStatus::Match
+                    }
+                    Some(entry) => ChecksumStatus::Mismatch {
+                        expected: entry.checksum.clone(),
+                    },
+                }
+            }
+        };
+        result.push(ChecksumVerification { package, status });
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const INDEX_FILE: &str = r#"{"name":"adler","vers":"1.0.1","deps":[],"cksum":"1111111111111111111111111111111111111111111111111111111111111111","features":{},"yanked":false}
+{"name":"adler","vers":"1.0.2","deps":[],"cksum":"f26201604c87b1e01bd3d98f8d5d9a8fcbb815e8cedb41ffccbeb4bf593a35fe","features":{},"yanked":false}
+"#;
+
+    #[test]
+    fn index_paths() {
+        assert_eq!(index_file_path("cc").unwrap(), "2/cc");
+        assert_eq!(index_file_path("syn").unwrap(), "3/s/syn");
+        assert_eq!(index_file_path("adler").unwrap(), "ad/le/adler");
+        assert_eq!(index_file_path("../../../../home/u/.bashrc"), None);
+        assert_eq!(index_file_path("a/b"), None);
+        assert_eq!(index_file_path(""), None);
+    }
+
+    #[test]
+    fn crate_file_checksums() {
+        assert_eq!(
+            crate_file_checksum(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn verification() {
+        let info = VersionInfo::from_str(
+            r#"{"packages":[
+            {"name":"adler","version":"1.0.2","source":"crates.io","checksum":"f26201604c87b1e01bd3d98f8d5d9a8fcbb815e8cedb41ffccbeb4bf593a35fe"},
+            {"name":"adler","version":"1.0.1","source":"crates.io","checksum":"2222222222222222222222222222222222222222222222222222222222222222"},
+            {"name":"adler","version":"0.2.3","source":"crates.io","checksum":"2222222222222222222222222222222222222222222222222222222222222222"},
+            {"name":"libc","version":"0.2.140","source":"crates.io"},
+            {"name":"hello","version":"0.1.0","source":"local","root":true,"dependencies":[0,1,2,3]}
+            ]}"#,
+        )
+        .unwrap();
+        let mut lookups = 0;
+        let result = verify_checksums(&info, |name| {
+            lookups += 1;
+            assert_eq!(name, "adler");
+            Ok::<_, ()>(Some(INDEX_FILE.to_owned()))
+        })
+        .unwrap();
+        let statuses: Vec<ChecksumStatus> = result.into_iter().map(|v| v.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ChecksumStatus::Match,
+                ChecksumStatus::Mismatch {
+                    expected: "1111111111111111111111111111111111111111111111111111111111111111"
+                        .to_owned()
+                },
+                ChecksumStatus::NotInIndex,
+                ChecksumStatus::NotRecorded,
+            ]
+        );
+        assert_eq!(lookups, 1);
+    }
+
+    #[test]
+    fn verification_of_serialized_data() {
+        // The audit data is written with serde and read back from the binary,
+        // so crates.io packages must survive the round trip to be verified at all
+        let info = VersionInfo::from_str(
+            r#"{"packages":[{"name":"adler","version":"1.0.1","source":"crates.io","checksum":"2222222222222222222222222222222222222222222222222222222222222222","root":true}]}"#,
+        )
+        .unwrap();
+        let extracted = VersionInfo::from_str(&serde_json::to_string(&info).unwrap()).unwrap();
+        assert_eq!(extracted.packages[0].source, Source::CratesIo);
+        let result =
+            verify_checksums(&extracted, |_| Ok::<_, ()>(Some(INDEX_FILE.to_owned()))).unwrap();
+        assert_eq!(result.len(), 1);
+        assert!(matches!(result[0].status, ChecksumStatus::Mismatch { .. }));
+    }
+
+    #[test]
+    fn traversal_names_are_not_looked_up() {
+        let info = VersionInfo::from_str(
+            r#"{"packages":[{"name":"../../../../home/u/.bashrc","version":"1.0.0","source":"crates.io","checksum":"2222222222222222222222222222222222222222222222222222222222222222","root":true}]}"#,
+        )
+        .unwrap();
+        let result = verify_checksums(&info, |name| -> Result<Option<String>, ()> {
+            panic!("looked up '{name}' in the index")
+        })
+        .unwrap();
+        assert_eq!(result[0].status, ChecksumStatus::NotInIndex);
+    }
+}
@@ -6,9 +6,20 @@ pub enum Error {
     Io(std::io::Error),
     BinaryParsing(auditable_extract::Error),
     Decompression(miniz_oxide::inflate::DecompressError),
+    #[cfg(feature = "zstd")]
+    ZstdDecompression(std::io::Error),
+    UnsupportedCompression,
+    PayloadCorrupted,
     #[cfg(feature = "serde")]
     Json(serde_json::Error),
+    /// The audit data is in the given format, which is newer than `auditable_serde::FORMAT_VERSION`
+    #[cfg(feature = "serde")]
+    NewerFormat(u32),
     Utf8(std::str::Utf8Error),
+    DetachedPayload(crate::DetachedPayload),
+    DigestMismatch,
+    CodeMismatch,
+    ConflictingAuditData,
 }
 
 impl std::fmt::Display for Error {
@@ -20,9 +31,25 @@ impl std::fmt::Display for Error {
             Error::Io(e) => write!(f, "Failed to read the binary: {e}"),
             Error::BinaryParsing(e) => write!(f, "Failed to parse the binary: {e}"),
             Error::Decompression(e) => write!(f, "Failed to decompress audit data: {e}"),
+            #[cfg(feature = "zstd")]
+            Error::ZstdDecompression(e) => write!(f, "Failed to decompress Zstandard-compressed audit data: {e}"),
+            Error::UnsupportedCompression => write!(f, "The audit data is compressed with Zstandard, which this build does not support. Enable the 'zstd' feature to read it."),
+            Error::PayloadCorrupted => write!(f, "The audit data is corrupted: its checksum does not match. The image may be truncated or damaged."),
             #[cfg(feature = "serde")]
             Error::Json(e) => write!(f, "Failed to deserialize audit data from JSON: {e}"),
+            #[cfg(feature = "serde")]
+            Error::NewerFormat(format) => write!(f, "The audit data is in format {format}, which is newer than this tool understands. Upgrade it to read the audit data."),
             Error::Utf8(e) => write!(f, "Invalid UTF-8 in audit data: {e}"),
+            Error::DetachedPayload(p) => {
+                write!(f, "The audit data is stored outside of the binary, its SHA-256 is {}", p.sha256)?;
+                if let Some(url) = &p.url {
+                    write!(f, ". It was published to {url}")?;
+                }
+                Ok(())
+            }
+            Error::DigestMismatch => write!(f, "The audit data in the store does not match the digest recorded in the binary"),
+            Error::CodeMismatch => write!(f, "The binaries contain different machine code, so the audit data does not describe the destination"),
+            Error::ConflictingAuditData => write!(f, "The destination already contains different audit data"),
         }
     }
 }
@@ -36,9 +63,19 @@ impl std::error::Error for Error {
             Error::Io(e) => Some(e),
             Error::BinaryParsing(e) => Some(e),
             Error::Decompression(e) => Some(e),
+            #[cfg(feature = "zstd")]
+            Error::ZstdDecompression(e) => Some(e),
+            Error::UnsupportedCompression => None,
+            Error::PayloadCorrupted => None,
             #[cfg(feature = "serde")]
             Error::Json(e) => Some(e),
+            #[cfg(feature = "serde")]
+            Error::NewerFormat(_) => None,
             Error::Utf8(e) => Some(e),
+            Error::DetachedPayload(_) => None,
+            Error::DigestMismatch => None,
+            Error::CodeMismatch => None,
+            Error::ConflictingAuditData => None,
         }
     }
 }
@@ -53,11 +90,22 @@ impl From<auditable_extract::Error> for Error {
     fn from(e: auditable_extract::Error) -> Self {
         match e {
             auditable_extract::Error::NoAuditData => Error::NoAuditData,
+            auditable_extract::Error::PayloadCorrupted => Error::PayloadCorrupted,
             other_err => Self::BinaryParsing(other_err),
         }
     }
 }
 
+impl From<auditable_extract::ReadError> for Error {
+    fn from(e: auditable_extract::ReadError) -> Self {
+        match e {
+            auditable_extract::ReadError::Io(e) => Error::Io(e),
+            auditable_extract::ReadError::Parse(e) => e.into(),
+            auditable_extract::ReadError::SectionTooLarge => Error::OutputLimitExceeded,
+        }
+    }
+}
+
 impl From<miniz_oxide::inflate::DecompressError> for Error {
     fn from(e: miniz_oxide::inflate::DecompressError) -> Self {
         match e.status {
@@ -79,3 +127,13 @@ impl From<serde_json::Error> for Error {
         Self::Json(e)
     }
 }
+
+#[cfg(feature = "serde")]
+impl From<auditable_serde::ParseError> for Error {
+    fn from(e: auditable_serde::ParseError) -> Self {
+        match e {
+            auditable_serde::ParseError::NewerFormat(format) => Self::NewerFormat(format),
+            auditable_serde::ParseError::Json(e) => Self::Json(e),
+        }
+    }
+}
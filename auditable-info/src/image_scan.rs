@@ -0,0 +1,162 @@
+//! Searches raw memory images for framed audit data while streaming them in fixed-size chunks,
+//! so that scanning multi-gigabyte images is bounded by the speed of the disk
+//! rather than by the amount of available memory.
+
+use crate::{decompress_json, Error, Limits};
+use auditable_extract::{
+    find_frame_magic, framed_auditable_data, is_plausible_frame, CANDIDATE_CHECK_BUDGET,
+    FRAME_HEADER_LEN, FRAME_MAGIC,
+};
+use std::io::Read;
+
+const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Returns the decompressed audit data from the first frame in the image that decompresses successfully,
+/// along with the number of earlier frames that were rejected.
+///
+/// At most one chunk plus one frame of at most `limits.decompressed_json_size` bytes is held in memory.
+/// Like [`auditable_extract::framed_auditable_data_candidates`], the search gives up
+/// once the frames read in add up to [`CANDIDATE_CHECK_BUDGET`].
+pub(crate) fn json_from_image_stream<T: Read>(
+    reader: &mut T,
+    limits: Limits,
+) -> Result<(String, usize), Error> {
+    // The data that has been read but not searched yet
+    let mut buffer: Vec<u8> = Vec::with_capacity(CHUNK_SIZE + FRAME_MAGIC.len());
+    let mut reached_eof = false;
+    // The magic may occur in the image by chance, so try every plausible frame in order
+    let mut first_error = None;
+    let mut rejected_candidates = 0;
+    // The total size of the frames read in so far
+    let mut checked = 0usize;
+    loop {
+        let frame_start = match find_frame_magic(&buffer) {
+            Some(frame_start) => frame_start,
+            None if reached_eof => break,
+            None => {
+                // Keep the end of the buffer in case the magic straddles the chunk boundary
+                let searched = buffer.len().saturating_sub(FRAME_MAGIC.len() - 1);
+                buffer.drain(..searched);
+                let target_len = buffer.len() + CHUNK_SIZE;
+                reached_eof = !fill_to(reader, &mut buffer, target_len)?;
+                continue;
+            }
+        };
+        buffer.drain(..frame_start);
+        match read_frame(reader, &mut buffer, limits, &mut checked) {
+            Ok(Some(json)) => return Ok((json, rejected_candidates)),
+            Ok(None) => rejected_candidates += 1,
+            Err(e) => {
+                rejected_candidates += 1;
+                first_error.get_or_insert(e);
+            }
+        }
+        if checked > CANDIDATE_CHECK_BUDGET {
+            break;
+        }
+        // Resume the search right after this magic, in case it was a false positive
+        buffer.drain(..1);
+    }
+    Err(first_error.unwrap_or(Error::NoAuditData))
+}
+
+/// Decompresses the frame at the start of `buffer`, reading the rest of it from `reader` if needed.
+/// Returns `None` if the frame cannot hold audit data or does not fit into the image,
+/// i.e. the magic occurred by chance, or if reading it would exceed [`CANDIDATE_CHECK_BUDGET`].
+/// The size of every plausible frame is added to `checked`.
+fn read_frame<T: Read>(
+    reader: &mut T,
+    buffer: &mut Vec<u8>,
+    limits: Limits,
+    checked: &mut usize,
+) -> Result<Option<String>, Error> {
+    // Also read the first byte of the payload for `is_plausible_frame` to check
+    if !fill_to(reader, buffer, FRAME_HEADER_LEN + 1)? || !is_plausible_frame(buffer) {
+        return Ok(None);
+    }
+    let len_bytes = &buffer[FRAME_MAGIC.len()..FRAME_MAGIC.len() + 4];
+    let len = u32::from_le_bytes([len_bytes[0], len_bytes[1], len_bytes[2], len_bytes[3]]) as usize;
+    // Don't read in a huge frame only for decompression to reject it
+    if len > limits.decompressed_json_size {
+        return Err(Error::OutputLimitExceeded);
+    }
+    *checked = checked.saturating_add(len);
+    if *checked > CANDIDATE_CHECK_BUDGET || !fill_to(reader, buffer, FRAME_HEADER_LEN + len)? {
+        return Ok(None);
+    }
+    let compressed_audit_data = framed_auditable_data(buffer)?;
+    decompress_json(compressed_audit_data, limits.decompressed_json_size).map(Some)
+}
+
+/// Reads from `reader` until `buffer` holds at least `len` bytes.
+/// Returns `false` if the reader ran out of data first.
+fn fill_to<T: Read>(reader: &mut T, buffer: &mut Vec<u8>, len: usize) -> Result<bool, Error> {
+    if buffer.len() < len {
+        let missing = (len - buffer.len()) as u64;
+        reader.by_ref().take(missing).read_to_end(buffer)?;
+    }
+    Ok(buffer.len() >= len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use auditable_extract::frame_header;
+
+    #[test]
+    fn frame_across_chunk_boundary() {
+        let json = r#"{"packages":[]}"#;
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(json.as_bytes(), 7);
+        // Place the magic so that it is split between the first and the second chunk
+        let mut image = vec![0xffu8; CHUNK_SIZE - 3];
+        // a stray magic with an implausible length must not prevent finding the real frame
+        // that follows it
+        image.extend_from_slice(&FRAME_MAGIC);
+        image.extend_from_slice(&u32::MAX.to_le_bytes());
+        image.extend_from_slice(&0u32.to_le_bytes());
+        image.extend_from_slice(&frame_header(&compressed).unwrap());
+        image.extend_from_slice(&compressed);
+        let mut reader = std::io::Cursor::new(&image);
+        let result = json_from_image_stream(&mut reader, Default::default());
+        assert_eq!(result.unwrap(), (json.to_owned(), 1));
+        // if there is no valid frame, the error from the first candidate is reported
+        let mut truncated = std::io::Cursor::new(&image[..image.len() - 1]);
+        let result = json_from_image_stream(&mut truncated, Default::default());
+        assert!(matches!(result, Err(Error::OutputLimitExceeded)));
+        let mut empty = std::io::Cursor::new(&image[..CHUNK_SIZE - 3]);
+        let result = json_from_image_stream(&mut empty, Default::default());
+        assert!(matches!(result, Err(Error::NoAuditData)));
+    }
+
+    #[test]
+    fn many_stray_magics() {
+        // Each stray frame claims to extend to the end of the image,
+        // so reading in all of them would take quadratic time
+        let count = 20_000;
+        let stray_len = FRAME_HEADER_LEN + 1;
+        let mut image = Vec::with_capacity(count * stray_len);
+        for i in 0..count {
+            let len = (count - i) * stray_len - FRAME_HEADER_LEN;
+            image.extend_from_slice(&FRAME_MAGIC);
+            image.extend_from_slice(&(len as u32).to_le_bytes());
+            image.extend_from_slice(&0u32.to_le_bytes());
+            image.push(b'{');
+        }
+        let mut reader = std::io::Cursor::new(&image);
+        let result = json_from_image_stream(&mut reader, Default::default());
+        assert!(matches!(result, Err(Error::PayloadCorrupted)));
+
+        // implausible frames are skipped without reading them in,
+        // so a real frame after them is still found
+        for stray in image.chunks_mut(stray_len) {
+            stray[FRAME_HEADER_LEN] = 0;
+        }
+        let json = r#"{"packages":[]}"#;
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(json.as_bytes(), 7);
+        image.extend_from_slice(&frame_header(&compressed).unwrap());
+        image.extend_from_slice(&compressed);
+        let mut reader = std::io::Cursor::new(&image);
+        let result = json_from_image_stream(&mut reader, Default::default());
+        assert_eq!(result.unwrap(), (json.to_owned(), count));
+    }
+}
@@ -0,0 +1,363 @@
+//! An on-disk index of the audit data of many binaries, enabled by the `index` feature.
+//!
+//! This is the building block for a search service over the binaries of an organization,
+//! answering questions such as "which binaries contain `openssl` older than 0.10.55?"
+//! without extracting the audit data from every binary again.
+//!
+//! The index is an [SQLite](https://www.sqlite.org/) database. Its schema is an implementation
+//! detail and may change between versions of this crate, so only access it through [`Index`].
+
+use crate::{Error, Limits};
+use auditable_serde::{DependencyKind, VersionInfo};
+use rusqlite::{params, Connection, OptionalExtension};
+use semver::{Version, VersionReq};
+use std::path::{Path, PathBuf};
+
+/// Incremented whenever the schema changes; databases created with another version are rejected
+const SCHEMA_VERSION: i64 = 1;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS binaries (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE
+    );
+    CREATE TABLE IF NOT EXISTS packages (
+        binary INTEGER NOT NULL REFERENCES binaries(id) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        version TEXT NOT NULL,
+        kind TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS packages_by_name ON packages(name);
+    CREATE INDEX IF NOT EXISTS packages_by_binary ON packages(binary);
+";
+
+/// The audit data of many binaries, stored in a database for querying
+pub struct Index {
+    connection: Connection,
+}
+
+/// A binary that contains the crate that was searched for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexMatch {
+    /// The name the binary was added to the index under
+    pub binary: String,
+    /// The version of the crate in the binary
+    pub version: Version,
+    /// Build dependencies are only used to build the binary and are not part of it
+    pub kind: DependencyKind,
+}
+
+/// Error returned by the methods of [`Index`]
+#[derive(Debug)]
+pub enum IndexError {
+    /// The database could not be opened, read or written
+    Database(rusqlite::Error),
+    /// The database was created by a version of this crate with a different schema
+    SchemaVersion(i64),
+    /// The database contains data this crate would never have written
+    Corrupted,
+}
+
+impl std::fmt::Display for IndexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IndexError::Database(e) => write!(f, "Failed to access the index: {e}"),
+            IndexError::SchemaVersion(v) => write!(
+                f,
+                "The index was created with schema version {v}, but only version {SCHEMA_VERSION} is supported. Rebuild the index."
+            ),
+            IndexError::Corrupted => write!(f, "The index is corrupted"),
+        }
+    }
+}
+
+impl std::error::Error for IndexError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IndexError::Database(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for IndexError {
+    fn from(e: rusqlite::Error) -> Self {
+        IndexError::Database(e)
+    }
+}
+
+/// Outcome of [`Index::ingest`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IngestStats {
+    /// Binaries whose audit data was added to the index
+    pub indexed: usize,
+    /// Binaries whose audit data could not be extracted, which were left out of the index
+    pub failed: usize,
+}
+
+impl Index {
+    /// Opens the index stored in the file at `path`, creating it if it does not exist
+    pub fn open(path: &Path) -> Result<Self, IndexError> {
+        Self::init(Connection::open(path)?)
+    }
+
+    /// Creates an index that is only kept in memory, e.g. for tests
+    pub fn open_in_memory() -> Result<Self, IndexError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(connection: Connection) -> Result<Self, IndexError> {
+        connection.pragma_update(None, "foreign_keys", true)?;
+        let version: i64 = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        let is_empty: bool =
+            connection.query_row("SELECT count(*) = 0 FROM sqlite_master", [], |row| {
+                row.get(0)
+            })?;
+        if is_empty {
+            connection.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        } else if version != SCHEMA_VERSION {
+            return Err(IndexError::SchemaVersion(version));
+        }
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    /// Adds the audit data of a binary to the index under the given name, e.g. its path
+    /// or an identifier of the container image it is in. If there is already a binary
+    /// with that name in the index, its audit data is replaced.
+    pub fn insert(&mut self, binary: &str, info: &VersionInfo) -> Result<(), IndexError> {
+        let transaction = self.connection.transaction()?;
+        insert(&transaction, binary, info)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Adds the results of extracting the audit data from many binaries, such as those
+    /// returned by [`scan_dir`](crate::scan_dir) or [`audit_info_from_files`](crate::audit_info_from_files),
+    /// in a single transaction. The binaries are named after their paths.
+    ///
+    /// Failed extractions are counted but otherwise ignored; binaries that were indexed
+    /// before keep their previous audit data.
+    pub fn ingest(
+        &mut self,
+        results: impl IntoIterator<Item = (PathBuf, Result<VersionInfo, Error>)>,
+    ) -> Result<IngestStats, IndexError> {
+        let mut stats = IngestStats::default();
+        let transaction = self.connection.transaction()?;
+        for (path, result) in results {
+            match result {
+                Ok(info) => {
+                    insert(&transaction, &path.to_string_lossy(), &info)?;
+                    stats.indexed += 1;
+                }
+                Err(_) => stats.failed += 1,
+            }
+        }
+        transaction.commit()?;
+        Ok(stats)
+    }
+
+    /// Removes a binary from the index. Returns `false` if it was not in the index.
+    pub fn remove(&mut self, binary: &str) -> Result<bool, IndexError> {
+        let removed = self
+            .connection
+            .execute("DELETE FROM binaries WHERE name = ?1", params![binary])?;
+        Ok(removed > 0)
+    }
+
+    /// Lists the binaries that contain a version of the crate `name` matching `requirement`,
+    /// sorted by the name of the binary and then by version.
+    ///
+    /// ```rust, ignore
+    /// let requirement = VersionReq::parse("<0.10.55")?;
+    /// for found in index.binaries_with("openssl", &requirement)? {
+    ///     println!("{} contains openssl {}", found.binary, found.version);
+    /// }
+    /// ```
+    pub fn binaries_with(
+        &self,
+        name: &str,
+        requirement: &VersionReq,
+    ) -> Result<Vec<IndexMatch>, IndexError> {
+        let mut statement = self.connection.prepare_cached(
+            "SELECT binaries.name, packages.version, packages.kind
+            FROM packages JOIN binaries ON packages.binary = binaries.id
+            WHERE packages.name = ?1",
+        )?;
+        let rows = statement.query_map(params![name], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        let mut matches = Vec::new();
+        for row in rows {
+            let (binary, version, kind) = row?;
+            // Only valid versions are ever written to the index
+            let version = Version::parse(&version).map_err(|_| IndexError::Corrupted)?;
+            if requirement.matches(&version) {
+                let kind = parse_kind(&kind).ok_or(IndexError::Corrupted)?;
+                matches.push(IndexMatch {
+                    binary,
+                    version,
+                    kind,
+                });
+            }
+        }
+        matches.sort_by(|a, b| (&a.binary, &a.version).cmp(&(&b.binary, &b.version)));
+        Ok(matches)
+    }
+
+    /// Returns `true` if a binary with this name is in the index
+    pub fn contains(&self, binary: &str) -> Result<bool, IndexError> {
+        let found = self
+            .connection
+            .query_row(
+                "SELECT 1 FROM binaries WHERE name = ?1",
+                params![binary],
+                |_| Ok(()),
+            )
+            .optional()?;
+        Ok(found.is_some())
+    }
+}
+
+/// Convenience function that extracts the audit data from every executable in a directory tree
+/// with [`scan_dir`](crate::scan_dir) and adds it to the index
+pub fn index_dir(
+    index: &mut Index,
+    path: &Path,
+    limits: Limits,
+) -> Result<IngestStats, IndexError> {
+    index.ingest(crate::scan_dir(path, limits))
+}
+
+fn insert(connection: &Connection, binary: &str, info: &VersionInfo) -> Result<(), IndexError> {
+    // Deleting the binary also deletes its packages, so re-inserting it replaces them
+    connection.execute("DELETE FROM binaries WHERE name = ?1", params![binary])?;
+    connection.execute("INSERT INTO binaries (name) VALUES (?1)", params![binary])?;
+    let id = connection.last_insert_rowid();
+    let mut statement = connection.prepare_cached(
+        "INSERT INTO packages (binary, name, version, kind) VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for package in &info.packages {
+        statement.execute(params![
+            id,
+            package.name,
+            package.version.to_string(),
+            kind_name(package.kind),
+        ])?;
+    }
+    Ok(())
+}
+
+fn kind_name(kind: DependencyKind) -> &'static str {
+    match kind {
+        DependencyKind::Development => "development",
+        DependencyKind::Build => "build",
+        DependencyKind::Runtime => "runtime",
+    }
+}
+
+fn parse_kind(kind: &str) -> Option<DependencyKind> {
+    match kind {
+        "development" => Some(DependencyKind::Development),
+        "build" => Some(DependencyKind::Build),
+        "runtime" => Some(DependencyKind::Runtime),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn info(json: &str) -> VersionInfo {
+        VersionInfo::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn queries() {
+        let mut index = Index::open_in_memory().unwrap();
+        index
+            .insert(
+                "old",
+                &info(
+                    r#"{"packages":[
+                    {"name":"openssl","version":"0.10.40","source":"crates.io"},
+                    {"name":"old","version":"0.1.0","source":"local","root":true,"dependencies":[0]}
+                    ]}"#,
+                ),
+            )
+            .unwrap();
+        index
+            .insert(
+                "new",
+                &info(
+                    r#"{"packages":[
+                    {"name":"openssl","version":"0.10.60","source":"crates.io"},
+                    {"name":"cc","version":"1.0.0","source":"crates.io","kind":"build"},
+                    {"name":"new","version":"0.1.0","source":"local","root":true,"dependencies":[0,1]}
+                    ]}"#,
+                ),
+            )
+            .unwrap();
+
+        let vulnerable = VersionReq::parse("<0.10.55").unwrap();
+        let found = index.binaries_with("openssl", &vulnerable).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].binary, "old");
+        assert_eq!(found[0].version, Version::new(0, 10, 40));
+
+        let found = index.binaries_with("cc", &VersionReq::STAR).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, DependencyKind::Build);
+
+        // Re-inserting a binary replaces its audit data
+        index
+            .insert(
+                "old",
+                &info(r#"{"packages":[{"name":"old","version":"0.2.0","source":"local","root":true}]}"#),
+            )
+            .unwrap();
+        assert!(index
+            .binaries_with("openssl", &vulnerable)
+            .unwrap()
+            .is_empty());
+
+        assert!(index.remove("new").unwrap());
+        assert!(!index.remove("new").unwrap());
+        assert!(!index.contains("new").unwrap());
+        assert!(index.contains("old").unwrap());
+        assert!(index
+            .binaries_with("cc", &VersionReq::STAR)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn failed_extractions_are_counted() {
+        let mut index = Index::open_in_memory().unwrap();
+        let results = vec![
+            (
+                PathBuf::from("a"),
+                Ok(info(
+                    r#"{"packages":[{"name":"a","version":"0.1.0","source":"local","root":true}]}"#,
+                )),
+            ),
+            (PathBuf::from("b"), Err(Error::NoAuditData)),
+        ];
+        let stats = index.ingest(results).unwrap();
+        assert_eq!(
+            stats,
+            IngestStats {
+                indexed: 1,
+                failed: 1
+            }
+        );
+        assert!(index.contains("a").unwrap());
+        assert!(!index.contains("b").unwrap());
+    }
+}
@@ -1,55 +1,189 @@
-#![forbid(unsafe_code)]
+#![cfg_attr(not(feature = "mmap"), forbid(unsafe_code))]
+#![cfg_attr(feature = "mmap", deny(unsafe_code))]
 
 //! High-level crate to extract the dependency trees embedded in binaries by [`cargo auditable`](https://crates.io/crates/cargo-auditable).
 //!
 //! Deserializes them to a JSON string or Rust data structures, at your option.
 //!
 //! ```rust, ignore
-//! // Uses the default limits: 1GiB input file size, 8MiB audit data size
+//! // Uses the default limit of 8MiB audit data size
 //! let info = audit_info_from_file(&PathBuf::from("path/to/file"), Default::default())?;
 //! ```
-//! Functions to load the data from a `Read` instance or from `&[u8]` are also provided.
+//! Only the headers and the audit data are read from the file, so binaries of any size can be processed.
+//! Functions to load the data from a `Read` instance or from `&[u8]` are also provided,
+//! as well as [`json_from_files`] and [`audit_info_from_files`] that process many binaries in parallel.
+//! [`scan_dir`] walks a directory tree, such as an unpacked container image, and loads the audit info
+//! from every executable in it. With the `rayon` feature enabled, `par_scan_dir` does the same on all CPU cores.
+//!
+//! Binaries built with the audit data written to a separate content-addressed store
+//! return [`Error::DetachedPayload`]; the data can then be loaded with [`json_from_store`].
+//!
+//! Post-processing tools that rebuild binaries may drop the audit data section.
+//! [`copy_audit_data`] copies the audit data from the original binary to the processed copy,
+//! after verifying that both contain the same machine code.
+//!
+//! Audit data found by searching a raw memory image or recovered from a minidump is a best-effort result
+//! rather than the data embedded into an executable. The `*_and_provenance_*` functions return a [`Provenance`]
+//! along with it, so that such data can be labeled accordingly.
+//!
+//! With the `mmap` feature enabled, `json_from_mmap` and `audit_info_from_mmap` extract the audit data
+//! from memory-mapped binaries, reading only the parts of the file that are needed from the disk.
+//!
+//! With the `index` feature enabled, `Index` stores the audit data of many binaries in an SQLite database
+//! and finds the binaries containing a given crate version, e.g. one affected by a vulnerability.
+//!
+//! The [`crates_index`] module verifies the recorded checksums of packages against the crates.io index.
+//! With the `advisories` feature enabled, the `advisories` module lists the packages affected
+//! by known vulnerabilities according to the [RustSec advisory database](https://rustsec.org/)
+//! and any other advisory sources plugged in through its `AdvisorySource` trait, such as feeds in the OSV format.
+//!
+//! With the `test-vectors` feature enabled, the [`test_vectors`] module provides reference data
+//! for verifying that other tools embed audit data identical to `cargo auditable`.
+//!
+//! With the `bench` feature enabled, the [`synthetic`] module generates binaries of any size
+//! with any number of packages, for measuring the performance of extraction on your hardware.
 //!
 //! If you need an even lower-level interface than the one provided by this crate,
 //! use the [`auditable-extract`](http://docs.rs/auditable-extract/) and
 //! [`auditable-serde`](http://docs.rs/auditable-serde/) crates.
 
-use auditable_extract::raw_auditable_data;
+use auditable_extract::{
+    framed_auditable_data, payload_pointer, raw_auditable_data, raw_auditable_data_and_build_id,
+    raw_auditable_data_from_minidump, raw_auditable_data_from_reader, UNCOMPRESSED_MARKER,
+    ZSTD_MARKER,
+};
 #[cfg(feature = "serde")]
 use auditable_serde::VersionInfo;
 use miniz_oxide::inflate::decompress_to_vec_zlib_with_limit;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufRead, BufReader, Read, Seek};
 use std::path::Path;
 
+#[cfg(feature = "advisories")]
+pub mod advisories;
+mod batch;
+#[cfg(feature = "serde")]
+pub mod crates_index;
 mod error;
+mod image_scan;
+#[cfg(feature = "index")]
+mod index;
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "advisories")]
+mod osv;
+mod provenance;
+#[cfg(feature = "serde")]
+mod scan_dir;
+mod sha256;
+mod store;
+#[cfg(feature = "bench")]
+pub mod synthetic;
+#[cfg(feature = "test-vectors")]
+pub mod test_vectors;
+mod transplant;
 
+#[cfg(feature = "serde")]
+pub use crate::batch::audit_info_from_files;
+pub use crate::batch::{json_from_files, scan_files, ScanEvent, ScanStats, SkipReason};
 pub use crate::error::Error;
+#[cfg(feature = "index")]
+pub use crate::index::{index_dir, Index, IndexError, IndexMatch, IngestStats};
+#[cfg(all(feature = "mmap", feature = "serde"))]
+pub use crate::mmap::audit_info_from_mmap;
+#[cfg(feature = "mmap")]
+pub use crate::mmap::json_from_mmap;
+pub use crate::provenance::{Confidence, Provenance};
+#[cfg(all(feature = "rayon", feature = "serde"))]
+pub use crate::scan_dir::par_scan_dir;
+#[cfg(feature = "serde")]
+pub use crate::scan_dir::{scan_dir, ScanDir};
+#[cfg(feature = "serde")]
+pub use crate::store::audit_info_from_store;
+pub use crate::store::{json_from_store, DetachedPayload};
+pub use crate::transplant::{copy_audit_data, copy_audit_data_between_files};
 
 /// Loads audit info from the specified binary compiled with `cargo auditable`.
 ///
-/// The entire file is loaded into memory. The RAM usage limit can be configured using the [`Limits`] struct.
+/// Only the headers and the audit data are read from the file rather than all of it,
+/// so `limits.input_file_size` does not apply unless it is not a regular file, e.g. a pipe. The size of the audit data is limited
+/// by `limits.decompressed_json_size`, see [`Limits`].
 ///
 /// ```rust, ignore
-/// // Uses the default limits: 1GiB input file size, 8MiB audit data size
+/// // Uses the default limit of 8MiB audit data size
 /// let info = audit_info_from_file(&PathBuf::from("path/to/file"), Default::default())?;
 /// ```
 ///
-/// The data is validated to only have a single root package and not contain any circular dependencies.
+/// The data is validated to not contain any circular dependencies.
 #[cfg(feature = "serde")]
 pub fn audit_info_from_file(path: &Path, limits: Limits) -> Result<VersionInfo, Error> {
-    Ok(serde_json::from_str(&json_from_file(path, limits)?)?)
+    Ok(VersionInfo::from_json(&json_from_file(path, limits)?)?)
+}
+
+/// Loads audit info from the specified binary and also returns the JSON string it was parsed from,
+/// exactly as it was embedded in the binary.
+///
+/// This is useful if you want to both inspect the data and forward it somewhere:
+/// serializing the parsed data back to JSON is not guaranteed to produce identical bytes,
+/// and calling both [`json_from_file`] and [`audit_info_from_file`] would decompress the data twice.
+///
+/// The data is validated to not contain any circular dependencies.
+#[cfg(feature = "serde")]
+pub fn json_and_audit_info_from_file(
+    path: &Path,
+    limits: Limits,
+) -> Result<(String, VersionInfo), Error> {
+    parse_keeping_json(json_from_file(path, limits)?)
 }
 
 /// Extracts the audit data from the specified binary and returns the JSON string.
 /// This is useful if you want to forward the data somewhere instead of parsing it to Rust data structures.
 ///
+/// Only the headers and the audit data are read from the file, see [`audit_info_from_file`].
+///
 /// If you want to obtain the Zlib-compressed data instead,
 /// use the [`auditable-extract`](https://docs.rs/auditable-extract/) crate directly.
 pub fn json_from_file(path: &Path, limits: Limits) -> Result<String, Error> {
     let file = File::open(path)?;
+    // Pipes such as `/dev/stdin` cannot seek, so they are read in full
+    let is_regular_file = file.metadata()?.is_file();
     let mut reader = BufReader::new(file);
-    json_from_reader(&mut reader, limits)
+    if is_regular_file {
+        json_from_seekable_reader(&mut reader, limits)
+    } else {
+        json_from_reader(&mut reader, limits)
+    }
+}
+
+/// Loads audit info from the binary loaded from a reader that supports seeking, such as a file.
+///
+/// Only the headers and the audit data are read rather than the whole binary,
+/// so `limits.input_file_size` does not apply. Use [`audit_info_from_reader`] for readers
+/// that cannot seek, such as the standard input.
+///
+/// The data is validated to not contain any circular dependencies.
+#[cfg(feature = "serde")]
+pub fn audit_info_from_seekable_reader<T: Read + Seek>(
+    reader: &mut T,
+    limits: Limits,
+) -> Result<VersionInfo, Error> {
+    Ok(VersionInfo::from_json(&json_from_seekable_reader(
+        reader, limits,
+    )?)?)
+}
+
+/// Extracts the audit data from a reader that supports seeking, such as a file, and returns the JSON string.
+///
+/// Only the headers and the audit data are read rather than the whole binary,
+/// so `limits.input_file_size` does not apply. Use [`json_from_reader`] for readers
+/// that cannot seek, such as the standard input.
+pub fn json_from_seekable_reader<T: Read + Seek>(
+    reader: &mut T,
+    limits: Limits,
+) -> Result<String, Error> {
+    let audit_data = raw_auditable_data_from_reader(reader, limits.decompressed_json_size as u64)?;
+    let compressed_audit_data = check_not_detached(&audit_data)?;
+    decompress_json(compressed_audit_data, limits.decompressed_json_size)
 }
 
 /// Loads audit info from the binary loaded from an arbitrary reader, e.g. the standard input.
@@ -61,13 +195,22 @@ pub fn json_from_file(path: &Path, limits: Limits) -> Result<String, Error> {
 /// let info = audit_info_from_reader(&mut handle, Default::default())?;
 /// ```
 ///
-/// The data is validated to only have a single root package and not contain any circular dependencies.
+/// The data is validated to not contain any circular dependencies.
 #[cfg(feature = "serde")]
 pub fn audit_info_from_reader<T: BufRead>(
     reader: &mut T,
     limits: Limits,
 ) -> Result<VersionInfo, Error> {
-    Ok(serde_json::from_str(&json_from_reader(reader, limits)?)?)
+    Ok(VersionInfo::from_json(&json_from_reader(reader, limits)?)?)
+}
+
+/// Like [`json_and_audit_info_from_file`], but reads the binary from an arbitrary reader.
+#[cfg(feature = "serde")]
+pub fn json_and_audit_info_from_reader<T: BufRead>(
+    reader: &mut T,
+    limits: Limits,
+) -> Result<(String, VersionInfo), Error> {
+    parse_keeping_json(json_from_reader(reader, limits)?)
 }
 
 /// Extracts the audit data and returns the JSON string.
@@ -77,15 +220,22 @@ pub fn audit_info_from_reader<T: BufRead>(
 /// use the [`auditable-extract`](https://docs.rs/auditable-extract/) crate directly.
 pub fn json_from_reader<T: BufRead>(reader: &mut T, limits: Limits) -> Result<String, Error> {
     let compressed_data = get_compressed_audit_data(reader, limits)?;
-    let decompressed_data =
-        decompress_to_vec_zlib_with_limit(&compressed_data, limits.decompressed_json_size)?;
-    Ok(String::from_utf8(decompressed_data)?)
+    decompress_json(&compressed_data, limits.decompressed_json_size)
 }
 
 // Factored into its own function for ease of unit testing,
 // and also so that the large allocation of the input file is dropped
 // before we start decompressing the data to minimize peak memory usage
 fn get_compressed_audit_data<T: BufRead>(reader: &mut T, limits: Limits) -> Result<Vec<u8>, Error> {
+    let input_binary = read_input(reader, limits)?;
+    let compressed_audit_data = embedded_audit_data(&input_binary)?;
+    if compressed_audit_data.len() > limits.decompressed_json_size {
+        Err(Error::OutputLimitExceeded)?;
+    }
+    Ok(compressed_audit_data.to_owned())
+}
+
+fn read_input<T: BufRead>(reader: &mut T, limits: Limits) -> Result<Vec<u8>, Error> {
     // In case you're wondering why the check for the limit is weird like that:
     // When .take() returns EOF, it doesn't tell you if that's because it reached the limit
     // or because the underlying reader ran out of data.
@@ -103,11 +253,7 @@ fn get_compressed_audit_data<T: BufRead>(reader: &mut T, limits: Limits) -> Resu
     if input_binary.len() as u64 == incremented_limit {
         Err(Error::InputLimitExceeded)?
     }
-    let compressed_audit_data = raw_auditable_data(&input_binary)?;
-    if compressed_audit_data.len() > limits.decompressed_json_size {
-        Err(Error::OutputLimitExceeded)?;
-    }
-    Ok(compressed_audit_data.to_owned())
+    Ok(input_binary)
 }
 
 /// The input slice should contain the entire binary.
@@ -117,12 +263,27 @@ pub fn audit_info_from_slice(
     input_binary: &[u8],
     decompressed_json_size_limit: usize,
 ) -> Result<VersionInfo, Error> {
-    Ok(serde_json::from_str(&json_from_slice(
+    Ok(VersionInfo::from_json(&json_from_slice(
         input_binary,
         decompressed_json_size_limit,
     )?)?)
 }
 
+/// Like [`json_and_audit_info_from_file`], but takes a slice containing the entire binary.
+#[cfg(feature = "serde")]
+pub fn json_and_audit_info_from_slice(
+    input_binary: &[u8],
+    decompressed_json_size_limit: usize,
+) -> Result<(String, VersionInfo), Error> {
+    parse_keeping_json(json_from_slice(input_binary, decompressed_json_size_limit)?)
+}
+
+#[cfg(feature = "serde")]
+fn parse_keeping_json(json: String) -> Result<(String, VersionInfo), Error> {
+    let info = VersionInfo::from_json(&json)?;
+    Ok((json, info))
+}
+
 /// The input slice should contain the entire binary.
 /// This function is useful if you have already loaded the binary to memory, e.g. via memory-mapping.
 ///
@@ -135,15 +296,252 @@ pub fn json_from_slice(
     input_binary: &[u8],
     decompressed_json_size_limit: usize,
 ) -> Result<String, Error> {
-    let compressed_audit_data = raw_auditable_data(input_binary)?;
+    let compressed_audit_data = embedded_audit_data(input_binary)?;
+    decompress_json(compressed_audit_data, decompressed_json_size_limit)
+}
+
+/// Like [`audit_info_from_slice`], but also returns the GNU build ID of ELF executables,
+/// the same identifier crash reporting and symbolication infrastructure uses.
+/// The binary is only parsed once.
+///
+/// The build ID is `None` if the binary is not an ELF executable or was linked without one.
+#[cfg(feature = "serde")]
+pub fn audit_info_and_build_id_from_slice(
+    input_binary: &[u8],
+    decompressed_json_size_limit: usize,
+) -> Result<(VersionInfo, Option<&[u8]>), Error> {
+    let (json, build_id) =
+        json_and_build_id_from_slice(input_binary, decompressed_json_size_limit)?;
+    Ok((VersionInfo::from_json(&json)?, build_id))
+}
+
+/// Like [`json_from_slice`], but also returns the GNU build ID of ELF executables,
+/// the same identifier crash reporting and symbolication infrastructure uses.
+/// The binary is only parsed once.
+///
+/// The build ID is `None` if the binary is not an ELF executable or was linked without one.
+pub fn json_and_build_id_from_slice(
+    input_binary: &[u8],
+    decompressed_json_size_limit: usize,
+) -> Result<(String, Option<&[u8]>), Error> {
+    let (audit_data, build_id) = raw_auditable_data_and_build_id(input_binary)?;
+    let compressed_audit_data = check_not_detached(audit_data)?;
+    let json = decompress_json(compressed_audit_data, decompressed_json_size_limit)?;
+    Ok((json, build_id))
+}
+
+/// Obtains the compressed audit data from the binary,
+/// or reports where to find it if it is stored outside of the binary
+fn embedded_audit_data(input_binary: &[u8]) -> Result<&[u8], Error> {
+    check_not_detached(raw_auditable_data(input_binary)?)
+}
+
+fn check_not_detached(audit_data: &[u8]) -> Result<&[u8], Error> {
+    if let Some(pointer) = payload_pointer(audit_data)? {
+        return Err(Error::DetachedPayload(pointer.into()));
+    }
+    Ok(audit_data)
+}
+
+/// Extracts the audit data from a raw memory image, such as a firmware image
+/// produced by `objcopy -O binary`, that has no section headers to locate it by.
+///
+/// `offset` is the location of the audit data relative to the start of the image,
+/// i.e. the address of the `__auditable_start` symbol defined by the linker script fragment
+/// generated by `cargo auditable emit-object --linker-script` minus the base address of the image.
+///
+/// The data is validated to not contain any circular dependencies.
+#[cfg(feature = "serde")]
+pub fn audit_info_from_image_slice(
+    image: &[u8],
+    offset: usize,
+    decompressed_json_size_limit: usize,
+) -> Result<VersionInfo, Error> {
+    Ok(VersionInfo::from_json(&json_from_image_slice(
+        image,
+        offset,
+        decompressed_json_size_limit,
+    )?)?)
+}
+
+/// Extracts the audit data from a raw memory image, such as a firmware image
+/// produced by `objcopy -O binary`, that has no section headers to locate it by.
+///
+/// `offset` is the location of the audit data relative to the start of the image,
+/// i.e. the address of the `__auditable_start` symbol defined by the linker script fragment
+/// generated by `cargo auditable emit-object --linker-script` minus the base address of the image.
+///
+/// Returns the decompressed audit data.
+/// This is useful if you want to forward the data somewhere instead of parsing it to Rust data structures.
+pub fn json_from_image_slice(
+    image: &[u8],
+    offset: usize,
+    decompressed_json_size_limit: usize,
+) -> Result<String, Error> {
+    let framed_data = image.get(offset..).ok_or(Error::NoAuditData)?;
+    let compressed_audit_data = framed_auditable_data(framed_data)?;
+    decompress_json(compressed_audit_data, decompressed_json_size_limit)
+}
+
+/// Searches the specified raw memory image, such as a firmware image produced by `objcopy -O binary`,
+/// for the audit data placed into it by the linker script fragment generated by
+/// `cargo auditable emit-object --linker-script`. Use this if the location of the audit data is not known.
+///
+/// The file is streamed rather than loaded into memory, so images of any size can be scanned
+/// and `limits.input_file_size` does not apply. At most a few MiB plus `limits.decompressed_json_size`
+/// are held in memory at once.
+///
+/// The data is validated to not contain any circular dependencies.
+#[cfg(feature = "serde")]
+pub fn audit_info_from_image_file(path: &Path, limits: Limits) -> Result<VersionInfo, Error> {
+    Ok(VersionInfo::from_json(&json_from_image_file(
+        path, limits,
+    )?)?)
+}
+
+/// Searches the specified raw memory image, such as a firmware image produced by `objcopy -O binary`,
+/// for the audit data placed into it by the linker script fragment generated by
+/// `cargo auditable emit-object --linker-script`. Use this if the location of the audit data is not known.
+///
+/// The file is streamed rather than loaded into memory, so images of any size can be scanned
+/// and `limits.input_file_size` does not apply. At most a few MiB plus `limits.decompressed_json_size`
+/// are held in memory at once.
+///
+/// Returns the decompressed audit data.
+/// This is useful if you want to forward the data somewhere instead of parsing it to Rust data structures.
+pub fn json_from_image_file(path: &Path, limits: Limits) -> Result<String, Error> {
+    let file = File::open(path)?;
+    let mut reader = BufReader::new(file);
+    json_from_image_reader(&mut reader, limits)
+}
+
+/// Searches a raw memory image loaded from an arbitrary reader for audit data.
+/// See [`json_from_image_file`] for details.
+pub fn json_from_image_reader<T: BufRead>(reader: &mut T, limits: Limits) -> Result<String, Error> {
+    Ok(json_and_provenance_from_image_reader(reader, limits)?.0)
+}
+
+/// Like [`json_from_image_file`], but also returns the [`Provenance::ImageScan`] of the audit data.
+///
+/// The audit data is located by a heuristic, so it may come from another program in the same image.
+/// The provenance records how many other candidates were rejected before it was found.
+pub fn json_and_provenance_from_image_file(
+    path: &Path,
+    limits: Limits,
+) -> Result<(String, Provenance), Error> {
+    let file = File::open(path)?;
+    let mut reader = BufReader::new(file);
+    json_and_provenance_from_image_reader(&mut reader, limits)
+}
+
+/// Like [`json_from_image_reader`], but also returns the [`Provenance::ImageScan`] of the audit data.
+/// See [`json_and_provenance_from_image_file`] for details.
+pub fn json_and_provenance_from_image_reader<T: BufRead>(
+    reader: &mut T,
+    limits: Limits,
+) -> Result<(String, Provenance), Error> {
+    let (json, rejected_candidates) = image_scan::json_from_image_stream(reader, limits)?;
+    Ok((
+        json,
+        Provenance::ImageScan {
+            rejected_candidates,
+        },
+    ))
+}
+
+/// Loads the audit info of the executable of a crashed process from a Windows minidump.
+///
+/// The audit data is read from the memory the executable was loaded into, so the dump must have
+/// been written with that memory, e.g. with `MiniDumpWithFullMemory`. Such dumps are large and
+/// are read into memory in full, so `limits.input_file_size` applies and may need to be raised.
+///
+/// The audit data is recovered from the memory of the process rather than read from the executable,
+/// so its provenance is [`Provenance::Minidump`] and it should be treated as a best-effort result.
+///
+/// The data is validated to not contain any circular dependencies.
+#[cfg(feature = "serde")]
+pub fn audit_info_from_minidump_file(path: &Path, limits: Limits) -> Result<VersionInfo, Error> {
+    Ok(VersionInfo::from_json(&json_from_minidump_file(
+        path, limits,
+    )?)?)
+}
+
+/// Extracts the audit data of the executable of a crashed process from a Windows minidump
+/// and returns the JSON string. See [`audit_info_from_minidump_file`] for details.
+pub fn json_from_minidump_file(path: &Path, limits: Limits) -> Result<String, Error> {
+    let file = File::open(path)?;
+    let mut reader = BufReader::new(file);
+    let minidump = read_input(&mut reader, limits)?;
+    json_from_minidump_slice(&minidump, limits.decompressed_json_size)
+}
+
+/// Extracts the audit data of the executable of a crashed process from a Windows minidump
+/// that has already been loaded into memory, e.g. via memory-mapping, and returns the JSON string.
+pub fn json_from_minidump_slice(
+    minidump: &[u8],
+    decompressed_json_size_limit: usize,
+) -> Result<String, Error> {
+    let compressed_audit_data = check_not_detached(raw_auditable_data_from_minidump(minidump)?)?;
+    decompress_json(compressed_audit_data, decompressed_json_size_limit)
+}
+
+fn decompress_json(
+    compressed_audit_data: &[u8],
+    decompressed_json_size_limit: usize,
+) -> Result<String, Error> {
     if compressed_audit_data.len() > decompressed_json_size_limit {
         Err(Error::OutputLimitExceeded)?;
     }
-    let decompressed_data =
-        decompress_to_vec_zlib_with_limit(compressed_audit_data, decompressed_json_size_limit)?;
+    let decompressed_data = match compressed_audit_data.split_first() {
+        Some((&ZSTD_MARKER, frame)) => decompress_zstd(frame, decompressed_json_size_limit)?,
+        // Already checked against the limit above
+        Some((&UNCOMPRESSED_MARKER, _)) => compressed_audit_data.to_vec(),
+        _ => {
+            decompress_to_vec_zlib_with_limit(compressed_audit_data, decompressed_json_size_limit)?
+        }
+    };
     Ok(String::from_utf8(decompressed_data)?)
 }
 
+#[cfg(feature = "zstd")]
+fn decompress_zstd(frame: &[u8], decompressed_json_size_limit: usize) -> Result<Vec<u8>, Error> {
+    let mut input = frame;
+    let decoder = ruzstd::StreamingDecoder::new(&mut input).map_err(|e| {
+        Error::ZstdDecompression(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{e:?}"),
+        ))
+    })?;
+    let mut output = Vec::new();
+    // Read one byte past the limit to tell data of exactly the maximum size apart from larger data
+    decoder
+        .take(decompressed_json_size_limit as u64 + 1)
+        .read_to_end(&mut output)
+        .map_err(Error::ZstdDecompression)?;
+    if output.len() > decompressed_json_size_limit {
+        return Err(Error::OutputLimitExceeded);
+    }
+    Ok(output)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress_zstd(_frame: &[u8], _decompressed_json_size_limit: usize) -> Result<Vec<u8>, Error> {
+    Err(Error::UnsupportedCompression)
+}
+
+/// Converts all line endings in the JSON returned by the `json_from_*` functions to `\n`.
+///
+/// The `json_from_*` functions already validate that the data is UTF-8, so it is safe to forward
+/// to text-only channels. However, a malicious binary may embed JSON that uses `\r\n` or a lone `\r`
+/// as whitespace, which is returned as is and may be mangled or rejected by line-oriented channels.
+/// JSON does not allow raw line breaks inside strings, so this never changes the meaning of valid JSON.
+pub fn normalize_line_endings(json: String) -> String {
+    if !json.contains('\r') {
+        return json;
+    }
+    json.replace("\r\n", "\n").replace('\r', "\n")
+}
+
 /// Protects against [denial-of-service attacks](https://en.wikipedia.org/wiki/Denial-of-service_attack)
 /// via infinite input streams or [zip bombs](https://en.wikipedia.org/wiki/Zip_bomb),
 /// which would otherwise use up all your memory and crash your machine.
@@ -152,6 +550,10 @@ pub fn json_from_slice(
 ///
 /// The default limits are **1 GiB** for the `input_file_size` and **8 MiB** for `decompressed_json_size`.
 ///
+/// The `input_file_size` limit only applies to functions that load the entire binary into memory.
+/// [`json_from_file`], [`json_from_seekable_reader`] and the functions built on them
+/// only read the headers and the audit data, so it does not apply to them.
+///
 /// Note that the `decompressed_json_size` is only enforced on the level of the *serialized* JSON, i.e. a string.
 /// We do not enforce that `serde_json` does not consume more memory when deserializing JSON to Rust data structures.
 /// Unfortunately Rust does not provide APIs for that.
@@ -189,4 +591,78 @@ mod tests {
             .to_string()
             .contains("The input file is too large"));
     }
+
+    #[test]
+    fn line_endings() {
+        let json = "{\r\n\"packages\":\r[]\n}".to_owned();
+        assert_eq!(normalize_line_endings(json), "{\n\"packages\":\n[]\n}");
+    }
+
+    #[test]
+    fn raw_image() {
+        let json = r#"{"packages":[]}"#;
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(json.as_bytes(), 7);
+        // the audit data is preceded by the code and followed by the rest of the flash contents
+        let mut image = vec![0xffu8; 64];
+        image.extend_from_slice(&auditable_extract::frame_header(&compressed).unwrap());
+        image.extend_from_slice(&compressed);
+        image.extend_from_slice(&[0xff; 64]);
+        assert_eq!(json_from_image_slice(&image, 64, 1024).unwrap(), json);
+        assert!(json_from_image_slice(&image, 0, 1024).is_err());
+        // the location of the data is not needed when scanning the image
+        let mut reader = std::io::Cursor::new(image);
+        let scanned = json_from_image_reader(&mut reader, Default::default()).unwrap();
+        assert_eq!(scanned, json);
+    }
+
+    /// A Zstandard frame holding `data`, which must be shorter than 256 bytes, in a single raw block
+    fn zstd_frame(data: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0x28, 0xb5, 0x2f, 0xfd];
+        // Single segment with a 1-byte content size and no checksum
+        frame.extend_from_slice(&[0x20, data.len() as u8]);
+        // The last block, uncompressed
+        let block_header = 1 | ((data.len() as u32) << 3);
+        frame.extend_from_slice(&block_header.to_le_bytes()[..3]);
+        frame.extend_from_slice(data);
+        frame
+    }
+
+    #[test]
+    fn zstd_payload() {
+        let json = r#"{"packages":[]}"#;
+        let mut payload = vec![ZSTD_MARKER];
+        payload.extend_from_slice(&zstd_frame(json.as_bytes()));
+        let result = decompress_json(&payload, 1024);
+        #[cfg(feature = "zstd")]
+        assert_eq!(result.unwrap(), json);
+        #[cfg(not(feature = "zstd"))]
+        assert!(matches!(result, Err(Error::UnsupportedCompression)));
+    }
+
+    #[test]
+    fn uncompressed_payload() {
+        let json = r#"{"packages":[]}"#;
+        assert_eq!(decompress_json(json.as_bytes(), 1024).unwrap(), json);
+        assert!(matches!(
+            decompress_json(json.as_bytes(), json.len() - 1),
+            Err(Error::OutputLimitExceeded)
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn markers_match_producer() {
+        assert_eq!(ZSTD_MARKER, auditable_serde::ZSTD_MARKER);
+        assert_eq!(UNCOMPRESSED_MARKER, auditable_serde::UNCOMPRESSED_MARKER);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn newer_format() {
+        let json = r#"{"format":1000,"packages":[],"from_the_future":true}"#.to_owned();
+        assert!(matches!(
+            parse_keeping_json(json),
+            Err(Error::NewerFormat(1000))
+        ));
+    }
 }
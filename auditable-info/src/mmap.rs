@@ -0,0 +1,48 @@
+//! Extraction of audit data from memory-mapped binaries, enabled by the `mmap` feature.
+//!
+//! This is the only place in the crate that uses `unsafe`: mapping a file is only sound
+//! if nothing modifies or truncates the file while it is mapped, which Rust cannot check.
+#![allow(unsafe_code)]
+
+#[cfg(feature = "serde")]
+use crate::audit_info_from_slice;
+use crate::{json_from_slice, Error, Limits};
+#[cfg(feature = "serde")]
+use auditable_serde::VersionInfo;
+use memmap2::Mmap;
+use std::fs::File;
+use std::path::Path;
+
+/// Loads audit info from the specified binary by memory-mapping it and calling [`audit_info_from_slice`].
+///
+/// Only the pages holding the headers and the audit data are read from the disk,
+/// so scanning many large binaries does not evict the rest of the page cache.
+/// If the binary has no audit data section, all of it is read to look for audit data appended to it.
+/// Since the file is not copied into memory, `limits.input_file_size` does not apply.
+///
+/// The data is validated to not contain any circular dependencies.
+///
+/// # Safety
+///
+/// The file must not be modified or truncated by this or any other process until this function returns.
+/// Otherwise the parser would observe the data changing underneath it, which is undefined behavior,
+/// and on some platforms the process is killed if the file is truncated.
+#[cfg(feature = "serde")]
+pub unsafe fn audit_info_from_mmap(path: &Path, limits: Limits) -> Result<VersionInfo, Error> {
+    let file = File::open(path)?;
+    let map = Mmap::map(&file)?;
+    audit_info_from_slice(&map, limits.decompressed_json_size)
+}
+
+/// Extracts the audit data from the specified binary by memory-mapping it and calling [`json_from_slice`].
+///
+/// See [`audit_info_from_mmap`] for details.
+///
+/// # Safety
+///
+/// The file must not be modified or truncated by this or any other process until this function returns.
+pub unsafe fn json_from_mmap(path: &Path, limits: Limits) -> Result<String, Error> {
+    let file = File::open(path)?;
+    let map = Mmap::map(&file)?;
+    json_from_slice(&map, limits.decompressed_json_size)
+}
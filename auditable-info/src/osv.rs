@@ -0,0 +1,346 @@
+//! Advisories in the [OSV format](https://ossf.github.io/osv-schema/), which most vulnerability
+//! databases can export, including the RustSec database and internal feeds.
+
+use crate::advisories::{Advisory, AdvisorySource, SourceError};
+use auditable_serde::{Package, VersionInfo};
+use semver::Version;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// OSV ecosystem of the packages on crates.io
+const ECOSYSTEM: &str = "crates.io";
+
+/// Advisories in the OSV format, loaded from JSON files.
+///
+/// Only the packages listed as affected in the `crates.io` ecosystem are checked. They are matched
+/// by their `versions` and by `SEMVER` or `ECOSYSTEM` ranges; `GIT` ranges are ignored.
+/// Withdrawn advisories are not reported.
+///
+/// ```rust
+/// use auditable_info::advisories::{AdvisorySource, OsvDatabase};
+/// use auditable_serde::VersionInfo;
+/// use std::str::FromStr;
+/// let database = OsvDatabase::from_json(r#"{
+///     "id": "INTERNAL-2023-0001",
+///     "summary": "Use of the deprecated backend",
+///     "affected": [{
+///         "package": {"ecosystem": "crates.io", "name": "adler"},
+///         "ranges": [{"type": "SEMVER", "events": [{"introduced": "0"}, {"fixed": "1.0.0"}]}]
+///     }]
+/// }"#).unwrap();
+/// let json_str = r#"{"packages":[{"name":"adler","version":"0.2.3","source":"crates.io","root":true}]}"#;
+/// let info = VersionInfo::from_str(json_str).unwrap();
+/// let affected = database.affected(&info).unwrap();
+/// assert_eq!(affected[0].1.id, "INTERNAL-2023-0001");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OsvDatabase {
+    name: Option<String>,
+    records: Vec<Record>,
+}
+
+#[derive(Debug, Clone)]
+struct Record {
+    advisory: Advisory,
+    affected: Vec<Affected>,
+}
+
+/// An entry of the `affected` list of an OSV record
+#[derive(Debug, Clone)]
+struct Affected {
+    name: String,
+    versions: Vec<String>,
+    ranges: Vec<Vec<Event>>,
+}
+
+#[derive(Debug, Clone)]
+enum Event {
+    Introduced(Version),
+    Fixed(Version),
+    LastAffected(Version),
+}
+
+impl Event {
+    fn version(&self) -> &Version {
+        match self {
+            Event::Introduced(v) | Event::Fixed(v) | Event::LastAffected(v) => v,
+        }
+    }
+}
+
+/// Error returned when loading an [`OsvDatabase`]
+#[derive(Debug)]
+pub enum OsvError {
+    Io(PathBuf, std::io::Error),
+    Json(serde_json::Error),
+    /// The JSON is not an OSV record, e.g. because it has no `id`
+    InvalidRecord(String),
+}
+
+impl std::error::Error for OsvError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OsvError::Io(_, e) => Some(e),
+            OsvError::Json(e) => Some(e),
+            OsvError::InvalidRecord(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for OsvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OsvError::Io(path, e) => write!(f, "Failed to read '{}': {e}", path.display()),
+            OsvError::Json(e) => write!(f, "Failed to parse OSV JSON: {e}"),
+            OsvError::InvalidRecord(message) => write!(f, "Invalid OSV record: {message}"),
+        }
+    }
+}
+
+impl From<serde_json::Error> for OsvError {
+    fn from(e: serde_json::Error) -> Self {
+        OsvError::Json(e)
+    }
+}
+
+impl OsvDatabase {
+    /// Loads every `.json` file in the directory at `path`, or the file at `path`
+    /// if it is not a directory. Each file holds a single OSV record or an array of them.
+    ///
+    /// The source is named after the file or directory in reports; use [`OsvDatabase::with_name`]
+    /// to name it after the feed instead.
+    pub fn open(path: &Path) -> Result<Self, OsvError> {
+        let io_error = |e| OsvError::Io(path.to_owned(), e);
+        let mut files = Vec::new();
+        if path.is_dir() {
+            for entry in std::fs::read_dir(path).map_err(io_error)? {
+                let file = entry.map_err(io_error)?.path();
+                if file.extension().map_or(false, |ext| ext == "json") {
+                    files.push(file);
+                }
+            }
+            // The order of the findings should not depend on the file system
+            files.sort();
+        } else {
+            files.push(path.to_owned());
+        }
+        let mut database = OsvDatabase::default();
+        for file in files {
+            let json = std::fs::read_to_string(&file).map_err(|e| OsvError::Io(file.clone(), e))?;
+            database.add_json(&json)?;
+        }
+        database.name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        Ok(database)
+    }
+
+    /// Parses a single OSV record or an array of them
+    pub fn from_json(json: &str) -> Result<Self, OsvError> {
+        let mut database = OsvDatabase::default();
+        database.add_json(json)?;
+        Ok(database)
+    }
+
+    /// Sets the name of the source shown in reports, e.g. the name of an internal feed
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    fn add_json(&mut self, json: &str) -> Result<(), OsvError> {
+        match serde_json::from_str(json)? {
+            Value::Array(records) => {
+                for record in &records {
+                    self.add_record(record)?;
+                }
+                Ok(())
+            }
+            record => self.add_record(&record),
+        }
+    }
+
+    fn add_record(&mut self, record: &Value) -> Result<(), OsvError> {
+        let id = record["id"]
+            .as_str()
+            .ok_or_else(|| OsvError::InvalidRecord("missing `id`".to_owned()))?;
+        if !record["withdrawn"].is_null() {
+            return Ok(());
+        }
+        let title = record["summary"]
+            .as_str()
+            .or_else(|| record["details"].as_str().and_then(|d| d.lines().next()))
+            .unwrap_or(id);
+        let aliases = strings(&record["aliases"]);
+        let affected: Vec<Affected> = record["affected"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|affected| affected["package"]["ecosystem"] == ECOSYSTEM)
+            .filter_map(|affected| {
+                Some(Affected {
+                    name: affected["package"]["name"].as_str()?.to_owned(),
+                    versions: strings(&affected["versions"]),
+                    ranges: affected["ranges"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter(|range| range["type"] == "SEMVER" || range["type"] == "ECOSYSTEM")
+                        .map(|range| events(&range["events"]))
+                        .collect(),
+                })
+            })
+            .collect();
+        if !affected.is_empty() {
+            self.records.push(Record {
+                advisory: Advisory {
+                    id: id.to_owned(),
+                    aliases,
+                    title: title.to_owned(),
+                },
+                affected,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl AdvisorySource for OsvDatabase {
+    fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or("OSV")
+    }
+
+    fn affected<'a>(
+        &self,
+        info: &'a VersionInfo,
+    ) -> Result<Vec<(&'a Package, Advisory)>, SourceError> {
+        let mut result = Vec::new();
+        for package in &info.packages {
+            for record in &self.records {
+                if record.affected.iter().any(|a| a.contains(package)) {
+                    result.push((package, record.advisory.clone()));
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl Affected {
+    fn contains(&self, package: &Package) -> bool {
+        if self.name != package.name {
+            return false;
+        }
+        let version = &package.version;
+        self.versions.iter().any(|v| *v == version.to_string())
+            || self.ranges.iter().any(|events| in_range(events, version))
+    }
+}
+
+/// Evaluates the events of a range as described in the OSV schema:
+/// the version is affected from an `introduced` event up to a `fixed` or after a `last_affected` one
+fn in_range(events: &[Event], version: &Version) -> bool {
+    let mut events: Vec<&Event> = events.iter().collect();
+    events.sort_by(|a, b| a.version().cmp(b.version()));
+    let mut affected = false;
+    for event in events {
+        match event {
+            Event::Introduced(v) if version >= v => affected = true,
+            Event::Fixed(v) if version >= v => affected = false,
+            Event::LastAffected(v) if version > v => affected = false,
+            _ => (),
+        }
+    }
+    affected
+}
+
+fn events(events: &Value) -> Vec<Event> {
+    let version = |v: &Value| match v.as_str()? {
+        // The start of all versions
+        "0" => Some(Version::new(0, 0, 0)),
+        v => Version::parse(v).ok(),
+    };
+    events
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|event| {
+            if let Some(v) = event.get("introduced") {
+                version(v).map(Event::Introduced)
+            } else if let Some(v) = event.get("fixed") {
+                version(v).map(Event::Fixed)
+            } else if let Some(v) = event.get("last_affected") {
+                version(v).map(Event::LastAffected)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn strings(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_str().map(str::to_owned))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const RECORDS: &str = r#"[
+        {
+            "id": "INTERNAL-2023-0001",
+            "aliases": ["CVE-2023-0001"],
+            "details": "Memory corruption\n\nMore details.",
+            "affected": [{
+                "package": {"ecosystem": "crates.io", "name": "vulnerable"},
+                "ranges": [{"type": "SEMVER", "events": [
+                    {"introduced": "0"}, {"fixed": "1.0.1"},
+                    {"introduced": "2.0.0"}, {"last_affected": "2.1.0"}
+                ]}]
+            }]
+        },
+        {
+            "id": "INTERNAL-2023-0002",
+            "withdrawn": "2023-02-01T00:00:00Z",
+            "affected": [{"package": {"ecosystem": "crates.io", "name": "vulnerable"}, "versions": ["1.0.0"]}]
+        },
+        {
+            "id": "PYSEC-2023-0001",
+            "affected": [{"package": {"ecosystem": "PyPI", "name": "vulnerable"}, "versions": ["1.0.0"]}]
+        }
+    ]"#;
+
+    fn affected_versions(versions: &[&str]) -> Vec<String> {
+        let database = OsvDatabase::from_json(RECORDS).unwrap();
+        let packages: Vec<String> = versions
+            .iter()
+            .map(|v| format!(r#"{{"name":"vulnerable","version":"{v}","source":"crates.io"}}"#))
+            .collect();
+        let json = format!(r#"{{"packages":[{}]}}"#, packages.join(","));
+        let info = VersionInfo::from_str(&json).unwrap();
+        let affected = database.affected(&info).unwrap();
+        for (_, advisory) in &affected {
+            assert_eq!(advisory.id, "INTERNAL-2023-0001");
+            assert_eq!(advisory.title, "Memory corruption");
+            assert_eq!(advisory.aliases, vec!["CVE-2023-0001"]);
+        }
+        affected
+            .iter()
+            .map(|(package, _)| package.version.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(
+            affected_versions(&["0.1.0", "1.0.0", "1.0.1", "2.0.0", "2.1.0", "2.1.1"]),
+            vec!["0.1.0", "1.0.0", "2.0.0", "2.1.0"]
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! Records how the audit data was located, so that data recovered by heuristics
+//! is never mistaken for the data authoritatively embedded in a binary.
+
+/// How the audit data was located in the input.
+///
+/// Audit data read from the audit data section of an executable is what `cargo auditable` embedded.
+/// Audit data found by searching a raw memory image or recovered from the memory of a process
+/// is a best-effort result: it may belong to another program in the same image,
+/// or to a different build of the program than the one that was running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Provenance {
+    /// Read from the audit data section of an executable, located through its headers,
+    /// or from the frame that `copy_audit_data` appends to a repackaged binary
+    Embedded,
+    /// Read from a raw memory image at the offset of the audit data given by the caller
+    ImageOffset,
+    /// Found by searching a raw memory image for the magic bytes of framed audit data.
+    /// `rejected_candidates` is the number of earlier occurrences of the magic that turned out
+    /// not to be valid audit data; if it is non-zero, the image contains bytes that look like audit data.
+    ImageScan { rejected_candidates: usize },
+    /// Recovered from the memory the main executable was loaded into, captured in a minidump
+    Minidump,
+}
+
+/// Whether the audit data can be relied on to describe the binary it was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Confidence {
+    /// The data was found by a heuristic and may not describe the binary
+    BestEffort,
+    /// The data is what `cargo auditable` embedded into the binary
+    Authoritative,
+}
+
+impl Provenance {
+    /// Returns how far the audit data located this way can be relied on
+    pub fn confidence(self) -> Confidence {
+        match self {
+            Provenance::Embedded | Provenance::ImageOffset => Confidence::Authoritative,
+            Provenance::ImageScan { .. } | Provenance::Minidump => Confidence::BestEffort,
+        }
+    }
+
+    /// Returns the name of the provenance used in machine-readable output,
+    /// such as `"embedded"` or `"image-scan"`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Provenance::Embedded => "embedded",
+            Provenance::ImageOffset => "image-offset",
+            Provenance::ImageScan { .. } => "image-scan",
+            Provenance::Minidump => "minidump",
+        }
+    }
+}
+
+impl Confidence {
+    /// Returns the name of the confidence level used in machine-readable output,
+    /// `"authoritative"` or `"best-effort"`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Confidence::Authoritative => "authoritative",
+            Confidence::BestEffort => "best-effort",
+        }
+    }
+}
+
+impl std::fmt::Display for Provenance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::fmt::Display for Confidence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heuristics_are_best_effort() {
+        assert_eq!(Provenance::Embedded.confidence(), Confidence::Authoritative);
+        assert_eq!(
+            Provenance::ImageOffset.confidence(),
+            Confidence::Authoritative
+        );
+        let scan = Provenance::ImageScan {
+            rejected_candidates: 0,
+        };
+        assert_eq!(scan.confidence(), Confidence::BestEffort);
+        assert_eq!(scan.to_string(), "image-scan");
+        assert_eq!(Provenance::Minidump.confidence(), Confidence::BestEffort);
+        assert_eq!(Confidence::BestEffort.to_string(), "best-effort");
+    }
+}
@@ -0,0 +1,210 @@
+//! Recursive scanning of directory trees, e.g. the file system of a container image.
+
+use crate::{audit_info_from_file, Error, Limits};
+use auditable_extract::{is_executable, EXECUTABLE_MAGIC_LEN};
+use auditable_serde::VersionInfo;
+use std::fs::{self, File, FileType};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Walks the directory tree at `path` and loads the audit info from every executable in it.
+///
+/// Executables are recognized by their magic bytes rather than their names or permissions,
+/// so Windows executables and WebAssembly modules are found too, and so are static libraries
+/// and rlibs, which are searched for object files with audit data. Other files are skipped
+/// without being read past the first few bytes. Each executable gets a result, which is
+/// [`Error::NoAuditData`] for those not built with `cargo auditable`.
+/// Failures to read a directory or a file are reported with the path they occurred on,
+/// and the walk continues past them.
+///
+/// Symbolic links inside the tree are not followed, so the walk does not loop or leave
+/// the tree through them; `path` itself is followed if it is a link. Special files such as
+/// devices and pipes are skipped. The tree is walked depth-first in lexicographic order
+/// of the entry names, and lazily: directories are only read as the iterator advances.
+///
+/// If `path` is a file rather than a directory, it is the only one checked.
+pub fn scan_dir(path: &Path, limits: Limits) -> ScanDir {
+    ScanDir {
+        executables: Executables::new(path),
+        limits,
+    }
+}
+
+/// Like [`scan_dir`], but extracts the audit data from the executables on all the threads
+/// of the [`rayon`] thread pool, for scanning large file systems such as a container image.
+///
+/// The directory tree is walked on one thread while the executables found so far
+/// are being processed, so the results are returned in no particular order.
+#[cfg(feature = "rayon")]
+pub fn par_scan_dir(
+    path: &Path,
+    limits: Limits,
+) -> impl rayon::iter::ParallelIterator<Item = (PathBuf, Result<VersionInfo, Error>)> {
+    use rayon::iter::{ParallelBridge, ParallelIterator};
+    Executables::new(path)
+        .par_bridge()
+        .map(move |executable| extract(executable, limits))
+}
+
+/// Iterator returned by [`scan_dir`]
+pub struct ScanDir {
+    executables: Executables,
+    limits: Limits,
+}
+
+impl Iterator for ScanDir {
+    type Item = (PathBuf, Result<VersionInfo, Error>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let limits = self.limits;
+        self.executables
+            .next()
+            .map(|executable| extract(executable, limits))
+    }
+}
+
+fn extract(
+    executable: Result<PathBuf, (PathBuf, Error)>,
+    limits: Limits,
+) -> (PathBuf, Result<VersionInfo, Error>) {
+    match executable {
+        Ok(path) => {
+            let result = audit_info_from_file(&path, limits);
+            (path, result)
+        }
+        Err((path, error)) => (path, Err(error)),
+    }
+}
+
+/// Walks a directory tree and returns the paths of the executables in it,
+/// along with the failures to read directories and files
+struct Executables {
+    root: Option<PathBuf>,
+    /// Entries yet to be visited, the next one last
+    pending: Vec<(PathBuf, FileType)>,
+    /// Failures to read directory entries, reported before continuing the walk
+    errors: Vec<(PathBuf, Error)>,
+}
+
+impl Executables {
+    fn new(root: &Path) -> Self {
+        Executables {
+            root: Some(root.to_owned()),
+            pending: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn read_dir(&mut self, dir: &Path) -> std::io::Result<()> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            match entry.and_then(|entry| Ok((entry.path(), entry.file_type()?))) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => self.errors.push((dir.to_owned(), e.into())),
+            }
+        }
+        // Sorted in reverse because the entries are popped off the end
+        entries.sort_by(|a, b| b.0.cmp(&a.0));
+        self.pending.extend(entries);
+        Ok(())
+    }
+}
+
+impl Iterator for Executables {
+    type Item = Result<PathBuf, (PathBuf, Error)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(error) = self.errors.pop() {
+                return Some(Err(error));
+            }
+            let (path, file_type) = match self.root.take() {
+                Some(root) => match fs::metadata(&root) {
+                    Ok(metadata) => (root, metadata.file_type()),
+                    Err(e) => return Some(Err((root, e.into()))),
+                },
+                None => self.pending.pop()?,
+            };
+            if file_type.is_dir() {
+                if let Err(e) = self.read_dir(&path) {
+                    return Some(Err((path, e.into())));
+                }
+            } else if file_type.is_file() {
+                match has_executable_magic(&path) {
+                    Ok(true) => return Some(Ok(path)),
+                    Ok(false) => continue,
+                    Err(e) => return Some(Err((path, e.into()))),
+                }
+            }
+            // Symbolic links and special files are skipped
+        }
+    }
+}
+
+fn has_executable_magic(path: &Path) -> std::io::Result<bool> {
+    let mut header = Vec::with_capacity(EXECUTABLE_MAGIC_LEN);
+    File::open(path)?
+        .take(EXECUTABLE_MAGIC_LEN as u64)
+        .read_to_end(&mut header)?;
+    Ok(is_executable(&header))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_executables_are_checked() {
+        let root = std::env::temp_dir().join(format!("auditable-scan-dir-{}", std::process::id()));
+        let nested = root.join("usr").join("bin");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join("README"), "not an executable").unwrap();
+        fs::write(nested.join("truncated-elf"), b"\x7fELF").unwrap();
+        fs::write(nested.join("empty"), b"").unwrap();
+        fs::write(root.join("app.exe"), b"MZ").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&nested, root.join("link")).unwrap();
+
+        let results: Vec<_> = scan_dir(&root, Default::default()).collect();
+        fs::remove_dir_all(&root).unwrap();
+
+        let paths: Vec<_> = results.iter().map(|(path, _)| path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![root.join("app.exe"), nested.join("truncated-elf")]
+        );
+        for (_, result) in results {
+            assert!(matches!(result, Err(Error::BinaryParsing(_))));
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_scan() {
+        use rayon::iter::ParallelIterator;
+        let root =
+            std::env::temp_dir().join(format!("auditable-par-scan-dir-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        for i in 0..16 {
+            fs::write(root.join(format!("{i}.exe")), b"MZ").unwrap();
+            fs::write(root.join(format!("{i}.txt")), "not an executable").unwrap();
+        }
+        let mut results: Vec<_> = par_scan_dir(&root, Default::default()).collect();
+        fs::remove_dir_all(&root).unwrap();
+
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut expected: Vec<_> = (0..16).map(|i| root.join(format!("{i}.exe"))).collect();
+        expected.sort();
+        let paths: Vec<_> = results.into_iter().map(|(path, _)| path).collect();
+        assert_eq!(paths, expected);
+    }
+
+    #[test]
+    fn missing_root() {
+        let missing = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("no-such-dir");
+        let results: Vec<_> = scan_dir(&missing, Default::default()).collect();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, missing);
+        assert!(matches!(results[0].1, Err(Error::Io(_))));
+    }
+}
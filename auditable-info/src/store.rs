@@ -0,0 +1,104 @@
+//! Resolution of audit data stored outside of the binary in a content-addressed store.
+//!
+//! The store is a directory laid out like the blobs of an
+//! [OCI image layout](https://github.com/opencontainers/image-spec/blob/main/image-layout.md):
+//! the compressed audit data is stored in `blobs/sha256/<hex digest>`.
+//! This lets the same data be served from a plain directory or pushed to an OCI registry as-is.
+
+use crate::sha256::sha256_hex;
+use crate::{decompress_json, read_input, Error, Limits};
+#[cfg(feature = "serde")]
+use auditable_serde::VersionInfo;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+/// Refers to audit data that is not stored in the binary itself.
+///
+/// Returned in [`Error::DetachedPayload`] when the binary only contains a pointer to its audit data.
+/// Pass it to [`json_from_store`] or [`audit_info_from_store`] to obtain the actual audit data.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DetachedPayload {
+    /// Lowercase hex-encoded SHA-256 digest of the compressed audit data
+    pub sha256: String,
+    /// Location of the store the audit data was published to, if it was recorded at build time
+    pub url: Option<String>,
+}
+
+impl DetachedPayload {
+    /// Path to the audit data relative to the root of the store
+    pub fn path_in_store(&self) -> PathBuf {
+        Path::new("blobs").join("sha256").join(&self.sha256)
+    }
+}
+
+impl From<auditable_extract::PayloadPointer<'_>> for DetachedPayload {
+    fn from(pointer: auditable_extract::PayloadPointer<'_>) -> Self {
+        Self {
+            sha256: pointer.sha256.to_owned(),
+            url: pointer.url.map(|url| url.to_owned()),
+        }
+    }
+}
+
+/// Loads the audit data referred to by the pointer from a local copy of the store.
+///
+/// The digest of the data is verified before it is decompressed.
+///
+/// The data is validated to not contain any circular dependencies.
+#[cfg(feature = "serde")]
+pub fn audit_info_from_store(
+    store: &Path,
+    payload: &DetachedPayload,
+    limits: Limits,
+) -> Result<VersionInfo, Error> {
+    Ok(VersionInfo::from_json(&json_from_store(
+        store, payload, limits,
+    )?)?)
+}
+
+/// Loads the audit data referred to by the pointer from a local copy of the store
+/// and returns the JSON string.
+///
+/// The digest of the data is verified before it is decompressed.
+pub fn json_from_store(
+    store: &Path,
+    payload: &DetachedPayload,
+    limits: Limits,
+) -> Result<String, Error> {
+    let file = File::open(store.join(payload.path_in_store()))?;
+    let compressed_audit_data = read_input(&mut BufReader::new(file), limits)?;
+    if sha256_hex(&compressed_audit_data) != payload.sha256 {
+        return Err(Error::DigestMismatch);
+    }
+    decompress_json(&compressed_audit_data, limits.decompressed_json_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_from_store() {
+        let json = r#"{"packages":[]}"#;
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(json.as_bytes(), 7);
+        let payload = DetachedPayload {
+            sha256: sha256_hex(&compressed),
+            url: None,
+        };
+        let store = std::env::temp_dir().join(format!("auditable-store-{}", std::process::id()));
+        let blob_path = store.join(payload.path_in_store());
+        std::fs::create_dir_all(blob_path.parent().unwrap()).unwrap();
+        std::fs::write(&blob_path, &compressed).unwrap();
+        assert_eq!(
+            json_from_store(&store, &payload, Default::default()).unwrap(),
+            json
+        );
+
+        // tampered data must be rejected
+        std::fs::write(&blob_path, b"something else").unwrap();
+        let result = json_from_store(&store, &payload, Default::default());
+        assert!(matches!(result, Err(Error::DigestMismatch)));
+        std::fs::remove_dir_all(&store).unwrap();
+    }
+}
@@ -0,0 +1,222 @@
+//! Synthetic binaries with audit data, for benchmarking extraction and parsing.
+//!
+//! The binaries are minimal but valid executables of a configurable size holding audit data
+//! with a configurable number of packages. They are generated in memory in a few milliseconds,
+//! so benchmarks can sweep over sizes that would be impractical to check into a repository.
+//! `cargo bench --features bench` runs the benchmarks of this crate that are built on them;
+//! they can also be used to check that extraction fits the time budget on your own hardware.
+//!
+//! ```rust, ignore
+//! let binary = SyntheticBinary::new(SyntheticFormat::Elf)
+//!     .size(64 * 1024 * 1024)
+//!     .packages(1000);
+//! let data = binary.build();
+//! let info = audit_info_from_slice(&data, Limits::default().decompressed_json_size)?;
+//! assert_eq!(info.packages.len(), 1000);
+//! ```
+
+use auditable_serde::{LocalSource, Package, Source, VersionInfo, VersionInfoBuilder};
+use semver::Version;
+use std::convert::TryFrom;
+
+/// Executable formats that synthetic binaries can be generated in
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntheticFormat {
+    /// 64-bit little-endian ELF, with the audit data in the `.dep-v0` section
+    Elf,
+    /// WebAssembly module, with the audit data in the `.dep-v0` custom section
+    Wasm,
+}
+
+impl SyntheticFormat {
+    /// All the supported formats
+    pub const ALL: &'static [SyntheticFormat] = &[SyntheticFormat::Elf, SyntheticFormat::Wasm];
+
+    /// Short identifier of the format, usable as a benchmark name
+    pub fn name(self) -> &'static str {
+        match self {
+            SyntheticFormat::Elf => "elf",
+            SyntheticFormat::Wasm => "wasm",
+        }
+    }
+}
+
+/// Describes a synthetic binary. Call [`SyntheticBinary::build`] to generate it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SyntheticBinary {
+    format: SyntheticFormat,
+    size: usize,
+    packages: usize,
+}
+
+impl SyntheticBinary {
+    /// A binary of the given format with 100 packages, padded to 1 MiB
+    pub fn new(format: SyntheticFormat) -> Self {
+        Self {
+            format,
+            size: 1024 * 1024,
+            packages: 100,
+        }
+    }
+
+    /// Sets the size of the binary in bytes, which is exact for ELF and within a few bytes
+    /// for WebAssembly. The headers and the audit data come first,
+    /// and the rest is zeroes standing in for code. If the audit data alone is larger,
+    /// the binary is as large as needed to hold it.
+    pub fn size(mut self, size: usize) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Sets the number of packages in the audit data, including the root package.
+    /// At least one package, the root, is always present.
+    pub fn packages(mut self, packages: usize) -> Self {
+        self.packages = packages.max(1);
+        self
+    }
+
+    /// The audit data embedded in the binary.
+    ///
+    /// The root package depends on every other package, and each package depends on the one
+    /// added before it, so the dependency graph is deep as well as wide.
+    pub fn audit_info(&self) -> VersionInfo {
+        let mut builder = VersionInfoBuilder::new();
+        let mut root = Package::new(
+            "synthetic",
+            Version::new(0, 1, 0),
+            Source::Local(LocalSource::default()),
+        );
+        root.root = true;
+        let root = builder.package(root);
+        for i in 1..self.packages {
+            let version = Version::new(1, i as u64, 0);
+            let package = builder.package(Package::new(
+                &format!("crate-{i}"),
+                version,
+                Source::CratesIo,
+            ));
+            builder.dependency(root, package);
+            if i > 1 {
+                builder.dependency(package, package - 1);
+            }
+        }
+        builder
+            .build()
+            .expect("Synthetic audit data failed validation")
+    }
+
+    /// Generates the binary
+    pub fn build(&self) -> Vec<u8> {
+        let payload = self.audit_info().to_compressed_payload();
+        match self.format {
+            SyntheticFormat::Elf => elf(&payload, self.size),
+            SyntheticFormat::Wasm => wasm(&payload, self.size),
+        }
+    }
+}
+
+/// An ELF file with a null section, the section names and the audit data,
+/// followed by the padding and the section headers, like a linker would lay it out
+fn elf(payload: &[u8], size: usize) -> Vec<u8> {
+    const HEADER_LEN: usize = 64;
+    const SECTION_HEADER_LEN: usize = 64;
+    let names = b"\0.shstrtab\0.dep-v0\0";
+    let names_offset = HEADER_LEN;
+    let payload_offset = names_offset + names.len();
+    let table_len = 3 * SECTION_HEADER_LEN;
+    let table_offset = (payload_offset + payload.len()).max(size.saturating_sub(table_len));
+    let mut data = vec![0u8; table_offset + table_len];
+    data[..7].copy_from_slice(b"\x7fELF\x02\x01\x01");
+    // An executable for x86_64
+    data[0x10..0x12].copy_from_slice(&2u16.to_le_bytes());
+    data[0x12..0x14].copy_from_slice(&0x3Eu16.to_le_bytes());
+    data[0x14..0x18].copy_from_slice(&1u32.to_le_bytes());
+    data[0x28..0x30].copy_from_slice(&(table_offset as u64).to_le_bytes());
+    data[0x34..0x36].copy_from_slice(&(HEADER_LEN as u16).to_le_bytes());
+    data[0x3A..0x3C].copy_from_slice(&(SECTION_HEADER_LEN as u16).to_le_bytes());
+    data[0x3C..0x3E].copy_from_slice(&3u16.to_le_bytes());
+    data[0x3E..0x40].copy_from_slice(&1u16.to_le_bytes());
+    data[names_offset..payload_offset].copy_from_slice(names);
+    data[payload_offset..payload_offset + payload.len()].copy_from_slice(payload);
+    // SHT_STRTAB for the names and SHT_PROGBITS for the audit data
+    let sections = [
+        (1u32, 3u32, names_offset, names.len()),
+        (11, 1, payload_offset, payload.len()),
+    ];
+    for (index, (name, kind, offset, len)) in sections.iter().enumerate() {
+        let header = table_offset + (index + 1) * SECTION_HEADER_LEN;
+        data[header..header + 4].copy_from_slice(&name.to_le_bytes());
+        data[header + 4..header + 8].copy_from_slice(&kind.to_le_bytes());
+        data[header + 0x18..header + 0x20].copy_from_slice(&(*offset as u64).to_le_bytes());
+        data[header + 0x20..header + 0x28].copy_from_slice(&(*len as u64).to_le_bytes());
+        data[header + 0x30..header + 0x38].copy_from_slice(&1u64.to_le_bytes());
+    }
+    data
+}
+
+/// A WebAssembly module with the audit data, followed by a custom section holding the padding
+fn wasm(payload: &[u8], size: usize) -> Vec<u8> {
+    let mut data = b"\0asm\x01\0\0\0".to_vec();
+    custom_section(&mut data, ".dep-v0", payload);
+    // The padding section has a header of its own, which is at most 16 bytes long
+    let padding = size.saturating_sub(data.len() + 16);
+    custom_section(&mut data, "padding", &vec![0; padding]);
+    data
+}
+
+fn custom_section(data: &mut Vec<u8>, name: &str, contents: &[u8]) {
+    let mut section = Vec::new();
+    leb128(&mut section, name.len());
+    section.extend_from_slice(name.as_bytes());
+    section.extend_from_slice(contents);
+    data.push(0);
+    leb128(data, section.len());
+    data.extend_from_slice(&section);
+}
+
+fn leb128(data: &mut Vec<u8>, value: usize) {
+    let mut value = u32::try_from(value).expect("WebAssembly sections are limited to 4 GiB");
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            data.push(byte);
+            return;
+        }
+        data.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{json_from_seekable_reader, json_from_slice, Limits};
+
+    #[test]
+    fn audit_data_is_extracted() {
+        for &format in SyntheticFormat::ALL {
+            let binary = SyntheticBinary::new(format).size(100_000).packages(50);
+            let data = binary.build();
+            // The size of the section headers of WebAssembly depends on the size of the sections
+            assert!(data.len() <= 100_000 && data.len() > 99_980, "{:?}", format);
+            let expected = serde_json::to_string(&binary.audit_info()).unwrap();
+            let limit = Limits::default().decompressed_json_size;
+            assert_eq!(json_from_slice(&data, limit).unwrap(), expected);
+            let mut reader = std::io::Cursor::new(data);
+            let json = json_from_seekable_reader(&mut reader, Limits::default()).unwrap();
+            assert_eq!(json, expected);
+        }
+    }
+
+    #[test]
+    fn audit_data_larger_than_requested_size() {
+        let binary = SyntheticBinary::new(SyntheticFormat::Elf)
+            .size(0)
+            .packages(1);
+        let info = binary.audit_info();
+        assert_eq!(info.packages.len(), 1);
+        let limit = Limits::default().decompressed_json_size;
+        assert!(json_from_slice(&binary.build(), limit).is_ok());
+    }
+}
@@ -0,0 +1,134 @@
+//! Test vectors for producers of audit data other than `cargo auditable`, such as Buck or Bazel rules,
+//! to verify that they embed byte-for-byte identical data into binaries.
+//!
+//! Each vector lists every representation of the audit data from the JSON a producer starts from
+//! down to the contents of the section in the binary:
+//!
+//! 1. [`TestVector::input_json`] is parsed and serialized back to [`TestVector::canonical_json`],
+//! 2. which is Zlib-compressed into [`TestVector::compressed_payload`],
+//! 3. which ends up in the binary as [`TestVector::section_contents`] for each [`SectionFormat`].
+//!
+//! The payloads are produced by the same code as in `cargo auditable`, so they are the reference
+//! for a given version of this crate. `cargo auditable test-vectors` writes them all to disk
+//! for tools not written in Rust.
+
+use auditable_extract::frame_header;
+use auditable_serde::VersionInfo;
+use std::str::FromStr;
+
+/// A single test vector. See the [module documentation](self) for how the fields relate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TestVector {
+    /// Short identifier of the vector, usable as a file name
+    pub name: &'static str,
+    /// The audit data as a producer may write it, not necessarily in canonical form
+    pub input_json: &'static str,
+    /// The audit data as `cargo auditable` serializes it before compression
+    pub canonical_json: &'static str,
+}
+
+macro_rules! test_vector {
+    ($name:literal) => {
+        TestVector {
+            name: $name,
+            input_json: include_str!(concat!("../test-vectors/", $name, ".input.json")),
+            canonical_json: include_str!(concat!("../test-vectors/", $name, ".canonical.json")),
+        }
+    };
+}
+
+/// All the test vectors shipped with this crate
+pub const TEST_VECTORS: &[TestVector] = &[
+    test_vector!("minimal"),
+    test_vector!("build-dependency"),
+    test_vector!("all-fields"),
+    test_vector!("non-canonical"),
+];
+
+impl TestVector {
+    /// The Zlib-compressed [`canonical_json`](TestVector::canonical_json),
+    /// exactly as `cargo auditable` would embed it
+    pub fn compressed_payload(&self) -> Vec<u8> {
+        VersionInfo::from_str(self.input_json)
+            .expect("Invalid JSON in a test vector")
+            .to_compressed_payload()
+    }
+
+    /// The exact contents of the section holding the audit data in a binary of the given format
+    pub fn section_contents(&self, format: SectionFormat) -> Vec<u8> {
+        let payload = self.compressed_payload();
+        match format {
+            SectionFormat::Elf | SectionFormat::Pe | SectionFormat::MachO => payload,
+            SectionFormat::RawImage => {
+                let mut framed = frame_header(&payload)
+                    .expect("test vectors are small")
+                    .to_vec();
+                framed.extend_from_slice(&payload);
+                framed
+            }
+        }
+    }
+}
+
+/// The ways the audit data is stored in binaries
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SectionFormat {
+    /// ELF executables, in the `.dep-v0` section
+    Elf,
+    /// PE (Windows) executables, in the `.dep-v0` section
+    Pe,
+    /// Mach-O (Apple) executables, in the `.dep-v0` section of the `__DATA` segment
+    MachO,
+    /// Raw memory images such as firmware, placed by the linker script fragment generated by
+    /// `cargo auditable emit-object --linker-script`. The payload is framed so that it can be located
+    /// without section headers, see [`auditable_extract::framed_auditable_data`].
+    RawImage,
+}
+
+impl SectionFormat {
+    /// All the supported formats
+    pub const ALL: &'static [SectionFormat] = &[
+        SectionFormat::Elf,
+        SectionFormat::Pe,
+        SectionFormat::MachO,
+        SectionFormat::RawImage,
+    ];
+
+    /// Short identifier of the format, usable as a file name
+    pub fn name(self) -> &'static str {
+        match self {
+            SectionFormat::Elf => "elf",
+            SectionFormat::Pe => "pe",
+            SectionFormat::MachO => "macho",
+            SectionFormat::RawImage => "raw-image",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{json_from_image_slice, Limits};
+
+    #[test]
+    fn vectors_are_consistent() {
+        let limit = Limits::default().decompressed_json_size;
+        for vector in TEST_VECTORS {
+            let info = VersionInfo::from_str(vector.input_json).unwrap();
+            assert_eq!(serde_json::to_string(&info).unwrap(), vector.canonical_json);
+            let payload = vector.compressed_payload();
+            let decompressed = miniz_oxide::inflate::decompress_to_vec_zlib(&payload).unwrap();
+            assert_eq!(decompressed, vector.canonical_json.as_bytes());
+            for format in SectionFormat::ALL {
+                let contents = vector.section_contents(*format);
+                if *format == SectionFormat::RawImage {
+                    let json = json_from_image_slice(&contents, 0, limit).unwrap();
+                    assert_eq!(json, vector.canonical_json);
+                } else {
+                    assert_eq!(contents, payload);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Copies the audit data from a binary to a post-processed copy of it, for pipelines
+//! that repackage binaries with tools that drop sections they do not know about.
+
+use crate::{read_input, Error, Limits};
+use auditable_extract::{code_section, frame_header, raw_audit_section, SECTION_NAME};
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Copies the audit data from `source` to `destination`, a copy of the same binary
+/// that went through post-processing such as watermarking or resource editing,
+/// and returns the contents of the destination with the audit data added.
+///
+/// The machine code of both binaries must be identical, as returned by
+/// [`auditable_extract::code_section`], otherwise [`Error::CodeMismatch`] is returned.
+/// This ensures that the audit data describes the program that is actually in the destination.
+///
+/// If the destination already has the same audit data, it is returned unchanged.
+/// If it has different audit data, [`Error::ConflictingAuditData`] is returned.
+///
+/// Otherwise the audit data is appended to the end of the destination: in a custom section
+/// for WebAssembly modules, and in a frame recognized by all the `json_from_*` functions
+/// for other formats. Appending data invalidates code signatures, so sign the binary
+/// after copying the audit data rather than before.
+pub fn copy_audit_data(source: &[u8], destination: &[u8]) -> Result<Vec<u8>, Error> {
+    let audit_data = raw_audit_section(source)?;
+    if code_section(source)? != code_section(destination)? {
+        return Err(Error::CodeMismatch);
+    }
+    match raw_audit_section(destination) {
+        Ok(existing) if existing == audit_data => return Ok(destination.to_vec()),
+        Ok(_) => return Err(Error::ConflictingAuditData),
+        Err(auditable_extract::Error::NoAuditData) => (),
+        Err(e) => return Err(e.into()),
+    }
+    let mut result = destination.to_vec();
+    if destination.starts_with(b"\0asm") {
+        append_wasm_custom_section(&mut result, audit_data)?;
+    } else {
+        let header = frame_header(audit_data).ok_or(Error::OutputLimitExceeded)?;
+        result.extend_from_slice(&header);
+        result.extend_from_slice(audit_data);
+    }
+    Ok(result)
+}
+
+/// Copies the audit data from the binary at `source` to the post-processed copy of it
+/// at `destination`, modifying the destination file in place. See [`copy_audit_data`] for details.
+///
+/// The `input_file_size` limit applies to both files; `decompressed_json_size` is not used.
+pub fn copy_audit_data_between_files(
+    source: &Path,
+    destination: &Path,
+    limits: Limits,
+) -> Result<(), Error> {
+    let source = read_input(&mut BufReader::new(File::open(source)?), limits)?;
+    let original = read_input(&mut BufReader::new(File::open(destination)?), limits)?;
+    let result = copy_audit_data(&source, &original)?;
+    if result != original {
+        std::fs::write(destination, result)?;
+    }
+    Ok(())
+}
+
+fn append_wasm_custom_section(module: &mut Vec<u8>, contents: &[u8]) -> Result<(), Error> {
+    let mut section = Vec::new();
+    write_leb128(&mut section, SECTION_NAME.len())?;
+    section.extend_from_slice(SECTION_NAME.as_bytes());
+    section.extend_from_slice(contents);
+    // custom sections have ID 0
+    module.push(0);
+    write_leb128(module, section.len())?;
+    module.extend_from_slice(&section);
+    Ok(())
+}
+
+/// Writes an unsigned LEB128-encoded 32-bit integer, the encoding of sizes in WebAssembly
+fn write_leb128(out: &mut Vec<u8>, value: usize) -> Result<(), Error> {
+    let mut value = u32::try_from(value).map_err(|_| Error::OutputLimitExceeded)?;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return Ok(());
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_from_slice;
+
+    /// Builds a WebAssembly module from sections that are all shorter than 128 bytes
+    fn module(sections: &[(u8, &[u8])]) -> Vec<u8> {
+        let mut data = b"\0asm\x01\0\0\0".to_vec();
+        for (id, contents) in sections {
+            data.push(*id);
+            data.push(contents.len() as u8);
+            data.extend_from_slice(contents);
+        }
+        data
+    }
+
+    fn audit_section(json: &str) -> Vec<u8> {
+        let mut section = b"\x07.dep-v0".to_vec();
+        section.extend(miniz_oxide::deflate::compress_to_vec_zlib(
+            json.as_bytes(),
+            7,
+        ));
+        section
+    }
+
+    #[test]
+    fn copies_to_processed_module() {
+        let json = r#"{"packages":[]}"#;
+        let source = module(&[(10, b"code"), (0, &audit_section(json))]);
+        // post-processing dropped the audit data and added a watermark
+        let processed = module(&[(10, b"code"), (0, b"\x09watermark")]);
+        assert!(json_from_slice(&processed, 1024).is_err());
+        let result = copy_audit_data(&source, &processed).unwrap();
+        assert!(result.starts_with(&processed));
+        assert_eq!(json_from_slice(&result, 1024).unwrap(), json);
+        // copying again changes nothing
+        assert_eq!(copy_audit_data(&source, &result).unwrap(), result);
+        let other = module(&[(10, b"code"), (0, &audit_section(r#"{"packages": []}"#))]);
+        assert!(matches!(
+            copy_audit_data(&other, &result),
+            Err(Error::ConflictingAuditData)
+        ));
+    }
+
+    #[test]
+    fn rejects_different_code() {
+        let source = module(&[(10, b"code"), (0, &audit_section(r#"{"packages":[]}"#))]);
+        let other_program = module(&[(10, b"edoc")]);
+        assert!(matches!(
+            copy_audit_data(&source, &other_program),
+            Err(Error::CodeMismatch)
+        ));
+    }
+}
@@ -4,7 +4,7 @@ use std::convert::TryInto;
 use std::str::FromStr;
 
 fn main() {
-    let path = std::env::args().skip(1).next().expect("No file specified");
+    let path = std::env::args().nth(1).expect("No file specified");
     let file_contents = std::fs::read_to_string(path).unwrap();
     let version_info = VersionInfo::from_str(&file_contents).unwrap();
     let lockfile: Lockfile = (&version_info).try_into().unwrap();
@@ -0,0 +1,109 @@
+//! Programmatic construction of dependency trees, for tests and custom tooling.
+
+use crate::validation::{RawVersionInfo, ValidationError};
+use crate::{Package, Toolchain, VersionInfo, Workspace, FORMAT_VERSION};
+use std::convert::TryFrom;
+
+/// Builds a [`VersionInfo`] one package at a time, connecting packages by the indices
+/// returned when adding them. The result is validated the same way as parsed audit data.
+///
+/// ```rust
+/// use auditable_serde::{LocalSource, Package, Source, VersionInfoBuilder};
+/// let mut builder = VersionInfoBuilder::new();
+/// let adler = builder.package(Package::new("adler", "1.0.2".parse().unwrap(), Source::CratesIo));
+/// let mut app = Package::new("app", "0.1.0".parse().unwrap(), Source::Local(LocalSource::default()));
+/// app.root = true;
+/// let app = builder.package(app);
+/// builder.dependency(app, adler).target_name("app");
+/// let info = builder.build().unwrap();
+/// assert_eq!(info.dependencies_of(app).count(), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct VersionInfoBuilder {
+    packages: Vec<Package>,
+    target_name: Option<String>,
+    toolchain: Option<Toolchain>,
+    workspace: Option<Workspace>,
+}
+
+impl VersionInfoBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a package and returns its index, for use with [`VersionInfoBuilder::dependency`]
+    pub fn package(&mut self, package: Package) -> usize {
+        self.packages.push(package);
+        self.packages.len() - 1
+    }
+
+    /// Records that the package at index `dependent` depends on the one at index `dependency`.
+    ///
+    /// If `dependent` records the kinds of its dependency edges in [`Package::dependency_kinds`],
+    /// the kind of this edge has to be added there as well, since there must be one for every edge.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `dependent` is out of bounds.
+    pub fn dependency(&mut self, dependent: usize, dependency: usize) -> &mut Self {
+        self.packages[dependent].dependencies.push(dependency);
+        self
+    }
+
+    /// Sets [`VersionInfo::target_name`]
+    pub fn target_name(&mut self, target_name: &str) -> &mut Self {
+        self.target_name = Some(target_name.to_owned());
+        self
+    }
+
+    /// Sets [`VersionInfo::toolchain`]
+    pub fn toolchain(&mut self, toolchain: Toolchain) -> &mut Self {
+        self.toolchain = Some(toolchain);
+        self
+    }
+
+    /// Sets [`VersionInfo::workspace`]
+    pub fn workspace(&mut self, workspace: Workspace) -> &mut Self {
+        self.workspace = Some(workspace);
+        self
+    }
+
+    /// Checks the dependency tree, e.g. for cycles, and returns it.
+    ///
+    /// The packages keep the order they were added in. Call [`VersionInfo::canonicalize`]
+    /// to sort them the way `cargo auditable` does.
+    pub fn build(self) -> Result<VersionInfo, ValidationError> {
+        VersionInfo::try_from(RawVersionInfo {
+            format: FORMAT_VERSION,
+            packages: self.packages,
+            target_name: self.target_name,
+            toolchain: self.toolchain,
+            workspace: self.workspace,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LocalSource, Source};
+
+    fn local_package(name: &str) -> Package {
+        Package::new(
+            name,
+            semver::Version::new(0, 1, 0),
+            Source::Local(LocalSource::default()),
+        )
+    }
+
+    #[test]
+    fn rejects_cycles() {
+        let mut builder = VersionInfoBuilder::new();
+        let foo = builder.package(local_package("foo"));
+        let bar = builder.package(local_package("bar"));
+        builder.dependency(foo, bar);
+        assert!(builder.clone().build().is_ok());
+        builder.dependency(bar, foo);
+        assert_eq!(builder.build(), Err(ValidationError::CyclicDependency));
+    }
+}
@@ -14,6 +14,7 @@
 //! - `IsEnumVariant<&str, ENUM>` for `VARIANT`,
 //! - `Into<VariantRepr<&'static str, ENUM, VARIANT>>` for `VARIANT`,
 //! - `TryFrom<VariantRepr<&'static str, ENUM, VARIANT>>` for `VARIANT`,
+//!
 //! where __`ENUM`__ is the __enum type__ containing the variant which
 //! serialization we would like to change and __`VARIANT`__ is the type
 //! __wrapped by the variant__.
@@ -34,8 +35,8 @@
 //! Changing a unit variant of an enum to wrap a type and use this module for
 //! the serialization can be made to be a backwards compatible change.
 
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::{convert::TryFrom, fmt::Display, marker::PhantomData};
+use serde::{de::IgnoredAny, Deserialize, Deserializer, Serialize, Serializer};
+use std::{collections::BTreeMap, convert::TryFrom, fmt::Display, marker::PhantomData};
 
 /// Marks a string or other type that can be converted to a string as a label
 /// for an variant of type `ENUM`.
@@ -93,9 +94,33 @@ pub enum VariantRepr<S: Into<String>, ENUM, INNER> {
         kind: EnumVariant<S, ENUM>,
         #[serde(flatten)]
         strct: INNER,
+        #[serde(flatten)]
+        #[cfg_attr(feature = "schema", schemars(skip))]
+        unknown: UnknownFields,
     },
 }
 
+/// Collects the fields of the struct representation that are not recognized by `INNER`.
+///
+/// `deny_unknown_fields` has no effect on flattened structs, so they are rejected
+/// in [`deserialize`] instead. Always serialized as no fields at all.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnknownFields(Vec<String>);
+
+impl Serialize for UnknownFields {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        serializer.serialize_map(Some(0))?.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for UnknownFields {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = BTreeMap::<String, IgnoredAny>::deserialize(deserializer)?;
+        Ok(UnknownFields(fields.into_keys().collect()))
+    }
+}
+
 pub fn serialize<S, ENUM, VARIANT>(inner: &VARIANT, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -117,6 +142,13 @@ where
     'de: 's,
 {
     let compact: VariantRepr<&'s str, ENUM, VARIANT> = Deserialize::deserialize(deserializer)?;
+    if let VariantRepr::Struct { unknown, .. } = &compact {
+        if let Some(name) = unknown.0.first() {
+            return Err(serde::de::Error::custom(format_args!(
+                "unknown field `{name}`"
+            )));
+        }
+    }
     let variant = VARIANT::try_from(compact).map_err(serde::de::Error::custom)?;
 
     Ok(variant)
@@ -0,0 +1,172 @@
+//! Converts the dependency tree to a [CycloneDX](https://cyclonedx.org/) SBOM.
+
+use crate::{DependencyKind, Package, VersionInfo};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// The version of the CycloneDX specification the output conforms to
+const SPEC_VERSION: &str = "1.4";
+
+impl VersionInfo {
+    /// Converts the dependency tree to a CycloneDX 1.4 JSON document,
+    /// for use with existing supply chain security tooling.
+    ///
+    /// The root package becomes the `metadata.component` of the document and every other package
+    /// is listed in `components`. The dependency graph is recorded in `dependencies`.
+    /// Build and development dependencies are not part of the binary, so their scope is `excluded`.
+    /// Non-Rust components bundled by a package are nested under its component.
+    ///
+    /// ```rust
+    /// use auditable_serde::VersionInfo;
+    /// use std::str::FromStr;
+    /// let json_str = r#"{"packages":[{"name":"adler","version":"0.2.3","source":"registry","root":true}]}"#;
+    /// let info = VersionInfo::from_str(json_str).unwrap();
+    /// let sbom = info.to_cyclonedx();
+    /// assert_eq!(sbom["bomFormat"], "CycloneDX");
+    /// assert_eq!(sbom["metadata"]["component"]["name"], "adler");
+    /// ```
+    pub fn to_cyclonedx(&self) -> Value {
+        let refs = bom_refs(&self.packages);
+        let mut root = None;
+        let mut components = Vec::new();
+        for (package, bom_ref) in self.packages.iter().zip(&refs) {
+            let component = component(package, bom_ref);
+            if package.root {
+                root = Some(component);
+            } else {
+                components.push(component);
+            }
+        }
+        let dependencies: Vec<Value> = self
+            .packages
+            .iter()
+            .zip(&refs)
+            .map(|(package, bom_ref)| {
+                let depends_on: Vec<&str> = package
+                    .dependencies
+                    .iter()
+                    .filter_map(|&index| refs.get(index))
+                    .map(String::as_str)
+                    .collect();
+                json!({ "ref": bom_ref, "dependsOn": depends_on })
+            })
+            .collect();
+
+        let mut metadata = Map::new();
+        metadata.insert(
+            "tools".to_owned(),
+            json!([{ "name": "auditable-serde", "version": env!("CARGO_PKG_VERSION") }]),
+        );
+        if let Some(root) = root {
+            metadata.insert("component".to_owned(), root);
+        }
+        json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": SPEC_VERSION,
+            "version": 1,
+            "metadata": metadata,
+            "components": components,
+            "dependencies": dependencies,
+        })
+    }
+}
+
+fn component(package: &Package, bom_ref: &str) -> Value {
+    let mut component = Map::new();
+    let component_type = if package.root {
+        "application"
+    } else {
+        "library"
+    };
+    component.insert("type".to_owned(), json!(component_type));
+    component.insert("bom-ref".to_owned(), json!(bom_ref));
+    component.insert("name".to_owned(), json!(package.name));
+    component.insert("version".to_owned(), json!(package.version.to_string()));
+    if !package.root {
+        let scope = match package.kind {
+            DependencyKind::Runtime => "required",
+            DependencyKind::Build | DependencyKind::Development => "excluded",
+        };
+        component.insert("scope".to_owned(), json!(scope));
+    }
+    if let Some(checksum) = &package.checksum {
+        component.insert(
+            "hashes".to_owned(),
+            json!([{ "alg": "SHA-256", "content": checksum }]),
+        );
+    }
+    component.insert("purl".to_owned(), json!(package.purl()));
+    if !package.components.is_empty() {
+        let nested: Vec<Value> = package
+            .components
+            .iter()
+            .map(|c| {
+                json!({
+                    "type": "library",
+                    "name": c.name,
+                    "version": c.version,
+                    "properties": [{ "name": "cargo-auditable:type", "value": c.component_type }],
+                })
+            })
+            .collect();
+        component.insert("components".to_owned(), json!(nested));
+    }
+    Value::Object(component)
+}
+
+/// CycloneDX requires unique references to components. Package URLs are unique
+/// unless the same version of a crate comes from several sources, e.g. a registry and git,
+/// in which case the index of the package is appended to disambiguate them.
+fn bom_refs(packages: &[Package]) -> Vec<String> {
+    let purls: Vec<String> = packages.iter().map(Package::purl).collect();
+    let mut occurrences: HashMap<&str, usize> = HashMap::new();
+    for purl in &purls {
+        *occurrences.entry(purl.as_str()).or_default() += 1;
+    }
+    purls
+        .iter()
+        .enumerate()
+        .map(|(index, purl)| {
+            if occurrences[purl.as_str()] > 1 {
+                format!("{purl}#{index}")
+            } else {
+                purl.clone()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::VersionInfo;
+    use std::str::FromStr;
+
+    #[test]
+    fn cyclonedx_document() {
+        let json = r#"{"packages":[
+            {"name":"adler","version":"0.2.3","source":"crates.io","kind":"build","checksum":"ee2a4ec343196209d6594e19543ae87a39f96d5534d7174822a3ad825dd6ed7e"},
+            {"name":"adler","version":"0.2.3","source":"git"},
+            {"name":"foo","version":"1.0.0","source":"local","dependencies":[0,1],"root":true}
+        ]}"#;
+        let info = VersionInfo::from_str(json).unwrap();
+        let sbom = info.to_cyclonedx();
+        assert_eq!(sbom["specVersion"], "1.4");
+        let root = &sbom["metadata"]["component"];
+        assert_eq!(root["type"], "application");
+        assert_eq!(root["bom-ref"], "pkg:cargo/foo@1.0.0");
+        let components = sbom["components"].as_array().unwrap();
+        assert_eq!(components.len(), 2);
+        // the same purl from two sources must get distinct references
+        assert_eq!(components[0]["bom-ref"], "pkg:cargo/adler@0.2.3#0");
+        assert_eq!(components[1]["bom-ref"], "pkg:cargo/adler@0.2.3#1");
+        assert_eq!(components[0]["scope"], "excluded");
+        assert_eq!(components[1]["scope"], "required");
+        assert_eq!(components[0]["hashes"][0]["alg"], "SHA-256");
+        let root_deps = &sbom["dependencies"][2];
+        assert_eq!(root_deps["ref"], "pkg:cargo/foo@1.0.0");
+        assert_eq!(
+            root_deps["dependsOn"],
+            serde_json::json!(["pkg:cargo/adler@0.2.3#0", "pkg:cargo/adler@0.2.3#1"])
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! Traversal of the dependency graph one edge at a time, for tools that inspect
+//! how individual packages are connected rather than the whole tree.
+//! With the `graph` feature the whole tree can be converted to a `petgraph` graph instead.
+
+use crate::{DependencyKind, Package, VersionInfo};
+
+impl VersionInfo {
+    /// Returns the direct dependencies of the package at `index` along with their indices,
+    /// in the order they are recorded in [`Package::dependencies`].
+    ///
+    /// Dependencies referring to nonexistent packages are skipped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn dependencies_of(&self, index: usize) -> impl Iterator<Item = (usize, &Package)> + '_ {
+        self.packages[index]
+            .dependencies
+            .iter()
+            .filter_map(move |&dep| self.packages.get(dep).map(|package| (dep, package)))
+    }
+
+    /// Returns the direct dependencies of the package at `index` that it uses at runtime,
+    /// i.e. whose code may end up in the binary through it, along with their indices.
+    ///
+    /// The kind of each edge is taken from [`Package::dependency_kinds`] if it was recorded.
+    /// Otherwise the [`Package::kind`] of the dependency is used, which is `runtime` if any
+    /// package uses it at runtime, so a build-only edge to such a dependency is included too.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    ///
+    /// ```rust
+    /// use auditable_serde::VersionInfo;
+    /// use std::str::FromStr;
+    /// let json_str = r#"{"packages":[
+    ///     {"name":"adler","version":"1.0.2","source":"crates.io"},
+    ///     {"name":"cc","version":"1.0.79","source":"crates.io","kind":"build"},
+    ///     {"name":"foo","version":"0.1.0","source":"local","dependencies":[0,1],"root":true}
+    /// ]}"#;
+    /// let info = VersionInfo::from_str(json_str).unwrap();
+    /// let runtime: Vec<&str> = info
+    ///     .runtime_dependencies_of(2)
+    ///     .map(|(_, package)| package.name.as_str())
+    ///     .collect();
+    /// assert_eq!(runtime, vec!["adler"]);
+    /// ```
+    pub fn runtime_dependencies_of(
+        &self,
+        index: usize,
+    ) -> impl Iterator<Item = (usize, &Package)> + '_ {
+        let package = &self.packages[index];
+        package
+            .dependencies
+            .iter()
+            .enumerate()
+            .filter_map(move |(edge, &dep)| {
+                let dependency = self.packages.get(dep)?;
+                let kind = match package.dependency_kinds.get(edge) {
+                    Some(kind) => *kind,
+                    None => dependency.kind,
+                };
+                if kind == DependencyKind::Runtime {
+                    Some((dep, dependency))
+                } else {
+                    None
+                }
+            })
+    }
+
+    /// Returns the packages that depend directly on the package at `index`,
+    /// along with their indices, in the order they appear in [`VersionInfo::packages`]
+    pub fn dependents_of(&self, index: usize) -> impl Iterator<Item = (usize, &Package)> + '_ {
+        self.packages
+            .iter()
+            .enumerate()
+            .filter(move |(_, package)| package.dependencies.contains(&index))
+    }
+
+    /// Converts the dependency tree into a [`petgraph`] graph, so that its algorithms
+    /// can be used for reachability, shortest path and topological queries.
+    ///
+    /// The node with index `i` is the package at index `i` in [`VersionInfo::packages`].
+    /// Edges point from a package to its dependencies and are weighted with the kind
+    /// of the edge if [`Package::dependency_kinds`] was recorded, or with the [`Package::kind`]
+    /// of the dependency otherwise. Dependencies referring to nonexistent packages are skipped.
+    ///
+    /// Available with the `graph` feature.
+    ///
+    /// ```rust
+    /// use auditable_serde::VersionInfo;
+    /// use petgraph::{algo::has_path_connecting, graph::NodeIndex};
+    /// use std::str::FromStr;
+    /// let json_str = r#"{"packages":[
+    ///     {"name":"adler","version":"1.0.2","source":"crates.io"},
+    ///     {"name":"miniz_oxide","version":"0.6.2","source":"crates.io","dependencies":[0]},
+    ///     {"name":"foo","version":"0.1.0","source":"local","dependencies":[1],"root":true}
+    /// ]}"#;
+    /// let info = VersionInfo::from_str(json_str).unwrap();
+    /// let graph = info.to_petgraph();
+    /// assert_eq!(graph[NodeIndex::new(0)].name, "adler");
+    /// assert!(has_path_connecting(&graph, NodeIndex::new(2), NodeIndex::new(0), None));
+    /// ```
+    #[cfg(feature = "graph")]
+    pub fn to_petgraph(&self) -> petgraph::graph::Graph<&Package, DependencyKind> {
+        let mut graph = petgraph::graph::Graph::with_capacity(self.packages.len(), 0);
+        for package in &self.packages {
+            graph.add_node(package);
+        }
+        for (index, package) in self.packages.iter().enumerate() {
+            for (edge, &dep) in package.dependencies.iter().enumerate() {
+                let dependency = match self.packages.get(dep) {
+                    Some(dependency) => dependency,
+                    None => continue,
+                };
+                let kind = match package.dependency_kinds.get(edge) {
+                    Some(kind) => *kind,
+                    None => dependency.kind,
+                };
+                graph.add_edge(
+                    petgraph::graph::NodeIndex::new(index),
+                    petgraph::graph::NodeIndex::new(dep),
+                    kind,
+                );
+            }
+        }
+        graph
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use crate::VersionInfo;
+    use std::str::FromStr;
+
+    const JSON: &str = r#"{"packages":[
+        {"name":"adler","version":"1.0.2","source":"crates.io"},
+        {"name":"cc","version":"1.0.0","source":"crates.io","kind":"build"},
+        {"name":"foo","version":"0.1.0","source":"local","root":true,"dependencies":[0,1,7]},
+        {"name":"bar","version":"0.1.0","source":"local","root":true,"dependencies":[0,1],"dependency_kinds":["build","build"]}
+    ]}"#;
+
+    fn indices<'a>(edges: impl Iterator<Item = (usize, &'a crate::Package)>) -> Vec<usize> {
+        edges.map(|(index, _)| index).collect()
+    }
+
+    #[test]
+    fn direct_edges() {
+        let info = VersionInfo::from_str(JSON).unwrap();
+        // the nonexistent dependency 7 is skipped
+        assert_eq!(indices(info.dependencies_of(2)), vec![0, 1]);
+        assert_eq!(indices(info.dependents_of(0)), vec![2, 3]);
+        assert_eq!(indices(info.dependents_of(2)), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn runtime_edges() {
+        let info = VersionInfo::from_str(JSON).unwrap();
+        assert_eq!(indices(info.runtime_dependencies_of(2)), vec![0]);
+        // per-edge kinds take precedence over the kind of the dependency
+        assert_eq!(
+            indices(info.runtime_dependencies_of(3)),
+            Vec::<usize>::new()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "graph")]
+    fn petgraph_conversion() {
+        use crate::DependencyKind;
+        use petgraph::{algo::toposort, graph::NodeIndex, Direction};
+
+        let info = VersionInfo::from_str(JSON).unwrap();
+        let graph = info.to_petgraph();
+        assert_eq!(graph.node_count(), 4);
+        // the nonexistent dependency 7 is skipped
+        assert_eq!(graph.edge_count(), 4);
+        let bar = NodeIndex::new(3);
+        assert!(graph
+            .edges_directed(bar, Direction::Outgoing)
+            .all(|edge| *edge.weight() == DependencyKind::Build));
+        let cc = NodeIndex::new(1);
+        assert_eq!(graph.neighbors_directed(cc, Direction::Incoming).count(), 2);
+        assert!(toposort(&graph, None).is_ok());
+    }
+}
@@ -0,0 +1,739 @@
+//! A small JSON parser that produces [`VersionInfo`] without `serde` or `serde_json`,
+//! for consumers with strict budgets on the number of dependencies and binary size.
+//!
+//! It accepts the same documents as the `serde`-based parser: optional fields may be omitted,
+//! unknown fields are rejected, and the result goes through the same validation.
+
+use crate::validation::{RawVersionInfo, ValidationError};
+use crate::{
+    cargo_ecosystem, Component, DependencyKind, GitSource, LocalSource, Package, RegistrySource,
+    Source, Toolchain, VersionInfo, Workspace, WorkspaceSource, FORMAT_VERSION,
+};
+use std::{convert::TryFrom, error::Error, fmt::Display};
+
+/// Documents nested deeper than this are rejected to bound the recursion on untrusted input.
+/// The format itself never nests more than 5 levels deep.
+const MAX_DEPTH: usize = 32;
+
+impl VersionInfo {
+    /// Parses the JSON audit data without relying on `serde`.
+    ///
+    /// Available with the `minimal_parser` feature, which can be used with `default-features = false`
+    /// to leave out `serde` and `serde_json` entirely. The result is the same as that of
+    /// `VersionInfo::from_str` with the default `json` feature.
+    ///
+    /// ```rust
+    /// use auditable_serde::VersionInfo;
+    /// let json_str = r#"{"packages":[{"name":"adler","version":"0.2.3","source":"crates.io","root":true}]}"#;
+    /// let info = VersionInfo::from_json_minimal(json_str).unwrap();
+    /// assert_eq!(&info.packages[0].name, "adler");
+    /// ```
+    pub fn from_json_minimal(json: &str) -> Result<VersionInfo, MinimalParseError> {
+        let document = Parser::new(json).parse_document()?;
+        let fields = object(&document, "the document")?;
+        // Check the version first, since newer formats may have fields this parser doesn't know about
+        let format = match field(fields, "format")? {
+            None => 0,
+            Some(Value::Number(n)) => n
+                .parse()
+                .map_err(|_| field_error("expected a format version", "format"))?,
+            Some(_) => return Err(field_error("expected a format version", "format")),
+        };
+        if format > FORMAT_VERSION {
+            return Err(ValidationError::NewerFormat(format).into());
+        }
+        known_fields(
+            fields,
+            &[
+                "format",
+                "packages",
+                "target_name",
+                "toolchain",
+                "workspace",
+            ],
+        )?;
+        let packages = required(fields, "packages")?;
+        let packages = array(packages, "packages")?
+            .iter()
+            .map(package)
+            .collect::<Result<Vec<Package>, MinimalParseError>>()?;
+        let raw = RawVersionInfo {
+            format,
+            packages,
+            target_name: optional_string(fields, "target_name")?,
+            toolchain: match field(fields, "toolchain")? {
+                None | Some(Value::Null) => None,
+                Some(value) => Some(toolchain(value)?),
+            },
+            workspace: match field(fields, "workspace")? {
+                None | Some(Value::Null) => None,
+                Some(value) => Some(workspace(value)?),
+            },
+        };
+        Ok(VersionInfo::try_from(raw)?)
+    }
+}
+
+/// Returned by [`VersionInfo::from_json_minimal`] when the input is not valid JSON
+/// or does not describe a valid dependency tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinimalParseError {
+    message: &'static str,
+    location: Location,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Location {
+    Offset(usize),
+    Field(&'static str),
+    UnknownField(String),
+    Validation(ValidationError),
+}
+
+impl MinimalParseError {
+    /// Returns why the dependency tree was rejected if the JSON itself was well-formed,
+    /// e.g. [`ValidationError::NewerFormat`] if it is in a newer format than this library understands
+    pub fn validation_error(&self) -> Option<ValidationError> {
+        match self.location {
+            Location::Validation(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ValidationError> for MinimalParseError {
+    fn from(e: ValidationError) -> Self {
+        MinimalParseError {
+            message: e.message(),
+            location: Location::Validation(e),
+        }
+    }
+}
+
+impl Display for MinimalParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.location {
+            Location::Offset(offset) => write!(f, "{} at byte {}", self.message, offset),
+            Location::Field(field) => write!(f, "{} in field `{}`", self.message, field),
+            Location::UnknownField(field) => write!(f, "{} `{}`", self.message, field),
+            Location::Validation(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for MinimalParseError {}
+
+fn field_error(message: &'static str, field: &'static str) -> MinimalParseError {
+    MinimalParseError {
+        message,
+        location: Location::Field(field),
+    }
+}
+
+enum Value<'a> {
+    Null,
+    Bool(bool),
+    /// Kept as written, and only converted once the expected type is known
+    Number(&'a str),
+    String(String),
+    Array(Vec<Value<'a>>),
+    Object(Vec<(String, Value<'a>)>),
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Parser { input, pos: 0 }
+    }
+
+    fn error(&self, message: &'static str) -> MinimalParseError {
+        MinimalParseError {
+            message,
+            location: Location::Offset(self.pos),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8, message: &'static str) -> Result<(), MinimalParseError> {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(message))
+        }
+    }
+
+    fn parse_document(&mut self) -> Result<Value<'a>, MinimalParseError> {
+        let value = self.parse_value(0)?;
+        self.skip_whitespace();
+        if self.pos != self.input.len() {
+            return Err(self.error("trailing characters"));
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<Value<'a>, MinimalParseError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.parse_object(depth + 1),
+            Some(b'[') => self.parse_array(depth + 1),
+            Some(b'"') => Ok(Value::String(self.parse_string()?)),
+            Some(b't') => self.parse_literal("true", Value::Bool(true)),
+            Some(b'f') => self.parse_literal("false", Value::Bool(false)),
+            Some(b'n') => self.parse_literal("null", Value::Null),
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn parse_literal(
+        &mut self,
+        literal: &str,
+        value: Value<'a>,
+    ) -> Result<Value<'a>, MinimalParseError> {
+        if self.input[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            Ok(value)
+        } else {
+            Err(self.error("expected a value"))
+        }
+    }
+
+    fn parse_object(&mut self, depth: usize) -> Result<Value<'a>, MinimalParseError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("nesting is too deep"));
+        }
+        self.expect(b'{', "expected an object")?;
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Value::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a string as the key"));
+            }
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(b':', "expected `:`")?;
+            let value = self.parse_value(depth)?;
+            entries.push((key, value));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Value::Object(entries));
+                }
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+
+    fn parse_array(&mut self, depth: usize) -> Result<Value<'a>, MinimalParseError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("nesting is too deep"));
+        }
+        self.expect(b'[', "expected an array")?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.parse_value(depth)?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Value::Array(items));
+                }
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, MinimalParseError> {
+        self.expect(b'"', "expected a string")?;
+        let mut result = String::new();
+        loop {
+            // Copy everything up to the next quote or escape at once.
+            // Both are ASCII, so this never splits a UTF-8 sequence.
+            let rest = &self.input[self.pos..];
+            let end = rest
+                .find(|c: char| c == '"' || c == '\\' || c < ' ')
+                .ok_or_else(|| self.error("unterminated string"))?;
+            result.push_str(&rest[..end]);
+            self.pos += end;
+            match self.peek() {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(result);
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    result.push(self.parse_escape()?);
+                }
+                _ => return Err(self.error("control character in string")),
+            }
+        }
+    }
+
+    /// Parses an escape sequence, after the backslash
+    fn parse_escape(&mut self) -> Result<char, MinimalParseError> {
+        let escaped = self
+            .peek()
+            .ok_or_else(|| self.error("unterminated string"))?;
+        self.pos += 1;
+        Ok(match escaped {
+            b'"' => '"',
+            b'\\' => '\\',
+            b'/' => '/',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'u' => {
+                let first = self.parse_hex4()?;
+                let code_point = match first {
+                    0xD800..=0xDBFF => {
+                        // A high surrogate must be followed by an escaped low surrogate
+                        if !self.input[self.pos..].starts_with("\\u") {
+                            return Err(self.error("unpaired surrogate in string"));
+                        }
+                        self.pos += 2;
+                        let second = self.parse_hex4()?;
+                        if !(0xDC00..=0xDFFF).contains(&second) {
+                            return Err(self.error("unpaired surrogate in string"));
+                        }
+                        0x10000 + ((first - 0xD800) << 10) + (second - 0xDC00)
+                    }
+                    0xDC00..=0xDFFF => return Err(self.error("unpaired surrogate in string")),
+                    other => other,
+                };
+                // Surrogates are excluded above, so this is always a valid `char`
+                char::from_u32(code_point).ok_or_else(|| self.error("invalid escape"))?
+            }
+            _ => return Err(self.error("invalid escape")),
+        })
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, MinimalParseError> {
+        let digits = self
+            .input
+            .get(self.pos..self.pos + 4)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("invalid escape"))?;
+        self.pos += 4;
+        Ok(u32::from_str_radix(digits, 16).expect("validated hex digits"))
+    }
+
+    fn parse_number(&mut self) -> Result<Value<'a>, MinimalParseError> {
+        let start = self.pos;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        match self.peek() {
+            Some(b'0') => self.pos += 1,
+            Some(b'1'..=b'9') => self.skip_digits(),
+            _ => return Err(self.error("invalid number")),
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            self.require_digits()?;
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            self.pos += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.pos += 1;
+            }
+            self.require_digits()?;
+        }
+        Ok(Value::Number(&self.input[start..self.pos]))
+    }
+
+    fn skip_digits(&mut self) {
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn require_digits(&mut self) -> Result<(), MinimalParseError> {
+        if !matches!(self.peek(), Some(b'0'..=b'9')) {
+            return Err(self.error("invalid number"));
+        }
+        self.skip_digits();
+        Ok(())
+    }
+}
+
+type Fields<'a> = [(String, Value<'a>)];
+
+/// Looks up a field, rejecting duplicates just like the `serde`-based parser
+fn field<'f, 'a>(
+    fields: &'f Fields<'a>,
+    name: &'static str,
+) -> Result<Option<&'f Value<'a>>, MinimalParseError> {
+    let mut matching = fields.iter().filter(|(key, _)| key == name);
+    let value = matching.next().map(|(_, value)| value);
+    if matching.next().is_some() {
+        return Err(field_error("duplicate field", name));
+    }
+    Ok(value)
+}
+
+/// Rejects fields other than the given ones, just like the `serde`-based parser
+fn known_fields(fields: &Fields<'_>, known: &[&str]) -> Result<(), MinimalParseError> {
+    match fields
+        .iter()
+        .find(|(key, _)| !known.contains(&key.as_str()))
+    {
+        Some((key, _)) => Err(MinimalParseError {
+            message: "unknown field",
+            location: Location::UnknownField(key.clone()),
+        }),
+        None => Ok(()),
+    }
+}
+
+fn required<'f, 'a>(
+    fields: &'f Fields<'a>,
+    name: &'static str,
+) -> Result<&'f Value<'a>, MinimalParseError> {
+    field(fields, name)?.ok_or_else(|| field_error("missing field", name))
+}
+
+fn object<'f, 'a>(
+    value: &'f Value<'a>,
+    name: &'static str,
+) -> Result<&'f Fields<'a>, MinimalParseError> {
+    match value {
+        Value::Object(fields) => Ok(fields),
+        _ => Err(field_error("expected an object", name)),
+    }
+}
+
+fn array<'f, 'a>(
+    value: &'f Value<'a>,
+    name: &'static str,
+) -> Result<&'f [Value<'a>], MinimalParseError> {
+    match value {
+        Value::Array(items) => Ok(items),
+        _ => Err(field_error("expected an array", name)),
+    }
+}
+
+fn string<'f>(value: &'f Value<'_>, name: &'static str) -> Result<&'f str, MinimalParseError> {
+    match value {
+        Value::String(s) => Ok(s),
+        _ => Err(field_error("expected a string", name)),
+    }
+}
+
+fn required_string(fields: &Fields<'_>, name: &'static str) -> Result<String, MinimalParseError> {
+    Ok(string(required(fields, name)?, name)?.to_owned())
+}
+
+/// Options accept `null` as well as a missing field, like `serde` does
+fn optional_string(
+    fields: &Fields<'_>,
+    name: &'static str,
+) -> Result<Option<String>, MinimalParseError> {
+    match field(fields, name)? {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => Ok(Some(string(value, name)?.to_owned())),
+    }
+}
+
+fn bool_or_false(fields: &Fields<'_>, name: &'static str) -> Result<bool, MinimalParseError> {
+    match field(fields, name)? {
+        None => Ok(false),
+        Some(Value::Bool(value)) => Ok(*value),
+        Some(_) => Err(field_error("expected a boolean", name)),
+    }
+}
+
+/// Lists default to empty if the field is missing
+fn list<'f, 'a, T>(
+    fields: &'f Fields<'a>,
+    name: &'static str,
+    convert: impl Fn(&'f Value<'a>) -> Result<T, MinimalParseError>,
+) -> Result<Vec<T>, MinimalParseError> {
+    match field(fields, name)? {
+        None => Ok(Vec::new()),
+        Some(value) => array(value, name)?.iter().map(convert).collect(),
+    }
+}
+
+fn dependency_kind(
+    value: &Value<'_>,
+    name: &'static str,
+) -> Result<DependencyKind, MinimalParseError> {
+    match string(value, name)? {
+        "development" => Ok(DependencyKind::Development),
+        "build" => Ok(DependencyKind::Build),
+        "runtime" => Ok(DependencyKind::Runtime),
+        _ => Err(field_error("unknown dependency kind", name)),
+    }
+}
+
+fn package(value: &Value<'_>) -> Result<Package, MinimalParseError> {
+    let fields = object(value, "packages")?;
+    known_fields(
+        fields,
+        &[
+            "name",
+            "version",
+            "source",
+            "checksum",
+            "vendored_checksum",
+            "kind",
+            "dependencies",
+            "dependency_kinds",
+            "root",
+            "renamed_as",
+            "features",
+            "has_build_script",
+            "components",
+            "ecosystem",
+        ],
+    )?;
+    let version = required_string(fields, "version")?;
+    let version = semver::Version::parse(&version)
+        .map_err(|_| field_error("invalid semantic version", "version"))?;
+    let kind = match field(fields, "kind")? {
+        None => DependencyKind::default(),
+        Some(value) => dependency_kind(value, "kind")?,
+    };
+    let ecosystem = match field(fields, "ecosystem")? {
+        None => cargo_ecosystem(),
+        Some(value) => string(value, "ecosystem")?.to_owned(),
+    };
+    Ok(Package {
+        name: required_string(fields, "name")?,
+        version,
+        source: source(required(fields, "source")?)?,
+        checksum: optional_string(fields, "checksum")?,
+        vendored_checksum: optional_string(fields, "vendored_checksum")?,
+        kind,
+        dependencies: list(fields, "dependencies", |value| match value {
+            Value::Number(n) => n
+                .parse()
+                .map_err(|_| field_error("expected a package index", "dependencies")),
+            _ => Err(field_error("expected a package index", "dependencies")),
+        })?,
+        dependency_kinds: list(fields, "dependency_kinds", |value| {
+            dependency_kind(value, "dependency_kinds")
+        })?,
+        root: bool_or_false(fields, "root")?,
+        renamed_as: list(fields, "renamed_as", |value| {
+            Ok(string(value, "renamed_as")?.to_owned())
+        })?,
+        features: list(fields, "features", |value| {
+            Ok(string(value, "features")?.to_owned())
+        })?,
+        has_build_script: bool_or_false(fields, "has_build_script")?,
+        components: list(fields, "components", component)?,
+        ecosystem,
+    })
+}
+
+/// Sources are either a plain string such as "crates.io", or an object
+/// with the kind of source in the `kind` field and details in the others
+fn source(value: &Value<'_>) -> Result<Source, MinimalParseError> {
+    let fields = match value {
+        Value::String(s) => return Ok(Source::from(s.as_str())),
+        Value::Object(fields) => fields,
+        _ => return Err(field_error("expected a string or an object", "source")),
+    };
+    match string(required(fields, "kind")?, "kind")? {
+        "git" => {
+            known_fields(fields, &["kind", "url", "rev"])?;
+            Ok(Source::Git(GitSource {
+                url: optional_string(fields, "url")?,
+                rev: optional_string(fields, "rev")?,
+            }))
+        }
+        "local" => {
+            known_fields(fields, &["kind", "path"])?;
+            Ok(Source::Local(LocalSource {
+                path: optional_string(fields, "path")?,
+            }))
+        }
+        "workspace" => {
+            known_fields(fields, &["kind", "path"])?;
+            Ok(Source::Workspace(WorkspaceSource {
+                path: optional_string(fields, "path")?,
+            }))
+        }
+        "registry" => {
+            known_fields(fields, &["kind", "url"])?;
+            Ok(Source::Registry(RegistrySource {
+                url: optional_string(fields, "url")?,
+            }))
+        }
+        _ => Err(field_error("unknown kind of source", "kind")),
+    }
+}
+
+fn component(value: &Value<'_>) -> Result<Component, MinimalParseError> {
+    let fields = object(value, "components")?;
+    known_fields(fields, &["name", "version", "type"])?;
+    Ok(Component {
+        name: required_string(fields, "name")?,
+        version: required_string(fields, "version")?,
+        component_type: required_string(fields, "type")?,
+    })
+}
+
+fn toolchain(value: &Value<'_>) -> Result<Toolchain, MinimalParseError> {
+    let fields = object(value, "toolchain")?;
+    known_fields(fields, &["rustc_version", "channel", "target", "profile"])?;
+    Ok(Toolchain {
+        rustc_version: required_string(fields, "rustc_version")?,
+        channel: required_string(fields, "channel")?,
+        target: required_string(fields, "target")?,
+        profile: optional_string(fields, "profile")?,
+    })
+}
+
+fn workspace(value: &Value<'_>) -> Result<Workspace, MinimalParseError> {
+    let fields = object(value, "workspace")?;
+    known_fields(fields, &["name", "repository"])?;
+    Ok(Workspace {
+        name: optional_string(fields, "name")?,
+        repository: optional_string(fields, "repository")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON: &str = r#"{"format":0,"packages":[
+        {"name":"adler","version":"1.0.2","source":"crates.io","kind":"build","checksum":"f26201604c87b1e01bd3d98f8d5d9a8fcbb815e8cedb41ffccbeb4bf593a35fe"},
+        {"name":"bar","version":"0.1.0","source":{"kind":"git","url":"https://example.com/bar.git","rev":"abc"},"components":[{"name":"zlib","version":"1.2.13","type":"c-library"}]},
+        {"name":"foo","version":"0.1.0-alpha.1","source":{"kind":"workspace","path":"crates/foo"},"dependencies":[0,1],"dependency_kinds":["build","runtime"],"features":["default","std"],"root":true}
+    ],"target_name":"foo-cli","toolchain":{"rustc_version":"1.72.0-nightly","channel":"nightly","target":"x86_64-unknown-linux-gnu","profile":"release"},"workspace":{"name":"foo-workspace","repository":"https://github.com/example/foo"}}"#;
+
+    #[test]
+    fn parses_all_fields() {
+        let info = VersionInfo::from_json_minimal(JSON).unwrap();
+        assert_eq!(info.target_name.as_deref(), Some("foo-cli"));
+        let toolchain = info.toolchain.as_ref().unwrap();
+        assert_eq!(toolchain.rustc_version, "1.72.0-nightly");
+        assert_eq!(toolchain.channel, "nightly");
+        assert_eq!(toolchain.target, "x86_64-unknown-linux-gnu");
+        assert_eq!(toolchain.profile.as_deref(), Some("release"));
+        let workspace = info.workspace.as_ref().unwrap();
+        assert_eq!(workspace.name.as_deref(), Some("foo-workspace"));
+        assert_eq!(
+            workspace.repository.as_deref(),
+            Some("https://github.com/example/foo")
+        );
+        assert_eq!(info.packages.len(), 3);
+        let (adler, bar, foo) = (&info.packages[0], &info.packages[1], &info.packages[2]);
+        assert_eq!(adler.source, Source::CratesIo);
+        assert_eq!(adler.kind, DependencyKind::Build);
+        assert!(adler.checksum.is_some());
+        assert_eq!(
+            bar.source,
+            Source::Git(GitSource {
+                url: Some("https://example.com/bar.git".to_owned()),
+                rev: Some("abc".to_owned())
+            })
+        );
+        assert_eq!(bar.components[0].component_type, "c-library");
+        assert_eq!(
+            foo.source,
+            Source::Workspace(WorkspaceSource {
+                path: Some("crates/foo".to_owned())
+            })
+        );
+        assert_eq!(foo.version.to_string(), "0.1.0-alpha.1");
+        assert_eq!(foo.dependencies, vec![0, 1]);
+        assert_eq!(
+            foo.dependency_kinds,
+            vec![DependencyKind::Build, DependencyKind::Runtime]
+        );
+        assert!(foo.root);
+        assert_eq!(foo.features, vec!["default", "std"]);
+        assert_eq!(foo.ecosystem, "cargo");
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn same_as_serde() {
+        use std::str::FromStr;
+        assert_eq!(
+            VersionInfo::from_json_minimal(JSON).unwrap(),
+            VersionInfo::from_str(JSON).unwrap()
+        );
+    }
+
+    #[test]
+    fn string_escapes() {
+        let json = r#"{"packages":[],"target_name":"a\"\\\/\u00e9\ud83e\udd80\n"}"#;
+        let info = VersionInfo::from_json_minimal(json).unwrap();
+        assert_eq!(info.target_name.unwrap(), "a\"\\/\u{e9}\u{1f980}\n");
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        for json in [
+            "",
+            r#"{"packages":[]"#,
+            r#"{"packages":[]} x"#,
+            r#"{"packages":[{"name":"foo","version":"1.0.0"}]}"#,
+            r#"{"packages":[{"name":"foo","version":"one","source":"local"}]}"#,
+            r#"{"packages":[{"name":"foo","version":"1.0.0","source":"local","dependencies":[-1]}]}"#,
+            r#"{"packages":[],"packages":[]}"#,
+            r#"{"packages":[],"target_name":"\ud800"}"#,
+            // cyclic dependency
+            r#"{"packages":[{"name":"foo","version":"1.0.0","source":"local","dependencies":[0]}]}"#,
+            // unknown fields
+            r#"{"packages":[],"extra":1}"#,
+            r#"{"packages":[{"name":"foo","version":"1.0.0","source":"local","extra":1}]}"#,
+            r#"{"packages":[{"name":"foo","version":"1.0.0","source":{"kind":"local","url":"x"}}]}"#,
+            r#"{"packages":[],"workspace":{"extra":1}}"#,
+            r#"{"format":-1,"packages":[]}"#,
+        ] {
+            assert!(VersionInfo::from_json_minimal(json).is_err(), "{}", json);
+        }
+        let deeply_nested = format!("{}{}", "[".repeat(1000), "]".repeat(1000));
+        assert!(VersionInfo::from_json_minimal(&deeply_nested).is_err());
+    }
+
+    #[test]
+    fn newer_format() {
+        // fields this parser doesn't know about are expected in newer formats
+        let json = r#"{"format":1000,"packages":[],"extra":1}"#;
+        let error = VersionInfo::from_json_minimal(json).unwrap_err();
+        assert_eq!(
+            error.validation_error(),
+            Some(ValidationError::NewerFormat(1000))
+        );
+        let error = VersionInfo::from_json_minimal(r#"{"packages":[],"extra":1}"#).unwrap_err();
+        assert_eq!(error.validation_error(), None);
+        assert_eq!(error.to_string(), "unknown field `extra`");
+    }
+}
@@ -0,0 +1,368 @@
+//! Normalization of audit data written by producers that emit valid but non-canonical values,
+//! such as names with stray whitespace or index URLs with a trailing `/`,
+//! so that matching it against vulnerability databases and policies is reliable.
+
+use crate::sources::{is_crates_io, strip_index_prefix};
+use crate::{GitSource, RegistrySource, Source, VersionInfo};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+/// Selects the changes made by [`VersionInfo::normalize`]
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizeOptions {
+    /// Remove leading and trailing whitespace from package names and source URLs.
+    pub trim_whitespace: bool,
+    /// Lowercase package names. crates.io does not allow publishing crates whose names
+    /// only differ in case, but it keeps the case they were published with, e.g. `Inflector`,
+    /// and so do vulnerability databases. Off by default for that reason.
+    pub lowercase_names: bool,
+    /// Rewrite source URLs the way Cargo compares them: the scheme and host are lowercased,
+    /// the trailing `/` and the `.git` suffix of repositories are removed, and so is the case
+    /// of GitHub paths. Registries whose index is that of crates.io become [`Source::CratesIo`],
+    /// and git revisions are lowercased.
+    pub canonical_source_urls: bool,
+    /// Maps the index URLs of registry mirrors to the index URL of the registry they mirror,
+    /// so that packages from a mirror match advisories about the upstream registry.
+    /// If the upstream registry is crates.io, e.g. `https://index.crates.io` or simply `crates.io`,
+    /// the packages become [`Source::CratesIo`].
+    ///
+    /// URLs are compared in the canonical form described above, with or without
+    /// the `registry+` or `sparse+` prefix. Empty by default.
+    pub registry_mirrors: BTreeMap<String, String>,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        Self {
+            trim_whitespace: true,
+            lowercase_names: false,
+            canonical_source_urls: true,
+            registry_mirrors: BTreeMap::new(),
+        }
+    }
+}
+
+/// A single change made by [`VersionInfo::normalize`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizationChange {
+    /// Index of the package in [`VersionInfo::packages`]
+    pub package: usize,
+    /// The field that was changed, `name` or `source`
+    pub field: &'static str,
+    pub before: String,
+    pub after: String,
+}
+
+impl Display for NormalizationChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "package {}: {} changed from {:?} to {:?}",
+            self.package, self.field, self.before, self.after
+        )
+    }
+}
+
+impl VersionInfo {
+    /// Rewrites values that are valid but not in canonical form, as selected by `options`,
+    /// and returns a report of what was changed. The report is empty if the data
+    /// was already in canonical form, e.g. if it was produced by `cargo auditable`.
+    ///
+    /// ```rust
+    /// use auditable_serde::{NormalizeOptions, Package, RegistrySource, Source, VersionInfoBuilder};
+    /// let mut builder = VersionInfoBuilder::new();
+    /// let source = Source::Registry(RegistrySource {
+    ///     url: Some("sparse+https://index.crates.io/".to_owned()),
+    /// });
+    /// let mut package = Package::new(" adler", "1.0.2".parse().unwrap(), source);
+    /// package.root = true;
+    /// builder.package(package);
+    /// let mut info = builder.build().unwrap();
+    /// let changes = info.normalize(&NormalizeOptions::default());
+    /// assert_eq!(changes.len(), 2);
+    /// assert_eq!(info.packages[0].name, "adler");
+    /// assert_eq!(info.packages[0].source, Source::CratesIo);
+    /// ```
+    pub fn normalize(&mut self, options: &NormalizeOptions) -> Vec<NormalizationChange> {
+        let mut changes = Vec::new();
+        for (index, package) in self.packages.iter_mut().enumerate() {
+            let mut name = package.name.clone();
+            if options.trim_whitespace {
+                name = name.trim().to_owned();
+            }
+            if options.lowercase_names {
+                name = name.to_lowercase();
+            }
+            if name != package.name {
+                changes.push(NormalizationChange {
+                    package: index,
+                    field: "name",
+                    before: std::mem::replace(&mut package.name, name.clone()),
+                    after: name,
+                });
+            }
+
+            let source = normalize_source(&package.source, options);
+            if source != package.source {
+                changes.push(NormalizationChange {
+                    package: index,
+                    field: "source",
+                    before: describe(&package.source),
+                    after: describe(&source),
+                });
+                package.source = source;
+            }
+        }
+        changes
+    }
+}
+
+fn normalize_source(source: &Source, options: &NormalizeOptions) -> Source {
+    let url = |url: &str| {
+        let url = if options.trim_whitespace {
+            url.trim()
+        } else {
+            url
+        };
+        if options.canonical_source_urls {
+            canonical_url(url)
+        } else {
+            url.to_owned()
+        }
+    };
+    match source {
+        Source::Git(git) => Source::Git(GitSource {
+            url: git.url.as_deref().map(|u| {
+                let u = url(u);
+                if options.canonical_source_urls {
+                    u.strip_suffix(".git").map(str::to_owned).unwrap_or(u)
+                } else {
+                    u
+                }
+            }),
+            rev: git.rev.as_ref().map(|rev| {
+                let rev = if options.trim_whitespace {
+                    rev.trim()
+                } else {
+                    rev
+                };
+                if options.canonical_source_urls {
+                    rev.to_lowercase()
+                } else {
+                    rev.to_owned()
+                }
+            }),
+        }),
+        Source::Registry(RegistrySource { url: Some(u) }) => {
+            let mirrored = options
+                .registry_mirrors
+                .iter()
+                .find(|(mirror, _)| same_registry(mirror, u));
+            let u = match mirrored {
+                Some((_, upstream)) => url(upstream),
+                None => url(u),
+            };
+            if (options.canonical_source_urls || mirrored.is_some()) && is_crates_io(&u) {
+                Source::CratesIo
+            } else {
+                Source::Registry(RegistrySource { url: Some(u) })
+            }
+        }
+        Source::Other(kind) if options.trim_whitespace => Source::Other(kind.trim().to_owned()),
+        other => other.clone(),
+    }
+}
+
+/// Whether both URLs refer to the same registry index, regardless of formatting
+fn same_registry(a: &str, b: &str) -> bool {
+    let canonical = |url: &str| canonical_url(strip_index_prefix(url.trim()));
+    canonical(a) == canonical(b)
+}
+
+/// Lowercases the scheme and host, and the path on GitHub, and removes the trailing `/`.
+/// The `registry+` or `sparse+` prefix is kept.
+fn canonical_url(url: &str) -> String {
+    let url = url.trim_end_matches('/');
+    let (scheme, rest) = match url.find("://") {
+        Some(end) => (&url[..end], &url[end + 3..]),
+        None => return url.to_owned(),
+    };
+    let (host, path) = match rest.find('/') {
+        Some(end) => (&rest[..end], &rest[end..]),
+        None => (rest, ""),
+    };
+    let host = host.to_lowercase();
+    // GitHub paths are case-insensitive, so Cargo lowercases them too
+    let path = if host == "github.com" {
+        path.to_lowercase()
+    } else {
+        path.to_owned()
+    };
+    format!("{}://{}{}", scheme.to_lowercase(), host, path)
+}
+
+/// Describes the source in a single line for the report
+fn describe(source: &Source) -> String {
+    match source {
+        Source::CratesIo => "crates.io".to_owned(),
+        Source::Local(_) => "local".to_owned(),
+        Source::Workspace(_) => "workspace".to_owned(),
+        Source::Registry(RegistrySource { url }) => {
+            url.clone().unwrap_or_else(|| "registry".to_owned())
+        }
+        Source::Git(GitSource { url, rev }) => format!(
+            "git+{}{}",
+            url.as_deref().unwrap_or(""),
+            rev.as_ref()
+                .map(|rev| format!("#{rev}"))
+                .unwrap_or_default()
+        ),
+        Source::Other(kind) => kind.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LocalSource, Package};
+
+    fn info(packages: Vec<Package>) -> VersionInfo {
+        VersionInfo {
+            packages,
+            target_name: None,
+            toolchain: None,
+            workspace: None,
+        }
+    }
+
+    fn git(url: &str, rev: &str) -> Source {
+        Source::Git(GitSource {
+            url: Some(url.to_owned()),
+            rev: Some(rev.to_owned()),
+        })
+    }
+
+    #[test]
+    fn canonical_data_is_unchanged() {
+        let mut data = info(vec![
+            Package::new("adler", "1.0.2".parse().unwrap(), Source::CratesIo),
+            Package::new(
+                "app",
+                "0.1.0".parse().unwrap(),
+                Source::Local(LocalSource::default()),
+            ),
+            Package::new(
+                "dep",
+                "0.1.0".parse().unwrap(),
+                git("https://github.com/example/dep", "abc123"),
+            ),
+        ]);
+        let before = data.clone();
+        assert!(data.normalize(&NormalizeOptions::default()).is_empty());
+        assert_eq!(data, before);
+    }
+
+    #[test]
+    fn source_urls() {
+        let mut data = info(vec![
+            Package::new(
+                "dep",
+                "0.1.0".parse().unwrap(),
+                git("HTTPS://GitHub.com/Example/Dep.git/", "ABC123"),
+            ),
+            Package::new(
+                "internal",
+                "0.1.0".parse().unwrap(),
+                Source::Registry(RegistrySource {
+                    url: Some("sparse+https://Registry.Example.com/Index/ ".to_owned()),
+                }),
+            ),
+        ]);
+        let changes = data.normalize(&NormalizeOptions::default());
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            data.packages[0].source,
+            git("https://github.com/example/dep", "abc123")
+        );
+        assert_eq!(
+            changes[0].before,
+            "git+HTTPS://GitHub.com/Example/Dep.git/#ABC123"
+        );
+        // Only the host is case-insensitive outside of GitHub
+        assert_eq!(
+            data.packages[1].source,
+            Source::Registry(RegistrySource {
+                url: Some("sparse+https://registry.example.com/Index".to_owned())
+            })
+        );
+    }
+
+    #[test]
+    fn registry_mirrors() {
+        let registry = |url: &str| {
+            Source::Registry(RegistrySource {
+                url: Some(url.to_owned()),
+            })
+        };
+        let mut data = info(vec![
+            Package::new(
+                "adler",
+                "1.0.2".parse().unwrap(),
+                registry("sparse+https://Mirror.example.com/crates-io/"),
+            ),
+            Package::new(
+                "internal",
+                "0.1.0".parse().unwrap(),
+                registry("registry+https://git.example.com/internal-mirror"),
+            ),
+            Package::new(
+                "other",
+                "0.1.0".parse().unwrap(),
+                registry("sparse+https://other.example.com/"),
+            ),
+        ]);
+        let mut options = NormalizeOptions::default();
+        options.registry_mirrors.insert(
+            "https://mirror.example.com/crates-io".to_owned(),
+            "crates.io".to_owned(),
+        );
+        options.registry_mirrors.insert(
+            "https://git.example.com/internal-mirror".to_owned(),
+            "sparse+https://registry.example.com/".to_owned(),
+        );
+        let changes = data.normalize(&options);
+        assert_eq!(changes.len(), 3);
+        assert_eq!(data.packages[0].source, Source::CratesIo);
+        assert_eq!(
+            data.packages[1].source,
+            registry("sparse+https://registry.example.com")
+        );
+        assert_eq!(
+            data.packages[2].source,
+            registry("sparse+https://other.example.com")
+        );
+    }
+
+    #[test]
+    fn names() {
+        let mut data = info(vec![Package::new(
+            " Inflector\t",
+            "0.11.4".parse().unwrap(),
+            Source::CratesIo,
+        )]);
+        let changes = data.normalize(&NormalizeOptions::default());
+        assert_eq!(data.packages[0].name, "Inflector");
+        assert_eq!(
+            changes[0].to_string(),
+            r#"package 0: name changed from " Inflector\t" to "Inflector""#
+        );
+
+        let options = NormalizeOptions {
+            lowercase_names: true,
+            ..Default::default()
+        };
+        data.normalize(&options);
+        assert_eq!(data.packages[0].name, "inflector");
+    }
+}
@@ -0,0 +1,129 @@
+//! The canonical order of packages in the dependency tree.
+//!
+//! Sorting the packages makes the serialized data identical between compilations
+//! and between tools that produce it, which is required for reproducible builds.
+
+use crate::{DependencyKind, Package, VersionInfo};
+use std::cmp::Ordering;
+
+/// The order in which packages are stored in [`VersionInfo::packages`]:
+/// by name, then by version, then by source, then by ecosystem.
+///
+/// Packages that compare equal are indistinguishable in the serialized data,
+/// so their relative order does not matter.
+///
+/// ```rust
+/// use auditable_serde::{canonical_cmp, VersionInfo};
+/// use std::str::FromStr;
+/// let json_str = r#"{"packages":[
+///     {"name":"adler","version":"1.0.2","source":"crates.io"},
+///     {"name":"adler","version":"0.2.3","source":"crates.io"}
+/// ]}"#;
+/// let info = VersionInfo::from_str(json_str).unwrap();
+/// let order = canonical_cmp(&info.packages[0], &info.packages[1]);
+/// assert_eq!(order, std::cmp::Ordering::Greater);
+/// ```
+pub fn canonical_cmp(a: &Package, b: &Package) -> Ordering {
+    a.name
+        .cmp(&b.name)
+        .then_with(|| a.version.cmp(&b.version))
+        .then_with(|| a.source.cmp(&b.source))
+        .then_with(|| a.ecosystem.cmp(&b.ecosystem))
+}
+
+impl VersionInfo {
+    /// Sorts the packages in the canonical order defined by [`canonical_cmp`],
+    /// and updates the dependency indices to match.
+    ///
+    /// The conversion from `cargo metadata` already produces packages in this order.
+    /// This is useful for tools that assemble a `VersionInfo` by other means
+    /// and want their output to be byte-for-byte identical to that of `cargo auditable`.
+    ///
+    /// The packages are left untouched if any dependency refers to a nonexistent package.
+    pub fn canonicalize(&mut self) {
+        let count = self.packages.len();
+        let out_of_bounds = self
+            .packages
+            .iter()
+            .any(|p| p.dependencies.iter().any(|&index| index >= count));
+        if out_of_bounds {
+            return;
+        }
+
+        // The stable sort keeps the order of packages that compare equal
+        let mut order: Vec<usize> = (0..count).collect();
+        order.sort_by(|&a, &b| canonical_cmp(&self.packages[a], &self.packages[b]));
+        let mut new_index = vec![0; count];
+        for (new, &old) in order.iter().enumerate() {
+            new_index[old] = new;
+        }
+
+        let mut old_packages: Vec<Option<Package>> = std::mem::take(&mut self.packages)
+            .into_iter()
+            .map(Some)
+            .collect();
+        self.packages = order
+            .iter()
+            .map(|&old| old_packages[old].take().unwrap())
+            .collect();
+
+        for package in self.packages.iter_mut() {
+            if package.dependency_kinds.is_empty() {
+                for index in package.dependencies.iter_mut() {
+                    *index = new_index[*index];
+                }
+                package.dependencies.sort_unstable();
+            } else {
+                // sorting the pairs keeps the two lists in sync
+                let mut edges: Vec<(usize, DependencyKind)> = package
+                    .dependencies
+                    .iter()
+                    .map(|&index| new_index[index])
+                    .zip(package.dependency_kinds.iter().copied())
+                    .collect();
+                edges.sort_unstable();
+                package.dependencies = edges.iter().map(|(index, _)| *index).collect();
+                package.dependency_kinds = edges.iter().map(|(_, kind)| *kind).collect();
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use crate::VersionInfo;
+    use std::str::FromStr;
+
+    #[test]
+    fn canonicalize_remaps_dependencies() {
+        let mut info = VersionInfo::from_str(
+            r#"{"packages":[
+            {"name":"hello","version":"0.1.0","source":"local","root":true,"dependencies":[2,1]},
+            {"name":"cc","version":"1.0.0","source":"crates.io","kind":"build"},
+            {"name":"adler","version":"1.0.2","source":"crates.io"}
+            ]}"#,
+        )
+        .unwrap();
+        info.canonicalize();
+        let names: Vec<&str> = info.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["adler", "cc", "hello"]);
+        assert_eq!(info.packages[2].dependencies, vec![0, 1]);
+        // already sorted data is left as-is
+        let sorted = info.clone();
+        info.canonicalize();
+        assert_eq!(info, sorted);
+    }
+
+    #[test]
+    fn versions_are_compared_semantically() {
+        let info = VersionInfo::from_str(
+            r#"{"packages":[
+            {"name":"adler","version":"0.10.0","source":"crates.io"},
+            {"name":"adler","version":"0.9.0","source":"crates.io"}
+            ]}"#,
+        )
+        .unwrap();
+        let order = super::canonical_cmp(&info.packages[0], &info.packages[1]);
+        assert_eq!(order, std::cmp::Ordering::Greater);
+    }
+}
@@ -0,0 +1,303 @@
+//! Produces the compressed payload that `cargo auditable` embeds into binaries.
+
+#[cfg(feature = "compression-zstd")]
+use crate::ZSTD_MARKER;
+use crate::{VersionInfo, COMPRESSION_LEVEL, COMPRESSION_STRATEGY, COMPRESSION_WINDOW_BITS};
+use miniz_oxide::deflate::core::{
+    compress, create_comp_flags_from_zip_params, CompressorOxide, TDEFLFlush, TDEFLStatus,
+};
+use std::{fmt::Display, io::Write};
+
+/// Compression algorithm and level of the payload produced by [`VersionInfo::to_compressed_payload_with`]
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PayloadCompression {
+    /// Zlib at the given level from 0 to 10, on the `miniz_oxide` scale.
+    /// The default is [`COMPRESSION_LEVEL`], which is what `cargo auditable` uses
+    /// unless configured otherwise.
+    Zlib(u8),
+    /// Zstandard at the given level from 1 to 22, prefixed with [`ZSTD_MARKER`](crate::ZSTD_MARKER).
+    /// Compresses large dependency trees noticeably better than Zlib at high levels,
+    /// but readers need Zstandard support to decompress it. The output is only reproducible
+    /// with the same version of the Zstandard library.
+    #[cfg(feature = "compression-zstd")]
+    Zstd(i32),
+    /// The JSON as-is, which starts with [`UNCOMPRESSED_MARKER`](crate::UNCOMPRESSED_MARKER).
+    /// For binaries with only a handful of dependencies the JSON is about as small
+    /// as the compressed data, and readers do not need a decompressor to parse it.
+    Uncompressed,
+}
+
+impl Default for PayloadCompression {
+    fn default() -> Self {
+        PayloadCompression::Zlib(COMPRESSION_LEVEL)
+    }
+}
+
+/// The `miniz_oxide` flags for the given level, [`COMPRESSION_WINDOW_BITS`]
+/// and [`COMPRESSION_STRATEGY`]. A positive window size selects the Zlib wrapper.
+fn compression_flags(level: u8) -> u32 {
+    create_comp_flags_from_zip_params(
+        level.into(),
+        COMPRESSION_WINDOW_BITS.into(),
+        COMPRESSION_STRATEGY.into(),
+    )
+}
+
+impl VersionInfo {
+    /// Serializes the dependency tree to JSON and Zlib-compresses it with the same parameters
+    /// as `cargo auditable`, producing the exact bytes it would embed into the binary.
+    ///
+    /// This lets build systems other than Cargo generate payloads that are byte-for-byte
+    /// identical to the ones produced by `cargo auditable` for the same dependency tree.
+    ///
+    /// ```rust
+    /// use auditable_serde::VersionInfo;
+    /// use std::str::FromStr;
+    /// let json_str = r#"{"packages":[{"name":"adler","version":"0.2.3","source":"registry","root":true}]}"#;
+    /// let info = VersionInfo::from_str(json_str).unwrap();
+    /// let payload = info.to_compressed_payload();
+    /// assert_eq!(&payload[..1], &[0x78]); // Zlib header
+    /// ```
+    pub fn to_compressed_payload(&self) -> Vec<u8> {
+        self.to_compressed_payload_with(PayloadCompression::default())
+    }
+
+    /// Like [`VersionInfo::to_compressed_payload`], but with the given compression algorithm
+    /// and level. Any other than the default makes the output differ from that of
+    /// other producers for the same dependency tree.
+    ///
+    /// ```rust
+    /// use auditable_serde::{PayloadCompression, VersionInfo};
+    /// use std::str::FromStr;
+    /// let json_str = r#"{"packages":[{"name":"adler","version":"0.2.3","source":"registry","root":true}]}"#;
+    /// let info = VersionInfo::from_str(json_str).unwrap();
+    /// let payload = info.to_compressed_payload_with(PayloadCompression::Zlib(9));
+    /// assert_eq!(&payload[..1], &[0x78]); // Zlib header
+    /// ```
+    pub fn to_compressed_payload_with(&self, compression: PayloadCompression) -> Vec<u8> {
+        let json = serde_json::to_string(self).expect("Failed to serialize audit data to JSON");
+        match compression {
+            PayloadCompression::Zlib(level) => compress_zlib(json.as_bytes(), level),
+            #[cfg(feature = "compression-zstd")]
+            PayloadCompression::Zstd(level) => {
+                let mut output = vec![ZSTD_MARKER];
+                let frame = zstd::bulk::compress(json.as_bytes(), level)
+                    .expect("Failed to compress the audit data");
+                output.extend_from_slice(&frame);
+                output
+            }
+            PayloadCompression::Uncompressed => json.into_bytes(),
+        }
+    }
+
+    /// Writes the same bytes as [`VersionInfo::to_compressed_payload`] into `out`,
+    /// and returns the number of bytes written.
+    ///
+    /// The JSON is compressed as it is serialized, so neither the JSON nor the compressed data
+    /// is buffered in a separate allocation. Only the compressor state is allocated.
+    /// This suits memory-constrained build tooling and large dependency trees.
+    ///
+    /// The packages are written in the order they are stored in. To get the canonical output,
+    /// call [`VersionInfo::canonicalize`] first if the data was not produced by `from_metadata`.
+    ///
+    /// ```rust
+    /// use auditable_serde::VersionInfo;
+    /// use std::str::FromStr;
+    /// let json_str = r#"{"packages":[{"name":"adler","version":"0.2.3","source":"registry","root":true}]}"#;
+    /// let info = VersionInfo::from_str(json_str).unwrap();
+    /// let mut buffer = [0u8; 1024];
+    /// let len = info.write_compressed_payload(&mut buffer).unwrap();
+    /// assert_eq!(&buffer[..len], &info.to_compressed_payload()[..]);
+    /// ```
+    pub fn write_compressed_payload(&self, out: &mut [u8]) -> Result<usize, BufferTooSmall> {
+        let mut writer = CompressingWriter {
+            compressor: CompressorOxide::new(compression_flags(COMPRESSION_LEVEL)),
+            out,
+            written: 0,
+        };
+        // Serializing this struct cannot fail, so any error comes from running out of space
+        serde_json::to_writer(&mut writer, self).map_err(|_| BufferTooSmall)?;
+        writer.finish()
+    }
+}
+
+fn compress_zlib(mut input: &[u8], level: u8) -> Vec<u8> {
+    let mut compressor = CompressorOxide::new(compression_flags(level));
+    let mut output = vec![0; input.len() / 2 + 128];
+    let mut written = 0;
+    loop {
+        let (status, consumed, produced) = compress(
+            &mut compressor,
+            input,
+            &mut output[written..],
+            TDEFLFlush::Finish,
+        );
+        input = &input[consumed..];
+        written += produced;
+        match status {
+            TDEFLStatus::Done => break,
+            TDEFLStatus::Okay => output.resize(output.len() * 2, 0),
+            TDEFLStatus::BadParam | TDEFLStatus::PutBufFailed => {
+                panic!("Failed to compress the audit data")
+            }
+        }
+    }
+    output.truncate(written);
+    output
+}
+
+/// Returned by [`VersionInfo::write_compressed_payload`] if the compressed data does not fit into the buffer
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BufferTooSmall;
+
+impl Display for BufferTooSmall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The buffer is too small to hold the compressed audit data"
+        )
+    }
+}
+
+impl std::error::Error for BufferTooSmall {}
+
+/// Compresses everything written to it into a fixed-size buffer
+struct CompressingWriter<'a> {
+    compressor: CompressorOxide,
+    out: &'a mut [u8],
+    written: usize,
+}
+
+impl CompressingWriter<'_> {
+    fn finish(mut self) -> Result<usize, BufferTooSmall> {
+        loop {
+            let (status, _, produced) = compress(
+                &mut self.compressor,
+                &[],
+                &mut self.out[self.written..],
+                TDEFLFlush::Finish,
+            );
+            self.written += produced;
+            match status {
+                TDEFLStatus::Done => return Ok(self.written),
+                // The compressor has more output than fits into the remaining space
+                TDEFLStatus::Okay if self.written == self.out.len() => return Err(BufferTooSmall),
+                TDEFLStatus::Okay => (),
+                TDEFLStatus::BadParam | TDEFLStatus::PutBufFailed => return Err(BufferTooSmall),
+            }
+        }
+    }
+}
+
+impl Write for CompressingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let (status, consumed, produced) = compress(
+            &mut self.compressor,
+            buf,
+            &mut self.out[self.written..],
+            TDEFLFlush::None,
+        );
+        self.written += produced;
+        match status {
+            TDEFLStatus::Okay if consumed > 0 || buf.is_empty() => Ok(consumed),
+            _ => Err(std::io::ErrorKind::WriteZero.into()),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "compression-zstd")]
+    use crate::ZSTD_MARKER;
+    use crate::{
+        PayloadCompression, VersionInfo, COMPRESSION_LEVEL, COMPRESSION_STRATEGY,
+        COMPRESSION_WINDOW_BITS, UNCOMPRESSED_MARKER,
+    };
+    use miniz_oxide::{deflate::compress_to_vec_zlib, inflate::decompress_to_vec_zlib};
+    use std::str::FromStr;
+
+    #[test]
+    fn payload_roundtrip() {
+        let json = r#"{"packages":[{"name":"adler","version":"0.2.3","source":"crates.io","kind":"build"},{"name":"foo","version":"1.0.0","source":"local","dependencies":[0],"root":true}]}"#;
+        let info = VersionInfo::from_str(json).unwrap();
+        let payload = info.to_compressed_payload();
+        let decompressed = decompress_to_vec_zlib(&payload).unwrap();
+        assert_eq!(decompressed, json.as_bytes());
+        // the output must be deterministic for reproducible builds
+        assert_eq!(payload, info.to_compressed_payload());
+    }
+
+    #[test]
+    fn compression_settings_are_pinned() {
+        // Changing any of these changes the bytes embedded into every binary,
+        // which breaks reproducible builds across versions and other producers of the format
+        assert_eq!(COMPRESSION_LEVEL, 7);
+        assert_eq!(COMPRESSION_WINDOW_BITS, 15);
+        assert_eq!(COMPRESSION_STRATEGY, 0);
+        let json = r#"{"packages":[{"name":"adler","version":"0.2.3","source":"crates.io","kind":"build"},{"name":"foo","version":"1.0.0","source":"local","dependencies":[0],"root":true}]}"#;
+        let payload = VersionInfo::from_str(json).unwrap().to_compressed_payload();
+        // Zlib header: Deflate with a 2^15 byte window
+        assert_eq!(payload[0], ((COMPRESSION_WINDOW_BITS - 8) << 4) | 8);
+        // the simplest way to get the same bytes with `miniz_oxide`, which earlier versions used
+        assert_eq!(
+            payload,
+            compress_to_vec_zlib(json.as_bytes(), COMPRESSION_LEVEL)
+        );
+    }
+
+    #[test]
+    fn compression_levels() {
+        let json = r#"{"packages":[{"name":"adler","version":"0.2.3","source":"crates.io","kind":"build"},{"name":"foo","version":"1.0.0","source":"local","dependencies":[0],"root":true}]}"#;
+        let info = VersionInfo::from_str(json).unwrap();
+        assert_eq!(
+            info.to_compressed_payload_with(PayloadCompression::default()),
+            info.to_compressed_payload()
+        );
+        for level in 0..=10 {
+            let payload = info.to_compressed_payload_with(PayloadCompression::Zlib(level));
+            assert_eq!(payload, compress_to_vec_zlib(json.as_bytes(), level));
+            assert_eq!(decompress_to_vec_zlib(&payload).unwrap(), json.as_bytes());
+        }
+    }
+
+    #[cfg(feature = "compression-zstd")]
+    #[test]
+    fn zstd_payload() {
+        let json = r#"{"packages":[{"name":"adler","version":"0.2.3","source":"crates.io","kind":"build"},{"name":"foo","version":"1.0.0","source":"local","dependencies":[0],"root":true}]}"#;
+        let info = VersionInfo::from_str(json).unwrap();
+        let payload = info.to_compressed_payload_with(PayloadCompression::Zstd(19));
+        assert_eq!(payload[0], ZSTD_MARKER);
+        assert_eq!(
+            zstd::bulk::decompress(&payload[1..], json.len()).unwrap(),
+            json.as_bytes()
+        );
+    }
+
+    #[test]
+    fn uncompressed_payload() {
+        let json = r#"{"packages":[{"name":"adler","version":"0.2.3","source":"crates.io","kind":"build"},{"name":"foo","version":"1.0.0","source":"local","dependencies":[0],"root":true}]}"#;
+        let info = VersionInfo::from_str(json).unwrap();
+        let payload = info.to_compressed_payload_with(PayloadCompression::Uncompressed);
+        assert_eq!(payload, json.as_bytes());
+        assert_eq!(payload[0], UNCOMPRESSED_MARKER);
+        // must not be mistaken for a Zlib stream, whose first byte has 8 in its low four bits
+        assert_ne!(UNCOMPRESSED_MARKER & 0x0f, 8);
+    }
+
+    #[test]
+    fn write_into_buffer() {
+        let json = r#"{"packages":[{"name":"adler","version":"0.2.3","source":"crates.io","kind":"build"},{"name":"foo","version":"1.0.0","source":"local","dependencies":[0],"root":true}]}"#;
+        let info = VersionInfo::from_str(json).unwrap();
+        let expected = info.to_compressed_payload();
+        let mut buffer = vec![0u8; expected.len()];
+        let len = info.write_compressed_payload(&mut buffer).unwrap();
+        assert_eq!(&buffer[..len], &expected[..]);
+        let mut short = vec![0u8; expected.len() - 1];
+        assert!(info.write_compressed_payload(&mut short).is_err());
+    }
+}
@@ -0,0 +1,254 @@
+//! Renders the dependency tree in the output formats of the command-line tools,
+//! so that custom tools can produce exactly the same output without shelling out to them.
+//!
+//! Every format implements [`ReportWriter`], so a tool can pick one at runtime:
+//!
+//! ```rust
+//! use auditable_serde::report::{Csv, Json, ReportWriter, Table};
+//! use auditable_serde::VersionInfo;
+//! use std::str::FromStr;
+//! let json_str = r#"{"packages":[{"name":"adler","version":"0.2.3","source":"crates.io","root":true}]}"#;
+//! let info = VersionInfo::from_str(json_str).unwrap();
+//! let writer: Box<dyn ReportWriter> = match "table" {
+//!     "csv" => Box::new(Csv),
+//!     "table" => Box::new(Table),
+//!     _ => Box::new(Json),
+//! };
+//! let mut output = Vec::new();
+//! writer.write(&info, &mut output).unwrap();
+//! assert!(String::from_utf8(output).unwrap().contains("adler"));
+//! ```
+
+use crate::{DependencyKind, Package, Source, VersionInfo};
+use std::io::{self, Write};
+
+/// Writes a report about the dependency tree of a single binary
+pub trait ReportWriter {
+    fn write(&self, info: &VersionInfo, out: &mut dyn Write) -> io::Result<()>;
+}
+
+/// The audit data as JSON, in the same form as it is embedded into binaries, on a single line
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+impl ReportWriter for Json {
+    fn write(&self, info: &VersionInfo, out: &mut dyn Write) -> io::Result<()> {
+        serde_json::to_writer(&mut *out, info)?;
+        writeln!(out)
+    }
+}
+
+/// A table of packages for humans, with columns aligned by padding them with spaces.
+/// Dependencies are omitted; use [`Csv`] or [`Json`] for the complete dependency tree.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Table;
+
+impl ReportWriter for Table {
+    fn write(&self, info: &VersionInfo, out: &mut dyn Write) -> io::Result<()> {
+        let mut rows = vec![[
+            "NAME".to_owned(),
+            "VERSION".to_owned(),
+            "KIND".to_owned(),
+            "SOURCE".to_owned(),
+        ]];
+        for package in &info.packages {
+            rows.push([
+                package.name.clone(),
+                package.version.to_string(),
+                kind_name(package).to_owned(),
+                source_name(&package.source),
+            ]);
+        }
+        let mut widths = [0; 4];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        for row in &rows {
+            let mut line = String::new();
+            for (column, (width, cell)) in widths.iter().zip(row.iter()).enumerate() {
+                if column + 1 == row.len() {
+                    line.push_str(cell);
+                } else {
+                    line.push_str(&format!("{:<width$}  ", cell, width = *width));
+                }
+            }
+            writeln!(out, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// One row per package as described in RFC 4180, with a header row.
+/// The `dependencies` column lists the indices of the dependencies of a package
+/// in the `index` column, separated by spaces.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Csv;
+
+impl ReportWriter for Csv {
+    fn write(&self, info: &VersionInfo, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "index,name,version,kind,source,root,checksum,dependencies"
+        )?;
+        for (index, package) in info.packages.iter().enumerate() {
+            let dependencies: Vec<String> = package
+                .dependencies
+                .iter()
+                .map(|dependency| dependency.to_string())
+                .collect();
+            let fields = [
+                index.to_string(),
+                package.name.clone(),
+                package.version.to_string(),
+                kind_name(package).to_owned(),
+                source_name(&package.source),
+                package.root.to_string(),
+                package.checksum.clone().unwrap_or_default(),
+                dependencies.join(" "),
+            ];
+            let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+            writeln!(out, "{}", fields.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// A CycloneDX SBOM as produced by [`VersionInfo::to_cyclonedx`], pretty-printed
+#[cfg(feature = "cyclonedx")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CycloneDx;
+
+#[cfg(feature = "cyclonedx")]
+impl ReportWriter for CycloneDx {
+    fn write(&self, info: &VersionInfo, out: &mut dyn Write) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut *out, &info.to_cyclonedx())?;
+        writeln!(out)
+    }
+}
+
+/// An SPDX document as produced by [`VersionInfo::to_spdx_json`] or
+/// [`VersionInfo::to_spdx_tag_value`], which describe the parameters
+#[cfg(feature = "spdx")]
+#[derive(Debug, Clone, Default)]
+pub struct Spdx {
+    pub document_namespace: String,
+    pub created: String,
+    /// Write the tag-value format instead of JSON
+    pub tag_value: bool,
+}
+
+#[cfg(feature = "spdx")]
+impl ReportWriter for Spdx {
+    fn write(&self, info: &VersionInfo, out: &mut dyn Write) -> io::Result<()> {
+        if self.tag_value {
+            out.write_all(
+                info.to_spdx_tag_value(&self.document_namespace, &self.created)
+                    .as_bytes(),
+            )
+        } else {
+            let document = info.to_spdx_json(&self.document_namespace, &self.created);
+            serde_json::to_writer_pretty(&mut *out, &document)?;
+            writeln!(out)
+        }
+    }
+}
+
+/// Formats a value as a CSV field as described in RFC 4180,
+/// quoting it only if it contains separators, quotes or line breaks
+pub fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_owned()
+    }
+}
+
+fn kind_name(package: &Package) -> &'static str {
+    match package.kind {
+        DependencyKind::Development => "development",
+        DependencyKind::Build => "build",
+        DependencyKind::Runtime => "runtime",
+    }
+}
+
+/// The source as written in `Cargo.lock` if its URL was recorded, or as written in JSON otherwise
+fn source_name(source: &Source) -> String {
+    match source {
+        Source::CratesIo => "crates.io".to_owned(),
+        other => other
+            .cargo_lock_repr()
+            .unwrap_or_else(|| String::from(other.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const JSON: &str = r#"{"packages":[{"name":"adler","version":"0.2.3","source":"crates.io","kind":"build"},{"name":"bar","version":"0.1.0","source":{"kind":"git","url":"https://example.com/bar.git","rev":"abc"}},{"name":"foo","version":"1.0.0","source":"local","dependencies":[0,1],"root":true}]}"#;
+
+    fn render(writer: &dyn ReportWriter) -> String {
+        let info = VersionInfo::from_str(JSON).unwrap();
+        let mut output = Vec::new();
+        writer.write(&info, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(render(&Json), format!("{}\n", JSON));
+    }
+
+    #[test]
+    fn table() {
+        let expected = "\
+NAME   VERSION  KIND     SOURCE
+adler  0.2.3    build    crates.io
+bar    0.1.0    runtime  git+https://example.com/bar.git?rev=abc
+foo    1.0.0    runtime  local
+";
+        assert_eq!(render(&Table), expected);
+    }
+
+    #[test]
+    fn csv() {
+        let expected = "\
+index,name,version,kind,source,root,checksum,dependencies
+0,adler,0.2.3,build,crates.io,false,,
+1,bar,0.1.0,runtime,git+https://example.com/bar.git?rev=abc,false,,
+2,foo,1.0.0,runtime,local,true,,0 1
+";
+        assert_eq!(render(&Csv), expected);
+    }
+
+    #[test]
+    fn csv_quoting() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    #[cfg(feature = "cyclonedx")]
+    fn cyclonedx() {
+        let sbom: serde_json::Value = serde_json::from_str(&render(&CycloneDx)).unwrap();
+        assert_eq!(sbom["bomFormat"], "CycloneDX");
+    }
+
+    #[test]
+    #[cfg(feature = "spdx")]
+    fn spdx() {
+        let mut writer = Spdx {
+            document_namespace: "https://example.com/foo".to_owned(),
+            created: "2023-01-01T00:00:00Z".to_owned(),
+            tag_value: false,
+        };
+        let document: serde_json::Value = serde_json::from_str(&render(&writer)).unwrap();
+        assert_eq!(document["spdxVersion"], "SPDX-2.3");
+        writer.tag_value = true;
+        assert!(render(&writer).starts_with("SPDXVersion: SPDX-2.3\n"));
+    }
+}
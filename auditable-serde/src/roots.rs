@@ -0,0 +1,133 @@
+//! Queries on payloads that describe several root packages, e.g. a `cdylib`
+//! bundling several components that are each built from their own crate.
+
+use crate::VersionInfo;
+
+impl VersionInfo {
+    /// Returns the indices of the root packages, in the order they appear in [`VersionInfo::packages`]
+    pub fn roots(&self) -> impl Iterator<Item = usize> + '_ {
+        self.packages
+            .iter()
+            .enumerate()
+            .filter(|(_, package)| package.root)
+            .map(|(index, _)| index)
+    }
+
+    /// Returns the indices of the packages reachable from the package at index `root`,
+    /// including `root` itself, sorted in ascending order.
+    ///
+    /// Dependencies referring to nonexistent packages are ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `root` is out of bounds.
+    pub fn reachable_from(&self, root: usize) -> Vec<usize> {
+        assert!(root < self.packages.len(), "package index out of bounds");
+        let mut reachable = vec![false; self.packages.len()];
+        reachable[root] = true;
+        let mut stack = vec![root];
+        while let Some(index) = stack.pop() {
+            for &dep in &self.packages[index].dependencies {
+                if let Some(seen @ false) = reachable.get_mut(dep) {
+                    *seen = true;
+                    stack.push(dep);
+                }
+            }
+        }
+        reachable
+            .iter()
+            .enumerate()
+            .filter(|(_, &reachable)| reachable)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Returns the dependency tree of the package at index `root` on its own,
+    /// with only the packages reachable from it and `root` as the only root package.
+    ///
+    /// The packages keep their relative order, so a canonically sorted `VersionInfo`
+    /// produces a canonically sorted subgraph. Fields describing the whole payload,
+    /// such as [`Package::kind`](crate::Package::kind), [`VersionInfo::target_name`]
+    /// [`VersionInfo::toolchain`] and [`VersionInfo::workspace`], are copied as-is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `root` is out of bounds.
+    pub fn subgraph(&self, root: usize) -> VersionInfo {
+        let included = self.reachable_from(root);
+        let mut new_index = vec![None; self.packages.len()];
+        for (new, &old) in included.iter().enumerate() {
+            new_index[old] = Some(new);
+        }
+        let packages = included
+            .iter()
+            .map(|&old| {
+                let mut package = self.packages[old].clone();
+                package.root = old == root;
+                // Every dependency of a reachable package is reachable, except nonexistent ones
+                let has_kinds = !package.dependency_kinds.is_empty();
+                let mut dependencies = Vec::new();
+                let mut dependency_kinds = Vec::new();
+                for (edge, &dep) in package.dependencies.iter().enumerate() {
+                    if let Some(Some(new)) = new_index.get(dep) {
+                        dependencies.push(*new);
+                        if has_kinds {
+                            dependency_kinds.push(package.dependency_kinds[edge]);
+                        }
+                    }
+                }
+                package.dependencies = dependencies;
+                package.dependency_kinds = dependency_kinds;
+                package
+            })
+            .collect();
+        VersionInfo {
+            packages,
+            target_name: self.target_name.clone(),
+            toolchain: self.toolchain.clone(),
+            workspace: self.workspace.clone(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use crate::{DependencyKind, VersionInfo};
+    use std::str::FromStr;
+
+    const JSON: &str = r#"{"packages":[
+        {"name":"adler","version":"1.0.2","source":"crates.io"},
+        {"name":"cc","version":"1.0.0","source":"crates.io","kind":"build"},
+        {"name":"component_a","version":"0.1.0","source":"local","root":true,"dependencies":[0,1],"dependency_kinds":["runtime","build"]},
+        {"name":"component_b","version":"0.1.0","source":"local","root":true,"dependencies":[0]},
+        {"name":"libc","version":"0.2.140","source":"crates.io"}
+    ]}"#;
+
+    #[test]
+    fn multiple_roots() {
+        let info = VersionInfo::from_str(JSON).unwrap();
+        assert_eq!(info.roots().collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(info.reachable_from(2), vec![0, 1, 2]);
+        assert_eq!(info.reachable_from(3), vec![0, 3]);
+    }
+
+    #[test]
+    fn subgraphs() {
+        let info = VersionInfo::from_str(JSON).unwrap();
+        let a = info.subgraph(2);
+        let names: Vec<&str> = a.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["adler", "cc", "component_a"]);
+        assert_eq!(a.packages[2].dependencies, vec![0, 1]);
+        assert_eq!(
+            a.packages[2].dependency_kinds,
+            vec![DependencyKind::Runtime, DependencyKind::Build]
+        );
+        assert_eq!(a.roots().collect::<Vec<_>>(), vec![2]);
+
+        let b = info.subgraph(3);
+        let names: Vec<&str> = b.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["adler", "component_b"]);
+        assert_eq!(b.packages[1].dependencies, vec![0]);
+        assert!(b.packages[1].root);
+    }
+}
@@ -0,0 +1,144 @@
+//! Filters on where packages come from, for policy checks such as "no git dependencies".
+
+use crate::{Package, RegistrySource, Source, VersionInfo};
+
+/// Index URLs of crates.io, with the `registry+` or `sparse+` prefix and any trailing `/` removed
+const CRATES_IO_INDEX_URLS: [&str; 2] = [
+    "https://github.com/rust-lang/crates.io-index",
+    "https://index.crates.io",
+];
+
+impl VersionInfo {
+    /// Returns the packages built from git repositories
+    pub fn packages_from_git(&self) -> impl Iterator<Item = &Package> + '_ {
+        self.packages
+            .iter()
+            .filter(|package| matches!(package.source, Source::Git(_)))
+    }
+
+    /// Returns the packages built from a local path, including workspace members
+    pub fn local_packages(&self) -> impl Iterator<Item = &Package> + '_ {
+        self.packages
+            .iter()
+            .filter(|package| matches!(package.source, Source::Local(_) | Source::Workspace(_)))
+    }
+
+    /// Returns the members of the workspace the binary was built in,
+    /// i.e. the first-party packages as opposed to other path dependencies
+    pub fn workspace_packages(&self) -> impl Iterator<Item = &Package> + '_ {
+        self.packages
+            .iter()
+            .filter(|package| matches!(package.source, Source::Workspace(_)))
+    }
+
+    /// Returns the packages downloaded from the registry with the given index URL.
+    ///
+    /// The URL may be given with or without the `registry+` or `sparse+` prefix
+    /// used in `Cargo.lock`, and crates.io can also be referred to as `crates.io`.
+    ///
+    /// Packages from registries other than crates.io are only recorded with their URL
+    /// if requested when the binary was built. Those recorded without it match any URL
+    /// other than crates.io.
+    ///
+    /// ```rust
+    /// use auditable_serde::VersionInfo;
+    /// use std::str::FromStr;
+    /// let json_str = r#"{"packages":[
+    ///     {"name":"adler","version":"0.2.3","source":"crates.io"},
+    ///     {"name":"internal","version":"1.0.0","source":"registry"},
+    ///     {"name":"foo","version":"0.1.0","source":"local","dependencies":[0,1],"root":true}
+    /// ]}"#;
+    /// let info = VersionInfo::from_str(json_str).unwrap();
+    /// let from_crates_io: Vec<&str> = info
+    ///     .packages_from_registry("sparse+https://index.crates.io/")
+    ///     .map(|p| p.name.as_str())
+    ///     .collect();
+    /// assert_eq!(from_crates_io, vec!["adler"]);
+    /// let from_elsewhere: Vec<&str> = info
+    ///     .packages_from_registry("https://example.com/index")
+    ///     .map(|p| p.name.as_str())
+    ///     .collect();
+    /// assert_eq!(from_elsewhere, vec!["internal"]);
+    ///
+    /// let json_str = r#"{"packages":[
+    ///     {"name":"internal","version":"1.0.0","source":{"kind":"registry","url":"sparse+https://example.com/index/"}},
+    ///     {"name":"foo","version":"0.1.0","source":"local","dependencies":[0],"root":true}
+    /// ]}"#;
+    /// let info = VersionInfo::from_str(json_str).unwrap();
+    /// assert_eq!(info.packages_from_registry("sparse+https://example.com/index").count(), 1);
+    /// assert_eq!(info.packages_from_registry("https://other.example.com/index").count(), 0);
+    /// ```
+    pub fn packages_from_registry<'a>(
+        &'a self,
+        url: &str,
+    ) -> impl Iterator<Item = &'a Package> + 'a {
+        let crates_io = is_crates_io(url);
+        let wanted = strip_index_prefix(url).trim_end_matches('/').to_owned();
+        self.packages.iter().filter(move |package| {
+            match &package.source {
+                Source::CratesIo => crates_io,
+                Source::Registry(RegistrySource {
+                    url: Some(recorded),
+                }) => !crates_io && strip_index_prefix(recorded).trim_end_matches('/') == wanted,
+                Source::Registry(_) => !crates_io,
+                // Older versions recorded sparse registries other than crates.io as "sparse"
+                Source::Other(kind) => !crates_io && kind == "sparse",
+                _ => false,
+            }
+        })
+    }
+}
+
+pub(crate) fn is_crates_io(url: &str) -> bool {
+    if url == "crates.io" {
+        return true;
+    }
+    CRATES_IO_INDEX_URLS.contains(&strip_index_prefix(url).trim_end_matches('/'))
+}
+
+/// Removes the `registry+` or `sparse+` prefix used in `Cargo.lock`, if any
+pub(crate) fn strip_index_prefix(url: &str) -> &str {
+    url.strip_prefix("registry+")
+        .or_else(|| url.strip_prefix("sparse+"))
+        .unwrap_or(url)
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn filters_by_source() {
+        let info = VersionInfo::from_str(
+            r#"{"packages":[
+                {"name":"adler","version":"0.2.3","source":"crates.io"},
+                {"name":"bar","version":"0.1.0","source":"git"},
+                {"name":"baz","version":"0.1.0","source":"sparse"},
+                {"name":"helper","version":"0.1.0","source":"local"},
+                {"name":"foo","version":"0.1.0","source":{"kind":"workspace","path":"."},"dependencies":[0,1,2,3],"root":true}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(names(info.packages_from_git()), vec!["bar"]);
+        assert_eq!(names(info.local_packages()), vec!["helper", "foo"]);
+        assert_eq!(names(info.workspace_packages()), vec!["foo"]);
+        let crates_io = "registry+https://github.com/rust-lang/crates.io-index";
+        assert_eq!(names(info.packages_from_registry(crates_io)), vec!["adler"]);
+        // packages from other registries recorded without a URL match any URL
+        let other = "https://example.com/";
+        assert_eq!(names(info.packages_from_registry(other)), vec!["baz"]);
+    }
+
+    fn names<'a>(packages: impl Iterator<Item = &'a Package>) -> Vec<&'a str> {
+        packages.map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn crates_io_urls() {
+        assert!(is_crates_io("crates.io"));
+        assert!(is_crates_io("https://github.com/rust-lang/crates.io-index"));
+        assert!(is_crates_io("sparse+https://index.crates.io/"));
+        assert!(!is_crates_io("https://example.com/crates.io-index"));
+    }
+}
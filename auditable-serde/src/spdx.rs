@@ -0,0 +1,265 @@
+//! Converts the dependency tree to an [SPDX](https://spdx.dev/) 2.3 document.
+
+use crate::{DependencyKind, GitSource, Package, Source, VersionInfo};
+use serde_json::{json, Value};
+use std::fmt::Write;
+
+const SPDX_VERSION: &str = "SPDX-2.3";
+const DOCUMENT_ID: &str = "SPDXRef-DOCUMENT";
+const NOASSERTION: &str = "NOASSERTION";
+
+impl VersionInfo {
+    /// Converts the dependency tree to an SPDX 2.3 document in the JSON format,
+    /// for compliance pipelines that only accept SPDX.
+    ///
+    /// SPDX requires every document to have a unique `document_namespace` URI
+    /// and a `created` timestamp in the `YYYY-MM-DDThh:mm:ssZ` format. They are passed in
+    /// rather than generated, so that the output is reproducible.
+    ///
+    /// Every package becomes an SPDX package with the ID `SPDXRef-Package-{index}`,
+    /// where the index is its position in [`VersionInfo::packages`].
+    /// Packages from crates.io get a download location and a package URL reference.
+    /// Licenses are not recorded by `cargo auditable`, so they are always `NOASSERTION`.
+    ///
+    /// ```rust
+    /// use auditable_serde::VersionInfo;
+    /// use std::str::FromStr;
+    /// let json_str = r#"{"packages":[{"name":"adler","version":"0.2.3","source":"crates.io","root":true}]}"#;
+    /// let info = VersionInfo::from_str(json_str).unwrap();
+    /// let spdx = info.to_spdx_json("https://example.com/spdx/adler-0.2.3", "2023-01-01T00:00:00Z");
+    /// assert_eq!(spdx["spdxVersion"], "SPDX-2.3");
+    /// assert_eq!(spdx["packages"][0]["name"], "adler");
+    /// ```
+    pub fn to_spdx_json(&self, document_namespace: &str, created: &str) -> Value {
+        let packages: Vec<Value> = self
+            .packages
+            .iter()
+            .enumerate()
+            .map(|(index, package)| {
+                let mut result = json!({
+                    "SPDXID": package_id(index),
+                    "name": package.name,
+                    "versionInfo": package.version.to_string(),
+                    "downloadLocation": download_location(package),
+                    "filesAnalyzed": false,
+                    "licenseConcluded": NOASSERTION,
+                    "licenseDeclared": NOASSERTION,
+                    "primaryPackagePurpose": purpose(package),
+                });
+                if let Some(checksum) = &package.checksum {
+                    result["checksums"] =
+                        json!([{ "algorithm": "SHA256", "checksumValue": checksum }]);
+                }
+                if package.source == Source::CratesIo {
+                    result["externalRefs"] = json!([{
+                        "referenceCategory": "PACKAGE-MANAGER",
+                        "referenceType": "purl",
+                        "referenceLocator": package.purl(),
+                    }]);
+                }
+                result
+            })
+            .collect();
+        let relationships: Vec<Value> = self
+            .relationships()
+            .into_iter()
+            .map(|(element, relationship, related)| {
+                json!({
+                    "spdxElementId": element,
+                    "relationshipType": relationship,
+                    "relatedSpdxElement": related,
+                })
+            })
+            .collect();
+        json!({
+            "spdxVersion": SPDX_VERSION,
+            "dataLicense": "CC0-1.0",
+            "SPDXID": DOCUMENT_ID,
+            "name": self.document_name(),
+            "documentNamespace": document_namespace,
+            "creationInfo": {
+                "creators": [creator()],
+                "created": created,
+            },
+            "packages": packages,
+            "relationships": relationships,
+        })
+    }
+
+    /// Converts the dependency tree to an SPDX 2.3 document in the tag-value format.
+    ///
+    /// The contents are the same as for [`VersionInfo::to_spdx_json`], see its documentation for details.
+    pub fn to_spdx_tag_value(&self, document_namespace: &str, created: &str) -> String {
+        let mut out = String::new();
+        // Writing to a String cannot fail
+        let _ = writeln!(out, "SPDXVersion: {SPDX_VERSION}");
+        let _ = writeln!(out, "DataLicense: CC0-1.0");
+        let _ = writeln!(out, "SPDXID: {DOCUMENT_ID}");
+        let _ = writeln!(out, "DocumentName: {}", self.document_name());
+        let _ = writeln!(out, "DocumentNamespace: {document_namespace}");
+        let _ = writeln!(out, "Creator: {}", creator());
+        let _ = writeln!(out, "Created: {created}");
+        for (index, package) in self.packages.iter().enumerate() {
+            let _ = writeln!(out);
+            let _ = writeln!(out, "PackageName: {}", package.name);
+            let _ = writeln!(out, "SPDXID: {}", package_id(index));
+            let _ = writeln!(out, "PackageVersion: {}", package.version);
+            let _ = writeln!(
+                out,
+                "PackageDownloadLocation: {}",
+                download_location(package)
+            );
+            let _ = writeln!(out, "FilesAnalyzed: false");
+            if let Some(checksum) = &package.checksum {
+                let _ = writeln!(out, "PackageChecksum: SHA256: {checksum}");
+            }
+            let _ = writeln!(out, "PackageLicenseConcluded: {NOASSERTION}");
+            let _ = writeln!(out, "PackageLicenseDeclared: {NOASSERTION}");
+            if package.source == Source::CratesIo {
+                let _ = writeln!(out, "ExternalRef: PACKAGE-MANAGER purl {}", package.purl());
+            }
+            let _ = writeln!(out, "PrimaryPackagePurpose: {}", purpose(package));
+        }
+        let _ = writeln!(out);
+        for (element, relationship, related) in self.relationships() {
+            let _ = writeln!(out, "Relationship: {element} {relationship} {related}");
+        }
+        out
+    }
+
+    fn document_name(&self) -> String {
+        match self.packages.iter().find(|p| p.root) {
+            Some(root) => format!("{}-{}", root.name, root.version),
+            None => "dependencies".to_owned(),
+        }
+    }
+
+    /// Lists the relationships between SPDX elements as (element, relationship, related element).
+    ///
+    /// Dependency edges use the per-edge kinds if they were recorded,
+    /// and fall back to the kind of the dependency otherwise.
+    fn relationships(&self) -> Vec<(String, &'static str, String)> {
+        let mut result = Vec::new();
+        if let Some(root) = self.packages.iter().position(|p| p.root) {
+            result.push((DOCUMENT_ID.to_owned(), "DESCRIBES", package_id(root)));
+        }
+        for (index, package) in self.packages.iter().enumerate() {
+            for (edge, &dependency) in package.dependencies.iter().enumerate() {
+                let kind = match (
+                    package.dependency_kinds.get(edge),
+                    self.packages.get(dependency),
+                ) {
+                    (Some(kind), _) => *kind,
+                    (None, Some(dependency)) => dependency.kind,
+                    // Cannot happen in validated data, but there is nothing to refer to
+                    (None, None) => continue,
+                };
+                let (dependent, dependency) = (package_id(index), package_id(dependency));
+                result.push(match kind {
+                    DependencyKind::Runtime => (dependent, "DEPENDS_ON", dependency),
+                    DependencyKind::Build => (dependency, "BUILD_DEPENDENCY_OF", dependent),
+                    DependencyKind::Development => (dependency, "DEV_DEPENDENCY_OF", dependent),
+                });
+            }
+        }
+        result
+    }
+}
+
+fn package_id(index: usize) -> String {
+    format!("SPDXRef-Package-{index}")
+}
+
+fn creator() -> String {
+    format!("Tool: auditable-serde-{}", env!("CARGO_PKG_VERSION"))
+}
+
+fn download_location(package: &Package) -> String {
+    match &package.source {
+        Source::CratesIo => format!(
+            "https://crates.io/api/v1/crates/{}/{}/download",
+            package.name, package.version
+        ),
+        // SPDX uses the `pip` style of VCS locations, with the revision after `@`
+        Source::Git(GitSource {
+            url: Some(url),
+            rev,
+        }) => match rev {
+            Some(rev) => format!("git+{url}@{rev}"),
+            None => format!("git+{url}"),
+        },
+        _ => NOASSERTION.to_owned(),
+    }
+}
+
+fn purpose(package: &Package) -> &'static str {
+    if package.root {
+        "APPLICATION"
+    } else {
+        "LIBRARY"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::VersionInfo;
+    use std::str::FromStr;
+
+    const JSON: &str = r#"{"packages":[
+        {"name":"adler","version":"0.2.3","source":"crates.io","kind":"build","checksum":"ee2a4ec343196209d6594e19543ae87a39f96d5534d7174822a3ad825dd6ed7e"},
+        {"name":"bar","version":"0.1.0","source":"git"},
+        {"name":"foo","version":"1.0.0","source":"local","dependencies":[0,1],"root":true}
+    ]}"#;
+
+    #[test]
+    fn spdx_json() {
+        let info = VersionInfo::from_str(JSON).unwrap();
+        let spdx = info.to_spdx_json("https://example.com/foo", "2023-01-01T00:00:00Z");
+        assert_eq!(spdx["name"], "foo-1.0.0");
+        let adler = &spdx["packages"][0];
+        assert_eq!(adler["SPDXID"], "SPDXRef-Package-0");
+        assert_eq!(
+            adler["externalRefs"][0]["referenceLocator"],
+            "pkg:cargo/adler@0.2.3"
+        );
+        assert_eq!(adler["checksums"][0]["algorithm"], "SHA256");
+        assert_eq!(
+            adler["downloadLocation"],
+            "https://crates.io/api/v1/crates/adler/0.2.3/download"
+        );
+        // packages not from crates.io cannot be downloaded or looked up by purl
+        let bar = &spdx["packages"][1];
+        assert_eq!(bar["downloadLocation"], "NOASSERTION");
+        assert!(bar.get("externalRefs").is_none());
+        let relationships = spdx["relationships"].as_array().unwrap();
+        assert_eq!(relationships.len(), 3);
+        assert_eq!(relationships[0]["relationshipType"], "DESCRIBES");
+        assert_eq!(relationships[1]["spdxElementId"], "SPDXRef-Package-0");
+        assert_eq!(relationships[1]["relationshipType"], "BUILD_DEPENDENCY_OF");
+        assert_eq!(relationships[1]["relatedSpdxElement"], "SPDXRef-Package-2");
+        assert_eq!(relationships[2]["relationshipType"], "DEPENDS_ON");
+    }
+
+    #[test]
+    fn git_download_location() {
+        let info = VersionInfo::from_str(
+            r#"{"packages":[{"name":"bar","version":"0.1.0","source":{"kind":"git","url":"https://example.com/bar.git","rev":"abc"},"root":true}]}"#,
+        )
+        .unwrap();
+        let spdx = info.to_spdx_json("https://example.com/bar", "2023-01-01T00:00:00Z");
+        assert_eq!(
+            spdx["packages"][0]["downloadLocation"],
+            "git+https://example.com/bar.git@abc"
+        );
+    }
+
+    #[test]
+    fn spdx_tag_value() {
+        let info = VersionInfo::from_str(JSON).unwrap();
+        let spdx = info.to_spdx_tag_value("https://example.com/foo", "2023-01-01T00:00:00Z");
+        assert!(spdx.starts_with("SPDXVersion: SPDX-2.3\n"));
+        assert!(spdx.contains("\nExternalRef: PACKAGE-MANAGER purl pkg:cargo/adler@0.2.3\n"));
+        assert!(spdx.contains("\nRelationship: SPDXRef-DOCUMENT DESCRIBES SPDXRef-Package-2\n"));
+        assert!(spdx.contains("\nRelationship: SPDXRef-Package-2 DEPENDS_ON SPDXRef-Package-1\n"));
+    }
+}
@@ -0,0 +1,398 @@
+//! Timestamps for optional time fields in the audit data.
+//! Any time fields added to the format must use [`Timestamp`], so that consumers
+//! do not have to handle several date formats.
+//!
+//! They are always serialized as RFC 3339 strings in UTC with whole seconds,
+//! e.g. `2023-03-06T12:34:56Z`, so that the same instant is always written the same way.
+
+#[cfg(feature = "json")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    convert::TryFrom,
+    error::Error,
+    fmt::Display,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Seconds since the Unix epoch at `0000-01-01T00:00:00Z`
+const MIN_UNIX_SECONDS: i64 = -62_167_219_200;
+/// Seconds since the Unix epoch at `9999-12-31T23:59:59Z`
+const MAX_UNIX_SECONDS: i64 = 253_402_300_799;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// A point in time with a precision of one second, between the years 0 and 9999.
+///
+/// Serialized as an RFC 3339 string in UTC, e.g. `2023-03-06T12:34:56Z`.
+/// Deserialization accepts any RFC 3339 timestamp: offsets are converted to UTC
+/// and fractions of a second are discarded.
+///
+/// ```rust
+/// use auditable_serde::Timestamp;
+/// use std::str::FromStr;
+/// let timestamp = Timestamp::from_str("2023-03-06T14:34:56.789+02:00").unwrap();
+/// assert_eq!(timestamp.to_string(), "2023-03-06T12:34:56Z");
+/// let time: std::time::SystemTime = timestamp.to_system_time();
+/// ```
+///
+/// With the `chrono` feature enabled it can be converted to and from `chrono::DateTime<Utc>`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+pub struct Timestamp {
+    unix_seconds: i64,
+}
+
+impl Timestamp {
+    /// Creates a timestamp from the number of seconds since the Unix epoch.
+    /// Returns `None` if it is outside of the years 0 to 9999.
+    pub fn from_unix_seconds(unix_seconds: i64) -> Option<Self> {
+        if (MIN_UNIX_SECONDS..=MAX_UNIX_SECONDS).contains(&unix_seconds) {
+            Some(Timestamp { unix_seconds })
+        } else {
+            None
+        }
+    }
+
+    /// The number of seconds since the Unix epoch; negative before 1970
+    pub fn unix_seconds(&self) -> i64 {
+        self.unix_seconds
+    }
+
+    pub fn to_system_time(&self) -> SystemTime {
+        let offset = Duration::from_secs(self.unix_seconds.unsigned_abs());
+        if self.unix_seconds >= 0 {
+            UNIX_EPOCH + offset
+        } else {
+            UNIX_EPOCH - offset
+        }
+    }
+}
+
+impl TryFrom<SystemTime> for Timestamp {
+    type Error = ParseTimestampError;
+
+    /// Fractions of a second are discarded, rounding towards the past
+    fn try_from(time: SystemTime) -> Result<Self, Self::Error> {
+        let unix_seconds = match time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => i64::try_from(duration.as_secs()).ok(),
+            Err(e) => {
+                let before = e.duration();
+                let whole = i64::try_from(before.as_secs()).ok();
+                // -0.5 seconds must become -1, not 0
+                let partial = if before.subsec_nanos() > 0 { 1 } else { 0 };
+                whole
+                    .and_then(|secs| secs.checked_add(partial))
+                    .map(|secs| -secs)
+            }
+        };
+        unix_seconds
+            .and_then(Timestamp::from_unix_seconds)
+            .ok_or(ParseTimestampError(
+                "the time is outside of the years 0 to 9999",
+            ))
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let days = self.unix_seconds.div_euclid(SECONDS_PER_DAY);
+        let seconds = self.unix_seconds.rem_euclid(SECONDS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year,
+            month,
+            day,
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60
+        )
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = ParseTimestampError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = s.as_bytes();
+        if bytes.len() < 20 {
+            return Err(ParseTimestampError("too short for an RFC 3339 timestamp"));
+        }
+        let separators_ok = bytes[4] == b'-'
+            && bytes[7] == b'-'
+            && matches!(bytes[10], b'T' | b't')
+            && bytes[13] == b':'
+            && bytes[16] == b':';
+        if !separators_ok {
+            return Err(ParseTimestampError(
+                "expected the YYYY-MM-DDThh:mm:ss format",
+            ));
+        }
+        let year = digits(&bytes[0..4])?;
+        let month = digits(&bytes[5..7])?;
+        let day = digits(&bytes[8..10])?;
+        let hour = digits(&bytes[11..13])?;
+        let minute = digits(&bytes[14..16])?;
+        // 60 is allowed for leap seconds, and ends up as the first second of the next minute
+        let second = digits(&bytes[17..19])?;
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+            return Err(ParseTimestampError("invalid date"));
+        }
+        if hour > 23 || minute > 59 || second > 60 {
+            return Err(ParseTimestampError("invalid time of day"));
+        }
+
+        // Fractions of a second are discarded
+        let mut rest = &bytes[19..];
+        if rest.first() == Some(&b'.') {
+            let fraction_len = rest[1..].iter().take_while(|b| b.is_ascii_digit()).count();
+            if fraction_len == 0 {
+                return Err(ParseTimestampError(
+                    "expected digits after the decimal point",
+                ));
+            }
+            rest = &rest[1 + fraction_len..];
+        }
+        let offset_seconds = match rest {
+            [b'Z'] | [b'z'] => 0,
+            [sign @ (b'+' | b'-'), h1, h2, b':', m1, m2] => {
+                let hours = digits(&[*h1, *h2])?;
+                let minutes = digits(&[*m1, *m2])?;
+                if hours > 23 || minutes > 59 {
+                    return Err(ParseTimestampError("invalid UTC offset"));
+                }
+                let offset = hours * 3600 + minutes * 60;
+                if *sign == b'+' {
+                    offset
+                } else {
+                    -offset
+                }
+            }
+            _ => {
+                return Err(ParseTimestampError(
+                    "expected `Z` or a UTC offset such as `+02:00`",
+                ))
+            }
+        };
+
+        let local_seconds = days_from_civil(year, month, day) * SECONDS_PER_DAY
+            + hour * 3600
+            + minute * 60
+            + second;
+        Timestamp::from_unix_seconds(local_seconds - offset_seconds).ok_or(ParseTimestampError(
+            "the time is outside of the years 0 to 9999",
+        ))
+    }
+}
+
+#[cfg(feature = "json")]
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "json")]
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Timestamp::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for Timestamp {
+    fn schema_name() -> String {
+        "Timestamp".to_owned()
+    }
+
+    fn json_schema(_gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        schemars::schema::SchemaObject {
+            instance_type: Some(schemars::schema::InstanceType::String.into()),
+            format: Some("date-time".to_owned()),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<Timestamp> for chrono::DateTime<chrono::Utc> {
+    fn from(timestamp: Timestamp) -> Self {
+        use chrono::TimeZone;
+        // Every timestamp in the years 0 to 9999 is representable by chrono
+        chrono::Utc
+            .timestamp_opt(timestamp.unix_seconds, 0)
+            .single()
+            .expect("timestamp out of range for chrono")
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<chrono::DateTime<chrono::Utc>> for Timestamp {
+    type Error = ParseTimestampError;
+
+    /// Fractions of a second are discarded, rounding towards the past
+    fn try_from(time: chrono::DateTime<chrono::Utc>) -> Result<Self, Self::Error> {
+        Timestamp::from_unix_seconds(time.timestamp()).ok_or(ParseTimestampError(
+            "the time is outside of the years 0 to 9999",
+        ))
+    }
+}
+
+/// Returned when a string is not a valid RFC 3339 timestamp,
+/// or a time cannot be represented as a [`Timestamp`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseTimestampError(&'static str);
+
+impl Display for ParseTimestampError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid timestamp: {}", self.0)
+    }
+}
+
+impl Error for ParseTimestampError {}
+
+fn digits(bytes: &[u8]) -> Result<i64, ParseTimestampError> {
+    if !bytes.iter().all(u8::is_ascii_digit) {
+        return Err(ParseTimestampError("expected a digit"));
+    }
+    Ok(bytes
+        .iter()
+        .fold(0, |acc, &b| acc * 10 + i64::from(b - b'0')))
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since the Unix epoch for a date in the proleptic Gregorian calendar.
+/// See <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The inverse of [`days_from_civil`].
+/// See <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        for s in [
+            "1970-01-01T00:00:00Z",
+            "2000-02-29T23:59:59Z",
+            "1969-12-31T23:59:59Z",
+            "0000-01-01T00:00:00Z",
+            "9999-12-31T23:59:59Z",
+        ] {
+            let timestamp = Timestamp::from_str(s).unwrap();
+            assert_eq!(timestamp.to_string(), s);
+        }
+        assert_eq!(
+            Timestamp::from_str("1970-01-01T00:00:00Z")
+                .unwrap()
+                .unix_seconds(),
+            0
+        );
+        assert_eq!(
+            Timestamp::from_str("0000-01-01T00:00:00Z")
+                .unwrap()
+                .unix_seconds(),
+            MIN_UNIX_SECONDS
+        );
+        assert_eq!(
+            Timestamp::from_str("9999-12-31T23:59:59Z")
+                .unwrap()
+                .unix_seconds(),
+            MAX_UNIX_SECONDS
+        );
+    }
+
+    #[test]
+    fn normalization() {
+        let expected = "2023-03-06T12:34:56Z";
+        for s in [
+            "2023-03-06T12:34:56Z",
+            "2023-03-06t12:34:56z",
+            "2023-03-06T12:34:56.999Z",
+            "2023-03-06T14:34:56+02:00",
+            "2023-03-06T07:04:56-05:30",
+        ] {
+            assert_eq!(Timestamp::from_str(s).unwrap().to_string(), expected, "{s}");
+        }
+        assert_eq!(
+            Timestamp::from_str("2016-12-31T23:59:60Z")
+                .unwrap()
+                .to_string(),
+            "2017-01-01T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        for s in [
+            "",
+            "2023-03-06",
+            "2023-03-06 12:34:56Z",
+            "2023-02-29T00:00:00Z",
+            "2023-13-01T00:00:00Z",
+            "2023-03-06T24:00:00Z",
+            "2023-03-06T12:34:56",
+            "2023-03-06T12:34:56.Z",
+            "2023-03-06T12:34:56+0200",
+            "+2023-03-06T12:34:56Z",
+            "0000-01-01T00:00:00+00:01",
+        ] {
+            assert!(Timestamp::from_str(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn system_time() {
+        let timestamp = Timestamp::from_str("2023-03-06T12:34:56Z").unwrap();
+        let time = timestamp.to_system_time();
+        assert_eq!(Timestamp::try_from(time).unwrap(), timestamp);
+        let before_epoch = UNIX_EPOCH - Duration::from_millis(500);
+        assert_eq!(
+            Timestamp::try_from(before_epoch).unwrap().unix_seconds(),
+            -1
+        );
+        let after_epoch = UNIX_EPOCH + Duration::from_millis(1500);
+        assert_eq!(Timestamp::try_from(after_epoch).unwrap().unix_seconds(), 1);
+    }
+}
@@ -1,26 +1,98 @@
-use crate::{Package, VersionInfo};
+use crate::{Package, Toolchain, VersionInfo, Workspace, FORMAT_VERSION};
+#[cfg(feature = "json")]
 use serde::{Deserialize, Serialize};
 use std::{convert::TryFrom, fmt::Display};
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "json", serde(deny_unknown_fields))]
 pub struct RawVersionInfo {
+    /// Version of the format, see [`FORMAT_VERSION`]. 0 if omitted.
+    #[cfg_attr(feature = "json", serde(default))]
+    pub format: u32,
     pub packages: Vec<Package>,
+    #[cfg_attr(feature = "json", serde(default))]
+    pub target_name: Option<String>,
+    #[cfg_attr(feature = "json", serde(default))]
+    pub toolchain: Option<Toolchain>,
+    #[cfg_attr(feature = "json", serde(default))]
+    pub workspace: Option<Workspace>,
 }
 
+/// Only reads the format version, ignoring all the other fields,
+/// so that it can be checked before the data is parsed for real
+#[cfg(feature = "json")]
+#[derive(Deserialize)]
+pub(crate) struct FormatProbe {
+    #[serde(default)]
+    pub format: u32,
+}
+
+/// Why a dependency tree was rejected
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ValidationError {
-    MultipleRoots,
     CyclicDependency,
+    DependencyKindsMismatch,
+    /// The data is in the given format, which is newer than [`FORMAT_VERSION`]
+    NewerFormat(u32),
 }
 
-impl Display for ValidationError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl ValidationError {
+    pub fn message(&self) -> &'static str {
         match self {
-            ValidationError::MultipleRoots => {
-                write!(f, "Multiple root packages specified in the input JSON")
-            }
             ValidationError::CyclicDependency => {
-                write!(f, "The input JSON specifies a cyclic dependency graph")
+                "The input JSON specifies a cyclic dependency graph"
+            }
+            ValidationError::DependencyKindsMismatch => {
+                "The number of dependency kinds does not match the number of dependencies"
             }
+            ValidationError::NewerFormat(_) => {
+                "The audit data is in a newer format than this library understands"
+            }
+        }
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())?;
+        if let ValidationError::NewerFormat(format) = self {
+            write!(f, " (format {format}, expected at most {FORMAT_VERSION})")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Returned by [`VersionInfo::from_json`]
+#[cfg(feature = "json")]
+#[derive(Debug)]
+pub enum ParseError {
+    /// The audit data is in the given format, which is newer than [`FORMAT_VERSION`].
+    /// A newer version of this library may be able to parse it.
+    NewerFormat(u32),
+    /// The input is not valid JSON or does not describe a valid dependency tree
+    Json(serde_json::Error),
+}
+
+#[cfg(feature = "json")]
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::NewerFormat(format) => ValidationError::NewerFormat(*format).fmt(f),
+            ParseError::Json(e) => e.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::NewerFormat(_) => None,
+            ParseError::Json(e) => Some(e),
         }
     }
 }
@@ -29,30 +101,29 @@ impl TryFrom<RawVersionInfo> for VersionInfo {
     type Error = ValidationError;
 
     fn try_from(v: RawVersionInfo) -> Result<Self, Self::Error> {
-        if has_multiple_root_packages(&v) {
-            Err(ValidationError::MultipleRoots)
+        if v.format > FORMAT_VERSION {
+            Err(ValidationError::NewerFormat(v.format))
         } else if has_cylic_dependencies(&v) {
             Err(ValidationError::CyclicDependency)
+        } else if has_mismatched_dependency_kinds(&v) {
+            Err(ValidationError::DependencyKindsMismatch)
         } else {
             Ok(VersionInfo {
                 packages: v.packages,
+                target_name: v.target_name,
+                toolchain: v.toolchain,
+                workspace: v.workspace,
             })
         }
     }
 }
 
-fn has_multiple_root_packages(v: &RawVersionInfo) -> bool {
-    let mut seen_a_root = false;
-    for package in &v.packages {
-        if package.root {
-            if seen_a_root {
-                return true;
-            } else {
-                seen_a_root = true;
-            }
-        }
-    }
-    false
+/// Dependency kinds are optional, but if they are present there must be one per dependency
+fn has_mismatched_dependency_kinds(v: &RawVersionInfo) -> bool {
+    v.packages.iter().any(|package| {
+        !package.dependency_kinds.is_empty()
+            && package.dependency_kinds.len() != package.dependencies.len()
+    })
 }
 
 fn has_cylic_dependencies(v: &RawVersionInfo) -> bool {
@@ -84,10 +155,18 @@ mod tests {
         Package {
             name: format!("test_{pkg_counter}"),
             version: semver::Version::from_str("0.0.0").unwrap(),
-            source: Source::Local,
+            source: Source::Local(LocalSource::default()),
+            checksum: None,
+            vendored_checksum: None,
             kind: DependencyKind::Build,
             dependencies: deps,
+            dependency_kinds: Vec::new(),
             root: root,
+            renamed_as: Vec::new(),
+            features: Vec::new(),
+            has_build_script: false,
+            components: Vec::new(),
+            ecosystem: CARGO_ECOSYSTEM.to_owned(),
         }
     }
 
@@ -98,7 +177,11 @@ mod tests {
         let pkg0 = dummy_package(0, true, vec![1]);
         let pkg1 = dummy_package(1, false, vec![0]);
         let raw = RawVersionInfo {
+            format: 0,
             packages: vec![pkg0, pkg1],
+            target_name: None,
+            toolchain: None,
+            workspace: None,
         };
         assert!(VersionInfo::try_from(raw).is_err());
     }
@@ -108,8 +191,84 @@ mod tests {
         let pkg0 = dummy_package(0, true, vec![1]);
         let pkg1 = dummy_package(1, false, vec![]);
         let raw = RawVersionInfo {
+            format: 0,
             packages: vec![pkg0, pkg1],
+            target_name: None,
+            toolchain: None,
+            workspace: None,
+        };
+        assert!(VersionInfo::try_from(raw).is_ok());
+    }
+
+    #[test]
+    fn dependency_kinds_length() {
+        let mut pkg0 = dummy_package(0, true, vec![1, 2]);
+        let pkg1 = dummy_package(1, false, vec![]);
+        let pkg2 = dummy_package(2, false, vec![]);
+        pkg0.dependency_kinds = vec![DependencyKind::Runtime];
+        let mut raw = RawVersionInfo {
+            format: 0,
+            packages: vec![pkg0, pkg1, pkg2],
+            target_name: None,
+            toolchain: None,
+            workspace: None,
         };
+        assert!(VersionInfo::try_from(raw.clone()).is_err());
+        raw.packages[0].dependency_kinds.push(DependencyKind::Build);
         assert!(VersionInfo::try_from(raw).is_ok());
     }
+
+    #[test]
+    fn newer_format() {
+        let raw = RawVersionInfo {
+            format: FORMAT_VERSION + 1,
+            packages: vec![dummy_package(0, true, vec![])],
+            target_name: None,
+            toolchain: None,
+            workspace: None,
+        };
+        assert_eq!(
+            VersionInfo::try_from(raw),
+            Err(ValidationError::NewerFormat(FORMAT_VERSION + 1))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn unknown_fields() {
+        let valid = r#"{"format":0,"packages":[{"name":"foo","version":"1.0.0","source":{"kind":"git","rev":"abc"},"root":true}]}"#;
+        assert!(VersionInfo::from_json(valid).is_ok());
+        for json in [
+            r#"{"packages":[],"extra":1}"#,
+            r#"{"packages":[{"name":"foo","version":"1.0.0","source":"local","extra":1}]}"#,
+            r#"{"packages":[{"name":"foo","version":"1.0.0","source":{"kind":"git","extra":1}}]}"#,
+            r#"{"packages":[{"name":"foo","version":"1.0.0","source":{"kind":"local","url":"x"}}]}"#,
+            r#"{"packages":[],"toolchain":{"rustc_version":"1.70.0","channel":"stable","target":"x","extra":1}}"#,
+            r#"{"packages":[],"workspace":{"extra":1}}"#,
+        ]
+        .iter()
+        {
+            assert!(
+                matches!(VersionInfo::from_json(json), Err(ParseError::Json(_))),
+                "{}",
+                json
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn newer_format_json() {
+        // fields this library doesn't know about are expected in newer formats
+        let json = r#"{"format":1000,"packages":[],"extra":1}"#;
+        assert!(matches!(
+            VersionInfo::from_json(json),
+            Err(ParseError::NewerFormat(1000))
+        ));
+        let error = VersionInfo::from_str(json).unwrap_err();
+        assert!(error.to_string().contains("newer format"));
+        // the `Deserialize` implementation rejects it too
+        let json = r#"{"format":1000,"packages":[]}"#;
+        assert!(serde_json::from_str::<VersionInfo>(json).is_err());
+    }
 }
@@ -0,0 +1,220 @@
+//! Implements `cargo auditable attach`, which publishes the audit data to an OCI registry
+//! as a referrer of a container image, next to signatures and SBOMs attached by other tools.
+//!
+//! Talking to registries (authentication, the referrers API and its fallback tag schema)
+//! is delegated to [`oras`](https://oras.land), so that it reuses the credentials
+//! the user has already configured for it or for Docker.
+
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+    process::{exit, Command},
+};
+
+use auditable_info::Limits;
+
+use crate::{
+    cargo_arguments::CargoArgs,
+    collect_audit_data,
+    package_args::{PackageArgs, PACKAGE_ARGS_USAGE},
+};
+
+/// Media type of the audit data in the format embedded into binaries, uncompressed.
+/// Also used as the artifact type, the same way `cosign attach sbom` does for SBOMs,
+/// so that it can be filtered on with `oras discover --artifact-type`.
+pub const AUDIT_DATA_MEDIA_TYPE: &str = "application/vnd.rust.cargo-auditable.dep-v0+json";
+
+/// Name of the file in the artifact. Registries display it as the title of the layer.
+const FILE_NAME: &str = "audit-data.json";
+
+const USAGE: &str = "\
+Usage: cargo auditable attach IMAGE [OPTIONS]
+
+Attaches the audit data to the container image IMAGE in an OCI registry
+as a referrer artifact of type application/vnd.rust.cargo-auditable.dep-v0+json.
+It can then be listed with `oras discover` or `cosign tree`.
+
+By default the audit data is collected for the package in the current directory.
+Pass --binary to attach the audit data embedded in an existing binary instead.
+
+Requires `oras` to be installed. Registry credentials are taken from its
+configuration, which falls back to the Docker credentials.
+
+Options:
+    --binary FILE             Attach the audit data embedded in FILE
+    --oras PATH               Path to the `oras` binary [default: $ORAS or oras]
+    --dry-run                 Print the `oras` command instead of running it
+";
+
+/// Includes only the arguments specific to `cargo auditable attach`
+struct AttachArgs {
+    image: String,
+    binary: Option<PathBuf>,
+    oras: Option<OsString>,
+    dry_run: bool,
+    package: PackageArgs,
+}
+
+impl AttachArgs {
+    fn from_args() -> Result<Self, pico_args::Error> {
+        // we .skip(3) to get over `cargo auditable attach` and to the start of the flags
+        let raw_args: Vec<OsString> = env::args_os().skip(3).collect();
+        Self::from_args_vec(raw_args)
+    }
+
+    /// Split into its own function for unit testing
+    fn from_args_vec(raw_args: Vec<OsString>) -> Result<Self, pico_args::Error> {
+        let mut parser = pico_args::Arguments::from_vec(raw_args);
+        let binary = parser.opt_value_from_os_str("--binary", |s| {
+            Ok::<PathBuf, pico_args::Error>(PathBuf::from(s))
+        })?;
+        let oras = parser
+            .opt_value_from_os_str("--oras", |s| Ok::<OsString, pico_args::Error>(s.to_owned()))?;
+        let dry_run = parser.contains("--dry-run");
+        let package = PackageArgs::from_parser(&mut parser)?;
+        // Cargo flags such as `--offline` are parsed separately by `CargoArgs`
+        for flag in ["--offline", "--locked", "--frozen"] {
+            parser.contains(flag);
+        }
+        let _: Vec<String> = parser.values_from_str("--config")?;
+        let image = parser.free_from_str()?;
+        let remaining = parser.finish();
+        if !remaining.is_empty() {
+            return Err(pico_args::Error::ArgumentParsingFailed {
+                cause: format!("unexpected arguments: {remaining:?}"),
+            });
+        }
+        Ok(AttachArgs {
+            image,
+            binary,
+            oras,
+            dry_run,
+            package,
+        })
+    }
+}
+
+pub fn main() {
+    if env::args_os()
+        .skip(3)
+        .any(|arg| arg == "-h" || arg == "--help")
+    {
+        print!("{USAGE}{PACKAGE_ARGS_USAGE}");
+        return;
+    }
+    let args = AttachArgs::from_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}{PACKAGE_ARGS_USAGE}");
+        exit(2);
+    });
+
+    let json = match &args.binary {
+        Some(path) => json_from_binary(path),
+        None => {
+            let cargo_args = CargoArgs::from_args();
+            let rustc_path = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+            let target_triple = args.package.target_triple(&rustc_path);
+            let metadata = args.package.metadata(&target_triple, &cargo_args);
+            serde_json::to_string(&collect_audit_data::version_info(&metadata)).unwrap()
+        }
+    };
+
+    let oras = args
+        .oras
+        .clone()
+        .or_else(|| env::var_os("ORAS"))
+        .unwrap_or_else(|| "oras".into());
+    // `oras` rejects absolute paths to the files being pushed by default,
+    // so write the file into a directory of its own and run `oras` from there
+    let workdir = env::temp_dir().join(format!("cargo-auditable-attach-{}", std::process::id()));
+    let mut command = oras_attach_command(&oras, &args.image, &workdir);
+    if args.dry_run {
+        println!("{command:?}");
+        return;
+    }
+    std::fs::create_dir_all(&workdir).expect("Failed to create a temporary directory");
+    std::fs::write(workdir.join(FILE_NAME), json).expect("Failed to write the audit data");
+    let status = command.status();
+    let _ = std::fs::remove_dir_all(&workdir);
+    match status {
+        Ok(status) if status.success() => (),
+        Ok(status) => exit(status.code().unwrap_or(1)),
+        Err(e) => {
+            eprintln!(
+                "ERROR: failed to run '{}': {e}\nMake sure `oras` is installed: https://oras.land/docs/installation",
+                oras.to_string_lossy()
+            );
+            exit(1);
+        }
+    }
+}
+
+fn json_from_binary(path: &Path) -> String {
+    auditable_info::json_from_file(path, Limits::default()).unwrap_or_else(|e| {
+        eprintln!(
+            "ERROR: failed to read audit data from '{}': {e}",
+            path.display()
+        );
+        exit(1);
+    })
+}
+
+fn oras_attach_command(oras: &OsStr, image: &str, workdir: &Path) -> Command {
+    let mut command = Command::new(oras);
+    command
+        .current_dir(workdir)
+        .arg("attach")
+        .args(["--artifact-type", AUDIT_DATA_MEDIA_TYPE])
+        .arg(image)
+        .arg(format!("{FILE_NAME}:{AUDIT_DATA_MEDIA_TYPE}"));
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &[&str]) -> Result<AttachArgs, pico_args::Error> {
+        AttachArgs::from_args_vec(input.iter().map(OsString::from).collect())
+    }
+
+    #[test]
+    fn basic_parsing() {
+        let args = parse(&[
+            "registry.example.com/app:1.0",
+            "--binary",
+            "target/release/app",
+            "--offline",
+        ])
+        .unwrap();
+        assert_eq!(args.image, "registry.example.com/app:1.0");
+        assert_eq!(args.binary, Some(PathBuf::from("target/release/app")));
+        assert!(!args.dry_run);
+    }
+
+    #[test]
+    fn image_is_required() {
+        assert!(parse(&["--binary", "app"]).is_err());
+        assert!(parse(&["image-a", "image-b"]).is_err());
+    }
+
+    #[test]
+    fn command_line() {
+        let command = oras_attach_command(
+            OsStr::new("oras"),
+            "registry.example.com/app:1.0",
+            Path::new("/tmp/attach"),
+        );
+        let args: Vec<&OsStr> = command.get_args().collect();
+        assert_eq!(
+            args,
+            [
+                "attach",
+                "--artifact-type",
+                AUDIT_DATA_MEDIA_TYPE,
+                "registry.example.com/app:1.0",
+                "audit-data.json:application/vnd.rust.cargo-auditable.dep-v0+json",
+            ]
+        );
+    }
+}
@@ -0,0 +1,350 @@
+//! Implements `cargo auditable audit`, which reports the dependencies of binaries
+//! that have known vulnerabilities according to the RustSec advisory database
+//! and any advisory feeds in the OSV format passed with `--osv`,
+//! so that the audit data can be embedded, extracted and audited with a single tool.
+
+use std::{
+    env,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::{exit, Command},
+    str::FromStr,
+};
+
+use auditable_info::advisories::{findings, AdvisorySource, OsvDatabase};
+use auditable_info::Limits;
+use auditable_serde::DependencyKind;
+use rustsec::Database;
+
+use crate::html_report::{BinaryReport, Finding, Report};
+
+/// Upstream repository of the advisory database
+const ADVISORY_DB_URL: &str = "https://github.com/rustsec/advisory-db.git";
+
+const USAGE: &str = "\
+Usage: cargo auditable audit [OPTIONS] BINARY...
+
+Reports the dependencies recorded in the audit data of each BINARY
+that are affected by advisories in the RustSec advisory database.
+
+By default the advisory database is cloned or updated in
+$CARGO_HOME/advisory-db using `git`, the same location `cargo audit` uses.
+Pass --db to use a local copy of the database as-is instead.
+
+Advisories from other databases, such as internal feeds, can be added
+with --osv. They are read from JSON files in the OSV format, and those
+also published by RustSec, judging by their IDs and aliases, are only
+reported once.
+
+Exit status:
+    0    No vulnerabilities were found
+    1    Vulnerabilities were found
+    2    The audit data or the advisory database could not be read
+
+Options:
+    --db DIR                  Path to a local copy of the advisory database
+    --format FORMAT           Output format: text (default) or html, a standalone
+                              report with the dependencies, duplicate crates,
+                              vulnerabilities and charts for every BINARY
+    --ignore ID               Do not report the advisory with this ID or alias,
+                              e.g. RUSTSEC-2020-0071. May be repeated
+    --no-build-deps           Do not report build dependencies,
+                              which are not part of the binary
+    --osv PATH                Also report advisories from the OSV records in the
+                              JSON file or directory of JSON files at PATH.
+                              May be repeated
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Html,
+}
+
+impl FromStr for Format {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "html" => Ok(Format::Html),
+            other => Err(format!(
+                "unknown format '{other}', expected 'text' or 'html'"
+            )),
+        }
+    }
+}
+
+/// Includes only the arguments specific to `cargo auditable audit`
+struct AuditArgs {
+    binaries: Vec<PathBuf>,
+    db: Option<PathBuf>,
+    format: Format,
+    ignore: Vec<String>,
+    no_build_deps: bool,
+    osv: Vec<PathBuf>,
+}
+
+impl AuditArgs {
+    fn from_args() -> Result<Self, pico_args::Error> {
+        // we .skip(3) to get over `cargo auditable audit` and to the start of the flags
+        let raw_args: Vec<OsString> = env::args_os().skip(3).collect();
+        Self::from_args_vec(raw_args)
+    }
+
+    /// Split into its own function for unit testing
+    fn from_args_vec(raw_args: Vec<OsString>) -> Result<Self, pico_args::Error> {
+        let mut parser = pico_args::Arguments::from_vec(raw_args);
+        let db = parser.opt_value_from_os_str("--db", |s| {
+            Ok::<PathBuf, pico_args::Error>(PathBuf::from(s))
+        })?;
+        let format = parser
+            .opt_value_from_str("--format")?
+            .unwrap_or(Format::Text);
+        let ignore = parser.values_from_str("--ignore")?;
+        let no_build_deps = parser.contains("--no-build-deps");
+        let osv = parser.values_from_os_str("--osv", |s| {
+            Ok::<PathBuf, pico_args::Error>(PathBuf::from(s))
+        })?;
+        let binaries: Vec<PathBuf> = parser.finish().into_iter().map(PathBuf::from).collect();
+        if binaries.is_empty() {
+            return Err(pico_args::Error::MissingArgument);
+        }
+        Ok(AuditArgs {
+            binaries,
+            db,
+            format,
+            ignore,
+            no_build_deps,
+            osv,
+        })
+    }
+}
+
+pub fn main() {
+    if env::args_os()
+        .skip(3)
+        .any(|arg| arg == "-h" || arg == "--help")
+    {
+        print!("{USAGE}");
+        return;
+    }
+    let args = AuditArgs::from_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}");
+        exit(2);
+    });
+
+    let db_path = match &args.db {
+        Some(path) => path.clone(),
+        None => {
+            let path = default_db_path();
+            fetch_db(&path).unwrap_or_else(|e| {
+                eprintln!("ERROR: failed to fetch the advisory database: {e}");
+                exit(2);
+            });
+            path
+        }
+    };
+    let database = Database::open(&db_path).unwrap_or_else(|e| {
+        eprintln!(
+            "ERROR: failed to load the advisory database from '{}': {e}",
+            db_path.display()
+        );
+        exit(2);
+    });
+    let feeds: Vec<OsvDatabase> = args
+        .osv
+        .iter()
+        .map(|path| {
+            OsvDatabase::open(path).unwrap_or_else(|e| {
+                eprintln!(
+                    "ERROR: failed to load the OSV advisories from '{}': {e}",
+                    path.display()
+                );
+                exit(2);
+            })
+        })
+        .collect();
+    let mut sources: Vec<&dyn AdvisorySource> = vec![&database];
+    sources.extend(feeds.iter().map(|feed| feed as &dyn AdvisorySource));
+
+    let mut found = false;
+    let mut report = Report::new("cargo auditable audit", true);
+    for binary in &args.binaries {
+        let info =
+            auditable_info::audit_info_from_file(binary, Limits::default()).unwrap_or_else(|e| {
+                eprintln!(
+                    "ERROR: failed to read audit data from '{}': {e}",
+                    binary.display()
+                );
+                exit(2);
+            });
+        let affected = findings(&info, &sources).unwrap_or_else(|e| {
+            eprintln!(
+                "ERROR: failed to check the audit data of '{}': {e}",
+                binary.display()
+            );
+            exit(2);
+        });
+        let mut findings = Vec::new();
+        for affected in affected {
+            let package = affected.package;
+            let advisory = &affected.advisory;
+            if args
+                .ignore
+                .iter()
+                .any(|id| *id == advisory.id || advisory.aliases.contains(id))
+                || (args.no_build_deps && package.kind == DependencyKind::Build)
+            {
+                continue;
+            }
+            found = true;
+            findings.push(Finding {
+                package: package.name.clone(),
+                version: package.version.to_string(),
+                advisory_id: advisory.id.clone(),
+                title: advisory.title.clone(),
+                build_dependency: package.kind == DependencyKind::Build,
+            });
+        }
+        match args.format {
+            Format::Text => print_findings(binary, &findings),
+            Format::Html => report.binaries.push(BinaryReport {
+                paths: vec![binary.display().to_string()],
+                info,
+                findings,
+            }),
+        }
+    }
+    if args.format == Format::Html {
+        let stdout = std::io::stdout();
+        report.write(&mut stdout.lock()).unwrap_or_else(|e| {
+            eprintln!("ERROR: failed to write the report: {e}");
+            exit(2);
+        });
+    }
+    if found {
+        exit(1);
+    }
+}
+
+fn print_findings(binary: &Path, findings: &[Finding]) {
+    for finding in findings {
+        let kind = if finding.build_dependency {
+            " (build dependency)"
+        } else {
+            ""
+        };
+        println!(
+            "{}: {} {}{kind}: {} {}",
+            binary.display(),
+            finding.package,
+            finding.version,
+            finding.advisory_id,
+            finding.title
+        );
+    }
+}
+
+/// `$CARGO_HOME/advisory-db`, which is shared with `cargo audit`
+fn default_db_path() -> PathBuf {
+    let cargo_home = env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cargo")))
+        .unwrap_or_else(|| {
+            eprintln!("ERROR: could not locate the Cargo home directory, pass --db instead");
+            exit(2);
+        });
+    cargo_home.join("advisory-db")
+}
+
+/// Clones the advisory database, or updates an existing clone to the latest upstream version,
+/// by shelling out to `git`, which takes care of proxies and credentials for us
+fn fetch_db(path: &Path) -> Result<(), String> {
+    if path.join(".git").exists() {
+        run(Command::new("git").arg("-C").arg(path).args([
+            "fetch",
+            "--quiet",
+            "--depth",
+            "1",
+            ADVISORY_DB_URL,
+            "HEAD",
+        ]))?;
+        // The clone is a cache that nobody edits, so discarding local state is fine
+        run(Command::new("git").arg("-C").arg(path).args([
+            "reset",
+            "--quiet",
+            "--hard",
+            "FETCH_HEAD",
+        ]))
+    } else {
+        run(Command::new("git")
+            .args(["clone", "--quiet", "--depth", "1", ADVISORY_DB_URL])
+            .arg(path))
+    }
+}
+
+fn run(command: &mut Command) -> Result<(), String> {
+    let status = command
+        .status()
+        .map_err(|e| format!("failed to run git: {e}"))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("git exited with {status}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &[&str]) -> Result<AuditArgs, pico_args::Error> {
+        AuditArgs::from_args_vec(input.iter().map(OsString::from).collect())
+    }
+
+    #[test]
+    fn basic_parsing() {
+        let args = parse(&[
+            "--db",
+            "advisory-db",
+            "--ignore",
+            "RUSTSEC-2020-0071",
+            "--ignore",
+            "RUSTSEC-2021-0139",
+            "app",
+            "other-app",
+        ])
+        .unwrap();
+        assert_eq!(args.db, Some(PathBuf::from("advisory-db")));
+        assert_eq!(args.ignore, vec!["RUSTSEC-2020-0071", "RUSTSEC-2021-0139"]);
+        assert_eq!(
+            args.binaries,
+            vec![PathBuf::from("app"), PathBuf::from("other-app")]
+        );
+        assert!(!args.no_build_deps);
+        assert!(args.osv.is_empty());
+        assert_eq!(args.format, Format::Text);
+    }
+
+    #[test]
+    fn osv_feeds() {
+        let args = parse(&["--osv", "internal", "--osv", "osv.json", "app"]).unwrap();
+        assert_eq!(
+            args.osv,
+            vec![PathBuf::from("internal"), PathBuf::from("osv.json")]
+        );
+        assert_eq!(args.binaries, vec![PathBuf::from("app")]);
+    }
+
+    #[test]
+    fn format() {
+        let args = parse(&["--format", "html", "app"]).unwrap();
+        assert_eq!(args.format, Format::Html);
+        assert!(parse(&["--format", "pdf", "app"]).is_err());
+    }
+
+    #[test]
+    fn binary_is_required() {
+        assert!(parse(&["--db", "advisory-db"]).is_err());
+    }
+}
@@ -0,0 +1,102 @@
+//! Extracts the data identifying a binary, so that records about it can be joined
+//! with other systems such as crash reporting and deployment tracking.
+
+use object::Object;
+use sha2::{Digest, Sha256};
+
+/// Identifying data of a binary file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryIdentity {
+    /// SHA-256 of the whole file, in lowercase hex
+    pub sha256: String,
+    /// Size of the file in bytes
+    pub size: u64,
+    /// ELF build ID from the `NT_GNU_BUILD_ID` note, in lowercase hex
+    pub build_id: Option<String>,
+    /// `LC_UUID` for Mach-O, or the GUID of the PDB file for PE, in uppercase hex with dashes
+    pub uuid: Option<String>,
+}
+
+impl BinaryIdentity {
+    /// Computes the identity of a binary from the contents of the file and its [`sha256_hex`].
+    /// Fields that cannot be determined, e.g. because the file is not an executable, are left empty.
+    pub fn from_contents(contents: &[u8], sha256: String) -> Self {
+        let mut identity = BinaryIdentity {
+            sha256,
+            size: contents.len() as u64,
+            build_id: None,
+            uuid: None,
+        };
+        if let Ok(file) = object::File::parse(contents) {
+            identity.build_id = build_id(&file);
+            identity.uuid = uuid(&file);
+        }
+        identity
+    }
+}
+
+/// Returns the SHA-256 of the file contents in lowercase hex
+pub fn sha256_hex(contents: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contents))
+}
+
+fn build_id(file: &object::File) -> Option<String> {
+    let build_id = file.build_id().ok()??;
+    Some(build_id.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+fn uuid(file: &object::File) -> Option<String> {
+    if let Some(uuid) = file.mach_uuid().ok()? {
+        return Some(format_uuid(uuid));
+    }
+    let code_view = file.pdb_info().ok()??;
+    Some(format_guid(code_view.guid()))
+}
+
+/// Formats the bytes in the order they are stored, as Mach-O tools do
+fn format_uuid(bytes: [u8; 16]) -> String {
+    let hex: String = bytes.iter().map(|byte| format!("{byte:02X}")).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// Windows stores the first three fields of a GUID in little-endian byte order
+fn format_guid(mut bytes: [u8; 16]) -> String {
+    bytes[0..4].reverse();
+    bytes[4..6].reverse();
+    bytes[6..8].reverse();
+    format_uuid(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn not_an_executable() {
+        let identity = BinaryIdentity::from_contents(b"hello", sha256_hex(b"hello"));
+        assert_eq!(
+            identity.sha256,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(identity.size, 5);
+        assert_eq!(identity.build_id, None);
+        assert_eq!(identity.uuid, None);
+    }
+
+    #[test]
+    fn uuid_formatting() {
+        let bytes = [
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd,
+            0xee, 0xff,
+        ];
+        assert_eq!(format_uuid(bytes), "00112233-4455-6677-8899-AABBCCDDEEFF");
+        assert_eq!(format_guid(bytes), "33221100-5544-7766-8899-AABBCCDDEEFF");
+    }
+}
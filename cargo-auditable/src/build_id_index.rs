@@ -0,0 +1,134 @@
+//! An index of audit data keyed by the GNU build ID of the binaries, written by
+//! `cargo auditable inventory --build-id-index` and queried by `cargo auditable lookup-build-id`.
+//!
+//! Crash reports and core dumps identify binaries by their build ID, so this lets symbolication
+//! infrastructure answer "which dependencies were in the binary that produced this crash".
+//! The layout mirrors the `.build-id` directories used for debug symbols:
+//! the audit data for build ID `abcdef...` is stored in `ab/cdef....json`.
+
+use std::{
+    env,
+    ffi::OsString,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::exit,
+};
+
+use auditable_serde::VersionInfo;
+
+const USAGE: &str = "\
+Usage: cargo auditable lookup-build-id --index DIR BUILD_ID
+
+Prints the audit data of the binary with the given GNU build ID, as recorded in
+the index written by `cargo auditable inventory --build-id-index DIR`.
+Exits with an error if the build ID is not in the index.
+
+The build ID is the hexadecimal string printed by e.g. `file` or `readelf -n`.
+
+Options:
+    --index DIR               Path to the build ID index
+";
+
+/// Returns the path of the index entry for the build ID, given in hex
+pub fn entry_path(index: &Path, build_id: &str) -> Option<PathBuf> {
+    let build_id = build_id.to_ascii_lowercase();
+    let is_valid = build_id.len() > 2 && build_id.bytes().all(|b| b.is_ascii_hexdigit());
+    if !is_valid {
+        return None;
+    }
+    let (directory, file) = build_id.split_at(2);
+    Some(index.join(directory).join(format!("{file}.json")))
+}
+
+/// Records the audit data of the binary with the given build ID in the index
+pub fn write_entry(index: &Path, build_id: &str, info: &VersionInfo) -> std::io::Result<()> {
+    let path = entry_path(index, build_id)
+        .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidInput, "invalid build ID"))?;
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    std::fs::write(path, serde_json::to_string(info).unwrap())
+}
+
+/// Includes only the arguments specific to `cargo auditable lookup-build-id`
+struct LookupArgs {
+    index: PathBuf,
+    build_id: String,
+}
+
+impl LookupArgs {
+    fn from_args() -> Result<Self, pico_args::Error> {
+        // we .skip(3) to get over `cargo auditable lookup-build-id` and to the start of the flags
+        let raw_args: Vec<OsString> = env::args_os().skip(3).collect();
+        Self::from_args_vec(raw_args)
+    }
+
+    /// Split into its own function for unit testing
+    fn from_args_vec(raw_args: Vec<OsString>) -> Result<Self, pico_args::Error> {
+        let mut parser = pico_args::Arguments::from_vec(raw_args);
+        let index = parser.value_from_os_str("--index", |s| {
+            Ok::<PathBuf, pico_args::Error>(PathBuf::from(s))
+        })?;
+        let build_id = parser.free_from_str()?;
+        let remaining = parser.finish();
+        if !remaining.is_empty() {
+            return Err(pico_args::Error::ArgumentParsingFailed {
+                cause: format!("unexpected arguments: {remaining:?}"),
+            });
+        }
+        Ok(LookupArgs { index, build_id })
+    }
+}
+
+pub fn main() {
+    if env::args_os()
+        .skip(3)
+        .any(|arg| arg == "-h" || arg == "--help")
+    {
+        print!("{USAGE}");
+        return;
+    }
+    let args = LookupArgs::from_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}");
+        exit(2);
+    });
+
+    let path = entry_path(&args.index, &args.build_id).unwrap_or_else(|| {
+        eprintln!("ERROR: '{}' is not a valid build ID", args.build_id);
+        exit(2);
+    });
+    match std::fs::read_to_string(&path) {
+        Ok(json) => println!("{json}"),
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            eprintln!("ERROR: build ID {} is not in the index", args.build_id);
+            exit(1);
+        }
+        Err(e) => {
+            eprintln!("ERROR: failed to read '{}': {e}", path.display());
+            exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_paths() {
+        let index = Path::new("index");
+        assert_eq!(
+            entry_path(index, "ABCDEF01"),
+            Some(index.join("ab").join("cdef01.json"))
+        );
+        assert_eq!(entry_path(index, "ab"), None);
+        assert_eq!(entry_path(index, "../../etc/passwd"), None);
+    }
+
+    #[test]
+    fn basic_parsing() {
+        let raw_args = ["--index", "index", "abcdef01"];
+        let args =
+            LookupArgs::from_args_vec(raw_args.iter().map(OsString::from).collect()).unwrap();
+        assert_eq!(args.index, PathBuf::from("index"));
+        assert_eq!(args.build_id, "abcdef01");
+    }
+}
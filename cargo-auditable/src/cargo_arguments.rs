@@ -35,6 +35,22 @@ impl CargoArgs {
         }
     }
 
+    /// Lists the flags that forbid `cargo metadata` from accessing the network
+    /// or updating `Cargo.lock`, as they were passed on the command line
+    pub fn network_restricting_flags(&self) -> Vec<&'static str> {
+        let mut flags = Vec::new();
+        if self.offline {
+            flags.push("--offline");
+        }
+        if self.locked {
+            flags.push("--locked");
+        }
+        if self.frozen {
+            flags.push("--frozen");
+        }
+        flags
+    }
+
     /// Recovers `SerializedCargoArgs` from an environment variable (if it was exported earlier)
     pub fn from_env() -> Result<Self, std::env::VarError> {
         let json_args = std::env::var("CARGO_AUDITABLE_ORIG_ARGS")?;
@@ -107,4 +123,22 @@ mod tests {
         assert!(!args.offline);
         assert_eq!(args.config, vec!["net.git-fetch-with-cli=true"]);
     }
+
+    #[test]
+    fn network_restricting_flags() {
+        let input = ["cargo", "auditable", "build", "--frozen", "--offline"];
+        let raw_args = input.iter().map(OsString::from).collect();
+        let args = CargoArgs::from_args_vec(raw_args);
+        assert_eq!(
+            args.network_restricting_flags(),
+            vec!["--offline", "--frozen"]
+        );
+        let raw_args = ["cargo", "auditable", "build"]
+            .iter()
+            .map(OsString::from)
+            .collect();
+        assert!(CargoArgs::from_args_vec(raw_args)
+            .network_restricting_flags()
+            .is_empty());
+    }
 }
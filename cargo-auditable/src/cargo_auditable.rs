@@ -1,7 +1,59 @@
-use crate::cargo_arguments::CargoArgs;
-use std::{env, process::Command};
+use crate::{
+    attach, audit, build_id_index, cargo_arguments::CargoArgs, check_dev_deps, emit_object,
+    extract, inventory, non_registry, size_report, test_vectors, verify,
+};
+use std::{env, ffi::OsStr, process::Command};
 
 pub fn main() {
+    // Handle the commands specific to `cargo auditable` before passing everything else on to Cargo
+    match env::args_os().nth(2).as_deref() {
+        Some(cmd) if cmd == OsStr::new("emit-object") => {
+            emit_object::main();
+            return;
+        }
+        Some(cmd) if cmd == OsStr::new("attach") => {
+            attach::main();
+            return;
+        }
+        Some(cmd) if cmd == OsStr::new("audit") => {
+            audit::main();
+            return;
+        }
+        Some(cmd) if cmd == OsStr::new("check-dev-deps") => {
+            check_dev_deps::main();
+            return;
+        }
+        Some(cmd) if cmd == OsStr::new("extract") => {
+            extract::main();
+            return;
+        }
+        Some(cmd) if cmd == OsStr::new("inventory") => {
+            inventory::main();
+            return;
+        }
+        Some(cmd) if cmd == OsStr::new("lookup-build-id") => {
+            build_id_index::main();
+            return;
+        }
+        Some(cmd) if cmd == OsStr::new("non-registry") => {
+            non_registry::main();
+            return;
+        }
+        Some(cmd) if cmd == OsStr::new("size-report") => {
+            size_report::main();
+            return;
+        }
+        Some(cmd) if cmd == OsStr::new("test-vectors") => {
+            test_vectors::main();
+            return;
+        }
+        Some(cmd) if cmd == OsStr::new("verify") => {
+            verify::main();
+            return;
+        }
+        _ => (),
+    }
+
     // set the RUSTFLAGS environment variable to inject our object and call Cargo with all the Cargo args
 
     // Cargo sets the path to itself in the `CARGO` environment variable:
@@ -9,7 +61,7 @@ pub fn main() {
     // This is also useful for using `cargo auditable` as a drop-in replacement for Cargo.
     let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
     let mut command = Command::new(cargo);
-    // Pass along all our arguments; we don't currently have any args specific to `cargo auditable`
+    // Pass along all our arguments; the subcommands specific to `cargo auditable` have been handled above
     // We skip argv[0] which is the path to this binary and the first argument which is 'auditable' passed by Cargo
     command.args(env::args_os().skip(2));
     // Set the environment variable to use this binary as a rustc wrapper, that's when we do the real work
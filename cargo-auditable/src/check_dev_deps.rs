@@ -0,0 +1,153 @@
+//! Implements `cargo auditable check-dev-deps`, which flags packages recorded in a binary
+//! that are only dev-dependencies in the source tree it was built from.
+//! Dev-dependencies are never linked into release binaries, so finding one
+//! means the audit data is wrong, or the binary was built from a different source tree.
+
+use std::{env, ffi::OsString, path::PathBuf, process::exit};
+
+use auditable_info::Limits;
+
+use crate::{
+    cargo_arguments::CargoArgs,
+    package_args::{PackageArgs, PACKAGE_ARGS_USAGE},
+};
+
+const USAGE: &str = "\
+Usage: cargo auditable check-dev-deps BINARY [OPTIONS]
+
+Reports the packages recorded in BINARY that are only reachable through
+dev-dependencies in the package in the current directory. Exits with an error
+if any are found.
+
+Pass the same --target and feature selection flags that the binary was built with.
+
+Options:
+";
+
+/// Includes only the arguments specific to `cargo auditable check-dev-deps`
+struct CheckDevDepsArgs {
+    binary: PathBuf,
+    package: PackageArgs,
+}
+
+impl CheckDevDepsArgs {
+    fn from_args() -> Result<Self, pico_args::Error> {
+        // we .skip(3) to get over `cargo auditable check-dev-deps` and to the start of the flags
+        let raw_args: Vec<OsString> = env::args_os().skip(3).collect();
+        Self::from_args_vec(raw_args)
+    }
+
+    /// Split into its own function for unit testing
+    fn from_args_vec(raw_args: Vec<OsString>) -> Result<Self, pico_args::Error> {
+        let mut parser = pico_args::Arguments::from_vec(raw_args);
+        let package = PackageArgs::from_parser(&mut parser)?;
+        // Cargo flags such as `--offline` are parsed separately by `CargoArgs`
+        for flag in ["--offline", "--locked", "--frozen"] {
+            parser.contains(flag);
+        }
+        let _: Vec<String> = parser.values_from_str("--config")?;
+        let binary = parser.free_from_os_str(|s| Ok::<PathBuf, pico_args::Error>(s.into()))?;
+        Ok(CheckDevDepsArgs { binary, package })
+    }
+}
+
+pub fn main() {
+    if env::args_os()
+        .skip(3)
+        .any(|arg| arg == "-h" || arg == "--help")
+    {
+        print!("{USAGE}{PACKAGE_ARGS_USAGE}");
+        return;
+    }
+    let args = CheckDevDepsArgs::from_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}{PACKAGE_ARGS_USAGE}");
+        exit(2);
+    });
+    let cargo_args = CargoArgs::from_args();
+
+    let info = auditable_info::audit_info_from_file(&args.binary, Limits::default())
+        .unwrap_or_else(|e| {
+            eprintln!(
+                "ERROR: failed to read audit data from '{}': {e}",
+                args.binary.display()
+            );
+            exit(1);
+        });
+    let rustc_path = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let target_triple = args.package.target_triple(&rustc_path);
+    let metadata = args.package.metadata(&target_triple, &cargo_args);
+    let leaks = info.dev_dependency_leaks(&metadata).unwrap_or_else(|e| {
+        eprintln!("ERROR: {e}");
+        exit(1);
+    });
+    if leaks.is_empty() {
+        return;
+    }
+    for package in &leaks {
+        println!(
+            "{}: {} {} is only a dev-dependency in the source tree",
+            args.binary.display(),
+            package.name,
+            package.version
+        );
+    }
+    exit(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use auditable_serde::{DependencyKind, MetadataOptions, VersionInfo};
+    use std::str::FromStr;
+
+    #[test]
+    fn basic_parsing() {
+        let raw_args = ["target/release/app", "--features", "foo", "--locked"];
+        let args =
+            CheckDevDepsArgs::from_args_vec(raw_args.iter().map(OsString::from).collect()).unwrap();
+        assert_eq!(args.binary, PathBuf::from("target/release/app"));
+        assert_eq!(args.package.features, vec!["foo"]);
+    }
+
+    #[test]
+    fn detects_leaked_dev_dependency() {
+        // `which` is a dev-dependency of this very crate
+        let metadata = cargo_metadata::MetadataCommand::new()
+            .manifest_path(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml"))
+            .exec()
+            .unwrap();
+        let which = metadata
+            .packages
+            .iter()
+            .find(|p| p.name == "which")
+            .unwrap();
+        let json = format!(
+            r#"{{"packages":[
+            {{"name":"which","version":"{}","source":"crates.io"}},
+            {{"name":"cargo-auditable","version":"0.0.0","source":"local","root":true,"dependencies":[0]}}
+            ]}}"#,
+            which.version
+        );
+        let info = VersionInfo::from_str(&json).unwrap();
+        let leaks = info.dev_dependency_leaks(&metadata).unwrap();
+        assert_eq!(leaks.len(), 1);
+        assert_eq!(leaks[0].name, "which");
+
+        let own_info = VersionInfo::try_from(&metadata).unwrap();
+        assert!(own_info.dev_dependency_leaks(&metadata).unwrap().is_empty());
+    }
+
+    #[test]
+    fn deliberately_included_dev_dependencies_are_not_leaks() {
+        let metadata = cargo_metadata::MetadataCommand::new()
+            .manifest_path(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml"))
+            .exec()
+            .unwrap();
+        let mut options = MetadataOptions::default();
+        options.include_dev_dependencies = true;
+        let info = VersionInfo::from_metadata(&metadata, &options).unwrap();
+        let which = info.packages.iter().find(|p| p.name == "which").unwrap();
+        assert_eq!(which.kind, DependencyKind::Development);
+        assert!(info.dev_dependency_leaks(&metadata).unwrap().is_empty());
+    }
+}
@@ -1,29 +1,99 @@
-use auditable_serde::VersionInfo;
+use auditable_serde::{MetadataOptions, Toolchain, VersionInfo};
 use cargo_metadata::{Metadata, MetadataCommand};
-use miniz_oxide::deflate::compress_to_vec_zlib;
-use std::{convert::TryFrom, str::from_utf8};
+use std::{ffi::OsStr, str::from_utf8};
 
-use crate::{cargo_arguments::CargoArgs, rustc_arguments::RustcArgs};
+use crate::{
+    cargo_arguments::CargoArgs, lockfile, rustc_arguments::RustcArgs, size_budget, target_info,
+    vendored_sources,
+};
 
-/// Calls `cargo metadata` to obtain the dependency tree, serializes it to JSON and compresses it.
-pub fn compressed_dependency_list(rustc_args: &RustcArgs, target_triple: &str) -> Vec<u8> {
+/// Calls `cargo metadata` to obtain the dependency tree and converts it to the format we embed.
+pub fn dependency_list(
+    rustc_args: &RustcArgs,
+    rustc_path: &OsStr,
+    target_triple: &str,
+) -> VersionInfo {
     let metadata = get_metadata(rustc_args, target_triple);
-    let version_info = VersionInfo::try_from(&metadata).unwrap();
-    let json = serde_json::to_string(&version_info).unwrap();
-    // compression level 7 makes this complete in a few milliseconds, so no need to drop to a lower level in debug mode
-    let compressed_json = compress_to_vec_zlib(json.as_bytes(), 7);
-    compressed_json
+    // Cargo only sets CARGO_BIN_NAME for binaries; the crate name of other targets
+    // is the target name with dashes replaced by underscores
+    let target_name =
+        std::env::var("CARGO_BIN_NAME").unwrap_or_else(|_| rustc_args.crate_name.clone());
+    let per_target = env_flag("CARGO_AUDITABLE_PER_TARGET");
+    let mut info = version_info_for_target(&metadata, per_target.then_some(target_name.as_str()));
+    if env_flag("CARGO_AUDITABLE_RECORD_TARGET_NAME") {
+        info.target_name = Some(target_name);
+    }
+    // Only invoke `rustc -vV` if the result is going to be used
+    if env_flag("CARGO_AUDITABLE_RECORD_TOOLCHAIN") {
+        info.toolchain = Some(toolchain(rustc_args, rustc_path, target_triple));
+    }
+    info
 }
 
-fn get_metadata(args: &RustcArgs, target_triple: &str) -> Metadata {
-    let mut metadata_command = MetadataCommand::new();
+fn toolchain(rustc_args: &RustcArgs, rustc_path: &OsStr, target_triple: &str) -> Toolchain {
+    let rustc_version = target_info::rustc_version(rustc_path);
+    Toolchain {
+        channel: target_info::release_channel(&rustc_version).to_owned(),
+        rustc_version,
+        target: target_triple.to_owned(),
+        profile: profile_dir_name(&rustc_args.out_dir),
+    }
+}
 
-    // Cargo sets the path to itself in the `CARGO` environment variable:
-    // https://doc.rust-lang.org/cargo/reference/environment-variables.html#environment-variables-cargo-sets-for-3rd-party-subcommands
-    // This is also useful for using `cargo auditable` as a drop-in replacement for Cargo.
-    if let Some(path) = std::env::var_os("CARGO") {
-        metadata_command.cargo_path(path);
+/// Cargo places the outputs of each profile into a directory named after it,
+/// such as `target/release/deps`, except that the `dev` profile uses `debug`
+fn profile_dir_name(out_dir: &std::path::Path) -> Option<String> {
+    let profile_dir = out_dir.parent()?.file_name()?.to_str()?;
+    Some(profile_dir.to_owned())
+}
+
+/// Serializes the dependency tree obtained from `cargo metadata` to JSON and compresses it
+/// as requested via `CARGO_AUDITABLE_COMPRESSION`, within `CARGO_AUDITABLE_MAX_SIZE` if set.
+pub fn compress_dependency_list(metadata: &Metadata) -> Vec<u8> {
+    size_budget::compressed_payload(version_info(metadata))
+}
+
+/// Converts the dependency tree obtained from `cargo metadata` to the format we embed,
+/// applying the options set through `CARGO_AUDITABLE_*` environment variables
+pub fn version_info(metadata: &Metadata) -> VersionInfo {
+    version_info_for_target(metadata, None)
+}
+
+/// Like [`version_info`], but leaves out the optional dependencies that are only enabled
+/// for other targets of the package, if `target` is set. See [`MetadataOptions::target`].
+fn version_info_for_target(metadata: &Metadata, target: Option<&str>) -> VersionInfo {
+    let mut options = MetadataOptions::default();
+    options.target = target.map(str::to_owned);
+    options.per_edge_kinds = env_flag("CARGO_AUDITABLE_PER_EDGE_KINDS");
+    options.include_dev_dependencies = include_dev_dependencies();
+    options.record_features = env_flag("CARGO_AUDITABLE_RECORD_FEATURES");
+    options.record_source_urls = env_flag("CARGO_AUDITABLE_RECORD_SOURCE_URLS");
+    options.record_workspace = env_flag("CARGO_AUDITABLE_RECORD_WORKSPACE");
+    let mut info = VersionInfo::from_metadata(metadata, &options).unwrap_or_else(|e| {
+        eprintln!("ERROR: failed to convert the output of 'cargo metadata': {e}");
+        std::process::exit(1);
+    });
+    if env_flag("CARGO_AUDITABLE_RECORD_CHECKSUMS") {
+        lockfile::record_checksums(metadata, &mut info);
+    }
+    if env_flag("CARGO_AUDITABLE_VENDOR_HASHES") {
+        vendored_sources::record_vendored_checksums(metadata, &mut info);
     }
+    info
+}
+
+/// Whether dev-dependencies should be recorded, as requested via `CARGO_AUDITABLE_INCLUDE_DEV_DEPS`
+pub fn include_dev_dependencies() -> bool {
+    env_flag("CARGO_AUDITABLE_INCLUDE_DEV_DEPS")
+}
+
+/// Whether an environment variable such as `CARGO_AUDITABLE_PER_EDGE_KINDS` is set to `1` or `true`
+pub fn env_flag(name: &str) -> bool {
+    std::env::var_os(name).is_some_and(|value| value == "1" || value == "true")
+}
+
+fn get_metadata(args: &RustcArgs, target_triple: &str) -> Metadata {
+    let mut metadata_command = MetadataCommand::new();
 
     // Point cargo-metadata to the correct Cargo.toml in a workspace.
     // CARGO_MANIFEST_DIR env var will be set by Cargo when it calls our rustc wrapper
@@ -40,14 +110,31 @@ fn get_metadata(args: &RustcArgs, target_triple: &str) -> Metadata {
     let owned_features: Vec<String> = features.iter().map(|s| s.to_string()).collect();
     metadata_command.features(cargo_metadata::CargoOpt::SomeFeatures(owned_features));
 
+    let orig_args = CargoArgs::from_env()
+        .expect("Env var 'CARGO_AUDITABLE_ORIG_ARGS' set by 'cargo-auditable' is unset!");
+    execute_metadata_command(metadata_command, target_triple, &orig_args)
+}
+
+/// Applies the options shared by all invocations of `cargo metadata`
+/// on top of the crate-specific ones already set, and runs the command.
+pub fn execute_metadata_command(
+    mut metadata_command: MetadataCommand,
+    target_triple: &str,
+    orig_args: &CargoArgs,
+) -> Metadata {
+    // Cargo sets the path to itself in the `CARGO` environment variable:
+    // https://doc.rust-lang.org/cargo/reference/environment-variables.html#environment-variables-cargo-sets-for-3rd-party-subcommands
+    // This is also useful for using `cargo auditable` as a drop-in replacement for Cargo.
+    if let Some(path) = std::env::var_os("CARGO") {
+        metadata_command.cargo_path(path);
+    }
+
     // Restrict the dependency resolution to just the platform the binary is being compiled for.
     // By default `cargo metadata` resolves the dependency tree for all platforms.
     let mut other_args = vec!["--filter-platform".to_owned(), target_triple.to_owned()];
 
     // Pass arguments such as `--config`, `--offline` and `--locked`
     // from the original CLI invocation of `cargo auditable`
-    let orig_args = CargoArgs::from_env()
-        .expect("Env var 'CARGO_AUDITABLE_ORIG_ARGS' set by 'cargo-auditable' is unset!");
     if orig_args.offline {
         other_args.push("--offline".to_owned());
     }
@@ -57,9 +144,9 @@ fn get_metadata(args: &RustcArgs, target_triple: &str) -> Metadata {
     if orig_args.locked {
         other_args.push("--locked".to_owned());
     }
-    for arg in orig_args.config {
+    for arg in &orig_args.config {
         other_args.push("--config".to_owned());
-        other_args.push(arg);
+        other_args.push(arg.clone());
     }
 
     // This can only be done once, multiple calls will replace previously set options.
@@ -74,10 +161,21 @@ fn get_metadata(args: &RustcArgs, target_triple: &str) -> Metadata {
     metadata_command.env_remove("RUSTC_WORKSPACE_WRAPPER");
     let output = metadata_command.output().unwrap();
     if !output.status.success() {
-        panic!(
-            "cargo metadata failure: {}",
-            String::from_utf8_lossy(&output.stderr)
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let flags = orig_args.network_restricting_flags();
+        if flags.is_empty() {
+            panic!("cargo metadata failure: {stderr}");
+        }
+        // Hermetic builds rely on these flags, so explain why the build failed
+        // instead of leaving the user to dig through a panic message
+        eprintln!(
+            "ERROR: 'cargo auditable' could not collect the dependency tree with 'cargo metadata' under {}.\n\
+            Collecting it must not require network access or changes to Cargo.lock; \
+            make sure Cargo.lock is up to date and all dependencies are already downloaded or vendored.\n\n\
+            cargo metadata output:\n{stderr}",
+            flags.join(" ")
         );
+        std::process::exit(1);
     }
     let stdout = from_utf8(&output.stdout)
         .expect("cargo metadata output not utf8")
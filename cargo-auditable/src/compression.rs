@@ -0,0 +1,98 @@
+//! Selects the compression of the audit data via the `CARGO_AUDITABLE_COMPRESSION`
+//! environment variable, for binaries where every kilobyte counts.
+//!
+//! The value is `zlib` or `zstd`, optionally followed by `:` and the level, e.g. `zstd:19`,
+//! or `none` to embed the JSON as-is. `zstd` requires `cargo auditable` to be built
+//! with the `zstd` feature.
+//! Unset, the data is compressed with Zlib at the level that other producers use too.
+
+use std::env;
+
+use auditable_serde::PayloadCompression;
+
+/// Zstandard level used if none is specified, which favors size since the input is small
+#[cfg(feature = "zstd")]
+const DEFAULT_ZSTD_LEVEL: i32 = 19;
+
+/// Returns the compression requested via `CARGO_AUDITABLE_COMPRESSION`
+pub fn payload_compression() -> PayloadCompression {
+    match env::var("CARGO_AUDITABLE_COMPRESSION") {
+        Ok(value) => parse(&value)
+            .unwrap_or_else(|e| panic!("Invalid value of CARGO_AUDITABLE_COMPRESSION: {e}")),
+        Err(env::VarError::NotPresent) => PayloadCompression::default(),
+        Err(e) => panic!("Invalid value of CARGO_AUDITABLE_COMPRESSION: {e}"),
+    }
+}
+
+fn parse(value: &str) -> Result<PayloadCompression, String> {
+    let (algorithm, level) = match value.split_once(':') {
+        Some((algorithm, level)) => (algorithm, Some(level)),
+        None => (value, None),
+    };
+    match algorithm {
+        "zlib" => match level {
+            None => Ok(PayloadCompression::default()),
+            Some(level) => match level.parse::<u8>() {
+                Ok(level) if level <= 10 => Ok(PayloadCompression::Zlib(level)),
+                _ => Err(format!("Zlib level must be from 0 to 10, got '{level}'")),
+            },
+        },
+        "none" => match level {
+            None => Ok(PayloadCompression::Uncompressed),
+            Some(level) => Err(format!("'none' does not take a level, got '{level}'")),
+        },
+        #[cfg(feature = "zstd")]
+        "zstd" => match level {
+            None => Ok(PayloadCompression::Zstd(DEFAULT_ZSTD_LEVEL)),
+            Some(level) => match level.parse::<i32>() {
+                Ok(level) if (1..=22).contains(&level) => Ok(PayloadCompression::Zstd(level)),
+                _ => Err(format!(
+                    "Zstandard level must be from 1 to 22, got '{level}'"
+                )),
+            },
+        },
+        #[cfg(not(feature = "zstd"))]
+        "zstd" => Err("cargo auditable was built without the `zstd` feature".to_owned()),
+        other => Err(format!(
+            "unknown compression algorithm '{other}', expected 'zlib', 'zstd' or 'none'"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zlib() {
+        assert_eq!(parse("zlib"), Ok(PayloadCompression::default()));
+        assert_eq!(parse("zlib:9"), Ok(PayloadCompression::Zlib(9)));
+        assert!(parse("zlib:11").is_err());
+        assert!(parse("zlib:").is_err());
+        assert!(parse("gzip").is_err());
+    }
+
+    #[test]
+    fn none() {
+        assert_eq!(parse("none"), Ok(PayloadCompression::Uncompressed));
+        assert!(parse("none:1").is_err());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd() {
+        assert_eq!(
+            parse("zstd"),
+            Ok(PayloadCompression::Zstd(DEFAULT_ZSTD_LEVEL))
+        );
+        assert_eq!(parse("zstd:3"), Ok(PayloadCompression::Zstd(3)));
+        assert!(parse("zstd:0").is_err());
+        assert!(parse("zstd:23").is_err());
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn zstd_unsupported() {
+        assert!(parse("zstd").is_err());
+    }
+}
@@ -0,0 +1,202 @@
+//! Implements `cargo auditable emit-object`, which writes the object file with the audit data
+//! to disk instead of injecting it into a build. This lets build systems other than Cargo
+//! (Buck, Bazel, Meson, etc.) add it to their own link line.
+
+use std::{env, ffi::OsString, path::PathBuf, process::exit};
+
+use auditable_extract::{frame_header, FRAME_HEADER_LEN};
+
+use crate::{
+    cargo_arguments::CargoArgs,
+    collect_audit_data, object_file,
+    package_args::{PackageArgs, PACKAGE_ARGS_USAGE},
+    payload_store, target_info,
+};
+
+const USAGE: &str = "\
+Usage: cargo auditable emit-object -o FILE [OPTIONS]
+
+Writes an object file containing the audit data for the package to FILE.
+Link it into your binary and pass the linker flag that keeps the symbol
+AUDITABLE_VERSION_INFO alive (`-Wl,--undefined=AUDITABLE_VERSION_INFO`,
+`-Wl,-u,_AUDITABLE_VERSION_INFO` on Apple platforms,
+or `/INCLUDE:AUDITABLE_VERSION_INFO` with the MSVC linker).
+
+For targets that are flashed as raw memory images, pass --linker-script
+to also generate a linker script fragment placing the audit data into
+flash between the symbols __auditable_start and __auditable_end.
+
+Options:
+    -o, --output FILE         Path to write the object file to
+    --linker-script FILE      Also write a linker script fragment to FILE
+    --region NAME             Memory region for the linker script fragment [default: FLASH]
+";
+
+/// Includes only the arguments specific to `cargo auditable emit-object`
+struct EmitObjectArgs {
+    output: PathBuf,
+    linker_script: Option<PathBuf>,
+    region: String,
+    package: PackageArgs,
+}
+
+impl EmitObjectArgs {
+    fn from_args() -> Result<Self, pico_args::Error> {
+        // we .skip(3) to get over `cargo auditable emit-object` and to the start of the flags
+        let raw_args: Vec<OsString> = env::args_os().skip(3).collect();
+        Self::from_args_vec(raw_args)
+    }
+
+    /// Split into its own function for unit testing
+    fn from_args_vec(raw_args: Vec<OsString>) -> Result<Self, pico_args::Error> {
+        let mut parser = pico_args::Arguments::from_vec(raw_args);
+        Ok(EmitObjectArgs {
+            output: parser.value_from_os_str(["-o", "--output"], |s| {
+                Ok::<PathBuf, pico_args::Error>(PathBuf::from(s))
+            })?,
+            linker_script: parser.opt_value_from_os_str("--linker-script", |s| {
+                Ok::<PathBuf, pico_args::Error>(PathBuf::from(s))
+            })?,
+            region: parser
+                .opt_value_from_str("--region")?
+                .unwrap_or_else(|| "FLASH".to_owned()),
+            package: PackageArgs::from_parser(&mut parser)?,
+        })
+    }
+}
+
+pub fn main() {
+    if env::args_os()
+        .skip(3)
+        .any(|arg| arg == "-h" || arg == "--help")
+    {
+        print!("{USAGE}{PACKAGE_ARGS_USAGE}");
+        return;
+    }
+    let args = EmitObjectArgs::from_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}{PACKAGE_ARGS_USAGE}");
+        exit(2);
+    });
+    // Flags such as `--offline` and `--config` are parsed the same way as for a regular build
+    let cargo_args = CargoArgs::from_args();
+
+    let rustc_path = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let target_triple = args.package.target_triple(&rustc_path);
+    let metadata = args.package.metadata(&target_triple, &cargo_args);
+    let contents = collect_audit_data::compress_dependency_list(&metadata);
+    let mut contents = payload_store::detach_if_requested(contents);
+    // Raw memory images have no section headers, so the data needs to be findable on its own
+    let raw_image = args.linker_script.is_some();
+    if raw_image {
+        contents = frame(&contents);
+    }
+
+    let target_info = target_info::rustc_target_info(&rustc_path, &target_triple);
+    let file = object_file::create_metadata_file(
+        &target_info,
+        &target_triple,
+        &contents,
+        "AUDITABLE_VERSION_INFO",
+        raw_image,
+    )
+    .unwrap_or_else(|| {
+        eprintln!("ERROR: target '{target_triple}' is not supported by 'cargo auditable'!");
+        exit(1);
+    });
+    std::fs::write(&args.output, file).unwrap_or_else(|e| {
+        eprintln!(
+            "ERROR: failed to write '{}': {e}",
+            args.output.to_string_lossy()
+        );
+        exit(1);
+    });
+    if let Some(path) = &args.linker_script {
+        std::fs::write(path, linker_script_fragment(&args.region)).unwrap_or_else(|e| {
+            eprintln!("ERROR: failed to write '{}': {e}", path.to_string_lossy());
+            exit(1);
+        });
+    }
+}
+
+/// Prefixes the payload with the magic, length and checksum, as described in `auditable_extract::FRAME_MAGIC`
+fn frame(payload: &[u8]) -> Vec<u8> {
+    let header = frame_header(payload).expect("Audit data is too large to be framed");
+    let mut result = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+    result.extend_from_slice(&header);
+    result.extend_from_slice(payload);
+    result
+}
+
+/// Generates a linker script fragment placing the audit data into the specified memory region.
+/// It is meant to be passed to the linker in addition to the main linker script.
+fn linker_script_fragment(region: &str) -> String {
+    format!(
+        "\
+/* Generated by `cargo auditable emit-object`.
+ * Pass this file to the linker after the main linker script, e.g. `-C link-arg=-Taudit.x`,
+ * along with the object file containing the audit data. */
+SECTIONS
+{{
+  .dep-v0 : ALIGN(4)
+  {{
+    __auditable_start = .;
+    KEEP(*(.dep-v0));
+    __auditable_end = .;
+  }} > {region}
+}}
+INSERT AFTER .rodata;
+"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &[&str]) -> Result<EmitObjectArgs, pico_args::Error> {
+        EmitObjectArgs::from_args_vec(input.iter().map(OsString::from).collect())
+    }
+
+    #[test]
+    fn basic_parsing() {
+        let args = parse(&[
+            "--target",
+            "x86_64-unknown-linux-gnu",
+            "-o",
+            "audit.o",
+            "--features",
+            "foo,bar baz",
+            "--offline",
+        ])
+        .unwrap();
+        assert_eq!(args.output, PathBuf::from("audit.o"));
+        assert_eq!(
+            args.package.target.as_deref(),
+            Some("x86_64-unknown-linux-gnu")
+        );
+        assert_eq!(args.package.features, vec!["foo", "bar", "baz"]);
+        assert!(!args.package.all_features);
+        assert!(!args.package.no_default_features);
+        assert!(args.linker_script.is_none());
+        assert_eq!(args.region, "FLASH");
+    }
+
+    #[test]
+    fn framing_roundtrip() {
+        let framed = frame(b"compressed audit data");
+        let payload = auditable_extract::framed_auditable_data(&framed).unwrap();
+        assert_eq!(payload, b"compressed audit data");
+    }
+
+    #[test]
+    fn linker_script_region() {
+        let fragment = linker_script_fragment("ROM");
+        assert!(fragment.contains("KEEP(*(.dep-v0))"));
+        assert!(fragment.contains("} > ROM\n"));
+    }
+
+    #[test]
+    fn output_is_required() {
+        assert!(parse(&["--target", "x86_64-unknown-linux-gnu"]).is_err());
+    }
+}
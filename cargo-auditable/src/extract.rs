@@ -0,0 +1,234 @@
+//! Implements `cargo auditable extract`, which prints the audit data embedded in a binary,
+//! so that looking at it does not require installing another tool or writing a program.
+
+use std::{
+    env,
+    ffi::OsString,
+    fs::File,
+    io::{BufReader, Write},
+    path::PathBuf,
+    process::exit,
+    str::FromStr,
+};
+
+use auditable_info::Limits;
+use auditable_serde::report::{CycloneDx, ReportWriter};
+use auditable_serde::{NormalizeOptions, VersionInfo};
+use cargo_lock::Lockfile;
+
+const USAGE: &str = "\
+Usage: cargo auditable extract [OPTIONS] BINARY
+
+Prints the audit data embedded in BINARY to stdout.
+
+Options:
+    --output-format FORMAT    One of:
+                                json       the embedded JSON, as-is (default)
+                                lock       a Cargo.lock file listing the same packages
+                                cyclonedx  a CycloneDX SBOM in JSON
+                                zlib       the compressed JSON exactly as embedded,
+                                           without decompressing it
+    --normalize               Trim whitespace from names and rewrite source URLs
+                              to their canonical form before printing.
+                              Every change is reported on stderr.
+                              Not supported with the zlib format.
+    --registry-mirror MIRROR[=UPSTREAM]
+                              Record packages from the registry with the index
+                              URL MIRROR as coming from the registry UPSTREAM,
+                              crates.io by default, so that they match advisories
+                              about it. May be repeated. Implies --normalize.
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Json,
+    Lock,
+    CycloneDx,
+    Zlib,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(OutputFormat::Json),
+            "lock" => Ok(OutputFormat::Lock),
+            "cyclonedx" => Ok(OutputFormat::CycloneDx),
+            "zlib" => Ok(OutputFormat::Zlib),
+            other => Err(format!("unknown output format '{other}'")),
+        }
+    }
+}
+
+/// Includes only the arguments specific to `cargo auditable extract`
+struct ExtractArgs {
+    binary: PathBuf,
+    format: OutputFormat,
+    /// Set if the audit data should be normalized before printing
+    normalize: Option<NormalizeOptions>,
+}
+
+impl ExtractArgs {
+    fn from_args() -> Result<Self, pico_args::Error> {
+        // we .skip(3) to get over `cargo auditable extract` and to the start of the flags
+        let raw_args: Vec<OsString> = env::args_os().skip(3).collect();
+        Self::from_args_vec(raw_args)
+    }
+
+    /// Split into its own function for unit testing
+    fn from_args_vec(raw_args: Vec<OsString>) -> Result<Self, pico_args::Error> {
+        let mut parser = pico_args::Arguments::from_vec(raw_args);
+        let format = parser
+            .opt_value_from_str("--output-format")?
+            .unwrap_or(OutputFormat::Json);
+        let mirrors: Vec<String> = parser.values_from_str("--registry-mirror")?;
+        let normalize = if parser.contains("--normalize") || !mirrors.is_empty() {
+            let mut options = NormalizeOptions::default();
+            for mirror in mirrors {
+                let (mirror, upstream) = mirror
+                    .split_once('=')
+                    .unwrap_or((mirror.as_str(), "crates.io"));
+                options
+                    .registry_mirrors
+                    .insert(mirror.to_owned(), upstream.to_owned());
+            }
+            Some(options)
+        } else {
+            None
+        };
+        let binary: PathBuf =
+            parser.free_from_os_str(|s| Ok::<PathBuf, pico_args::Error>(PathBuf::from(s)))?;
+        let remaining = parser.finish();
+        if !remaining.is_empty() {
+            let remaining: Vec<String> = remaining
+                .into_iter()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect();
+            return Err(pico_args::Error::ArgumentParsingFailed {
+                cause: format!("unexpected arguments: {}", remaining.join(" ")),
+            });
+        }
+        if normalize.is_some() && format == OutputFormat::Zlib {
+            return Err(pico_args::Error::ArgumentParsingFailed {
+                cause: "--normalize cannot be used with --output-format zlib".to_owned(),
+            });
+        }
+        Ok(ExtractArgs {
+            binary,
+            format,
+            normalize,
+        })
+    }
+}
+
+pub fn main() {
+    if env::args_os()
+        .skip(3)
+        .any(|arg| arg == "-h" || arg == "--help")
+    {
+        print!("{USAGE}");
+        return;
+    }
+    let args = ExtractArgs::from_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}");
+        exit(2);
+    });
+    let output = extract(&args).unwrap_or_else(|e| {
+        eprintln!(
+            "ERROR: failed to read audit data from '{}': {e}",
+            args.binary.display()
+        );
+        exit(1);
+    });
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    stdout
+        .write_all(&output)
+        .and_then(|()| stdout.flush())
+        .expect("Failed to write to stdout");
+}
+
+fn extract(args: &ExtractArgs) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let limits = Limits::default();
+    let output = match args.format {
+        OutputFormat::Json if args.normalize.is_some() => {
+            serde_json::to_vec(&audit_info(args, limits)?)?
+        }
+        OutputFormat::Json => auditable_info::json_from_file(&args.binary, limits)?.into_bytes(),
+        OutputFormat::Lock => {
+            let info = audit_info(args, limits)?;
+            Lockfile::try_from(&info)?.to_string().into_bytes()
+        }
+        OutputFormat::CycloneDx => {
+            let info = audit_info(args, limits)?;
+            let mut output = Vec::new();
+            CycloneDx.write(&info, &mut output)?;
+            output
+        }
+        OutputFormat::Zlib => {
+            let mut reader = BufReader::new(File::open(&args.binary)?);
+            let max_size = limits.decompressed_json_size as u64;
+            auditable_extract::raw_auditable_data_from_reader(&mut reader, max_size)?
+        }
+    };
+    Ok(output)
+}
+
+/// Reads the audit data, normalizing it if requested
+fn audit_info(args: &ExtractArgs, limits: Limits) -> Result<VersionInfo, auditable_info::Error> {
+    let mut info = auditable_info::audit_info_from_file(&args.binary, limits)?;
+    if let Some(options) = &args.normalize {
+        for change in info.normalize(options) {
+            eprintln!("Normalized {change}");
+        }
+    }
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &[&str]) -> Result<ExtractArgs, pico_args::Error> {
+        ExtractArgs::from_args_vec(input.iter().map(OsString::from).collect())
+    }
+
+    #[test]
+    fn basic_parsing() {
+        let args = parse(&["app"]).unwrap();
+        assert_eq!(args.binary, PathBuf::from("app"));
+        assert_eq!(args.format, OutputFormat::Json);
+        let args = parse(&["--output-format", "lock", "app"]).unwrap();
+        assert_eq!(args.format, OutputFormat::Lock);
+        assert!(args.normalize.is_none());
+        let args = parse(&["--normalize", "app"]).unwrap();
+        assert_eq!(args.normalize, Some(NormalizeOptions::default()));
+    }
+
+    #[test]
+    fn registry_mirrors() {
+        let args = parse(&[
+            "--registry-mirror",
+            "sparse+https://mirror.example.com/",
+            "--registry-mirror",
+            "https://git.example.com/mirror=https://registry.example.com",
+            "app",
+        ])
+        .unwrap();
+        let mirrors = args.normalize.unwrap().registry_mirrors;
+        assert_eq!(mirrors["sparse+https://mirror.example.com/"], "crates.io");
+        assert_eq!(
+            mirrors["https://git.example.com/mirror"],
+            "https://registry.example.com"
+        );
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["app", "other-app"]).is_err());
+        assert!(parse(&["--output-format", "toml", "app"]).is_err());
+        assert!(parse(&["--normalize", "--output-format", "zlib", "app"]).is_err());
+    }
+}
@@ -0,0 +1,464 @@
+//! Renders a standalone HTML report on the dependencies of one or more binaries,
+//! for `--format html` of `cargo auditable audit` and `cargo auditable inventory`.
+//!
+//! The report is meant to be attached to release sign-off tickets, so it is a single file
+//! with the styles and charts inline: it can be opened in any browser without network access,
+//! JavaScript or extra tooling.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{self, Write},
+};
+
+use auditable_serde::{DependencyKind, Package, VersionInfo};
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.25em 0.75em; text-align: left; }
+th { background: #f0f0f0; }
+.vulnerable { color: #b00020; font-weight: bold; }
+.ok { color: #1b5e20; font-weight: bold; }
+summary { cursor: pointer; margin: 0.5em 0; }
+";
+
+/// Width of the bar charts in pixels, including the labels
+const CHART_WIDTH: usize = 720;
+/// Width of the labels to the left of the bars
+const LABEL_WIDTH: usize = 280;
+const BAR_HEIGHT: usize = 20;
+
+/// A package affected by a security advisory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub package: String,
+    pub version: String,
+    pub advisory_id: String,
+    pub title: String,
+    pub build_dependency: bool,
+}
+
+/// A binary to describe in the report
+#[derive(Debug, Clone)]
+pub struct BinaryReport {
+    /// Paths the binary was found at; identical files found at several paths are listed once
+    pub paths: Vec<String>,
+    pub info: VersionInfo,
+    pub findings: Vec<Finding>,
+}
+
+impl BinaryReport {
+    fn name(&self) -> String {
+        self.paths.join(", ")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub title: String,
+    pub binaries: Vec<BinaryReport>,
+    /// Whether the binaries were checked against the advisory database.
+    /// If not, the report says so instead of claiming there are no vulnerabilities.
+    pub vulnerabilities_checked: bool,
+}
+
+impl Report {
+    pub fn new(title: &str, vulnerabilities_checked: bool) -> Self {
+        Report {
+            title: title.to_owned(),
+            binaries: Vec::new(),
+            vulnerabilities_checked,
+        }
+    }
+
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "<!DOCTYPE html>")?;
+        writeln!(out, "<html lang=\"en\">")?;
+        writeln!(out, "<head>")?;
+        writeln!(out, "<meta charset=\"utf-8\">")?;
+        writeln!(out, "<title>{}</title>", escape(&self.title))?;
+        writeln!(out, "<style>\n{STYLE}</style>")?;
+        writeln!(out, "</head>")?;
+        writeln!(out, "<body>")?;
+        writeln!(out, "<h1>{}</h1>", escape(&self.title))?;
+        writeln!(
+            out,
+            "<p>Generated by cargo-auditable {} from the audit data embedded in the binaries.</p>",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        self.write_summary(out)?;
+        self.write_charts(out)?;
+        self.write_vulnerabilities(out)?;
+        self.write_duplicates(out)?;
+        self.write_dependencies(out)?;
+        writeln!(out, "</body>")?;
+        writeln!(out, "</html>")
+    }
+
+    fn write_summary(&self, out: &mut impl Write) -> io::Result<()> {
+        let crates: BTreeSet<(&str, String)> = self
+            .binaries
+            .iter()
+            .flat_map(|binary| &binary.info.packages)
+            .map(|package| (package.name.as_str(), package.version.to_string()))
+            .collect();
+        let duplicates: usize = self
+            .binaries
+            .iter()
+            .map(|binary| duplicates(&binary.info).len())
+            .sum();
+        writeln!(out, "<h2>Summary</h2>")?;
+        writeln!(out, "<table>")?;
+        summary_row(out, "Binaries", &self.binaries.len().to_string())?;
+        summary_row(out, "Distinct crate versions", &crates.len().to_string())?;
+        summary_row(
+            out,
+            "Crates present in several versions",
+            &duplicates.to_string(),
+        )?;
+        let findings: usize = self.binaries.iter().map(|b| b.findings.len()).sum();
+        let vulnerabilities = if !self.vulnerabilities_checked {
+            "not checked".to_owned()
+        } else if findings == 0 {
+            "<span class=\"ok\">none found</span>".to_owned()
+        } else {
+            format!("<span class=\"vulnerable\">{findings}</span>")
+        };
+        writeln!(
+            out,
+            "<tr><th>Vulnerabilities</th><td>{vulnerabilities}</td></tr>"
+        )?;
+        writeln!(out, "</table>")
+    }
+
+    fn write_charts(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "<h2>Charts</h2>")?;
+        let packages: Vec<(String, usize)> = self
+            .binaries
+            .iter()
+            .map(|binary| (binary.name(), binary.info.packages.len()))
+            .collect();
+        bar_chart(out, "Packages per binary", &packages, "#1565c0")?;
+        let mut sources: BTreeMap<String, usize> = BTreeMap::new();
+        for package in self.binaries.iter().flat_map(|b| &b.info.packages) {
+            *sources.entry(source_name(package)).or_default() += 1;
+        }
+        let sources: Vec<(String, usize)> = sources.into_iter().collect();
+        bar_chart(out, "Packages by source", &sources, "#6a1b9a")?;
+        if self.vulnerabilities_checked {
+            let findings: Vec<(String, usize)> = self
+                .binaries
+                .iter()
+                .map(|binary| (binary.name(), binary.findings.len()))
+                .collect();
+            bar_chart(out, "Vulnerabilities per binary", &findings, "#b00020")?;
+        }
+        Ok(())
+    }
+
+    fn write_vulnerabilities(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "<h2>Vulnerabilities</h2>")?;
+        if !self.vulnerabilities_checked {
+            return writeln!(
+                out,
+                "<p>The binaries were not checked against the RustSec advisory database. \
+                Use <code>cargo auditable audit --format html</code> to include vulnerabilities.</p>"
+            );
+        }
+        if self.binaries.iter().all(|b| b.findings.is_empty()) {
+            return writeln!(
+                out,
+                "<p class=\"ok\">No vulnerabilities were found in the RustSec advisory database.</p>"
+            );
+        }
+        writeln!(out, "<table>")?;
+        writeln!(
+            out,
+            "<tr><th>Binary</th><th>Crate</th><th>Version</th><th>Advisory</th><th>Title</th><th>Kind</th></tr>"
+        )?;
+        for binary in &self.binaries {
+            for finding in &binary.findings {
+                let kind = if finding.build_dependency {
+                    "build dependency"
+                } else {
+                    ""
+                };
+                writeln!(
+                    out,
+                    "<tr class=\"vulnerable\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{kind}</td></tr>",
+                    escape(&binary.name()),
+                    escape(&finding.package),
+                    escape(&finding.version),
+                    advisory_link(&finding.advisory_id),
+                    escape(&finding.title),
+                )?;
+            }
+        }
+        writeln!(out, "</table>")
+    }
+
+    fn write_duplicates(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "<h2>Duplicate crates</h2>")?;
+        let rows: Vec<(String, String, Vec<String>)> = self
+            .binaries
+            .iter()
+            .flat_map(|binary| {
+                duplicates(&binary.info)
+                    .into_iter()
+                    .map(move |(name, versions)| (binary.name(), name, versions))
+            })
+            .collect();
+        if rows.is_empty() {
+            return writeln!(
+                out,
+                "<p>No binary contains several versions of the same crate.</p>"
+            );
+        }
+        writeln!(out, "<table>")?;
+        writeln!(
+            out,
+            "<tr><th>Binary</th><th>Crate</th><th>Versions</th></tr>"
+        )?;
+        for (binary, name, versions) in rows {
+            writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape(&binary),
+                escape(&name),
+                escape(&versions.join(", "))
+            )?;
+        }
+        writeln!(out, "</table>")
+    }
+
+    fn write_dependencies(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "<h2>Dependencies</h2>")?;
+        for binary in &self.binaries {
+            writeln!(
+                out,
+                "<details><summary>{} ({} packages)</summary>",
+                escape(&binary.name()),
+                binary.info.packages.len()
+            )?;
+            writeln!(out, "<table>")?;
+            writeln!(
+                out,
+                "<tr><th>Name</th><th>Version</th><th>Kind</th><th>Source</th></tr>"
+            )?;
+            for package in &binary.info.packages {
+                let vulnerable = binary.findings.iter().any(|finding| {
+                    finding.package == package.name
+                        && finding.version == package.version.to_string()
+                });
+                let class = if vulnerable {
+                    " class=\"vulnerable\""
+                } else {
+                    ""
+                };
+                writeln!(
+                    out,
+                    "<tr{class}><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    escape(&package.name),
+                    package.version,
+                    kind_name(package),
+                    escape(&source_name(package)),
+                )?;
+            }
+            writeln!(out, "</table>")?;
+            writeln!(out, "</details>")?;
+        }
+        Ok(())
+    }
+}
+
+fn summary_row(out: &mut impl Write, name: &str, value: &str) -> io::Result<()> {
+    writeln!(out, "<tr><th>{name}</th><td>{value}</td></tr>")
+}
+
+/// Crates that are present in several versions, with the versions sorted
+fn duplicates(info: &VersionInfo) -> Vec<(String, Vec<String>)> {
+    let mut versions: BTreeMap<&str, BTreeSet<&semver::Version>> = BTreeMap::new();
+    for package in &info.packages {
+        versions
+            .entry(package.name.as_str())
+            .or_default()
+            .insert(&package.version);
+    }
+    versions
+        .into_iter()
+        .filter(|(_, versions)| versions.len() > 1)
+        .map(|(name, versions)| {
+            let versions = versions.iter().map(|v| v.to_string()).collect();
+            (name.to_owned(), versions)
+        })
+        .collect()
+}
+
+/// Draws a horizontal bar chart as inline SVG, one bar per entry
+fn bar_chart(
+    out: &mut impl Write,
+    title: &str,
+    bars: &[(String, usize)],
+    color: &str,
+) -> io::Result<()> {
+    writeln!(out, "<h3>{}</h3>", escape(title))?;
+    let max = bars
+        .iter()
+        .map(|(_, value)| *value)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    // leave room for the value to the right of the longest bar
+    let scale = (CHART_WIDTH - LABEL_WIDTH - 60) as f64 / max as f64;
+    let height = bars.len() * (BAR_HEIGHT + 4);
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{CHART_WIDTH}\" height=\"{height}\" role=\"img\" aria-label=\"{}\">",
+        escape(title)
+    )?;
+    for (row, (label, value)) in bars.iter().enumerate() {
+        let y = row * (BAR_HEIGHT + 4);
+        let text_y = y + BAR_HEIGHT - 5;
+        let width = (*value as f64 * scale).round() as usize;
+        writeln!(
+            out,
+            "<text x=\"{}\" y=\"{text_y}\" text-anchor=\"end\" font-size=\"13\">{}</text>",
+            LABEL_WIDTH - 8,
+            escape(&truncate(label, 40))
+        )?;
+        writeln!(
+            out,
+            "<rect x=\"{LABEL_WIDTH}\" y=\"{y}\" width=\"{width}\" height=\"{BAR_HEIGHT}\" fill=\"{color}\"><title>{}: {value}</title></rect>",
+            escape(label)
+        )?;
+        writeln!(
+            out,
+            "<text x=\"{}\" y=\"{text_y}\" font-size=\"13\">{value}</text>",
+            LABEL_WIDTH + width + 6
+        )?;
+    }
+    writeln!(out, "</svg>")
+}
+
+/// Shortens long labels such as paths from the start, since the end is the most telling part
+fn truncate(label: &str, max_chars: usize) -> String {
+    let count = label.chars().count();
+    if count <= max_chars {
+        label.to_owned()
+    } else {
+        let tail: String = label.chars().skip(count - (max_chars - 1)).collect();
+        format!("…{tail}")
+    }
+}
+
+/// Links RustSec advisories to their page on rustsec.org; other IDs are shown as-is
+fn advisory_link(id: &str) -> String {
+    if id.starts_with("RUSTSEC-") {
+        format!(
+            "<a href=\"https://rustsec.org/advisories/{id}.html\">{id}</a>",
+            id = escape(id)
+        )
+    } else {
+        escape(id)
+    }
+}
+
+fn kind_name(package: &Package) -> &'static str {
+    match package.kind {
+        DependencyKind::Runtime => "runtime",
+        DependencyKind::Build => "build",
+        DependencyKind::Development => "development",
+    }
+}
+
+fn source_name(package: &Package) -> String {
+    String::from(package.source.clone())
+}
+
+/// Escapes text for use in HTML element contents and quoted attribute values
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn report(vulnerabilities_checked: bool) -> Report {
+        let info = VersionInfo::from_str(
+            r#"{"packages":[
+            {"name":"adler","version":"0.2.3","source":"crates.io"},
+            {"name":"adler","version":"1.0.2","source":"crates.io"},
+            {"name":"app<script>","version":"0.1.0","source":"local","root":true,"dependencies":[0,1]}
+            ]}"#,
+        )
+        .unwrap();
+        let findings = vec![Finding {
+            package: "adler".to_owned(),
+            version: "0.2.3".to_owned(),
+            advisory_id: "RUSTSEC-2099-0001".to_owned(),
+            title: "Something <bad>".to_owned(),
+            build_dependency: false,
+        }];
+        let mut report = Report::new("Release sign-off", vulnerabilities_checked);
+        report.binaries.push(BinaryReport {
+            paths: vec!["target/release/app".to_owned()],
+            info,
+            findings: if vulnerabilities_checked {
+                findings
+            } else {
+                Vec::new()
+            },
+        });
+        report
+    }
+
+    fn render(report: &Report) -> String {
+        let mut out = Vec::new();
+        report.write(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn sections() {
+        let html = render(&report(true));
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<h2>Vulnerabilities</h2>"));
+        assert!(html.contains("https://rustsec.org/advisories/RUSTSEC-2099-0001.html"));
+        // the duplicate versions of adler are listed together
+        assert!(html.contains("<td>adler</td><td>0.2.3, 1.0.2</td>"));
+        assert!(html.contains("<svg"));
+        // everything taken from the audit data is escaped
+        assert!(html.contains("app&lt;script&gt;"));
+        assert!(html.contains("Something &lt;bad&gt;"));
+        // the report is self-contained
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("<link"));
+    }
+
+    #[test]
+    fn unchecked_vulnerabilities() {
+        let html = render(&report(false));
+        assert!(html.contains("not checked"));
+        assert!(!html.contains("none found"));
+        assert!(!html.contains("Vulnerabilities per binary"));
+    }
+
+    #[test]
+    fn truncation() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("/a/very/long/path/app", 8), "…ath/app");
+    }
+}
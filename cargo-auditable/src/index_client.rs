@@ -0,0 +1,317 @@
+//! Client for the crates.io index shared by the commands that look up crates online,
+//! such as `cargo auditable verify`.
+//!
+//! Index files fetched from the sparse index are cached on disk, so that repeated scans
+//! of many binaries only fetch every crate once in a while. Requests are spaced out to
+//! avoid hammering crates.io, and if the network fails, the cached copies are used instead,
+//! however old they are, so that scans still work on flaky CI runners.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use auditable_info::crates_index::index_file_path;
+
+/// Location of the crates.io sparse index
+const SPARSE_INDEX_URL: &str = "https://index.crates.io";
+
+/// Cached index files younger than this are used without contacting crates.io
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Minimum time between two requests to crates.io
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Where the index files are read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Source {
+    /// A local copy of the index, such as a clone of the git index
+    Local(PathBuf),
+    /// The crates.io sparse index, with the cache in the given directory
+    Sparse { cache_dir: PathBuf },
+}
+
+/// Result of a request to the sparse index
+enum Fetched {
+    Found(String),
+    NotFound,
+    /// The cached copy is still up to date
+    NotModified,
+}
+
+/// Reads index files from a local copy of the index, or from the sparse index through a cache.
+pub struct IndexClient {
+    source: Source,
+    max_age: Duration,
+    /// Only use the cache, set by `--offline` or after the network failed
+    offline: bool,
+    last_request: Option<Instant>,
+}
+
+impl IndexClient {
+    /// Reads the index files from a local copy of the index as-is
+    pub fn local(index: PathBuf) -> Self {
+        IndexClient {
+            source: Source::Local(index),
+            max_age: DEFAULT_MAX_AGE,
+            offline: true,
+            last_request: None,
+        }
+    }
+
+    /// Fetches the index files from the crates.io sparse index, caching them in `cache_dir`.
+    /// If `offline` is set, only the cache is used.
+    pub fn sparse(cache_dir: PathBuf, offline: bool) -> Self {
+        IndexClient {
+            source: Source::Sparse { cache_dir },
+            max_age: DEFAULT_MAX_AGE,
+            offline,
+            last_request: None,
+        }
+    }
+
+    /// Returns the contents of the index file of the crate, or `None` if there is no such crate
+    pub fn index_file(&mut self, crate_name: &str) -> Result<Option<String>, String> {
+        let relative_path = index_file_path(crate_name)
+            .ok_or_else(|| format!("'{crate_name}' is not a valid crate name"))?;
+        let cache_dir = match &self.source {
+            Source::Local(index) => return read_index_file(&index.join(relative_path)),
+            Source::Sparse { cache_dir } => cache_dir.clone(),
+        };
+        let cached_path = cache_dir.join(&relative_path);
+        let cached = read_cached(&cached_path)?;
+        let is_fresh = cached.as_ref().is_some_and(|(_, age)| *age < self.max_age);
+        if let Some((contents, _)) = &cached {
+            if is_fresh || self.offline {
+                return Ok(contents.clone());
+            }
+        } else if self.offline {
+            return Err(format!(
+                "the index file of '{crate_name}' is not cached, and the index cannot be fetched offline"
+            ));
+        }
+
+        self.wait_for_rate_limit();
+        let condition = cached.as_ref().map(|_| cached_path.as_path());
+        match fetch_index_file(&relative_path, condition) {
+            Ok(Fetched::Found(contents)) => {
+                write_cached(&cached_path, Some(&contents))?;
+                Ok(Some(contents))
+            }
+            Ok(Fetched::NotFound) => {
+                write_cached(&cached_path, None)?;
+                Ok(None)
+            }
+            Ok(Fetched::NotModified) => {
+                // Rewriting the file resets its age
+                let contents = cached.and_then(|(contents, _)| contents);
+                write_cached(&cached_path, contents.as_deref())?;
+                Ok(contents)
+            }
+            Err(e) => match cached {
+                Some((contents, _)) => {
+                    eprintln!(
+                        "WARNING: {e}\nFalling back to the cached copy of the crates.io index, \
+                        which may be out of date."
+                    );
+                    // Don't wait for every remaining request to fail the same way
+                    self.offline = true;
+                    Ok(contents)
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    /// Sleeps until enough time has passed since the previous request
+    fn wait_for_rate_limit(&mut self) {
+        if let Some(last_request) = self.last_request {
+            let elapsed = last_request.elapsed();
+            if elapsed < MIN_REQUEST_INTERVAL {
+                thread::sleep(MIN_REQUEST_INTERVAL - elapsed);
+            }
+        }
+        self.last_request = Some(Instant::now());
+    }
+}
+
+/// `$CARGO_HOME/auditable-index-cache`, next to the caches Cargo keeps there
+pub fn default_cache_dir() -> Option<PathBuf> {
+    let cargo_home = env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cargo")))?;
+    Some(cargo_home.join("auditable-index-cache"))
+}
+
+/// Whether Cargo was told not to access the network, via `CARGO_NET_OFFLINE`
+pub fn cargo_offline() -> bool {
+    env::var_os("CARGO_NET_OFFLINE").is_some_and(|value| value == "1" || value == "true")
+}
+
+fn read_index_file(path: &Path) -> Result<Option<String>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Returns the cached index file and its age, if it is cached.
+///
+/// Crates that are not in the index are cached as empty files,
+/// since the index file of a crate that exists is never empty.
+#[allow(clippy::type_complexity)]
+fn read_cached(path: &Path) -> Result<Option<(Option<String>, Duration)>, String> {
+    let contents = match read_index_file(path)? {
+        Some(contents) => contents,
+        None => return Ok(None),
+    };
+    let modified = std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| e.to_string())?;
+    // A modification time in the future means the clock was adjusted; treat the file as stale
+    let age = SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or(Duration::MAX);
+    let contents = if contents.is_empty() {
+        None
+    } else {
+        Some(contents)
+    };
+    Ok(Some((contents, age)))
+}
+
+fn write_cached(path: &Path, contents: Option<&str>) -> Result<(), String> {
+    let write = || -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write to a temporary file first, so that concurrent scans never read a partial file
+        let temp_path = path.with_extension(format!("tmp{}", std::process::id()));
+        std::fs::write(&temp_path, contents.unwrap_or_default())?;
+        std::fs::rename(&temp_path, path)
+    };
+    write().map_err(|e| {
+        format!(
+            "failed to write the index cache at '{}': {e}",
+            path.display()
+        )
+    })
+}
+
+/// Fetches the index file over the sparse protocol by shelling out to `curl`,
+/// which takes care of proxies and TLS configuration for us.
+///
+/// If `cached` is set, the file is only downloaded if it changed since that file was written.
+fn fetch_index_file(relative_path: &str, cached: Option<&Path>) -> Result<Fetched, String> {
+    let url = format!("{SPARSE_INDEX_URL}/{relative_path}");
+    let mut command = Command::new("curl");
+    command
+        .args(["--silent", "--show-error", "--location"])
+        // Retry transient failures, such as HTTP 429 or 503, with exponential backoff
+        .args(["--retry", "3"])
+        // Print the HTTP status code on the last line so that we can tell 404 apart from errors
+        .args(["--write-out", "\n%{http_code}"]);
+    if let Some(cached) = cached {
+        command.arg("--time-cond").arg(cached);
+    }
+    let output = command
+        .arg(&url)
+        .output()
+        .map_err(|e| format!("failed to run curl: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "failed to fetch {url}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    let output = String::from_utf8(output.stdout).map_err(|e| e.to_string())?;
+    parse_curl_output(&url, &output)
+}
+
+/// Interprets the body followed by the HTTP status code on the last line, as printed by `curl`
+fn parse_curl_output(url: &str, output: &str) -> Result<Fetched, String> {
+    let (body, status) = output.rsplit_once('\n').unwrap_or(("", output));
+    match status {
+        "200" => Ok(Fetched::Found(body.to_owned())),
+        "304" => Ok(Fetched::NotModified),
+        "404" => Ok(Fetched::NotFound),
+        // Anything else, including a 403 from a misbehaving CDN or proxy, says nothing
+        // about whether the crate exists, so it must not be cached as missing
+        other => Err(format!("failed to fetch {url}: HTTP status {other}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offline_cache() {
+        let cache_dir = env::temp_dir().join(format!(
+            "cargo-auditable-index-cache-{}",
+            std::process::id()
+        ));
+        let mut client = IndexClient::sparse(cache_dir.clone(), true);
+        // nothing is cached yet, and the network must not be used
+        assert!(client.index_file("adler").is_err());
+
+        write_cached(
+            &cache_dir.join(index_file_path("adler").unwrap()),
+            Some("{}\n"),
+        )
+        .unwrap();
+        write_cached(&cache_dir.join(index_file_path("missing").unwrap()), None).unwrap();
+        assert_eq!(client.index_file("adler").unwrap().as_deref(), Some("{}\n"));
+        assert_eq!(client.index_file("missing").unwrap(), None);
+
+        // fresh entries are used without going online either
+        let mut client = IndexClient::sparse(cache_dir.clone(), false);
+        assert_eq!(client.index_file("adler").unwrap().as_deref(), Some("{}\n"));
+        assert!(client.last_request.is_none());
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[test]
+    fn http_status() {
+        let url = "https://index.crates.io/ad/le/adler";
+        assert!(matches!(
+            parse_curl_output(url, "{}\n\n200"),
+            Ok(Fetched::Found(body)) if body == "{}\n"
+        ));
+        assert!(matches!(
+            parse_curl_output(url, "\n304"),
+            Ok(Fetched::NotModified)
+        ));
+        assert!(matches!(
+            parse_curl_output(url, "\n404"),
+            Ok(Fetched::NotFound)
+        ));
+        // only a 404 means that the crate does not exist
+        assert!(parse_curl_output(url, "Forbidden\n403").is_err());
+        assert!(parse_curl_output(url, "\n503").is_err());
+    }
+
+    #[test]
+    fn local_index() {
+        let mut client = IndexClient::local(PathBuf::from("/nonexistent"));
+        assert_eq!(client.index_file("adler").unwrap(), None);
+    }
+
+    #[test]
+    fn traversal_is_refused() {
+        let cache_dir = env::temp_dir().join(format!(
+            "cargo-auditable-index-traversal-{}",
+            std::process::id()
+        ));
+        let name = "../../../../outside";
+        let mut client = IndexClient::sparse(cache_dir.clone(), true);
+        assert!(client.index_file(name).is_err());
+        let mut client = IndexClient::local(cache_dir.clone());
+        assert!(client.index_file(name).is_err());
+        assert!(!cache_dir.exists());
+    }
+}
@@ -0,0 +1,571 @@
+//! Implements `cargo auditable inventory`, which flattens the audit data of many binaries
+//! into relational tables, for loading into SQL databases and analytics stores
+//! that power organization-wide dependency dashboards.
+
+use std::{
+    collections::HashMap,
+    env,
+    ffi::OsString,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    process::exit,
+    str::FromStr,
+};
+
+use auditable_info::Limits;
+use auditable_serde::VersionInfo;
+use serde_json::{json, Value};
+
+use crate::{
+    binary_identity::{sha256_hex, BinaryIdentity},
+    build_id_index,
+    html_report::{BinaryReport, Report},
+    signing::SigningKey,
+    snapshot::{crate_versions, BinarySnapshot, Change, CrateVersions, Snapshot},
+};
+
+const USAGE: &str = "\
+Usage: cargo auditable inventory [OPTIONS] BINARY...
+
+Exports the audit data of each BINARY as flat tables, for loading into
+SQL databases and analytics stores:
+
+    binaries    one row per unique binary, with its size, ELF build ID
+                and Mach-O UUID or PDB GUID where available
+    paths       the paths each binary was found at
+    packages    one row per package recorded in a binary
+    edges       one row per dependency between packages in a binary
+
+Binaries are identified by the SHA-256 hash of the file, and packages by
+that hash together with their index in the audit data. The same binary
+always gets the same identifiers, no matter where or when it is exported.
+Identical files found at several paths are only parsed once.
+
+Binaries without audit data are reported and skipped.
+
+Options:
+    --format FORMAT           Table format: csv (default) or ndjson. html writes
+                              a standalone report.html with the dependencies,
+                              duplicate crates and charts instead of the tables;
+                              use `cargo auditable audit --format html` to also
+                              include vulnerabilities
+    --output-dir DIR          Directory to write the tables to [default: .]
+    --build-id-index DIR      Also record the audit data of each ELF binary in DIR
+                              by its build ID, for `cargo auditable lookup-build-id`
+    --snapshot FILE           Also write the crate versions in each binary to FILE,
+                              for use as the --baseline of a later scan
+    --baseline FILE           Instead of the tables above, only write a `changes` table
+                              listing what changed since the snapshot in FILE:
+                              new and removed binaries, binaries that lost or gained
+                              audit data, and added, removed, upgraded or downgraded crates
+    --signing-key FILE        Sign every table and the snapshot with the key in FILE,
+                              writing a detached JWS (HS256) next to each of them
+                              as FILE.jws. The key must be at least 32 bytes long.
+    --key-id ID               Record ID as the `kid` of the signatures, so that the
+                              receiving system can tell which key to verify them with
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Csv,
+    Ndjson,
+    Html,
+}
+
+impl Format {
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Ndjson => "ndjson",
+            Format::Html => "html",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "ndjson" => Ok(Format::Ndjson),
+            "html" => Ok(Format::Html),
+            other => Err(format!(
+                "unknown format '{other}', expected 'csv', 'ndjson' or 'html'"
+            )),
+        }
+    }
+}
+
+/// Includes only the arguments specific to `cargo auditable inventory`
+struct InventoryArgs {
+    binaries: Vec<PathBuf>,
+    format: Format,
+    output_dir: PathBuf,
+    build_id_index: Option<PathBuf>,
+    snapshot: Option<PathBuf>,
+    baseline: Option<PathBuf>,
+    signing_key: Option<PathBuf>,
+    key_id: Option<String>,
+}
+
+impl InventoryArgs {
+    fn from_args() -> Result<Self, pico_args::Error> {
+        // we .skip(3) to get over `cargo auditable inventory` and to the start of the flags
+        let raw_args: Vec<OsString> = env::args_os().skip(3).collect();
+        Self::from_args_vec(raw_args)
+    }
+
+    /// Split into its own function for unit testing
+    fn from_args_vec(raw_args: Vec<OsString>) -> Result<Self, pico_args::Error> {
+        let mut parser = pico_args::Arguments::from_vec(raw_args);
+        let format = parser
+            .opt_value_from_str("--format")?
+            .unwrap_or(Format::Csv);
+        let output_dir = parser
+            .opt_value_from_os_str("--output-dir", |s| {
+                Ok::<PathBuf, pico_args::Error>(PathBuf::from(s))
+            })?
+            .unwrap_or_else(|| PathBuf::from("."));
+        let build_id_index = parser.opt_value_from_os_str("--build-id-index", |s| {
+            Ok::<PathBuf, pico_args::Error>(PathBuf::from(s))
+        })?;
+        let snapshot = parser.opt_value_from_os_str("--snapshot", |s| {
+            Ok::<PathBuf, pico_args::Error>(PathBuf::from(s))
+        })?;
+        let baseline = parser.opt_value_from_os_str("--baseline", |s| {
+            Ok::<PathBuf, pico_args::Error>(PathBuf::from(s))
+        })?;
+        let signing_key = parser.opt_value_from_os_str("--signing-key", |s| {
+            Ok::<PathBuf, pico_args::Error>(PathBuf::from(s))
+        })?;
+        let key_id = parser.opt_value_from_str("--key-id")?;
+        let binaries: Vec<PathBuf> = parser.finish().into_iter().map(PathBuf::from).collect();
+        if binaries.is_empty() {
+            return Err(pico_args::Error::MissingArgument);
+        }
+        if format == Format::Html && baseline.is_some() {
+            return Err(pico_args::Error::ArgumentParsingFailed {
+                cause: "--baseline only writes a changes table, which cannot be written as html"
+                    .to_owned(),
+            });
+        }
+        Ok(InventoryArgs {
+            binaries,
+            format,
+            output_dir,
+            build_id_index,
+            snapshot,
+            baseline,
+            signing_key,
+            key_id,
+        })
+    }
+}
+
+pub fn main() {
+    if env::args_os()
+        .skip(3)
+        .any(|arg| arg == "-h" || arg == "--help")
+    {
+        print!("{USAGE}");
+        return;
+    }
+    let args = InventoryArgs::from_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}");
+        exit(2);
+    });
+
+    // Load the key and the baseline up front so that a typo in the path doesn't waste a whole scan
+    let signing_key = args.signing_key.as_ref().map(|path| {
+        SigningKey::from_file(path, args.key_id.clone()).unwrap_or_else(|e| {
+            eprintln!("ERROR: {e}");
+            exit(1);
+        })
+    });
+    let baseline = args.baseline.as_ref().map(|path| {
+        read_snapshot(path).unwrap_or_else(|e| {
+            eprintln!("ERROR: failed to read baseline '{}': {e}", path.display());
+            exit(1);
+        })
+    });
+
+    let limits = Limits::default();
+    let mut inventory = Inventory::new();
+    let mut snapshot = Snapshot::default();
+    // Fleets often deploy the same binary at many paths, so we only parse each unique file once.
+    // Maps the SHA-256 of every file seen so far to the crate versions in it,
+    // or `None` if it had no audit data.
+    let mut scanned: HashMap<String, Option<CrateVersions>> = HashMap::new();
+    // Only filled in for `--format html`, with the position of each binary by its SHA-256
+    let mut report = Report::new("cargo auditable inventory", false);
+    let mut reported: HashMap<String, usize> = HashMap::new();
+    for binary in &args.binaries {
+        let path = binary.display().to_string();
+        let contents = match read_file(binary, limits) {
+            Ok(contents) => contents,
+            Err(e) => {
+                eprintln!("WARNING: skipping '{path}': failed to read audit data: {e}");
+                continue;
+            }
+        };
+        let sha256 = sha256_hex(&contents);
+        if let Some(crates) = scanned.get(&sha256) {
+            if crates.is_some() {
+                inventory.add_path(&sha256, &path);
+                if let Some(&position) = reported.get(&sha256) {
+                    report.binaries[position].paths.push(path.clone());
+                }
+            } else {
+                eprintln!("WARNING: skipping '{path}': identical to a file without audit data");
+            }
+            let entry = BinarySnapshot {
+                sha256,
+                crates: crates.clone(),
+            };
+            snapshot.binaries.insert(path, entry);
+            continue;
+        }
+        let info = auditable_info::audit_info_from_slice(&contents, limits.decompressed_json_size);
+        let crates = info.as_ref().ok().map(crate_versions);
+        scanned.insert(sha256.clone(), crates.clone());
+        let entry = BinarySnapshot {
+            sha256: sha256.clone(),
+            crates,
+        };
+        snapshot.binaries.insert(path.clone(), entry);
+        let info = match info {
+            Ok(info) => info,
+            Err(e) => {
+                eprintln!("WARNING: skipping '{path}': failed to read audit data: {e}");
+                continue;
+            }
+        };
+        let identity = BinaryIdentity::from_contents(&contents, sha256);
+        inventory.add_binary(&identity, &info);
+        inventory.add_path(&identity.sha256, &path);
+        if args.format == Format::Html {
+            reported.insert(identity.sha256.clone(), report.binaries.len());
+            report.binaries.push(BinaryReport {
+                paths: vec![path.clone()],
+                info: info.clone(),
+                findings: Vec::new(),
+            });
+        }
+        if let (Some(index), Some(build_id)) = (&args.build_id_index, &identity.build_id) {
+            if let Err(e) = build_id_index::write_entry(index, build_id, &info) {
+                eprintln!(
+                    "ERROR: failed to write build ID index at '{}': {e}",
+                    index.display()
+                );
+                exit(1);
+            }
+        }
+    }
+    if let Some(path) = &args.snapshot {
+        let contents = serde_json::to_vec(&snapshot).unwrap();
+        if let Err(e) = write_report(path, &contents, signing_key.as_ref()) {
+            eprintln!("ERROR: failed to write snapshot '{}': {e}", path.display());
+            exit(1);
+        }
+    }
+    if args.format == Format::Html {
+        let path = args.output_dir.join("report.html");
+        let mut contents = Vec::new();
+        report.write(&mut contents).unwrap();
+        if let Err(e) = write_report(&path, &contents, signing_key.as_ref()) {
+            eprintln!("ERROR: failed to write '{}': {e}", path.display());
+            exit(1);
+        }
+        return;
+    }
+    let tables = match &baseline {
+        Some(baseline) => vec![changes_table(&snapshot.changes_since(baseline))],
+        None => inventory.into_tables(),
+    };
+    for table in &tables {
+        let path = args
+            .output_dir
+            .join(format!("{}.{}", table.name, args.format.extension()));
+        let mut contents = Vec::new();
+        table.write(args.format, &mut contents).unwrap();
+        if let Err(e) = write_report(&path, &contents, signing_key.as_ref()) {
+            eprintln!("ERROR: failed to write '{}': {e}", path.display());
+            exit(1);
+        }
+    }
+}
+
+/// A table with a fixed set of columns
+struct Table {
+    name: &'static str,
+    columns: &'static [&'static str],
+    rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    fn new(name: &'static str, columns: &'static [&'static str]) -> Self {
+        Table {
+            name,
+            columns,
+            rows: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, row: Vec<Value>) {
+        debug_assert_eq!(row.len(), self.columns.len());
+        self.rows.push(row);
+    }
+
+    fn write(&self, format: Format, out: &mut impl Write) -> std::io::Result<()> {
+        match format {
+            Format::Csv => {
+                writeln!(out, "{}", self.columns.join(","))?;
+                for row in &self.rows {
+                    let fields: Vec<String> = row.iter().map(csv_field).collect();
+                    writeln!(out, "{}", fields.join(","))?;
+                }
+            }
+            Format::Html => unreachable!("tables are not written as html"),
+            Format::Ndjson => {
+                for row in &self.rows {
+                    let object: serde_json::Map<String, Value> = self
+                        .columns
+                        .iter()
+                        .map(|column| column.to_string())
+                        .zip(row.iter().cloned())
+                        .collect();
+                    writeln!(out, "{}", Value::Object(object))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Formats a value as a CSV field as described in RFC 4180.
+/// Nulls are represented by empty fields.
+fn csv_field(value: &Value) -> String {
+    let text = match value {
+        Value::Null => return String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    auditable_serde::report::csv_field(&text)
+}
+
+fn read_snapshot(path: &Path) -> Result<Snapshot, Box<dyn std::error::Error>> {
+    let file = std::io::BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(file)?)
+}
+
+/// Writes a table or a snapshot, along with its detached signature if a key was provided
+fn write_report(path: &Path, contents: &[u8], key: Option<&SigningKey>) -> std::io::Result<()> {
+    std::fs::write(path, contents)?;
+    if let Some(key) = key {
+        let mut signature_path = path.as_os_str().to_owned();
+        signature_path.push(".jws");
+        std::fs::write(signature_path, key.detached_jws(contents))?;
+    }
+    Ok(())
+}
+
+fn changes_table(changes: &[Change]) -> Table {
+    let mut table = Table::new(
+        "changes",
+        &["path", "change", "name", "old_version", "new_version"],
+    );
+    for change in changes {
+        table.push(vec![
+            json!(change.path),
+            json!(change.kind.as_str()),
+            json!(change.name),
+            json!(change.old_version.as_ref().map(|v| v.to_string())),
+            json!(change.new_version.as_ref().map(|v| v.to_string())),
+        ]);
+    }
+    table
+}
+
+fn read_file(path: &Path, limits: Limits) -> Result<Vec<u8>, auditable_info::Error> {
+    if std::fs::metadata(path)?.len() > limits.input_file_size as u64 {
+        return Err(auditable_info::Error::InputLimitExceeded);
+    }
+    Ok(std::fs::read(path)?)
+}
+
+/// The tables exported by `cargo auditable inventory`
+struct Inventory {
+    binaries: Table,
+    paths: Table,
+    packages: Table,
+    edges: Table,
+}
+
+impl Inventory {
+    fn new() -> Self {
+        Inventory {
+            binaries: Table::new("binaries", &["binary_sha256", "size", "build_id", "uuid"]),
+            paths: Table::new("paths", &["binary_sha256", "path"]),
+            packages: Table::new(
+                "packages",
+                &[
+                    "binary_sha256",
+                    "package_index",
+                    "name",
+                    "version",
+                    "source",
+                    "kind",
+                    "root",
+                    "has_build_script",
+                    "checksum",
+                ],
+            ),
+            edges: Table::new(
+                "edges",
+                &["binary_sha256", "from_index", "to_index", "kind"],
+            ),
+        }
+    }
+
+    /// Records a path the binary with the given hash was found at
+    fn add_path(&mut self, sha256: &str, path: &str) {
+        self.paths.push(vec![json!(sha256), json!(path)]);
+    }
+
+    fn add_binary(&mut self, identity: &BinaryIdentity, info: &VersionInfo) {
+        let sha256 = &identity.sha256;
+        self.binaries.push(vec![
+            json!(sha256),
+            json!(identity.size),
+            json!(identity.build_id),
+            json!(identity.uuid),
+        ]);
+        for (index, package) in info.packages.iter().enumerate() {
+            self.packages.push(vec![
+                json!(sha256),
+                json!(index),
+                json!(package.name),
+                json!(package.version.to_string()),
+                json!(String::from(package.source.clone())),
+                json!(package.kind),
+                json!(package.root),
+                json!(package.has_build_script),
+                json!(package.checksum),
+            ]);
+            for (edge, dependency) in package.dependencies.iter().enumerate() {
+                // the kind of each edge is only known if it was requested at build time
+                let kind = package.dependency_kinds.get(edge);
+                self.edges.push(vec![
+                    json!(sha256),
+                    json!(index),
+                    json!(dependency),
+                    json!(kind),
+                ]);
+            }
+        }
+    }
+
+    fn into_tables(self) -> Vec<Table> {
+        vec![self.binaries, self.paths, self.packages, self.edges]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic_parsing() {
+        let raw_args = ["--format", "ndjson", "--output-dir", "out", "app"];
+        let args =
+            InventoryArgs::from_args_vec(raw_args.iter().map(OsString::from).collect()).unwrap();
+        assert_eq!(args.format, Format::Ndjson);
+        assert_eq!(args.output_dir, PathBuf::from("out"));
+        assert_eq!(args.binaries, vec![PathBuf::from("app")]);
+        assert_eq!(args.baseline, None);
+
+        let raw_args = ["--baseline", "prev.json", "--snapshot", "next.json", "app"];
+        let args =
+            InventoryArgs::from_args_vec(raw_args.iter().map(OsString::from).collect()).unwrap();
+        assert_eq!(args.baseline, Some(PathBuf::from("prev.json")));
+        assert_eq!(args.snapshot, Some(PathBuf::from("next.json")));
+
+        let raw_args = ["--signing-key", "key.bin", "--key-id", "host-1", "app"];
+        let args =
+            InventoryArgs::from_args_vec(raw_args.iter().map(OsString::from).collect()).unwrap();
+        assert_eq!(args.signing_key, Some(PathBuf::from("key.bin")));
+        assert_eq!(args.key_id.as_deref(), Some("host-1"));
+
+        let raw_args = ["--format", "html", "app"];
+        let args =
+            InventoryArgs::from_args_vec(raw_args.iter().map(OsString::from).collect()).unwrap();
+        assert_eq!(args.format, Format::Html);
+        // the changes table has no html form
+        let raw_args = ["--format", "html", "--baseline", "prev.json", "app"];
+        assert!(
+            InventoryArgs::from_args_vec(raw_args.iter().map(OsString::from).collect()).is_err()
+        );
+    }
+
+    #[test]
+    fn csv_quoting() {
+        assert_eq!(csv_field(&json!("plain")), "plain");
+        assert_eq!(csv_field(&json!("a,b")), "\"a,b\"");
+        assert_eq!(csv_field(&json!("say \"hi\"")), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field(&json!(null)), "");
+        assert_eq!(csv_field(&json!(true)), "true");
+    }
+
+    #[test]
+    fn tables() {
+        let info = VersionInfo::from_str(
+            r#"{"packages":[
+            {"name":"adler","version":"1.0.2","source":"crates.io"},
+            {"name":"hello","version":"0.1.0","source":"local","root":true,"dependencies":[0]}
+            ]}"#,
+        )
+        .unwrap();
+        let identity = BinaryIdentity {
+            sha256: "abc".to_owned(),
+            size: 1024,
+            build_id: Some("0123".to_owned()),
+            uuid: None,
+        };
+        let mut inventory = Inventory::new();
+        inventory.add_binary(&identity, &info);
+        inventory.add_path("abc", "bin/hello");
+        inventory.add_path("abc", "opt/hello");
+
+        let mut csv = Vec::new();
+        inventory.binaries.write(Format::Csv, &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "binary_sha256,size,build_id,uuid\nabc,1024,0123,\n"
+        );
+
+        let mut csv = Vec::new();
+        inventory.paths.write(Format::Csv, &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "binary_sha256,path\nabc,bin/hello\nabc,opt/hello\n"
+        );
+
+        let mut csv = Vec::new();
+        inventory.packages.write(Format::Csv, &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "binary_sha256,package_index,name,version,source,kind,root,has_build_script,checksum\n\
+            abc,0,adler,1.0.2,crates.io,runtime,false,false,\n\
+            abc,1,hello,0.1.0,local,runtime,true,false,\n"
+        );
+
+        let mut ndjson = Vec::new();
+        inventory.edges.write(Format::Ndjson, &mut ndjson).unwrap();
+        let row: Value = serde_json::from_slice(&ndjson).unwrap();
+        assert_eq!(
+            row,
+            json!({"binary_sha256": "abc", "from_index": 1, "to_index": 0, "kind": null})
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! Records the checksums of registry packages from `Cargo.lock`,
+//! which `cargo metadata` does not expose.
+
+use auditable_serde::{Source, VersionInfo};
+use cargo_lock::Lockfile;
+use cargo_metadata::Metadata;
+use std::collections::BTreeMap;
+
+/// Fills in [`auditable_serde::Package::checksum`] for every package in `info`
+/// that has a checksum in the `Cargo.lock` of the workspace.
+///
+/// A missing or unreadable `Cargo.lock` only means that no checksums are recorded,
+/// so it is reported as a warning rather than failing the build.
+pub fn record_checksums(metadata: &Metadata, info: &mut VersionInfo) {
+    let path = metadata.workspace_root.join("Cargo.lock");
+    let lockfile = match Lockfile::load(&path) {
+        Ok(lockfile) => lockfile,
+        Err(e) => {
+            eprintln!(
+                "WARNING: could not read {path}: {e}\n\
+                Checksums of the dependencies will not be recorded."
+            );
+            return;
+        }
+    };
+    let checksums = checksums_by_package(&lockfile);
+    for package in &mut info.packages {
+        let key = (
+            package.name.clone(),
+            package.version.to_string(),
+            package.source.without_urls(),
+        );
+        if let Some(checksum) = checksums.get(&key) {
+            package.checksum = Some(checksum.clone());
+        }
+    }
+}
+
+fn checksums_by_package(lockfile: &Lockfile) -> BTreeMap<(String, String, Source), String> {
+    let mut checksums = BTreeMap::new();
+    for package in &lockfile.packages {
+        // Only packages from registries have checksums
+        let (source, checksum) = match (&package.source, &package.checksum) {
+            (Some(source), Some(checksum)) => (source, checksum),
+            _ => continue,
+        };
+        // `Cargo.lock` and `cargo metadata` share the encoding of sources
+        let source = Source::from(&cargo_metadata::Source {
+            repr: source.to_string(),
+        })
+        .without_urls();
+        checksums.insert(
+            (
+                package.name.as_str().to_owned(),
+                package.version.to_string(),
+                source,
+            ),
+            checksum.to_string(),
+        );
+    }
+    checksums
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn checksums_from_lockfile() {
+        let lockfile = Lockfile::from_str(
+            r#"version = 3
+
+[[package]]
+name = "adler"
+version = "1.0.2"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "f26201604c87b1e01bd3d98f8d5d9a8fcbb815e8cedb41ffccbeb4bf593a35fe"
+
+[[package]]
+name = "foo"
+version = "0.1.0"
+dependencies = [
+ "adler",
+]
+"#,
+        )
+        .unwrap();
+        let checksums = checksums_by_package(&lockfile);
+        assert_eq!(checksums.len(), 1);
+        let key = ("adler".to_owned(), "1.0.2".to_owned(), Source::CratesIo);
+        assert_eq!(
+            checksums[&key],
+            "f26201604c87b1e01bd3d98f8d5d9a8fcbb815e8cedb41ffccbeb4bf593a35fe"
+        );
+    }
+}
@@ -1,12 +1,34 @@
 #![forbid(unsafe_code)]
 
+mod attach;
+mod audit;
+mod binary_identity;
+mod build_id_index;
 mod cargo_arguments;
 mod cargo_auditable;
+mod check_dev_deps;
 mod collect_audit_data;
+mod compression;
+mod emit_object;
+mod extract;
+mod html_report;
+mod index_client;
+mod inventory;
+mod lockfile;
+mod non_registry;
 mod object_file;
+mod package_args;
+mod payload_store;
 mod rustc_arguments;
 mod rustc_wrapper;
+mod signing;
+mod size_budget;
+mod size_report;
+mod snapshot;
 mod target_info;
+mod test_vectors;
+mod vendored_sources;
+mod verify;
 
 use std::process::exit;
 
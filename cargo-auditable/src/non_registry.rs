@@ -0,0 +1,182 @@
+//! Implements `cargo auditable non-registry`, which lists the dependencies of binaries
+//! that were not downloaded from a registry: git repositories and local paths.
+//! Those bypass the review and immutability guarantees of a registry,
+//! so they are usually the first thing to look at in a supply chain review.
+
+use std::{env, ffi::OsString, path::PathBuf, process::exit};
+
+use auditable_info::Limits;
+use auditable_serde::{Package, Source};
+
+const USAGE: &str = "\
+Usage: cargo auditable non-registry [OPTIONS] BINARY...
+
+Lists the dependencies recorded in each BINARY that come from git repositories
+or local paths rather than from a registry. The root package of each binary
+is not listed.
+
+Options:
+    --json                    Print the report as JSON
+    --deny                    Exit with an error if any such dependencies are found
+";
+
+/// Includes only the arguments specific to `cargo auditable non-registry`
+struct NonRegistryArgs {
+    binaries: Vec<PathBuf>,
+    json: bool,
+    deny: bool,
+}
+
+impl NonRegistryArgs {
+    fn from_args() -> Result<Self, pico_args::Error> {
+        // we .skip(3) to get over `cargo auditable non-registry` and to the start of the flags
+        let raw_args: Vec<OsString> = env::args_os().skip(3).collect();
+        Self::from_args_vec(raw_args)
+    }
+
+    /// Split into its own function for unit testing
+    fn from_args_vec(raw_args: Vec<OsString>) -> Result<Self, pico_args::Error> {
+        let mut parser = pico_args::Arguments::from_vec(raw_args);
+        let json = parser.contains("--json");
+        let deny = parser.contains("--deny");
+        let binaries: Vec<PathBuf> = parser.finish().into_iter().map(PathBuf::from).collect();
+        if binaries.is_empty() {
+            return Err(pico_args::Error::MissingArgument);
+        }
+        Ok(NonRegistryArgs {
+            binaries,
+            json,
+            deny,
+        })
+    }
+}
+
+pub fn main() {
+    if env::args_os()
+        .skip(3)
+        .any(|arg| arg == "-h" || arg == "--help")
+    {
+        print!("{USAGE}");
+        return;
+    }
+    let args = NonRegistryArgs::from_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}");
+        exit(2);
+    });
+
+    let mut found = false;
+    let mut json_report = Vec::new();
+    for binary in &args.binaries {
+        let info =
+            auditable_info::audit_info_from_file(binary, Limits::default()).unwrap_or_else(|e| {
+                eprintln!(
+                    "ERROR: failed to read audit data from '{}': {e}",
+                    binary.display()
+                );
+                exit(1);
+            });
+        for package in info.packages.iter().filter(|p| is_non_registry(p)) {
+            found = true;
+            if args.json {
+                json_report.push(serde_json::json!({
+                    "binary": binary.display().to_string(),
+                    "name": package.name,
+                    "version": package.version,
+                    "source": package.source,
+                }));
+            } else {
+                println!(
+                    "{}: {} {} ({})",
+                    binary.display(),
+                    package.name,
+                    package.version,
+                    describe_source(&package.source)
+                );
+            }
+        }
+    }
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&json_report).unwrap());
+    }
+    if found && args.deny {
+        exit(1);
+    }
+}
+
+/// Returns `true` for dependencies from git repositories and local paths,
+/// including other members of the workspace, except for the root package, which is the binary itself
+fn is_non_registry(package: &Package) -> bool {
+    !package.root
+        && matches!(
+            package.source,
+            Source::Git(_) | Source::Local(_) | Source::Workspace(_)
+        )
+}
+
+fn describe_source(source: &Source) -> String {
+    match source {
+        Source::Git(git) => {
+            let revision = match &git.rev {
+                Some(rev) => format!("revision {rev}"),
+                None => "unknown revision".to_owned(),
+            };
+            match &git.url {
+                Some(url) => format!("git, {url}, {revision}"),
+                None => format!("git, {revision}"),
+            }
+        }
+        Source::Workspace(workspace) => match &workspace.path {
+            Some(path) => format!("workspace member at {path}"),
+            None => "workspace member".to_owned(),
+        },
+        // Workspace members were recorded with a path but no source of their own before
+        Source::Local(local) => match &local.path {
+            Some(path) => format!("workspace member at {path}"),
+            None => "local path".to_owned(),
+        },
+        other => String::from(other.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use auditable_serde::VersionInfo;
+    use std::str::FromStr;
+
+    #[test]
+    fn filtering() {
+        let info = VersionInfo::from_str(
+            r#"{"packages":[
+            {"name":"adler","version":"1.0.2","source":"crates.io"},
+            {"name":"forked","version":"0.1.0","source":{"kind":"git","rev":"abc"}},
+            {"name":"helper","version":"0.1.0","source":"local"},
+            {"name":"common","version":"0.1.0","source":{"kind":"workspace","path":"crates/common"}},
+            {"name":"hello","version":"0.1.0","source":{"kind":"workspace","path":"."},"root":true,"dependencies":[0,1,2,3]}
+            ]}"#,
+        )
+        .unwrap();
+        let names: Vec<&str> = info
+            .packages
+            .iter()
+            .filter(|p| is_non_registry(p))
+            .map(|p| p.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["forked", "helper", "common"]);
+        assert_eq!(
+            describe_source(&info.packages[1].source),
+            "git, revision abc"
+        );
+        assert_eq!(describe_source(&info.packages[2].source), "local path");
+        assert_eq!(
+            describe_source(&info.packages[3].source),
+            "workspace member at crates/common"
+        );
+    }
+
+    #[test]
+    fn binary_is_required() {
+        let args: Vec<OsString> = vec!["--json".into()];
+        assert!(NonRegistryArgs::from_args_vec(args).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+//! Flags selecting the package and configuration to collect the audit data for,
+//! shared by the `cargo auditable` subcommands that run outside of a build.
+
+use std::{ffi::OsStr, path::PathBuf};
+
+use cargo_metadata::{CargoOpt, Metadata, MetadataCommand};
+
+use crate::{cargo_arguments::CargoArgs, collect_audit_data, target_info};
+
+/// Help text for the flags parsed by [`PackageArgs`], to be included in the usage of subcommands
+pub const PACKAGE_ARGS_USAGE: &str = "\
+    --target TRIPLE           Target to collect the audit data for [default: host]
+    --manifest-path PATH      Path to Cargo.toml
+    --features FEATURES       Space or comma separated list of features to activate
+    --all-features            Activate all available features
+    --no-default-features     Do not activate the `default` feature
+    --offline, --locked, --frozen, --config KEY=VALUE
+                              Passed on to `cargo metadata`
+";
+
+pub struct PackageArgs {
+    pub target: Option<String>,
+    pub manifest_path: Option<PathBuf>,
+    pub features: Vec<String>,
+    pub all_features: bool,
+    pub no_default_features: bool,
+}
+
+impl PackageArgs {
+    pub fn from_parser(parser: &mut pico_args::Arguments) -> Result<Self, pico_args::Error> {
+        let features: Vec<String> = parser.values_from_str("--features")?;
+        Ok(PackageArgs {
+            target: parser.opt_value_from_str("--target")?,
+            manifest_path: parser.opt_value_from_os_str("--manifest-path", |s| {
+                Ok::<PathBuf, pico_args::Error>(PathBuf::from(s))
+            })?,
+            // Cargo accepts both `--features a,b` and `--features "a b"`
+            features: features
+                .iter()
+                .flat_map(|f| f.split(|c: char| c == ',' || c.is_whitespace()))
+                .filter(|f| !f.is_empty())
+                .map(|f| f.to_owned())
+                .collect(),
+            all_features: parser.contains("--all-features"),
+            no_default_features: parser.contains("--no-default-features"),
+        })
+    }
+
+    /// Returns the target passed on the command line, or the host target if none was specified
+    pub fn target_triple(&self, rustc_path: &OsStr) -> String {
+        self.target
+            .clone()
+            .unwrap_or_else(|| target_info::rustc_host_target_triple(rustc_path))
+    }
+
+    /// Runs `cargo metadata` for the selected package and configuration
+    pub fn metadata(&self, target_triple: &str, cargo_args: &CargoArgs) -> Metadata {
+        let mut metadata_command = MetadataCommand::new();
+        if let Some(path) = &self.manifest_path {
+            metadata_command.manifest_path(path);
+        }
+        if self.all_features {
+            metadata_command.features(CargoOpt::AllFeatures);
+        }
+        if self.no_default_features {
+            metadata_command.features(CargoOpt::NoDefaultFeatures);
+        }
+        if !self.features.is_empty() {
+            metadata_command.features(CargoOpt::SomeFeatures(self.features.clone()));
+        }
+        collect_audit_data::execute_metadata_command(metadata_command, target_triple, cargo_args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsString;
+
+    #[test]
+    fn feature_splitting() {
+        let raw_args = ["--features", "foo,bar baz", "--features", "quux"];
+        let mut parser =
+            pico_args::Arguments::from_vec(raw_args.iter().map(OsString::from).collect());
+        let args = PackageArgs::from_parser(&mut parser).unwrap();
+        assert_eq!(args.features, vec!["foo", "bar", "baz", "quux"]);
+        assert!(!args.all_features);
+        assert!(args.target.is_none());
+    }
+}
@@ -0,0 +1,75 @@
+//! Writes the audit data to a content-addressed store and embeds only a pointer to it,
+//! for binaries with extremely tight size budgets.
+//!
+//! Enabled by setting the `CARGO_AUDITABLE_STORE` environment variable to the path of the store.
+//! The URL the store will be published at can be recorded in the binary
+//! via the `CARGO_AUDITABLE_STORE_URL` environment variable.
+
+use std::{env, path::Path};
+
+use auditable_extract::POINTER_MAGIC;
+use sha2::{Digest, Sha256};
+
+/// Returns the pointer to embed instead of the audit data if a store is configured,
+/// otherwise returns the audit data unchanged
+pub fn detach_if_requested(compressed_audit_data: Vec<u8>) -> Vec<u8> {
+    match env::var_os("CARGO_AUDITABLE_STORE") {
+        Some(store) => {
+            let url = env::var("CARGO_AUDITABLE_STORE_URL").ok();
+            detach(Path::new(&store), &compressed_audit_data, url.as_deref())
+        }
+        None => compressed_audit_data,
+    }
+}
+
+fn detach(store: &Path, compressed_audit_data: &[u8], url: Option<&str>) -> Vec<u8> {
+    let digest = format!("{:x}", Sha256::digest(compressed_audit_data));
+    // Same layout as the blobs in an OCI image layout, so the store can be pushed to a registry as-is
+    let blob_dir = store.join("blobs").join("sha256");
+    std::fs::create_dir_all(&blob_dir).unwrap_or_else(|e| {
+        panic!(
+            "Failed to create audit data store at {}: {e}",
+            store.display()
+        )
+    });
+    // Several binaries may be built in parallel, so write to a temporary file and rename it.
+    // The name is derived from the contents, so if it already exists it's identical.
+    let blob_path = blob_dir.join(&digest);
+    if !blob_path.exists() {
+        let temp_path = blob_dir.join(format!("{digest}.{}.tmp", std::process::id()));
+        std::fs::write(&temp_path, compressed_audit_data).expect("Unable to write audit data");
+        std::fs::rename(&temp_path, &blob_path).expect("Unable to write audit data");
+    }
+    pointer(&digest, url)
+}
+
+/// Serializes the pointer in the format parsed by `auditable_extract::payload_pointer`
+fn pointer(digest: &str, url: Option<&str>) -> Vec<u8> {
+    let mut result = POINTER_MAGIC.to_vec();
+    result.extend_from_slice(b"sha256:");
+    result.extend_from_slice(digest.as_bytes());
+    if let Some(url) = url {
+        result.push(b'\n');
+        result.extend_from_slice(url.as_bytes());
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detach_to_store() {
+        let store = env::temp_dir().join(format!("cargo-auditable-store-{}", std::process::id()));
+        let data = b"compressed audit data";
+        let embedded = detach(&store, data, Some("https://example.com/store"));
+        let pointer = auditable_extract::payload_pointer(&embedded)
+            .unwrap()
+            .unwrap();
+        assert_eq!(pointer.url, Some("https://example.com/store"));
+        let stored = std::fs::read(store.join("blobs/sha256").join(pointer.sha256)).unwrap();
+        assert_eq!(stored, data);
+        std::fs::remove_dir_all(&store).unwrap();
+    }
+}
@@ -17,8 +17,12 @@ pub struct RustcArgs {
     pub crate_types: Vec<String>,
     pub cfg: Vec<String>,
     pub out_dir: PathBuf,
+    /// Cargo passes `-C extra-filename=-HASH` to make output file names unique
+    pub extra_filename: Option<String>,
     pub target: Option<String>,
     pub print: Vec<String>,
+    /// Set when building a test harness, e.g. for `cargo test`
+    pub test: bool,
 }
 
 impl RustcArgs {
@@ -46,7 +50,12 @@ pub fn parse_args() -> Result<RustcArgs, pico_args::Error> {
         out_dir: parser.value_from_os_str::<&str, PathBuf, pico_args::Error>("--out-dir", |s| {
             Ok(PathBuf::from(s))
         })?,
+        extra_filename: parser
+            .values_from_str::<_, String>("-C")?
+            .into_iter()
+            .find_map(|option| option.strip_prefix("extra-filename=").map(str::to_owned)),
         target: parser.opt_value_from_str("--target")?,
         print: parser.values_from_str("--print")?,
+        test: parser.contains("--test"),
     })
 }
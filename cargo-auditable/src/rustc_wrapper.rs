@@ -1,64 +1,105 @@
 use std::{
     env,
     ffi::{OsStr, OsString},
+    fs::File,
+    io::BufReader,
+    path::PathBuf,
     process::Command,
 };
 
-use crate::{collect_audit_data, object_file, rustc_arguments, target_info};
+use auditable_extract::ReadError;
 
-use std::io::BufRead;
+use crate::{
+    collect_audit_data, object_file, payload_store,
+    rustc_arguments::{self, RustcArgs},
+    size_budget,
+    size_report::SizeReport,
+    target_info,
+};
 
 pub fn main(rustc_path: &OsStr) {
     let mut command = rustc_command(rustc_path);
+    // Set if audit data is passed to the linker, so that we can check that it made it into the output
+    let mut injected_into: Option<RustcArgs> = None;
 
     // Binaries and C dynamic libraries are not built as non-primary packages,
     // so this should not cause issues with Cargo caches.
     if env::var_os("CARGO_PRIMARY_PACKAGE").is_some() {
         let arg_parsing_result = rustc_arguments::parse_args();
         if let Ok(args) = rustc_arguments::parse_args() {
-            // Only inject audit data into crate types 'bin' and 'cdylib',
-            // and only if --print is not specified (which disables compilation)
-            if args.print.is_empty()
-                && (args.crate_types.contains(&"bin".to_owned())
-                    || args.crate_types.contains(&"cdylib".to_owned()))
-            {
+            // Only inject audit data into crate types 'bin', 'cdylib' and 'staticlib',
+            // and only if --print is not specified (which disables compilation).
+            // Test harnesses link dev-dependencies, so only inject into them if those are recorded.
+            let is_linked = args.crate_types.contains(&"bin".to_owned())
+                || args.crate_types.contains(&"cdylib".to_owned())
+                || (args.test && collect_audit_data::include_dev_dependencies());
+            let is_staticlib = args.crate_types.contains(&"staticlib".to_owned());
+            if args.print.is_empty() && (is_linked || is_staticlib) {
                 // Get the audit data to embed
                 let target_triple = args
                     .target
                     .clone()
-                    .unwrap_or_else(|| rustc_host_target_triple(rustc_path));
-                let contents: Vec<u8> =
-                    collect_audit_data::compressed_dependency_list(&args, &target_triple);
-                // write the audit info to an object file
-                let target_info = target_info::rustc_target_info(rustc_path, &target_triple);
-                let binfile = object_file::create_metadata_file(
-                    &target_info,
-                    &target_triple,
-                    &contents,
-                    "AUDITABLE_VERSION_INFO",
-                );
-                if let Some(file) = binfile {
-                    // Place the audit data in the output dir.
-                    // We can place it anywhere really, the only concern is clutter and name collisions,
-                    // and the target dir is locked so we're probably good
-                    let filename = format!("{}_audit_data.o", args.crate_name);
-                    let path = args.out_dir.join(filename);
-                    std::fs::write(&path, file).expect("Unable to write output file");
-
-                    // Modify the rustc command to link the object file with audit data
-                    let mut linker_command = OsString::from("-Clink-arg=");
-                    linker_command.push(&path);
-                    command.arg(linker_command);
-                    // Prevent the symbol from being removed as unused by the linker
-                    if target_triple.contains("-apple-") {
-                        command.arg("-Clink-arg=-Wl,-u,_AUDITABLE_VERSION_INFO");
-                    } else {
-                        command.arg("-Clink-arg=-Wl,--undefined=AUDITABLE_VERSION_INFO");
-                    }
+                    .unwrap_or_else(|| target_info::rustc_host_target_triple(rustc_path));
+                let info = collect_audit_data::dependency_list(&args, rustc_path, &target_triple);
+                if collect_audit_data::env_flag("CARGO_AUDITABLE_SIZE_REPORT") {
+                    eprint!(
+                        "Size of the audit data for '{}':\n{}",
+                        args.crate_name,
+                        SizeReport::new(&info)
+                    );
+                }
+                let contents: Vec<u8> = size_budget::compressed_payload(info);
+                let contents = payload_store::detach_if_requested(contents);
+                if collect_audit_data::env_flag("CARGO_AUDITABLE_EMBED_MACRO") {
+                    // The crate embeds the audit data itself via `auditable_embed::embed_audit_data!()`,
+                    // for binaries where the linker cannot be relied on to keep our object file
+                    let path = audit_data_path(&args, "bin");
+                    std::fs::write(&path, &contents).expect("Unable to write output file");
+                    command.env("CARGO_AUDITABLE_PAYLOAD", &path);
+                    command.args(["--cfg", "cargo_auditable_payload"]);
+                    injected_into = Some(args);
                 } else {
-                    // create_metadata_file() returned None, indicating an unsupported architecture
-                    eprintln!("WARNING: target '{target_triple}' is not supported by 'cargo auditable'!\n\
-                    The build will continue, but no audit data will be injected into the binary.");
+                    let target_info = target_info::rustc_target_info(rustc_path, &target_triple);
+                    if is_staticlib {
+                        bundle_into_staticlib(
+                            &mut command,
+                            &args,
+                            &target_info,
+                            &target_triple,
+                            &contents,
+                        );
+                    }
+                    // write the audit info to an object file
+                    let binfile = object_file::create_metadata_file(
+                        &target_info,
+                        &target_triple,
+                        &contents,
+                        "AUDITABLE_VERSION_INFO",
+                        false,
+                    );
+                    match binfile {
+                        Some(file) if is_linked => {
+                            let path = audit_data_path(&args, "o");
+                            std::fs::write(&path, file).expect("Unable to write output file");
+
+                            // Modify the rustc command to link the object file with audit data
+                            let mut linker_command = OsString::from("-Clink-arg=");
+                            linker_command.push(&path);
+                            command.arg(linker_command);
+                            // Prevent the symbol from being removed as unused by the linker
+                            if let Some(flag) = keep_symbol_flag(&target_info, &target_triple) {
+                                command.arg(flag);
+                            }
+                            injected_into = Some(args);
+                        }
+                        // Static libraries are not linked; the audit data was bundled into them above
+                        Some(_) => (),
+                        None => {
+                            // create_metadata_file() returned None, indicating an unsupported architecture
+                            eprintln!("WARNING: target '{target_triple}' is not supported by 'cargo auditable'!\n\
+                            The build will continue, but no audit data will be injected into the binary.");
+                        }
+                    }
                 }
             }
         } else {
@@ -98,9 +139,191 @@ pub fn main(rustc_path: &OsStr) {
         );
         std::process::exit(1);
     });
+    if results.success() {
+        if let Some(args) = injected_into {
+            check_audit_data_embedded(&args);
+        }
+    }
     std::process::exit(results.code().unwrap());
 }
 
+/// Returns the linker flag that keeps the audit data symbol from being discarded as unused,
+/// or `None` if the target keeps it anyway
+fn keep_symbol_flag(
+    target_info: &target_info::RustcTargetInfo,
+    target_triple: &str,
+) -> Option<String> {
+    let target_value = |key: &str| target_info.get(key).map(String::as_str);
+    // The object file writer applies the C symbol mangling of the target,
+    // which prefixes the name with `_` on Apple platforms and on 32-bit Windows
+    let is_apple = target_triple.contains("-apple-");
+    let is_windows_x86 =
+        target_value("target_os") == Some("windows") && target_value("target_arch") == Some("x86");
+    let symbol = if is_apple || is_windows_x86 {
+        "_AUDITABLE_VERSION_INFO"
+    } else {
+        "AUDITABLE_VERSION_INFO"
+    };
+    if target_value("target_family") == Some("wasm") {
+        // The audit data is in a custom section, which `wasm-ld` always keeps
+        None
+    } else if is_apple {
+        Some(format!("-Clink-arg=-Wl,-u,{symbol}"))
+    } else if target_value("target_env") == Some("msvc") {
+        // link.exe does not understand GNU-style flags and would ignore them,
+        // letting `/OPT:REF` discard the audit data
+        Some(format!("-Clink-arg=/INCLUDE:{symbol}"))
+    } else if target_value("target_os") == Some("none") {
+        // Bare-metal targets usually invoke the linker directly rather than through
+        // a compiler driver, so `-Wl,` is not understood. Both accept `-u` though.
+        Some(format!("-Clink-args=-u {symbol}"))
+    } else {
+        Some(format!("-Clink-arg=-Wl,--undefined={symbol}"))
+    }
+}
+
+/// Name of the symbol defined by the audit data bundled into static libraries.
+/// It differs from the one in executables so that a crate built as both a `cdylib`
+/// and a `staticlib` does not define it twice when the `cdylib` is linked.
+const STATICLIB_SYMBOL_NAME: &str = "AUDITABLE_STATICLIB_VERSION_INFO";
+
+/// Makes rustc bundle an object file with the audit data into the `staticlib` it builds.
+///
+/// Static libraries are archives of object files rather than linked artifacts, so the object file
+/// cannot be passed to a linker. Instead it is wrapped into a static library of its own,
+/// which rustc copies into the output archive as it does with any native static library.
+/// Linkers only pull in the archive members that define symbols in use, so the program linking
+/// the library has to reference [`STATICLIB_SYMBOL_NAME`] for the audit data to reach the final binary.
+fn bundle_into_staticlib(
+    command: &mut Command,
+    args: &RustcArgs,
+    target_info: &target_info::RustcTargetInfo,
+    target_triple: &str,
+    contents: &[u8],
+) {
+    let object = match object_file::create_metadata_file(
+        target_info,
+        target_triple,
+        contents,
+        STATICLIB_SYMBOL_NAME,
+        false,
+    ) {
+        Some(object) => object,
+        // Unsupported targets are reported by the caller, as for the other crate types
+        None => return,
+    };
+    let archive = object_file::create_archive("audit_data.o", &object);
+    // rustc looks for static libraries named the way the target's linker expects
+    let library_name = format!("{}_audit_data", args.crate_name);
+    let file_name = if target_info.get("target_env").map(String::as_str) == Some("msvc") {
+        format!("{library_name}.lib")
+    } else {
+        format!("lib{library_name}.a")
+    };
+    let path = args.out_dir.join(file_name);
+    std::fs::write(&path, archive).expect("Unable to write output file");
+    let mut search_path = OsString::from("native=");
+    search_path.push(&args.out_dir);
+    command.arg("-L").arg(search_path);
+    // `+bundle` is the default, but spell it out since that is the whole point
+    command.arg(format!("-lstatic:+bundle={library_name}"));
+}
+
+/// Place the audit data in the output dir.
+/// We can place it anywhere really, the only concern is clutter and name collisions,
+/// and the target dir is locked so we're probably good
+fn audit_data_path(args: &RustcArgs, extension: &str) -> PathBuf {
+    let filename = if args.test {
+        format!("{}_test_audit_data.{extension}", args.crate_name)
+    } else {
+        format!("{}_audit_data.{extension}", args.crate_name)
+    };
+    args.out_dir.join(filename)
+}
+
+/// Audit data larger than this is not read back by [`check_audit_data_embedded`],
+/// since finding the section is enough to know that it was embedded
+const MAX_CHECKED_AUDIT_DATA_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Reports linked artifacts that do not contain the audit data we passed to the linker.
+///
+/// This happens when a custom linker or linker script discards the section,
+/// and would otherwise go unnoticed until someone tries to audit the binary.
+/// Only the headers and the audit data are read, so checking large binaries is cheap.
+fn check_audit_data_embedded(args: &RustcArgs) {
+    let stem = format!(
+        "{}{}",
+        args.crate_name,
+        args.extra_filename.as_deref().unwrap_or("")
+    );
+    for path in output_artifacts(args, &stem) {
+        // The file was just written by the linker, so failing to read it back is not our problem
+        let mut reader = match File::open(&path) {
+            Ok(file) => BufReader::new(file),
+            Err(_) => continue,
+        };
+        let result = auditable_extract::raw_auditable_data_from_reader(
+            &mut reader,
+            MAX_CHECKED_AUDIT_DATA_SIZE,
+        );
+        let e = match result {
+            Ok(_) | Err(ReadError::SectionTooLarge) | Err(ReadError::Io(_)) => continue,
+            Err(ReadError::Parse(e)) => e,
+        };
+        let deny = collect_audit_data::env_flag("CARGO_AUDITABLE_DENY_MISSING");
+        let hint = if collect_audit_data::env_flag("CARGO_AUDITABLE_EMBED_MACRO") {
+            "Make sure the binary invokes `auditable_embed::embed_audit_data!()`."
+        } else {
+            "The linker may have discarded the section containing it, \
+            e.g. due to a custom linker script or linker flags."
+        };
+        eprintln!(
+            "{}: audit data was not embedded into '{}': {e}\n{hint}",
+            if deny { "ERROR" } else { "WARNING" },
+            path.display()
+        );
+        if deny {
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Lists the linked files in the output directory for the crate, identified by their file stem
+fn output_artifacts(args: &RustcArgs, stem: &str) -> Vec<PathBuf> {
+    let entries = match std::fs::read_dir(&args.out_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| is_linked_artifact(name, stem))
+        })
+        .collect()
+}
+
+/// Matches the names of executables and dynamic libraries for a crate, e.g. `foo-1a2b`, `foo-1a2b.exe`,
+/// `libfoo-1a2b.so`, `foo-1a2b.dll` or `foo-1a2b.wasm`, but not the other files rustc writes next to them
+fn is_linked_artifact(file_name: &str, stem: &str) -> bool {
+    let name = if file_name.starts_with(stem) {
+        file_name
+    } else {
+        match file_name.strip_prefix("lib") {
+            Some(name) => name,
+            None => return false,
+        }
+    };
+    match name.strip_prefix(stem) {
+        Some("") => true,
+        Some(extension) => matches!(extension, ".exe" | ".dll" | ".so" | ".dylib" | ".wasm"),
+        None => false,
+    }
+}
+
 /// Creates a rustc command line and populates arguments from arguments passed to us.
 fn rustc_command(rustc_path: &OsStr) -> Command {
     let mut command = Command::new(rustc_path);
@@ -111,16 +334,87 @@ fn rustc_command(rustc_path: &OsStr) -> Command {
     command
 }
 
-/// Returns the default target triple for the rustc we're running
-fn rustc_host_target_triple(rustc_path: &OsStr) -> String {
-    Command::new(rustc_path)
-        .arg("-vV")
-        .output()
-        .expect("Failed to invoke rustc! Is it in your $PATH?")
-        .stdout
-        .lines()
-        .map(|l| l.unwrap())
-        .find(|l| l.starts_with("host: "))
-        .map(|l| l[6..].to_string())
-        .expect("Failed to parse rustc output to determine the current platform. Please report this bug!")
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target_info_from(pairs: &[(&str, &str)]) -> target_info::RustcTargetInfo {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn keep_symbol_flags() {
+        let linux = target_info_from(&[
+            ("target_arch", "x86_64"),
+            ("target_os", "linux"),
+            ("target_family", "unix"),
+        ]);
+        assert_eq!(
+            keep_symbol_flag(&linux, "x86_64-unknown-linux-gnu").unwrap(),
+            "-Clink-arg=-Wl,--undefined=AUDITABLE_VERSION_INFO"
+        );
+        let macos = target_info_from(&[("target_arch", "aarch64"), ("target_os", "macos")]);
+        assert_eq!(
+            keep_symbol_flag(&macos, "aarch64-apple-darwin").unwrap(),
+            "-Clink-arg=-Wl,-u,_AUDITABLE_VERSION_INFO"
+        );
+        let msvc = |arch| {
+            target_info_from(&[
+                ("target_arch", arch),
+                ("target_os", "windows"),
+                ("target_env", "msvc"),
+            ])
+        };
+        assert_eq!(
+            keep_symbol_flag(&msvc("x86_64"), "x86_64-pc-windows-msvc").unwrap(),
+            "-Clink-arg=/INCLUDE:AUDITABLE_VERSION_INFO"
+        );
+        assert_eq!(
+            keep_symbol_flag(&msvc("x86"), "i686-pc-windows-msvc").unwrap(),
+            "-Clink-arg=/INCLUDE:_AUDITABLE_VERSION_INFO"
+        );
+        let mingw_x86 = target_info_from(&[
+            ("target_arch", "x86"),
+            ("target_os", "windows"),
+            ("target_env", "gnu"),
+        ]);
+        assert_eq!(
+            keep_symbol_flag(&mingw_x86, "i686-pc-windows-gnu").unwrap(),
+            "-Clink-arg=-Wl,--undefined=_AUDITABLE_VERSION_INFO"
+        );
+        let bare_metal = target_info_from(&[("target_arch", "arm"), ("target_os", "none")]);
+        assert_eq!(
+            keep_symbol_flag(&bare_metal, "thumbv7em-none-eabihf").unwrap(),
+            "-Clink-args=-u AUDITABLE_VERSION_INFO"
+        );
+        let wasm = target_info_from(&[("target_arch", "wasm32"), ("target_family", "wasm")]);
+        assert_eq!(keep_symbol_flag(&wasm, "wasm32-unknown-unknown"), None);
+    }
+
+    #[test]
+    fn linked_artifacts() {
+        let stem = "foo-1a2b";
+        for name in [
+            "foo-1a2b",
+            "foo-1a2b.exe",
+            "libfoo-1a2b.so",
+            "foo-1a2b.dll",
+            "foo-1a2b.wasm",
+        ] {
+            assert!(is_linked_artifact(name, stem), "{name}");
+        }
+        for name in [
+            "foo-1a2b.d",
+            "foo-1a2b.pdb",
+            "foo-1a2b.dll.lib",
+            "libfoo-1a2b.rlib",
+            "foo_audit_data.o",
+            "foo-1a2b3c",
+        ] {
+            assert!(!is_linked_artifact(name, stem), "{name}");
+        }
+    }
 }
@@ -0,0 +1,136 @@
+//! Detached JSON Web Signatures ([RFC 7515](https://www.rfc-editor.org/rfc/rfc7515), Appendix F)
+//! over the reports written by `cargo auditable inventory`, so that systems collecting them
+//! from many hosts can verify that they were not tampered with in transit.
+//!
+//! Reports are signed with HMAC-SHA256 (`HS256`) using a key shared with the receiving system,
+//! which any JWS library can verify once the report is reattached as the payload.
+
+use std::path::Path;
+
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+/// Shorter keys weaken HMAC-SHA256, see RFC 7518 section 3.2
+pub const MIN_KEY_LEN: usize = 32;
+
+const BLOCK_SIZE: usize = 64;
+
+pub struct SigningKey {
+    key: Vec<u8>,
+    key_id: Option<String>,
+}
+
+impl SigningKey {
+    pub fn new(key: Vec<u8>, key_id: Option<String>) -> Result<Self, String> {
+        if key.len() < MIN_KEY_LEN {
+            return Err(format!(
+                "the signing key is {} bytes long, at least {MIN_KEY_LEN} are required",
+                key.len()
+            ));
+        }
+        Ok(SigningKey { key, key_id })
+    }
+
+    /// Uses the entire contents of the file as the key
+    pub fn from_file(path: &Path, key_id: Option<String>) -> Result<Self, String> {
+        let key = std::fs::read(path)
+            .map_err(|e| format!("failed to read signing key '{}': {e}", path.display()))?;
+        Self::new(key, key_id)
+    }
+
+    /// Signs `payload` and returns the JWS in compact serialization with the payload left out,
+    /// i.e. `header..signature`
+    pub fn detached_jws(&self, payload: &[u8]) -> String {
+        let header = match &self.key_id {
+            Some(key_id) => json!({"alg": "HS256", "kid": key_id}),
+            None => json!({"alg": "HS256"}),
+        };
+        let header = base64url(header.to_string().as_bytes());
+        let signing_input = format!("{header}.{}", base64url(payload));
+        let signature = hmac_sha256(&self.key, signing_input.as_bytes());
+        format!("{header}..{}", base64url(&signature))
+    }
+}
+
+/// HMAC as defined in RFC 2104, instantiated with SHA-256
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let inner_pad: Vec<u8> = block.iter().map(|byte| byte ^ 0x36).collect();
+    let outer_pad: Vec<u8> = block.iter().map(|byte| byte ^ 0x5c).collect();
+    let inner = Sha256::new()
+        .chain_update(&inner_pad)
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(&outer_pad)
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+/// Base64 with the URL-safe alphabet and without padding, as used by JWS
+fn base64url(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut result = String::with_capacity(data.len() * 4 / 3 + 3);
+    for chunk in data.chunks(3) {
+        let byte = |i: usize| chunk.get(i).copied().unwrap_or(0);
+        let bits = u32::from_be_bytes([0, byte(0), byte(1), byte(2)]);
+        // n bytes of input make n + 1 characters of output
+        for i in 0..=chunk.len() {
+            let index = (bits >> (18 - 6 * i)) & 0x3f;
+            result.push(ALPHABET[index as usize] as char);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    #[test]
+    fn hmac_test_vectors() {
+        // RFC 4231, test cases 2 and 6
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hex(&hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn base64url_encoding() {
+        assert_eq!(base64url(b""), "");
+        assert_eq!(base64url(b"f"), "Zg");
+        assert_eq!(base64url(b"fo"), "Zm8");
+        assert_eq!(base64url(b"foo"), "Zm9v");
+        assert_eq!(base64url(&[0xfb, 0xff]), "-_8");
+    }
+
+    #[test]
+    fn detached_signature() {
+        assert!(SigningKey::new(vec![0; MIN_KEY_LEN - 1], None).is_err());
+        let key = SigningKey::new(vec![7; MIN_KEY_LEN], Some("host-1".to_owned())).unwrap();
+        let jws = key.detached_jws(b"path,sha256\n");
+        let (header, signature) = jws.split_once("..").unwrap();
+        assert_eq!(header, base64url(br#"{"alg":"HS256","kid":"host-1"}"#));
+        let signing_input = format!("{header}.{}", base64url(b"path,sha256\n"));
+        let expected = hmac_sha256(&[7; MIN_KEY_LEN], signing_input.as_bytes());
+        assert_eq!(signature, base64url(&expected));
+    }
+}
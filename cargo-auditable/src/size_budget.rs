@@ -0,0 +1,211 @@
+//! Enforces a maximum size of the embedded audit data, set in bytes via the
+//! `CARGO_AUDITABLE_MAX_SIZE` environment variable, for firmware with a hard flash budget.
+//!
+//! If the compressed audit data is larger, the build fails. With
+//! `CARGO_AUDITABLE_MAX_SIZE_POLICY=degrade`, optional fields are dropped instead,
+//! one at a time in a fixed order, until the data fits; the build only fails if it does not fit
+//! even without them. The result only depends on the dependency tree, so it is deterministic.
+
+use std::{env, process::exit};
+
+use auditable_serde::{PayloadCompression, VersionInfo};
+
+use crate::compression;
+
+/// What to do if the audit data is over the budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Policy {
+    Fail,
+    Degrade,
+}
+
+/// The name of an optional field, and how to remove it from the audit data
+type Degradation = (&'static str, fn(&mut VersionInfo));
+
+/// Optional fields that may be dropped to fit into the budget, in the order they are dropped:
+/// the ones that are least useful for vulnerability scanning go first.
+/// Fields whose absence would mean something else, like `has_build_script`, are never dropped.
+const DEGRADATIONS: &[Degradation] = &[
+    ("features", |info| {
+        info.packages.iter_mut().for_each(|p| p.features.clear())
+    }),
+    ("dependency_kinds", |info| {
+        info.packages
+            .iter_mut()
+            .for_each(|p| p.dependency_kinds.clear())
+    }),
+    ("vendored_checksum", |info| {
+        info.packages
+            .iter_mut()
+            .for_each(|p| p.vendored_checksum = None)
+    }),
+    ("checksum", |info| {
+        info.packages.iter_mut().for_each(|p| p.checksum = None)
+    }),
+    ("workspace", |info| info.workspace = None),
+    ("toolchain", |info| info.toolchain = None),
+    ("target_name", |info| info.target_name = None),
+];
+
+/// Compresses the audit data as requested via `CARGO_AUDITABLE_COMPRESSION`,
+/// and makes it fit into `CARGO_AUDITABLE_MAX_SIZE` if set, or exits with an error.
+pub fn compressed_payload(info: VersionInfo) -> Vec<u8> {
+    let compression = compression::payload_compression();
+    let max_size = match max_size() {
+        Some(max_size) => max_size,
+        None => return info.to_compressed_payload_with(compression),
+    };
+    let name = info
+        .packages
+        .iter()
+        .find(|p| p.root)
+        .map(|p| p.name.clone())
+        .unwrap_or_default();
+    match fit(info, compression, max_size, policy()) {
+        Ok(fitted) => {
+            if !fitted.dropped.is_empty() {
+                eprintln!(
+                    "WARNING: dropped {} from the audit data of '{name}' to fit into CARGO_AUDITABLE_MAX_SIZE={max_size}",
+                    fitted.dropped.join(", ")
+                );
+            }
+            fitted.payload
+        }
+        Err(size) => {
+            eprintln!(
+                "ERROR: the audit data of '{name}' is {size} bytes, which is over CARGO_AUDITABLE_MAX_SIZE={max_size}.\n\
+                Set CARGO_AUDITABLE_MAX_SIZE_POLICY=degrade to drop optional fields to make it fit, \
+                or set CARGO_AUDITABLE_COMPRESSION to compress it better."
+            );
+            exit(1);
+        }
+    }
+}
+
+fn max_size() -> Option<usize> {
+    let value = env::var("CARGO_AUDITABLE_MAX_SIZE").ok()?;
+    Some(value.parse().unwrap_or_else(|_| {
+        panic!("Invalid value of CARGO_AUDITABLE_MAX_SIZE: expected a size in bytes, got '{value}'")
+    }))
+}
+
+fn policy() -> Policy {
+    match env::var("CARGO_AUDITABLE_MAX_SIZE_POLICY").as_deref() {
+        Err(env::VarError::NotPresent) | Ok("fail") => Policy::Fail,
+        Ok("degrade") => Policy::Degrade,
+        Ok(other) => panic!(
+            "Invalid value of CARGO_AUDITABLE_MAX_SIZE_POLICY: expected 'fail' or 'degrade', got '{other}'"
+        ),
+        Err(e) => panic!("Invalid value of CARGO_AUDITABLE_MAX_SIZE_POLICY: {e}"),
+    }
+}
+
+/// The audit data that fits into the budget, along with the fields that had to be dropped
+#[derive(Debug)]
+struct Fitted {
+    payload: Vec<u8>,
+    dropped: Vec<&'static str>,
+}
+
+/// Returns the smallest size that could be achieved if the data does not fit
+fn fit(
+    mut info: VersionInfo,
+    compression: PayloadCompression,
+    max_size: usize,
+    policy: Policy,
+) -> Result<Fitted, usize> {
+    let mut payload = info.to_compressed_payload_with(compression);
+    let mut dropped = Vec::new();
+    if policy == Policy::Degrade {
+        for (field, remove) in DEGRADATIONS {
+            if payload.len() <= max_size {
+                break;
+            }
+            let before = info.clone();
+            remove(&mut info);
+            // Only report the fields that were actually present
+            if info != before {
+                dropped.push(*field);
+                payload = info.to_compressed_payload_with(compression);
+            }
+        }
+    }
+    if payload.len() <= max_size {
+        Ok(Fitted { payload, dropped })
+    } else {
+        Err(payload.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn info() -> VersionInfo {
+        let checksum = "f26201604c87b1e01bd3d98f8d5d9a8fcbb815e8cedb41ffccbeb4bf593a35fe";
+        VersionInfo::from_str(&format!(
+            r#"{{"packages":[
+            {{"name":"adler","version":"1.0.2","source":"crates.io","checksum":"{checksum}","features":["default","std"]}},
+            {{"name":"app","version":"0.1.0","source":"local","root":true,"dependencies":[0]}}
+            ]}}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn within_budget() {
+        let full = info().to_compressed_payload();
+        let fitted = fit(
+            info(),
+            PayloadCompression::default(),
+            full.len(),
+            Policy::Fail,
+        )
+        .unwrap();
+        assert_eq!(fitted.payload, full);
+        assert!(fitted.dropped.is_empty());
+        assert_eq!(
+            fit(
+                info(),
+                PayloadCompression::default(),
+                full.len() - 1,
+                Policy::Fail
+            )
+            .unwrap_err(),
+            full.len()
+        );
+    }
+
+    #[test]
+    fn degrade() {
+        let full = info().to_compressed_payload();
+        let mut without_features = info();
+        without_features.packages[0].features.clear();
+        let smaller = without_features.to_compressed_payload();
+        let fitted = fit(
+            info(),
+            PayloadCompression::default(),
+            smaller.len(),
+            Policy::Degrade,
+        )
+        .unwrap();
+        assert!(smaller.len() < full.len());
+        assert_eq!(fitted.dropped, vec!["features"]);
+        assert_eq!(fitted.payload, smaller);
+        // the packages themselves are never dropped
+        let fitted = fit(info(), PayloadCompression::default(), 0, Policy::Degrade);
+        assert!(fitted.is_err());
+        // fields that were not recorded, such as `dependency_kinds`, are not reported as dropped
+        let mut minimal = without_features;
+        minimal.packages[0].checksum = None;
+        let fitted = fit(
+            info(),
+            PayloadCompression::default(),
+            minimal.to_compressed_payload().len(),
+            Policy::Degrade,
+        )
+        .unwrap();
+        assert_eq!(fitted.dropped, vec!["features", "checksum"]);
+    }
+}
@@ -0,0 +1,251 @@
+//! Implements `cargo auditable size-report`, which breaks down the size of the audit data
+//! to help users with tight size budgets figure out what takes up space.
+//! The same report is printed during the build if `CARGO_AUDITABLE_SIZE_REPORT=1` is set.
+
+use std::{env, ffi::OsString, fmt, path::PathBuf, process::exit};
+
+use auditable_info::Limits;
+use auditable_serde::{Package, Source, VersionInfo};
+
+use crate::{
+    cargo_arguments::CargoArgs,
+    collect_audit_data,
+    package_args::{PackageArgs, PACKAGE_ARGS_USAGE},
+};
+
+const USAGE: &str = "\
+Usage: cargo auditable size-report [OPTIONS]
+       cargo auditable size-report --binary BINARY
+
+Breaks down the size of the audit data for the package in the current directory,
+or of the audit data embedded in BINARY.
+
+Options:
+    --binary BINARY           Report on the audit data embedded in BINARY
+";
+
+/// Number of the longest strings to list
+const LONGEST_STRINGS: usize = 5;
+
+/// Includes only the arguments specific to `cargo auditable size-report`
+struct SizeReportArgs {
+    binary: Option<PathBuf>,
+    package: PackageArgs,
+}
+
+impl SizeReportArgs {
+    fn from_args() -> Result<Self, pico_args::Error> {
+        // we .skip(3) to get over `cargo auditable size-report` and to the start of the flags
+        let raw_args: Vec<OsString> = env::args_os().skip(3).collect();
+        Self::from_args_vec(raw_args)
+    }
+
+    /// Split into its own function for unit testing
+    fn from_args_vec(raw_args: Vec<OsString>) -> Result<Self, pico_args::Error> {
+        let mut parser = pico_args::Arguments::from_vec(raw_args);
+        Ok(SizeReportArgs {
+            binary: parser.opt_value_from_os_str("--binary", |s| {
+                Ok::<PathBuf, pico_args::Error>(PathBuf::from(s))
+            })?,
+            package: PackageArgs::from_parser(&mut parser)?,
+        })
+    }
+}
+
+pub fn main() {
+    if env::args_os()
+        .skip(3)
+        .any(|arg| arg == "-h" || arg == "--help")
+    {
+        print!("{USAGE}{PACKAGE_ARGS_USAGE}");
+        return;
+    }
+    let args = SizeReportArgs::from_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}{PACKAGE_ARGS_USAGE}");
+        exit(2);
+    });
+
+    let info = match &args.binary {
+        Some(binary) => auditable_info::audit_info_from_file(binary, Limits::default())
+            .unwrap_or_else(|e| {
+                eprintln!(
+                    "ERROR: failed to read audit data from '{}': {e}",
+                    binary.display()
+                );
+                exit(1);
+            }),
+        None => {
+            // Flags such as `--offline` and `--config` are parsed the same way as for a regular build
+            let cargo_args = CargoArgs::from_args();
+            let rustc_path = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+            let target_triple = args.package.target_triple(&rustc_path);
+            let metadata = args.package.metadata(&target_triple, &cargo_args);
+            collect_audit_data::version_info(&metadata)
+        }
+    };
+    print!("{}", SizeReport::new(&info));
+}
+
+/// Breakdown of the size of the audit data
+pub struct SizeReport {
+    packages: usize,
+    json_size: usize,
+    compressed_size: usize,
+    /// Compressed size with all the optional fields dropped
+    minimal_size: usize,
+    /// Descriptions of the longest strings and their lengths, longest first
+    longest_strings: Vec<(String, usize)>,
+}
+
+impl SizeReport {
+    pub fn new(info: &VersionInfo) -> Self {
+        let json_size = serde_json::to_string(info).unwrap().len();
+        let compressed_size = info.to_compressed_payload().len();
+        let minimal_size = minimal(info).to_compressed_payload().len();
+        let mut strings: Vec<(String, usize)> = info
+            .packages
+            .iter()
+            .flat_map(package_strings)
+            .map(|(package, field, value)| (format!("{package}: {field} {value}"), value.len()))
+            .collect();
+        strings.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        strings.dedup();
+        strings.truncate(LONGEST_STRINGS);
+        SizeReport {
+            packages: info.packages.len(),
+            json_size,
+            compressed_size,
+            minimal_size,
+            longest_strings: strings,
+        }
+    }
+}
+
+impl fmt::Display for SizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let per_package = |size: usize| size as f64 / self.packages.max(1) as f64;
+        writeln!(f, "Packages:              {}", self.packages)?;
+        writeln!(
+            f,
+            "JSON size:             {} bytes ({:.1} per package)",
+            self.json_size,
+            per_package(self.json_size)
+        )?;
+        writeln!(
+            f,
+            "Compressed size:       {} bytes ({:.1} per package)",
+            self.compressed_size,
+            per_package(self.compressed_size)
+        )?;
+        let savings = self.compressed_size.saturating_sub(self.minimal_size);
+        writeln!(
+            f,
+            "Without optional data: {} bytes (saves {} bytes, {:.0}%)",
+            self.minimal_size,
+            savings,
+            100.0 * savings as f64 / self.compressed_size.max(1) as f64
+        )?;
+        writeln!(
+            f,
+            "  (checksums, vendored checksums, dependency_kinds, renamed_as, features, has_build_script, git revisions and paths)"
+        )?;
+        writeln!(f, "Longest strings:")?;
+        for (description, len) in &self.longest_strings {
+            writeln!(f, "  {len:>5}  {description}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists the strings recorded for a package as (package, field, value)
+fn package_strings(package: &Package) -> Vec<(String, &'static str, String)> {
+    let id = format!("{} {}", package.name, package.version);
+    let mut strings = vec![
+        (id.clone(), "name", package.name.clone()),
+        (id.clone(), "version", package.version.to_string()),
+    ];
+    if let Some(checksum) = &package.checksum {
+        strings.push((id.clone(), "checksum", checksum.clone()));
+    }
+    if let Some(checksum) = &package.vendored_checksum {
+        strings.push((id.clone(), "vendored_checksum", checksum.clone()));
+    }
+    match &package.source {
+        Source::Git(git) => {
+            if let Some(url) = &git.url {
+                strings.push((id.clone(), "git url", url.clone()));
+            }
+            if let Some(rev) = &git.rev {
+                strings.push((id.clone(), "git rev", rev.clone()));
+            }
+        }
+        Source::Registry(registry) => {
+            if let Some(url) = &registry.url {
+                strings.push((id.clone(), "registry url", url.clone()));
+            }
+        }
+        Source::Local(local) => {
+            if let Some(path) = &local.path {
+                strings.push((id.clone(), "path", path.clone()));
+            }
+        }
+        Source::Workspace(workspace) => {
+            if let Some(path) = &workspace.path {
+                strings.push((id.clone(), "path", path.clone()));
+            }
+        }
+        Source::Other(source) => strings.push((id.clone(), "source", source.clone())),
+        _ => (),
+    }
+    for name in &package.renamed_as {
+        strings.push((id.clone(), "renamed_as", name.clone()));
+    }
+    for feature in &package.features {
+        strings.push((id.clone(), "feature", feature.clone()));
+    }
+    strings
+}
+
+/// Drops everything that is not required to identify the packages and the dependency tree
+fn minimal(info: &VersionInfo) -> VersionInfo {
+    let mut info = info.clone();
+    for package in &mut info.packages {
+        package.checksum = None;
+        package.vendored_checksum = None;
+        package.dependency_kinds.clear();
+        package.renamed_as.clear();
+        package.features.clear();
+        package.has_build_script = false;
+        package.source = package.source.without_urls();
+        match &mut package.source {
+            Source::Git(git) => git.rev = None,
+            Source::Local(local) => local.path = None,
+            Source::Workspace(workspace) => workspace.path = None,
+            _ => (),
+        }
+    }
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn report() {
+        let json = r#"{"packages":[{"name":"adler","version":"0.2.3","source":"crates.io","checksum":"ee2a4ec343196209d6594e19543ae87a39f96d5534d7174822a3ad825dd6ed7e","kind":"build"},{"name":"foo","version":"1.0.0","source":"local","dependencies":[0],"root":true}]}"#;
+        let info = VersionInfo::from_str(json).unwrap();
+        let report = SizeReport::new(&info);
+        assert_eq!(report.packages, 2);
+        assert_eq!(
+            report.json_size,
+            serde_json::to_string(&info).unwrap().len()
+        );
+        assert!(report.minimal_size < report.compressed_size);
+        assert_eq!(report.longest_strings[0].1, 64);
+        assert!(report.longest_strings[0]
+            .0
+            .starts_with("adler 0.2.3: checksum"));
+    }
+}
@@ -0,0 +1,217 @@
+//! Snapshots of the crates in a set of binaries, written by `cargo auditable inventory --snapshot`
+//! and compared against by `--baseline`, so that periodic scans of a fleet report
+//! what changed since the last scan instead of everything that is deployed.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use auditable_serde::VersionInfo;
+use serde::{Deserialize, Serialize};
+
+/// Versions of each crate in a binary, by crate name
+pub type CrateVersions = BTreeMap<String, BTreeSet<semver::Version>>;
+
+/// Returns the versions of each crate recorded in the audit data
+pub fn crate_versions(info: &VersionInfo) -> CrateVersions {
+    let mut crates = CrateVersions::new();
+    for package in &info.packages {
+        crates
+            .entry(package.name.clone())
+            .or_default()
+            .insert(package.version.clone());
+    }
+    crates
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Keyed by the path each binary was found at
+    pub binaries: BTreeMap<String, BinarySnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BinarySnapshot {
+    pub sha256: String,
+    /// `None` if the binary has no audit data
+    pub crates: Option<CrateVersions>,
+}
+
+/// A single difference between two snapshots
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub path: String,
+    pub kind: ChangeKind,
+    /// Only set for changes to a specific crate
+    pub name: Option<String>,
+    pub old_version: Option<semver::Version>,
+    pub new_version: Option<semver::Version>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    NewBinary,
+    RemovedBinary,
+    /// The binary used to have audit data, but no longer does
+    LostAuditData,
+    GainedAuditData,
+    AddedCrate,
+    RemovedCrate,
+    Upgraded,
+    Downgraded,
+}
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::NewBinary => "new_binary",
+            ChangeKind::RemovedBinary => "removed_binary",
+            ChangeKind::LostAuditData => "lost_audit_data",
+            ChangeKind::GainedAuditData => "gained_audit_data",
+            ChangeKind::AddedCrate => "added_crate",
+            ChangeKind::RemovedCrate => "removed_crate",
+            ChangeKind::Upgraded => "upgraded",
+            ChangeKind::Downgraded => "downgraded",
+        }
+    }
+}
+
+impl Snapshot {
+    /// Lists the changes from `baseline` to `self`, ordered by path
+    pub fn changes_since(&self, baseline: &Snapshot) -> Vec<Change> {
+        let mut changes = Vec::new();
+        let paths: BTreeSet<&String> = self
+            .binaries
+            .keys()
+            .chain(baseline.binaries.keys())
+            .collect();
+        for path in paths {
+            let binary_change = |kind| Change {
+                path: path.clone(),
+                kind,
+                name: None,
+                old_version: None,
+                new_version: None,
+            };
+            let (old, new) = match (baseline.binaries.get(path), self.binaries.get(path)) {
+                (Some(old), Some(new)) => (old, new),
+                (None, Some(_)) => {
+                    changes.push(binary_change(ChangeKind::NewBinary));
+                    continue;
+                }
+                (Some(_), None) => {
+                    changes.push(binary_change(ChangeKind::RemovedBinary));
+                    continue;
+                }
+                (None, None) => unreachable!(),
+            };
+            if old.sha256 == new.sha256 {
+                continue;
+            }
+            match (&old.crates, &new.crates) {
+                (Some(_), None) => changes.push(binary_change(ChangeKind::LostAuditData)),
+                (None, Some(_)) => changes.push(binary_change(ChangeKind::GainedAuditData)),
+                (None, None) => (),
+                (Some(old), Some(new)) => crate_changes(path, old, new, &mut changes),
+            }
+        }
+        changes
+    }
+}
+
+fn crate_changes(path: &str, old: &CrateVersions, new: &CrateVersions, out: &mut Vec<Change>) {
+    let empty = BTreeSet::new();
+    let names: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    for name in names {
+        let old_versions = old.get(name).unwrap_or(&empty);
+        let new_versions = new.get(name).unwrap_or(&empty);
+        let change = |kind,
+                      old_version: Option<&semver::Version>,
+                      new_version: Option<&semver::Version>| Change {
+            path: path.to_owned(),
+            kind,
+            name: Some(name.clone()),
+            old_version: old_version.cloned(),
+            new_version: new_version.cloned(),
+        };
+        // The common case of a single version being replaced by another
+        if old_versions.len() == 1 && new_versions.len() == 1 {
+            let (old_version, new_version) =
+                (old_versions.iter().next(), new_versions.iter().next());
+            if old_version < new_version {
+                out.push(change(ChangeKind::Upgraded, old_version, new_version));
+            } else if old_version > new_version {
+                out.push(change(ChangeKind::Downgraded, old_version, new_version));
+            }
+            continue;
+        }
+        for version in old_versions.difference(new_versions) {
+            out.push(change(ChangeKind::RemovedCrate, Some(version), None));
+        }
+        for version in new_versions.difference(old_versions) {
+            out.push(change(ChangeKind::AddedCrate, None, Some(version)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary(sha256: &str, crates: Option<&[(&str, &str)]>) -> BinarySnapshot {
+        let crates = crates.map(|crates| {
+            let mut versions = CrateVersions::new();
+            for (name, version) in crates {
+                versions
+                    .entry(name.to_string())
+                    .or_default()
+                    .insert(version.parse().unwrap());
+            }
+            versions
+        });
+        BinarySnapshot {
+            sha256: sha256.to_owned(),
+            crates,
+        }
+    }
+
+    #[test]
+    fn changes() {
+        let mut baseline = Snapshot::default();
+        let mut current = Snapshot::default();
+        let old_app = binary("1", Some(&[("serde", "1.0.0"), ("log", "0.4.0")]));
+        let new_app = binary("2", Some(&[("serde", "1.0.1"), ("libc", "0.2.0")]));
+        baseline.binaries.insert("app".to_owned(), old_app);
+        current.binaries.insert("app".to_owned(), new_app);
+        baseline
+            .binaries
+            .insert("gone".to_owned(), binary("3", None));
+        current
+            .binaries
+            .insert("stripped".to_owned(), binary("4", None));
+        let unchanged = binary("5", Some(&[("serde", "1.0.0")]));
+        baseline
+            .binaries
+            .insert("same".to_owned(), unchanged.clone());
+        current.binaries.insert("same".to_owned(), unchanged);
+        baseline
+            .binaries
+            .insert("tool".to_owned(), binary("6", Some(&[])));
+        current
+            .binaries
+            .insert("tool".to_owned(), binary("7", None));
+
+        let summary: Vec<(String, &str, Option<String>)> = current
+            .changes_since(&baseline)
+            .into_iter()
+            .map(|c| (c.path, c.kind.as_str(), c.name))
+            .collect();
+        let expected: Vec<(String, &str, Option<String>)> = vec![
+            ("app".into(), "added_crate", Some("libc".into())),
+            ("app".into(), "removed_crate", Some("log".into())),
+            ("app".into(), "upgraded", Some("serde".into())),
+            ("gone".into(), "removed_binary", None),
+            ("stripped".into(), "new_binary", None),
+            ("tool".into(), "lost_audit_data", None),
+        ];
+        assert_eq!(summary, expected);
+    }
+}
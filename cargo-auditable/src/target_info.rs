@@ -12,6 +12,44 @@ pub fn rustc_target_info(rustc_path: &OsStr, target_triple: &str) -> RustcTarget
         .stdout)
 }
 
+/// Returns the default target triple for the rustc we're running
+pub fn rustc_host_target_triple(rustc_path: &OsStr) -> String {
+    std::process::Command::new(rustc_path)
+        .arg("-vV")
+        .output()
+        .expect("Failed to invoke rustc! Is it in your $PATH?")
+        .stdout
+        .lines()
+        .map(|l| l.unwrap())
+        .find(|l| l.starts_with("host: "))
+        .map(|l| l[6..].to_string())
+        .expect("Failed to parse rustc output to determine the current platform. Please report this bug!")
+}
+
+/// Returns the version of the rustc we're running, e.g. "1.70.0" or "1.72.0-nightly"
+pub fn rustc_version(rustc_path: &OsStr) -> String {
+    std::process::Command::new(rustc_path)
+        .arg("-vV")
+        .output()
+        .expect("Failed to invoke rustc! Is it in your $PATH?")
+        .stdout
+        .lines()
+        .map(|l| l.unwrap())
+        .find(|l| l.starts_with("release: "))
+        .map(|l| l[9..].to_string())
+        .expect("Failed to parse rustc output to determine its version. Please report this bug!")
+}
+
+/// Derives the release channel from the rustc version, e.g. "nightly" for "1.72.0-nightly"
+pub fn release_channel(rustc_version: &str) -> &'static str {
+    for channel in ["beta", "nightly", "dev"] {
+        if rustc_version.contains(&format!("-{channel}")) {
+            return channel;
+        }
+    }
+    "stable"
+}
+
 pub(crate) fn parse_rustc_target_info(rustc_output: &[u8]) -> RustcTargetInfo {
     // Decoupled from `rustc_target_info` to allow unit testing
     // `pub(crate)` so that unit tests in other modules could use it
@@ -60,4 +98,11 @@ unix
         assert_eq!(result.get("target_pointer_width").unwrap(), "64");
         assert_eq!(result.get("target_vendor").unwrap(), "unknown");
     }
+    #[test]
+    fn release_channels() {
+        assert_eq!(release_channel("1.70.0"), "stable");
+        assert_eq!(release_channel("1.71.0-beta.3"), "beta");
+        assert_eq!(release_channel("1.72.0-nightly"), "nightly");
+        assert_eq!(release_channel("1.72.0-dev"), "dev");
+    }
 }
@@ -0,0 +1,108 @@
+//! Implements `cargo auditable test-vectors`, which writes the reference data from
+//! [`auditable_info::test_vectors`] to disk, so that producers of audit data not written in Rust
+//! can check that they embed exactly the same bytes as `cargo auditable`.
+
+use std::{
+    env,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::exit,
+};
+
+use auditable_info::test_vectors::{SectionFormat, TEST_VECTORS};
+
+const USAGE: &str = "\
+Usage: cargo auditable test-vectors --output-dir DIR
+
+Writes the following files into DIR for each test vector NAME:
+
+    NAME.input.json           The audit data as another producer may write it
+    NAME.canonical.json       The audit data as serialized by cargo auditable
+    NAME.payload.zlib         The compressed audit data
+    NAME.FORMAT.bin           The contents of the section holding the audit data
+                              in a binary of each FORMAT: elf, pe, macho, raw-image
+
+Options:
+    --output-dir DIR          Directory to write the files to; created if missing
+";
+
+/// Includes only the arguments specific to `cargo auditable test-vectors`
+struct TestVectorsArgs {
+    output_dir: PathBuf,
+}
+
+impl TestVectorsArgs {
+    fn from_args() -> Result<Self, pico_args::Error> {
+        // we .skip(3) to get over `cargo auditable test-vectors` and to the start of the flags
+        let raw_args: Vec<OsString> = env::args_os().skip(3).collect();
+        Self::from_args_vec(raw_args)
+    }
+
+    /// Split into its own function for unit testing
+    fn from_args_vec(raw_args: Vec<OsString>) -> Result<Self, pico_args::Error> {
+        let mut parser = pico_args::Arguments::from_vec(raw_args);
+        let output_dir = parser.value_from_os_str("--output-dir", |s| {
+            Ok::<PathBuf, pico_args::Error>(PathBuf::from(s))
+        })?;
+        let remaining = parser.finish();
+        if !remaining.is_empty() {
+            return Err(pico_args::Error::ArgumentParsingFailed {
+                cause: format!("unexpected arguments: {remaining:?}"),
+            });
+        }
+        Ok(TestVectorsArgs { output_dir })
+    }
+}
+
+pub fn main() {
+    if env::args_os()
+        .skip(3)
+        .any(|arg| arg == "-h" || arg == "--help")
+    {
+        print!("{USAGE}");
+        return;
+    }
+    let args = TestVectorsArgs::from_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}");
+        exit(2);
+    });
+
+    if let Err(e) = write_test_vectors(&args.output_dir) {
+        eprintln!(
+            "ERROR: failed to write test vectors to '{}': {e}",
+            args.output_dir.display()
+        );
+        exit(1);
+    }
+}
+
+fn write_test_vectors(output_dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(output_dir)?;
+    for vector in TEST_VECTORS {
+        let name = vector.name;
+        let file = |suffix: &str| output_dir.join(format!("{name}.{suffix}"));
+        std::fs::write(file("input.json"), vector.input_json)?;
+        std::fs::write(file("canonical.json"), vector.canonical_json)?;
+        std::fs::write(file("payload.zlib"), vector.compressed_payload())?;
+        for format in SectionFormat::ALL {
+            let contents = vector.section_contents(*format);
+            std::fs::write(file(&format!("{}.bin", format.name())), contents)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic_parsing() {
+        let raw_args = ["--output-dir", "vectors"];
+        let args =
+            TestVectorsArgs::from_args_vec(raw_args.iter().map(OsString::from).collect()).unwrap();
+        assert_eq!(args.output_dir, PathBuf::from("vectors"));
+        let missing = TestVectorsArgs::from_args_vec(Vec::new());
+        assert!(missing.is_err());
+    }
+}
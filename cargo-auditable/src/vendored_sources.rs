@@ -0,0 +1,127 @@
+//! Records hashes of packages built from a vendor directory created by `cargo vendor`,
+//! as requested via `CARGO_AUDITABLE_VENDOR_HASHES`.
+
+use auditable_serde::{Source, VersionInfo};
+use cargo_metadata::Metadata;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Written by `cargo vendor` into every package directory. It is not part of the package
+/// as published, so it is left out of the hash.
+const CHECKSUM_FILE: &str = ".cargo-checksum.json";
+
+/// Fills in [`auditable_serde::Package::vendored_checksum`] for every package in `info`
+/// that was built from a vendor directory.
+pub fn record_vendored_checksums(metadata: &Metadata, info: &mut VersionInfo) {
+    let mut checksums: BTreeMap<(String, String, Source), String> = BTreeMap::new();
+    for package in &metadata.packages {
+        // Packages in a vendor directory are still reported with their original source
+        let source = match &package.source {
+            Some(source) => Source::from(source).without_urls(),
+            None => continue,
+        };
+        let dir = match package.manifest_path.parent() {
+            Some(dir) => dir.as_std_path(),
+            None => continue,
+        };
+        if !dir.join(CHECKSUM_FILE).is_file() {
+            continue;
+        }
+        let checksum = directory_checksum(dir).unwrap_or_else(|e| {
+            panic!("Failed to hash vendored package at {}: {e}", dir.display())
+        });
+        checksums.insert(
+            (package.name.clone(), package.version.to_string(), source),
+            checksum,
+        );
+    }
+    for package in &mut info.packages {
+        let key = (
+            package.name.clone(),
+            package.version.to_string(),
+            package.source.without_urls(),
+        );
+        if let Some(checksum) = checksums.get(&key) {
+            package.vendored_checksum = Some(checksum.clone());
+        }
+    }
+}
+
+/// Hashes the output `sha256sum` would print for every file in the directory
+/// except [`CHECKSUM_FILE`], sorted by path, so that the result can be reproduced with standard tools:
+///
+/// ```text
+/// find . -type f ! -path ./.cargo-checksum.json | cut -c3- | LC_ALL=C sort | xargs sha256sum | sha256sum
+/// ```
+fn directory_checksum(dir: &Path) -> io::Result<String> {
+    let mut files = Vec::new();
+    list_files(dir, dir, &mut files)?;
+    files.sort_unstable();
+    let mut hasher = Sha256::new();
+    for (relative_path, path) in files {
+        if relative_path == CHECKSUM_FILE {
+            continue;
+        }
+        let file_hash = Sha256::digest(std::fs::read(&path)?);
+        hasher.update(format!("{file_hash:x}  {relative_path}\n"));
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Collects the files under `dir` as (path relative to `root` with `/` separators, full path)
+fn list_files(root: &Path, dir: &Path, files: &mut Vec<(String, PathBuf)>) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            list_files(root, &path, files)?;
+        } else if file_type.is_file() {
+            let relative: Vec<String> = path
+                .strip_prefix(root)
+                .expect("listed file is outside of the listed directory")
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            files.push((relative.join("/"), path));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum_of_directory() {
+        let dir =
+            std::env::temp_dir().join(format!("cargo-auditable-vendored-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), "[package]\n").unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "").unwrap();
+        std::fs::write(dir.join(CHECKSUM_FILE), "{}").unwrap();
+
+        let expected_listing = format!(
+            "{:x}  Cargo.toml\n{:x}  src/lib.rs\n",
+            Sha256::digest("[package]\n"),
+            Sha256::digest("")
+        );
+        let expected = format!("{:x}", Sha256::digest(expected_listing));
+        assert_eq!(directory_checksum(&dir).unwrap(), expected);
+
+        // the checksum file is rewritten by `cargo vendor` and must not affect the hash
+        std::fs::write(dir.join(CHECKSUM_FILE), "{\"files\":{}}").unwrap();
+        assert_eq!(directory_checksum(&dir).unwrap(), expected);
+        // but any change to the package contents must
+        std::fs::write(dir.join("src/lib.rs"), "// patched").unwrap();
+        assert_ne!(directory_checksum(&dir).unwrap(), expected);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
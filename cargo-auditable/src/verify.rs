@@ -0,0 +1,191 @@
+//! Implements `cargo auditable verify`, which compares the checksums of crates.io packages
+//! recorded in a binary against the crates.io index, to detect binaries built from
+//! modified copies of crates that claim to be the published versions.
+
+use std::{env, ffi::OsString, path::PathBuf, process::exit};
+
+use auditable_info::crates_index::{verify_checksums, ChecksumStatus};
+use auditable_info::Limits;
+
+use crate::index_client::{self, IndexClient};
+
+const USAGE: &str = "\
+Usage: cargo auditable verify [OPTIONS] BINARY...
+
+Compares the checksums of crates.io packages recorded in the audit data
+of each BINARY against the crates.io index. Exits with an error if any of
+them do not match, which means the binary was built from a modified copy
+of the crate rather than the published version.
+
+Packages without a recorded checksum are reported but cannot be verified.
+Checksums are only recorded in binaries built with
+CARGO_AUDITABLE_RECORD_CHECKSUMS=1.
+
+By default the index files are fetched from https://index.crates.io
+using `curl`. Pass --index to use a local copy of the index instead,
+such as a clone of https://github.com/rust-lang/crates.io-index
+
+Fetched index files are cached in $CARGO_HOME/auditable-index-cache
+and reused for an hour. If crates.io cannot be reached, older cached
+files are used instead, with a warning.
+
+Options:
+    --index DIR               Path to a local copy of the crates.io index
+    --cache-dir DIR           Directory to cache the fetched index files in
+    --offline                 Only use the cached index files; also enabled
+                              by the CARGO_NET_OFFLINE environment variable
+    --quiet                   Only print the packages that failed verification
+";
+
+/// Includes only the arguments specific to `cargo auditable verify`
+struct VerifyArgs {
+    binaries: Vec<PathBuf>,
+    index: Option<PathBuf>,
+    cache_dir: Option<PathBuf>,
+    offline: bool,
+    quiet: bool,
+}
+
+impl VerifyArgs {
+    fn from_args() -> Result<Self, pico_args::Error> {
+        // we .skip(3) to get over `cargo auditable verify` and to the start of the flags
+        let raw_args: Vec<OsString> = env::args_os().skip(3).collect();
+        Self::from_args_vec(raw_args)
+    }
+
+    /// Split into its own function for unit testing
+    fn from_args_vec(raw_args: Vec<OsString>) -> Result<Self, pico_args::Error> {
+        let mut parser = pico_args::Arguments::from_vec(raw_args);
+        let index = parser.opt_value_from_os_str("--index", |s| {
+            Ok::<PathBuf, pico_args::Error>(PathBuf::from(s))
+        })?;
+        let cache_dir = parser.opt_value_from_os_str("--cache-dir", |s| {
+            Ok::<PathBuf, pico_args::Error>(PathBuf::from(s))
+        })?;
+        let offline = parser.contains("--offline");
+        let quiet = parser.contains("--quiet");
+        let binaries: Vec<PathBuf> = parser.finish().into_iter().map(PathBuf::from).collect();
+        if binaries.is_empty() {
+            return Err(pico_args::Error::MissingArgument);
+        }
+        Ok(VerifyArgs {
+            binaries,
+            index,
+            cache_dir,
+            offline,
+            quiet,
+        })
+    }
+}
+
+pub fn main() {
+    if env::args_os()
+        .skip(3)
+        .any(|arg| arg == "-h" || arg == "--help")
+    {
+        print!("{USAGE}");
+        return;
+    }
+    let args = VerifyArgs::from_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}");
+        exit(2);
+    });
+
+    let mut client = match &args.index {
+        Some(index) => IndexClient::local(index.clone()),
+        None => {
+            let cache_dir = args
+                .cache_dir
+                .clone()
+                .or_else(index_client::default_cache_dir)
+                .unwrap_or_else(|| {
+                    eprintln!(
+                        "ERROR: could not locate the Cargo home directory to cache the index in, pass --cache-dir"
+                    );
+                    exit(1);
+                });
+            IndexClient::sparse(cache_dir, args.offline || index_client::cargo_offline())
+        }
+    };
+
+    let mut failed = false;
+    for binary in &args.binaries {
+        let info =
+            auditable_info::audit_info_from_file(binary, Limits::default()).unwrap_or_else(|e| {
+                eprintln!(
+                    "ERROR: failed to read audit data from '{}': {e}",
+                    binary.display()
+                );
+                exit(1);
+            });
+        let verifications =
+            verify_checksums(&info, |name| client.index_file(name)).unwrap_or_else(|e| {
+                eprintln!("ERROR: failed to query the crates.io index: {e}");
+                exit(1);
+            });
+        for verification in verifications {
+            let package = verification.package;
+            let message = match &verification.status {
+                ChecksumStatus::Match => "OK".to_owned(),
+                ChecksumStatus::NotRecorded => "no checksum recorded".to_owned(),
+                ChecksumStatus::NotInIndex => {
+                    "MISSING: this version is not in the crates.io index".to_owned()
+                }
+                ChecksumStatus::Mismatch { expected } => format!(
+                    "MISMATCH: recorded checksum {}, crates.io index has {expected}",
+                    package.checksum.as_deref().unwrap_or_default()
+                ),
+            };
+            let is_failure = matches!(
+                verification.status,
+                ChecksumStatus::NotInIndex | ChecksumStatus::Mismatch { .. }
+            );
+            failed |= is_failure;
+            if is_failure || !args.quiet {
+                println!(
+                    "{}: {} {}: {message}",
+                    binary.display(),
+                    package.name,
+                    package.version
+                );
+            }
+        }
+    }
+    if failed {
+        exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &[&str]) -> Result<VerifyArgs, pico_args::Error> {
+        VerifyArgs::from_args_vec(input.iter().map(OsString::from).collect())
+    }
+
+    #[test]
+    fn basic_parsing() {
+        let args = parse(&["--index", "crates.io-index", "app", "other-app"]).unwrap();
+        assert_eq!(args.index, Some(PathBuf::from("crates.io-index")));
+        assert_eq!(
+            args.binaries,
+            vec![PathBuf::from("app"), PathBuf::from("other-app")]
+        );
+        assert!(!args.quiet);
+        assert!(!args.offline);
+    }
+
+    #[test]
+    fn cache_options() {
+        let args = parse(&["--offline", "--cache-dir", "cache", "app"]).unwrap();
+        assert!(args.offline);
+        assert_eq!(args.cache_dir, Some(PathBuf::from("cache")));
+        assert_eq!(args.binaries, vec![PathBuf::from("app")]);
+    }
+
+    #[test]
+    fn binary_is_required() {
+        assert!(parse(&["--index", "crates.io-index"]).is_err());
+    }
+}
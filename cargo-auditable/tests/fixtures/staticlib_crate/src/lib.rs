@@ -0,0 +1,4 @@
+#[no_mangle]
+pub extern "C" fn staticlib_crate_answer() -> u32 {
+    42
+}
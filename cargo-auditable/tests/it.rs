@@ -7,7 +7,7 @@ use std::{
     process::{Command, Output, Stdio},
 };
 
-use auditable_serde::{DependencyKind, Source, VersionInfo};
+use auditable_serde::{DependencyKind, Source, VersionInfo, WorkspaceSource};
 use cargo_metadata::{
     camino::{Utf8Path, Utf8PathBuf},
     Artifact,
@@ -20,7 +20,7 @@ const EXE: &str = env!("CARGO_BIN_EXE_cargo-auditable");
 const CARGO: &str = env!("CARGO");
 
 /// Run cargo auditable with --manifest-path <cargo_toml_path arg> and extra args,
-/// returning of map of workspace member names -> produced binaries (bin and cdylib)
+/// returning of map of workspace member names -> produced binaries (bin, cdylib and staticlib)
 /// Reads the AUDITABLE_TEST_TARGET environment variable to determine the target to compile for
 fn run_cargo_auditable<P>(
     cargo_toml_path: P,
@@ -105,6 +105,20 @@ where
                         .for_each(|f| {
                             binaries.push((member.clone(), f));
                         });
+                // static libraries are .a everywhere except for .lib on Windows with MSVC
+                } else if artifact
+                    .target
+                    .kind
+                    .iter()
+                    .any(|kind| kind.as_str() == "staticlib")
+                {
+                    artifact
+                        .filenames
+                        .into_iter()
+                        .filter(|f| f.extension() == Some("a") || f.extension() == Some("lib"))
+                        .for_each(|f| {
+                            binaries.push((member.clone(), f));
+                        });
                 }
             }
             binaries
@@ -139,7 +153,7 @@ fn test_cargo_auditable_workspaces() {
     let bins = run_cargo_auditable(&workspace_cargo_toml, &[], &[]);
     eprintln!("Test fixture binary map: {bins:?}");
     // No binaries for library_crate
-    assert!(bins.get("library_crate").is_none());
+    assert!(!bins.contains_key("library_crate"));
 
     // binary_and_cdylib_crate
     let binary_and_cdylib_crate_bins = bins.get("binary_and_cdylib_crate").unwrap();
@@ -172,6 +186,18 @@ fn test_cargo_auditable_workspaces() {
         .packages
         .iter()
         .any(|p| p.name == "crate_with_features"));
+    // workspace members record their location relative to the workspace root
+    let library_crate = dep_info
+        .packages
+        .iter()
+        .find(|p| p.name == "library_crate")
+        .unwrap();
+    assert_eq!(
+        library_crate.source,
+        Source::Workspace(WorkspaceSource {
+            path: Some("library_crate".to_owned())
+        })
+    );
 
     // Run enabling binary_and_cdylib_crate feature
     let bins = run_cargo_auditable(
@@ -287,7 +313,7 @@ fn test_build_script() {
     assert!(dep_info
         .packages
         .iter()
-        .any(|p| p.name == "crate_with_build_script"));
+        .any(|p| p.name == "crate_with_build_script" && p.has_build_script));
 }
 
 #[test]
@@ -362,6 +388,121 @@ fn test_runtime_then_build_dep() {
         .any(|p| p.name == "build_dep_of_runtime_dep" && p.kind == DependencyKind::Build));
 }
 
+#[test]
+fn test_per_edge_kinds() {
+    // Path to workspace fixture Cargo.toml. See that file for overview of workspace members and their dependencies.
+    let workspace_cargo_toml = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/build_and_runtime_dep/Cargo.toml");
+    let bins = run_cargo_auditable(
+        workspace_cargo_toml,
+        &[],
+        &[("CARGO_AUDITABLE_PER_EDGE_KINDS", OsStr::new("1"))],
+    );
+    eprintln!("Test fixture binary map: {bins:?}");
+
+    let toplevel_crate_bin = &bins.get("top_level_crate").unwrap()[0];
+    let dep_info = get_dependency_info(toplevel_crate_bin);
+    eprintln!("{toplevel_crate_bin} dependency info: {dep_info:?}");
+    assert!(dep_info.packages.len() == 3);
+    let find = |name: &str| dep_info.packages.iter().find(|p| p.name == name).unwrap();
+    // shared_dep is reachable at runtime, so that's its strongest kind...
+    assert_eq!(find("shared_dep").kind, DependencyKind::Runtime);
+    // ...but the edge from the top-level crate is a build dependency
+    let top_level_crate = find("top_level_crate");
+    let edges: Vec<(&str, DependencyKind)> = top_level_crate
+        .dependencies
+        .iter()
+        .zip(top_level_crate.dependency_kinds.iter())
+        .map(|(index, kind)| (dep_info.packages[*index].name.as_str(), *kind))
+        .collect();
+    assert_eq!(edges.len(), 2);
+    assert!(edges.contains(&("shared_dep", DependencyKind::Build)));
+    assert!(edges.contains(&("runtime_dep", DependencyKind::Runtime)));
+    assert_eq!(
+        find("runtime_dep").dependency_kinds,
+        vec![DependencyKind::Runtime]
+    );
+}
+
+#[test]
+fn test_renamed_dep() {
+    // Path to workspace fixture Cargo.toml. See that file for overview of workspace members and their dependencies.
+    let workspace_cargo_toml =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/renamed_dep/Cargo.toml");
+    let bins = run_cargo_auditable(workspace_cargo_toml, &[], &[]);
+    eprintln!("Test fixture binary map: {bins:?}");
+
+    // the real name is recorded, along with the name it was renamed to
+    let toplevel_crate_bin = &bins.get("top_level_crate").unwrap()[0];
+    let dep_info = get_dependency_info(toplevel_crate_bin);
+    eprintln!("{toplevel_crate_bin} dependency info: {dep_info:?}");
+    assert!(dep_info.packages.len() == 2);
+    let dep = dep_info
+        .packages
+        .iter()
+        .find(|p| p.name == "real_name")
+        .unwrap();
+    assert_eq!(dep.renamed_as, vec!["alias-name"]);
+}
+
+#[test]
+fn test_per_target_dependencies() {
+    // Path to workspace fixture Cargo.toml. See that file for overview of workspace members and their dependencies.
+    let workspace_cargo_toml =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/multiple_bins/Cargo.toml");
+    let bins = run_cargo_auditable(
+        workspace_cargo_toml,
+        &["--features=app/cli"],
+        &[
+            ("CARGO_AUDITABLE_PER_TARGET", OsStr::new("1")),
+            ("CARGO_AUDITABLE_RECORD_TARGET_NAME", OsStr::new("1")),
+        ],
+    );
+    eprintln!("Test fixture binary map: {bins:?}");
+
+    let app_bins = bins.get("app").unwrap();
+    let bin = |name: &str| {
+        app_bins
+            .iter()
+            .find(|path| path.file_stem() == Some(name))
+            .unwrap()
+    };
+    // only app-cli requires the `cli` feature that enables cli_helper
+    let cli_info = get_dependency_info(bin("app-cli"));
+    eprintln!("app-cli dependency info: {cli_info:?}");
+    assert_eq!(cli_info.target_name.as_deref(), Some("app-cli"));
+    assert!(cli_info.packages.iter().any(|p| p.name == "cli_helper"));
+    let server_info = get_dependency_info(bin("app-server"));
+    eprintln!("app-server dependency info: {server_info:?}");
+    assert_eq!(server_info.target_name.as_deref(), Some("app-server"));
+    assert_eq!(server_info.packages.len(), 1);
+    assert_eq!(server_info.packages[0].name, "app");
+}
+
+#[test]
+fn test_staticlib() {
+    // Path to workspace fixture Cargo.toml. See that file for overview of workspace members and their dependencies.
+    let workspace_cargo_toml =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/staticlib_crate/Cargo.toml");
+    let bins = run_cargo_auditable(workspace_cargo_toml, &[], &[]);
+    eprintln!("Test fixture binary map: {bins:?}");
+
+    // the object file with the audit data is bundled into the archive as a member
+    let staticlib = &bins.get("staticlib_crate").unwrap()[0];
+    let archive = std::fs::read(staticlib).unwrap();
+    assert!(archive.starts_with(b"!<arch>\n"));
+    let contains = |needle: &[u8]| archive.windows(needle.len()).any(|w| w == needle);
+    assert!(contains(b"AUDITABLE_STATICLIB_VERSION_INFO"));
+    assert!(contains(b"audit_data.o"));
+    // and the audit data is found in the archive
+    let dep_info = get_dependency_info(staticlib);
+    eprintln!("{staticlib} dependency info: {dep_info:?}");
+    assert!(dep_info
+        .packages
+        .iter()
+        .any(|p| p.name == "staticlib_crate" && p.root));
+}
+
 #[test]
 fn test_custom_rustc_path() {
     // Path to workspace fixture Cargo.toml. See that file for overview of workspace members and their dependencies.
@@ -422,14 +563,39 @@ fn test_git_source_of_dep() {
         .iter()
         .any(|p| p.name == "git_source_of_dep"));
     assert!(dep_info.packages.iter().any(|p| p.name == "serde"
-        && match &p.source {
-            Source::Git(git) => {
-                if git.rev == Some(String::from("2ba406726f9f84bc3b65ce4e824ae636dfa7dc85")) {
-                    true
-                } else {
-                    false
-                }
-            }
-            _ => false,
-        }));
+        && matches!(&p.source, Source::Git(git)
+            if git.rev.as_deref() == Some("2ba406726f9f84bc3b65ce4e824ae636dfa7dc85"))));
+}
+
+#[test]
+fn test_emit_object() {
+    // Path to workspace fixture Cargo.toml. See that file for overview of workspace members and their dependencies.
+    let crate_cargo_toml = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/runtime_then_build_dep/top_level_crate/Cargo.toml");
+    let output_path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("emit_object_audit_data.o");
+
+    let mut command = Command::new(EXE);
+    command
+        .args(["auditable", "emit-object", "--manifest-path"])
+        .arg(&crate_cargo_toml)
+        .arg("-o")
+        .arg(&output_path);
+    if let Ok(target) = std::env::var("AUDITABLE_TEST_TARGET") {
+        command.arg(format!("--target={target}"));
+    }
+    let status = command.status().unwrap();
+    assert!(status.success());
+
+    // The object file is an ELF file on Linux, so we can read it back with the regular tooling
+    if cfg!(target_os = "linux") {
+        let dep_info = get_dependency_info(Utf8Path::from_path(&output_path).unwrap());
+        eprintln!("{output_path:?} dependency info: {dep_info:?}");
+        assert!(dep_info.packages.len() == 3);
+        assert!(dep_info
+            .packages
+            .iter()
+            .any(|p| p.name == "top_level_crate" && p.root));
+    } else {
+        assert!(std::fs::metadata(&output_path).unwrap().len() > 0);
+    }
 }
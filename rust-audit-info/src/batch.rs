@@ -0,0 +1,80 @@
+//! `rust-audit-info --files`: extracts the audit data from many files without stopping
+//! at the first one that cannot be read, contains no audit data or takes too long.
+
+use crate::{extract_json, timeout::with_timeout, Args};
+use std::error::Error;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Processes every input in order, printing one JSON object per line for each of them,
+/// and fails with a summary if any of them could not be processed
+pub(crate) fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    let mut failed = 0;
+    let mut timed_out = 0;
+    // Shared with the threads the files are processed on when there is a time limit
+    let shared_args = Arc::new(args.clone());
+    for input in &args.inputs {
+        let path = json_string(&input.display().to_string());
+        let result = {
+            let input = input.clone();
+            let args = Arc::clone(&shared_args);
+            with_timeout(args.timeout, move || extract_json(&input, &args))
+        };
+        // The audit data is emitted as a string rather than inlined, because it is not parsed
+        // and malformed data from one binary must not corrupt the output for the others
+        let line = match result {
+            None => {
+                timed_out += 1;
+                format!("{{\"path\":{},\"skipped\":\"timeout\"}}", path)
+            }
+            Some(Ok((json, provenance))) => format!(
+                "{{\"path\":{},\"provenance\":\"{}\",\"confidence\":\"{}\",\"audit_data\":{}}}",
+                path,
+                provenance,
+                provenance.confidence(),
+                json_string(&json)
+            ),
+            Some(Err(e)) => {
+                failed += 1;
+                format!(
+                    "{{\"path\":{},\"error\":{}}}",
+                    path,
+                    json_string(&e.to_string())
+                )
+            }
+        };
+        writeln!(stdout, "{}", line)?;
+    }
+    stdout.flush()?;
+    let total = args.inputs.len();
+    if failed > 0 || timed_out > 0 {
+        return Err(format!(
+            "{} of {} files could not be processed, {} were skipped for exceeding the time limit",
+            failed, total, timed_out
+        )
+        .into());
+    }
+    eprintln!("All {} files processed successfully", total);
+    Ok(())
+}
+
+/// Formats a string as a JSON string literal
+fn json_string(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
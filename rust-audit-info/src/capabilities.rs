@@ -0,0 +1,74 @@
+//! Machine-readable description of what this build of `rust-audit-info` supports,
+//! printed by `rust-audit-info --capabilities`.
+//!
+//! Orchestration systems run many versions of the tool side by side and use this
+//! to decide how to scan a given binary. The output is a single JSON object.
+//! Fields may be added to it over time, but existing fields are never removed or
+//! change meaning without incrementing `capabilities_version`.
+
+/// Incremented on incompatible changes to the output
+const CAPABILITIES_VERSION: u32 = 1;
+
+/// Executable formats the audit data can be extracted from
+const BINARY_FORMATS: &[&str] = &["elf", "pe", "macho", "wasm", "raw-image", "minidump"];
+
+/// Ways the audit data may be stored in a binary that are understood by this build
+const PAYLOAD_FORMATS: &[&str] = &["section", "framed", "container", "store-pointer"];
+
+/// Compression algorithms the embedded JSON may be compressed with
+#[cfg(feature = "zstd")]
+const COMPRESSION: &[&str] = &["zlib", "zstd", "none"];
+#[cfg(not(feature = "zstd"))]
+const COMPRESSION: &[&str] = &["zlib", "none"];
+
+/// Formats accepted by `--format` in addition to the embedded JSON
+#[cfg(feature = "report")]
+const REPORT_FORMATS: &[&str] = crate::report::FORMATS;
+#[cfg(not(feature = "report"))]
+const REPORT_FORMATS: &[&str] = &[];
+
+pub fn capabilities_json() -> String {
+    let mut features = Vec::new();
+    let mut subcommands = vec!["self-check"];
+    let mut output_formats = vec!["json"];
+    if cfg!(feature = "exporter") {
+        features.push("exporter");
+        subcommands.push("exporter");
+    }
+    if cfg!(feature = "report") {
+        features.push("report");
+        subcommands.push("release-diff");
+    }
+    if cfg!(feature = "tui") {
+        features.push("tui");
+        subcommands.push("tui");
+    }
+    if cfg!(feature = "fetch-source") {
+        features.push("fetch-source");
+        subcommands.push("fetch-source");
+    }
+    if cfg!(feature = "zstd") {
+        features.push("zstd");
+    }
+    output_formats.extend_from_slice(REPORT_FORMATS);
+    format!(
+        "{{\"capabilities_version\":{},\"version\":\"{}\",\"binary_formats\":{},\
+        \"section_names\":{},\"payload_formats\":{},\"compression\":{},\
+        \"features\":{},\"subcommands\":{},\"output_formats\":{}}}\n",
+        CAPABILITIES_VERSION,
+        env!("CARGO_PKG_VERSION"),
+        json_list(BINARY_FORMATS),
+        json_list(auditable_extract::KNOWN_SECTION_NAMES),
+        json_list(PAYLOAD_FORMATS),
+        json_list(COMPRESSION),
+        json_list(&features),
+        json_list(&subcommands),
+        json_list(&output_formats),
+    )
+}
+
+/// Formats a list of strings that never need escaping as a JSON array
+fn json_list(items: &[&str]) -> String {
+    let quoted: Vec<String> = items.iter().map(|item| format!("\"{}\"", item)).collect();
+    format!("[{}]", quoted.join(","))
+}
@@ -0,0 +1,392 @@
+//! Implements `rust-audit-info exporter`, which periodically rescans directories for binaries
+//! and serves metrics about their audit data in the Prometheus text format,
+//! for alerting on binaries deployed without audit data or with vulnerable dependencies.
+
+use auditable_info::{audit_info_from_file, Limits};
+use auditable_serde::{DependencyKind, Package};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::timeout::{parse_timeout, with_timeout};
+use crate::walk::{walk, WalkOptions, WalkStats};
+
+pub const USAGE: &'static str = "\
+Usage: rust-audit-info exporter [OPTIONS] --paths PATH...
+
+Periodically scans the given files and directories (recursively) for executables
+and serves metrics about their audit data at http://ADDR/metrics
+in the Prometheus text format. Files that are not executables are ignored.
+
+Symlinks inside the scanned directories are not followed unless --follow-symlinks
+is passed; the paths given on the command line are always followed. Devices, pipes
+and other special files are never read. Directories reached more than once, e.g.
+through a symlink loop, are only scanned once.
+
+Options:
+    --listen ADDR         Address to serve the metrics on [default: 127.0.0.1:9777]
+    --interval SECONDS    Time between scans [default: 300]
+    --vulnerable FILE     File listing known-vulnerable crate versions, one `name@version`
+                          per line, e.g. exported from the RustSec advisory database.
+                          Enables the rust_audit_vulnerable_binaries metric.
+    --ignore-build-deps   Do not count vulnerable crates that are only build dependencies,
+                          since they are not part of the shipped binary.
+    --timeout SECONDS     Skip files that take longer than this to process, so that
+                          a single pathological file cannot stall the scan.
+                          Skipped files are counted in rust_audit_scan_timeouts.
+    --follow-symlinks     Follow symlinks found in the scanned directories.
+    --one-file-system     Do not descend into directories on other filesystems,
+                          such as /proc or network mounts. Only supported on Unix.
+    --paths PATH...       Files and directories to scan. Must come after all other options.
+";
+
+struct ExporterArgs {
+    paths: Vec<PathBuf>,
+    listen: String,
+    interval: Duration,
+    vulnerable: Option<PathBuf>,
+    ignore_build_deps: bool,
+    timeout: Option<Duration>,
+    walk_options: WalkOptions,
+}
+
+/// Crate versions known to be vulnerable, as (name, version) pairs
+type VulnerableSet = HashSet<(String, String)>;
+
+/// Decides which packages in a binary count towards the rust_audit_vulnerable_binaries metric
+struct VulnerabilityMatcher {
+    vulnerable: VulnerableSet,
+    /// Skip packages recorded with the `build` kind. Vulnerabilities in build tools
+    /// such as code generators often do not affect the binary they helped build.
+    ignore_build_deps: bool,
+}
+
+impl VulnerabilityMatcher {
+    fn matches(&self, package: &Package) -> bool {
+        if self.ignore_build_deps && package.kind == DependencyKind::Build {
+            return false;
+        }
+        self.vulnerable
+            .contains(&(package.name.clone(), package.version.to_string()))
+    }
+}
+
+pub fn main(args: Vec<OsString>) -> Result<(), Box<dyn Error>> {
+    let args = parse_args(args)?;
+    let vulnerable = match &args.vulnerable {
+        Some(path) => Some(VulnerabilityMatcher {
+            vulnerable: load_vulnerable(path)?,
+            ignore_build_deps: args.ignore_build_deps,
+        }),
+        None => None,
+    };
+    let listener = TcpListener::bind(&args.listen)?;
+
+    // Scan once before serving anything, so that the first scrape already has data
+    let metrics = Arc::new(Mutex::new(scan(&args, vulnerable.as_ref()).render()));
+    let scanner_metrics = Arc::clone(&metrics);
+    std::thread::spawn(move || loop {
+        std::thread::sleep(args.interval);
+        let rendered = scan(&args, vulnerable.as_ref()).render();
+        *scanner_metrics.lock().unwrap() = rendered;
+    });
+
+    for stream in listener.incoming() {
+        let result = stream.and_then(|stream| serve(stream, &metrics));
+        if let Err(e) = result {
+            eprintln!("Failed to serve a metrics request: {}", e);
+        }
+    }
+    Ok(())
+}
+
+fn parse_args(args: Vec<OsString>) -> Result<ExporterArgs, Box<dyn Error>> {
+    let mut result = ExporterArgs {
+        paths: Vec::new(),
+        listen: "127.0.0.1:9777".to_owned(),
+        interval: Duration::from_secs(300),
+        vulnerable: None,
+        ignore_build_deps: false,
+        timeout: None,
+        walk_options: WalkOptions {
+            follow_symlinks: false,
+            one_file_system: false,
+        },
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--paths" {
+            result.paths.extend(args.by_ref().map(PathBuf::from));
+        } else if arg == "--listen" {
+            let value = args.next().ok_or("No address specified for --listen")?;
+            result.listen = value
+                .into_string()
+                .map_err(|_| "Invalid UTF-8 in --listen argument")?;
+        } else if arg == "--interval" {
+            let value = args.next().ok_or("No interval specified for --interval")?;
+            let value = value
+                .to_str()
+                .ok_or("Invalid UTF-8 in --interval argument")?;
+            result.interval = Duration::from_secs(value.parse::<u64>()?);
+        } else if arg == "--vulnerable" {
+            let value = args.next().ok_or("No file specified for --vulnerable")?;
+            result.vulnerable = Some(value.into());
+        } else if arg == "--ignore-build-deps" {
+            result.ignore_build_deps = true;
+        } else if arg == "--timeout" {
+            let value = args.next().ok_or("No time limit specified for --timeout")?;
+            let value = value
+                .to_str()
+                .ok_or("Invalid UTF-8 in --timeout argument")?;
+            result.timeout = Some(parse_timeout(value)?);
+        } else if arg == "--follow-symlinks" {
+            result.walk_options.follow_symlinks = true;
+        } else if arg == "--one-file-system" {
+            result.walk_options.one_file_system = true;
+        } else {
+            return Err(format!(
+                "Unexpected argument: {}\n\n{}",
+                arg.to_string_lossy(),
+                USAGE
+            )
+            .into());
+        }
+    }
+    if result.paths.is_empty() {
+        return Err(USAGE.into());
+    }
+    if result.ignore_build_deps && result.vulnerable.is_none() {
+        return Err("--ignore-build-deps requires --vulnerable".into());
+    }
+    Ok(result)
+}
+
+/// Parses a list of `name@version` lines. Blank lines and lines starting with `#` are ignored.
+fn load_vulnerable(path: &Path) -> Result<VulnerableSet, Box<dyn Error>> {
+    let contents = std::fs::read_to_string(path)?;
+    let mut result = HashSet::new();
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, version) = line.split_once('@').ok_or_else(|| {
+            format!(
+                "Expected `name@version` in {}, got '{}'",
+                path.display(),
+                line
+            )
+        })?;
+        result.insert((name.to_owned(), version.to_owned()));
+    }
+    Ok(result)
+}
+
+/// The outcome of scanning all the configured paths once
+#[derive(Default)]
+struct ScanResults {
+    with_audit_data: usize,
+    detached_audit_data: usize,
+    without_audit_data: usize,
+    errors: usize,
+    /// Files and directories the walk could not read or skipped
+    walk: WalkStats,
+    /// Files skipped because processing them exceeded the time limit
+    timeouts: usize,
+    /// Crates present in more than one version within at least one binary
+    duplicate_crates: HashSet<String>,
+    binaries_with_duplicates: usize,
+    /// Only set if a list of vulnerable crate versions was provided
+    vulnerable_binaries: Option<usize>,
+    finished_at: Duration,
+    duration: Duration,
+}
+
+fn scan(args: &ExporterArgs, vulnerable: Option<&VulnerabilityMatcher>) -> ScanResults {
+    let start = Instant::now();
+    let mut results = ScanResults::default();
+    if vulnerable.is_some() {
+        results.vulnerable_binaries = Some(0);
+    }
+    let walk_stats = walk(&args.paths, &args.walk_options, |path| {
+        scan_file(path, vulnerable, args.timeout, &mut results)
+    });
+    results.walk = walk_stats;
+    results.duration = start.elapsed();
+    results.finished_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    results
+}
+
+fn scan_file(
+    path: &Path,
+    vulnerable: Option<&VulnerabilityMatcher>,
+    timeout: Option<Duration>,
+    results: &mut ScanResults,
+) {
+    let owned_path = path.to_owned();
+    let result = with_timeout(timeout, move || {
+        audit_info_from_file(&owned_path, Limits::default())
+    });
+    let result = match result {
+        Some(result) => result,
+        None => {
+            eprintln!(
+                "Skipped '{}': processing it exceeded the time limit",
+                path.display()
+            );
+            results.timeouts += 1;
+            return;
+        }
+    };
+    let info = match result {
+        Ok(info) => info,
+        Err(auditable_info::Error::NoAuditData) => {
+            results.without_audit_data += 1;
+            return;
+        }
+        Err(auditable_info::Error::DetachedPayload(_)) => {
+            results.detached_audit_data += 1;
+            return;
+        }
+        // Most files in a directory tree are not executables at all
+        Err(auditable_info::Error::BinaryParsing(_)) => return,
+        Err(_) => {
+            results.errors += 1;
+            return;
+        }
+    };
+    results.with_audit_data += 1;
+
+    let mut versions_per_crate: HashMap<&str, usize> = HashMap::new();
+    let mut seen: HashSet<(&str, String)> = HashSet::new();
+    for package in &info.packages {
+        if seen.insert((package.name.as_str(), package.version.to_string())) {
+            *versions_per_crate.entry(package.name.as_str()).or_default() += 1;
+        }
+    }
+    let mut has_duplicates = false;
+    for (name, count) in versions_per_crate {
+        if count > 1 {
+            has_duplicates = true;
+            results.duplicate_crates.insert(name.to_owned());
+        }
+    }
+    if has_duplicates {
+        results.binaries_with_duplicates += 1;
+    }
+
+    if let (Some(vulnerable), Some(count)) = (vulnerable, results.vulnerable_binaries.as_mut()) {
+        let is_vulnerable = info.packages.iter().any(|p| vulnerable.matches(p));
+        if is_vulnerable {
+            *count += 1;
+        }
+    }
+}
+
+impl ScanResults {
+    /// Renders the metrics in the Prometheus text exposition format
+    fn render(&self) -> String {
+        let mut out = String::new();
+        let mut gauge = |name: &str, help: &str, samples: &[(&str, String)]| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} gauge", name);
+            for (labels, value) in samples {
+                let _ = writeln!(out, "{}{} {}", name, labels, value);
+            }
+        };
+        gauge(
+            "rust_audit_binaries",
+            "Executables found by the last scan, by whether they contain audit data",
+            &[
+                ("{audit_data=\"present\"}", self.with_audit_data.to_string()),
+                (
+                    "{audit_data=\"detached\"}",
+                    self.detached_audit_data.to_string(),
+                ),
+                (
+                    "{audit_data=\"absent\"}",
+                    self.without_audit_data.to_string(),
+                ),
+            ],
+        );
+        gauge(
+            "rust_audit_scan_errors",
+            "Files and directories that could not be read during the last scan",
+            &[("", (self.errors + self.walk.errors).to_string())],
+        );
+        gauge(
+            "rust_audit_scan_skipped",
+            "Files and directories not scanned during the last scan, by the reason why",
+            &[
+                ("{reason=\"symlink\"}", self.walk.symlinks.to_string()),
+                (
+                    "{reason=\"special_file\"}",
+                    self.walk.special_files.to_string(),
+                ),
+                (
+                    "{reason=\"other_filesystem\"}",
+                    self.walk.other_filesystems.to_string(),
+                ),
+                ("{reason=\"loop\"}", self.walk.loops.to_string()),
+            ],
+        );
+        gauge(
+            "rust_audit_scan_timeouts",
+            "Files skipped during the last scan because processing them exceeded the time limit",
+            &[("", self.timeouts.to_string())],
+        );
+        gauge(
+            "rust_audit_duplicate_crates",
+            "Crates present in more than one version within at least one binary",
+            &[("", self.duplicate_crates.len().to_string())],
+        );
+        gauge(
+            "rust_audit_binaries_with_duplicate_crates",
+            "Binaries that contain more than one version of the same crate",
+            &[("", self.binaries_with_duplicates.to_string())],
+        );
+        if let Some(vulnerable) = self.vulnerable_binaries {
+            gauge(
+                "rust_audit_vulnerable_binaries",
+                "Binaries that contain a crate version listed as vulnerable",
+                &[("", vulnerable.to_string())],
+            );
+        }
+        gauge(
+            "rust_audit_last_scan_timestamp_seconds",
+            "Time the last scan finished, as a Unix timestamp",
+            &[("", self.finished_at.as_secs().to_string())],
+        );
+        gauge(
+            "rust_audit_last_scan_duration_seconds",
+            "Time the last scan took",
+            &[("", self.duration.as_secs_f64().to_string())],
+        );
+        out
+    }
+}
+
+/// Answers a single HTTP request. Only `GET /metrics` is supported.
+fn serve(mut stream: TcpStream, metrics: &Mutex<String>) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let response = if request_line.starts_with("GET /metrics ") {
+        let body = metrics.lock().unwrap().clone();
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_owned()
+    };
+    stream.write_all(response.as_bytes())
+}
@@ -0,0 +1,241 @@
+//! Implements `rust-audit-info fetch-source`, which downloads the exact source code
+//! of a crate recorded in the audit data, for reading the code that is actually in a binary.
+//!
+//! The downloads are delegated to `curl`, `tar` and `git` so that this tool
+//! does not need to carry an HTTP client and a TLS stack.
+
+use auditable_info::crates_index::{crate_file_checksum, is_valid_crate_name};
+use auditable_info::{json_from_file, Limits};
+use auditable_serde::{Package, Source, VersionInfo};
+use std::error::Error;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+
+pub const USAGE: &'static str = "\
+Usage: rust-audit-info fetch-source [--version VERSION] [--output DIR] INVENTORY CRATE
+
+Downloads the source code of the crate CRATE exactly as it was built into a binary.
+INVENTORY is either the binary itself or the JSON audit data extracted from it.
+
+Crates from crates.io are downloaded as .crate files, checked against the checksum
+recorded in the audit data if there is one, and unpacked. Crates from git repositories
+are cloned and checked out at the recorded commit; in a workspace the crate
+may be in a subdirectory of the repository. Crates from other registries and
+local paths cannot be downloaded.
+
+Pass --version if the binary contains several versions of CRATE.
+The source is placed in DIR/CRATE-VERSION, where DIR defaults to the current directory,
+and the path to it is printed.
+
+Requires `curl` and `tar` for crates.io, and `git` for git repositories.
+";
+
+pub fn main(mut args: Vec<OsString>) -> Result<(), Box<dyn Error>> {
+    let version = take_option(&mut args, "--version")?;
+    let output =
+        take_option(&mut args, "--output")?.map_or_else(|| PathBuf::from("."), PathBuf::from);
+    let (inventory, name) = match args.as_slice() {
+        [inventory, name] => (
+            PathBuf::from(inventory),
+            name.to_string_lossy().into_owned(),
+        ),
+        _ => return Err(USAGE.into()),
+    };
+    let info = load(&inventory)?;
+    let version = version.map(|v| v.to_string_lossy().into_owned());
+    let package = find_package(&info, &name, version.as_deref())?;
+    // The name is used in paths and URLs, so don't trust the audit data to hold a valid crate name
+    if !is_valid_crate_name(&package.name) {
+        return Err(format!("'{}' is not a valid crate name", package.name).into());
+    }
+    let destination = output.join(format!("{}-{}", package.name, package.version));
+    if destination.exists() {
+        return Err(format!("'{}' already exists", destination.display()).into());
+    }
+    match &package.source {
+        Source::CratesIo => fetch_crate(package, &output, &destination)?,
+        Source::Git(git) => match (&git.url, &git.rev) {
+            (Some(url), Some(rev)) => clone_at(url, rev, &destination)?,
+            _ => {
+                return Err(format!(
+                    "No repository URL and commit were recorded for '{}'",
+                    package.name
+                )
+                .into())
+            }
+        },
+        Source::Local(_) | Source::Workspace(_) => {
+            return Err(format!(
+                "'{}' was built from a local path, so there is no published copy to download",
+                package.name
+            )
+            .into())
+        }
+        _ => {
+            return Err(format!(
+                "'{}' comes from a source other than crates.io or git, which is not supported",
+                package.name
+            )
+            .into())
+        }
+    }
+    println!("{}", destination.display());
+    Ok(())
+}
+
+fn take_option(args: &mut Vec<OsString>, name: &str) -> Result<Option<OsString>, Box<dyn Error>> {
+    match args.iter().position(|arg| arg == name) {
+        Some(position) => {
+            args.remove(position);
+            if position >= args.len() {
+                return Err(format!("No value specified for {}", name).into());
+            }
+            Ok(Some(args.remove(position)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Loads the audit data from a binary, or from a JSON file if it is not a binary
+fn load(path: &Path) -> Result<VersionInfo, Box<dyn Error>> {
+    let json = match json_from_file(path, Limits::default()) {
+        Ok(json) => json,
+        Err(auditable_info::Error::BinaryParsing(_)) => fs::read_to_string(path)?,
+        Err(e) => return Err(format!("{}: {}", path.display(), e).into()),
+    };
+    Ok(VersionInfo::from_str(&json)?)
+}
+
+fn find_package<'a>(
+    info: &'a VersionInfo,
+    name: &str,
+    version: Option<&str>,
+) -> Result<&'a Package, Box<dyn Error>> {
+    let mut candidates: Vec<&Package> = info
+        .packages
+        .iter()
+        .filter(|package| package.name == name)
+        .filter(|package| version.map_or(true, |v| package.version.to_string() == v))
+        .collect();
+    candidates.sort_by(|a, b| a.version.cmp(&b.version));
+    candidates.dedup_by(|a, b| a.version == b.version);
+    match candidates.as_slice() {
+        [package] => Ok(package),
+        [] => match version {
+            Some(version) => {
+                Err(format!("The binary does not contain {} {}", name, version).into())
+            }
+            None => Err(format!("The binary does not contain a crate named '{}'", name).into()),
+        },
+        several => {
+            let versions: Vec<String> = several.iter().map(|p| p.version.to_string()).collect();
+            Err(format!(
+                "The binary contains several versions of '{}': {}. Pick one with --version",
+                name,
+                versions.join(", ")
+            )
+            .into())
+        }
+    }
+}
+
+/// Downloads the `.crate` file from crates.io into `output` and unpacks it, which creates `destination`
+fn fetch_crate(package: &Package, output: &Path, destination: &Path) -> Result<(), Box<dyn Error>> {
+    let url = format!(
+        "https://static.crates.io/crates/{0}/{0}-{1}.crate",
+        package.name, package.version
+    );
+    let crate_file = output.join(format!("{}-{}.crate", package.name, package.version));
+    run(Command::new("curl")
+        .args(&[
+            "--fail",
+            "--silent",
+            "--show-error",
+            "--location",
+            "--output",
+        ])
+        .arg(&crate_file)
+        .arg(&url))?;
+    if let Some(expected) = &package.checksum {
+        let actual = crate_file_checksum(&fs::read(&crate_file)?);
+        if !actual.eq_ignore_ascii_case(expected) {
+            fs::remove_file(&crate_file)?;
+            return Err(format!(
+                "The checksum of the downloaded {} is {}, but the binary was built from {}. \
+                The binary was not built from the published version of this crate.",
+                url, actual, expected
+            )
+            .into());
+        }
+    } else {
+        eprintln!(
+            "No checksum was recorded for {} {}, so the download cannot be verified",
+            package.name, package.version
+        );
+    }
+    // `.crate` files always unpack into a single NAME-VERSION directory
+    run(Command::new("tar")
+        .arg("-xzf")
+        .arg(&crate_file)
+        .arg("-C")
+        .arg(output))?;
+    fs::remove_file(&crate_file)?;
+    if !destination.is_dir() {
+        return Err(format!("{} did not unpack into the expected directory", url).into());
+    }
+    Ok(())
+}
+
+fn clone_at(url: &str, rev: &str, destination: &Path) -> Result<(), Box<dyn Error>> {
+    // Both end up on the git command line, so they must not be mistaken for options
+    if url.starts_with('-') || !rev.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Refusing to clone '{}' at '{}'", url, rev).into());
+    }
+    run(Command::new("git")
+        .args(&["clone", "--quiet", "--no-checkout", "--"])
+        .arg(url)
+        .arg(destination))?;
+    run(Command::new("git")
+        .arg("-C")
+        .arg(destination)
+        .args(&["checkout", "--quiet", "--detach", rev]))
+}
+
+fn run(command: &mut Command) -> Result<(), Box<dyn Error>> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let status = command
+        .status()
+        .map_err(|e| format!("Failed to run `{}`: {}", program, e))?;
+    if !status.success() {
+        return Err(format!("`{}` failed with {}", program, status).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crates_io_from_inventory() {
+        let path = std::env::temp_dir().join(format!(
+            "rust-audit-info-fetch-source-{}.json",
+            std::process::id()
+        ));
+        fs::write(
+            &path,
+            r#"{"packages":[{"name":"adler","version":"1.0.2","source":"crates.io"},{"name":"hello","version":"0.1.0","source":"local","dependencies":[0],"root":true}]}"#,
+        )
+        .unwrap();
+        let info = load(&path);
+        fs::remove_file(&path).unwrap();
+        let info = info.unwrap();
+        // Downloads are dispatched on the source, so it must be parsed as crates.io
+        let package = find_package(&info, "adler", None).unwrap();
+        assert_eq!(package.source, Source::CratesIo);
+        assert!(find_package(&info, "adler", Some("1.0.1")).is_err());
+    }
+}
@@ -1,20 +1,122 @@
 #![forbid(unsafe_code)]
 
-use auditable_info::{json_from_file, Limits};
+use auditable_info::{
+    json_and_provenance_from_image_file, json_from_file, json_from_minidump_file, json_from_store,
+    Confidence, Limits, Provenance,
+};
 use std::env::args_os;
 use std::error::Error;
+use std::ffi::OsString;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+mod batch;
+mod capabilities;
+#[cfg(feature = "exporter")]
+mod exporter;
+#[cfg(feature = "fetch-source")]
+mod fetch_source;
+#[cfg(feature = "report")]
+mod release_diff;
+#[cfg(feature = "report")]
+mod report;
+mod timeout;
+#[cfg(feature = "tui")]
+mod tui;
+#[cfg(feature = "exporter")]
+mod walk;
 
 const USAGE: &'static str = "\
-Usage: rust-audit-info FILE [INPUT_SIZE_LIMIT] [OUTPUT_SIZE_LIMIT]
+Usage: rust-audit-info [--raw | --minidump] [--store DIR] [--format FORMAT] FILE [INPUT_SIZE_LIMIT] [OUTPUT_SIZE_LIMIT]
+       rust-audit-info [--raw | --minidump] [--store DIR] [--timeout SECONDS] --files FILE...
+       rust-audit-info exporter [OPTIONS] --paths PATH...
+       rust-audit-info release-diff [--format markdown] OLD NEW
+       rust-audit-info tui FILE
+       rust-audit-info fetch-source [--version VERSION] [--output DIR] INVENTORY CRATE
+       rust-audit-info self-check
+       rust-audit-info --capabilities
 
 The limits are specified in bytes. The default values are:
 
     INPUT_SIZE_LIMIT: 1073741824 (1 GiB)
     OUTPUT_SIZE_LIMIT: 8388608 (8 MiB)
+
+Only the headers and the audit data are read from FILE, and --raw streams the image,
+so INPUT_SIZE_LIMIT only applies to pipes such as /dev/stdin and to minidumps,
+which are read in full, and to the audit data loaded with --store.
+
+Pass --raw to search a raw memory image without section headers,
+such as a firmware image produced by `objcopy -O binary`, for the audit data.
+
+Pass --minidump to read the audit data of the executable of a crashed process
+from a Windows minidump. The dump must contain the memory the executable was
+loaded into, e.g. be written with `MiniDumpWithFullMemory`.
+
+The audit data found with --raw or --minidump is a best-effort result: it may
+belong to another program in the image, or to a different build of the program.
+A warning saying so is printed to stderr, and with --files every line records the
+`provenance` of the data and its `confidence`, `authoritative` or `best-effort`.
+
+Pass --store to load the audit data from a local copy of the content-addressed
+store if the binary only contains a pointer to it.
+
+Pass --format to print the audit data as a `table` for humans, as `csv` with one row
+per package, or as a `cyclonedx`, `spdx` or `spdx-tag-value` SBOM instead of the
+embedded JSON. The SPDX formats also require --spdx-namespace URI, a unique URI
+identifying the document. This requires the `report` feature.
+
+Pass --files to process several files. Every file is processed even if some of them
+fail, and one JSON object per file is printed on its own line, holding the `path` and
+either the `audit_data` as a JSON string or an `error`. A summary is printed to stderr,
+and the exit code is 1 if any of the files could not be processed.
+
+Pass --timeout along with --files to limit the time spent on each file. Files that
+take longer are reported with `skipped` set to `timeout` instead of an `error`,
+and the scan moves on to the next file.
+
+The `exporter` subcommand periodically scans the given paths and serves metrics
+about the audit data of the binaries found there in the Prometheus format.
+It requires the `exporter` feature. Run `rust-audit-info exporter --help` for details.
+
+The `release-diff` subcommand compares the dependencies of two releases of a binary
+and prints the crates that were added, removed, upgraded or downgraded as Markdown,
+with links to crates.io, ready to paste into release notes. It requires the `report` feature.
+
+The `tui` subcommand opens an interactive view of the dependency tree for browsing it,
+searching crates and finding out what depends on them. It requires the `tui` feature.
+Run `rust-audit-info tui --help` for the keys.
+
+The `fetch-source` subcommand downloads the exact source code of a crate recorded
+in a binary or its extracted audit data, from crates.io or the recorded git commit,
+for reading the code that is actually in the binary. It requires the `fetch-source`
+feature. Run `rust-audit-info fetch-source --help` for details.
+
+The `self-check` subcommand prints the audit data embedded in rust-audit-info itself,
+to check that it works. It requires rust-audit-info to have been built with
+`cargo auditable`, e.g. installed with `cargo auditable install rust-audit-info`.
+
+Pass --capabilities to print a JSON object describing the binary formats,
+payload formats, compression algorithms and features supported by this build.
 ";
 
+#[derive(Clone)]
+struct Args {
+    inputs: Vec<PathBuf>,
+    /// Set by `--files`: process every input and report the results as NDJSON
+    multiple: bool,
+    limits: Limits,
+    raw: bool,
+    /// Set by `--minidump`: the input is a Windows minidump rather than an executable
+    minidump: bool,
+    store: Option<PathBuf>,
+    /// Time limit for processing each file with `--files`
+    timeout: Option<Duration>,
+    /// Set by `--format`; the embedded JSON is printed as-is if unset
+    format: Option<String>,
+    spdx_namespace: Option<String>,
+}
+
 fn main() {
     if let Err(e) = actual_main() {
         eprintln!("{}", e);
@@ -23,30 +125,294 @@ fn main() {
 }
 
 fn actual_main() -> Result<(), Box<dyn Error>> {
-    let (input, limits) = parse_args()?;
-    let decompressed_data: String = json_from_file(&input, limits)?;
+    if args_os().nth(1).map_or(false, |arg| arg == "exporter") {
+        return run_exporter(args_os().skip(2).collect());
+    }
+    if args_os().nth(1).map_or(false, |arg| arg == "release-diff") {
+        return run_release_diff(args_os().skip(2).collect());
+    }
+    if args_os().nth(1).map_or(false, |arg| arg == "tui") {
+        return run_tui(args_os().skip(2).collect());
+    }
+    if args_os().nth(1).map_or(false, |arg| arg == "fetch-source") {
+        return run_fetch_source(args_os().skip(2).collect());
+    }
+    if args_os().nth(1).map_or(false, |arg| arg == "self-check") {
+        return self_check();
+    }
+    if args_os()
+        .nth(1)
+        .map_or(false, |arg| arg == "--capabilities")
+    {
+        print!("{}", capabilities::capabilities_json());
+        return Ok(());
+    }
+    let args = parse_args()?;
+    if args.multiple {
+        return batch::run(&args);
+    }
+    let (decompressed_data, provenance) = extract_json(&args.inputs[0], &args)?;
+    if provenance.confidence() == Confidence::BestEffort {
+        eprintln!("{}", best_effort_warning(provenance));
+    }
 
     let stdout = std::io::stdout();
     let mut stdout = stdout.lock();
-    stdout.write_all(&decompressed_data.as_bytes())?;
+    match &args.format {
+        Some(format) => write_report(&decompressed_data, format, &args, &mut stdout)?,
+        None => stdout.write_all(&decompressed_data.as_bytes())?,
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "report")]
+fn write_report(
+    json: &str,
+    format: &str,
+    args: &Args,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    report::write(json, format, args.spdx_namespace.as_deref(), out)
+}
+
+#[cfg(not(feature = "report"))]
+fn write_report(
+    _json: &str,
+    _format: &str,
+    _args: &Args,
+    _out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    Err("rust-audit-info was built without the `report` feature required by --format".into())
+}
+
+/// Extracts the audit data from a single file, honoring `--raw`, `--minidump` and `--store`,
+/// and reports how it was located
+fn extract_json(input: &Path, args: &Args) -> Result<(String, Provenance), auditable_info::Error> {
+    let result = if args.raw {
+        json_and_provenance_from_image_file(input, args.limits)
+    } else if args.minidump {
+        json_from_minidump_file(input, args.limits).map(|json| (json, Provenance::Minidump))
+    } else {
+        json_from_file(input, args.limits).map(|json| (json, Provenance::Embedded))
+    };
+    match (result, &args.store) {
+        // Raw images never hold pointers, and the data in the store is only as trustworthy
+        // as the pointer to it
+        (Err(auditable_info::Error::DetachedPayload(payload)), Some(store)) => {
+            let provenance = if args.minidump {
+                Provenance::Minidump
+            } else {
+                Provenance::Embedded
+            };
+            json_from_store(store, &payload, args.limits).map(|json| (json, provenance))
+        }
+        (result, _) => result,
+    }
+}
 
+/// Explains why audit data that was not read from an executable should not be taken at face value
+fn best_effort_warning(provenance: Provenance) -> String {
+    let how = match provenance {
+        Provenance::ImageScan {
+            rejected_candidates: 0,
+        } => "found by searching the image".to_owned(),
+        Provenance::ImageScan {
+            rejected_candidates,
+        } => format!(
+            "found by searching the image, after rejecting {} other candidates",
+            rejected_candidates
+        ),
+        Provenance::Minidump => "recovered from the memory of the crashed process".to_owned(),
+        other => format!("located by {}", other),
+    };
+    format!(
+        "Warning: the audit data was {}. It is a best-effort result and may not describe this binary.",
+        how
+    )
+}
+
+/// Prints the audit data embedded in this very binary, as a smoke test and a demonstration
+fn self_check() -> Result<(), Box<dyn Error>> {
+    let path = std::env::current_exe()?;
+    let json = match json_from_file(&path, Limits::default()) {
+        Ok(json) => json,
+        Err(auditable_info::Error::NoAuditData) => {
+            return Err(format!(
+                "No audit data found in '{}'. Was it built with 'cargo auditable'?\n\
+                Install it with 'cargo auditable install rust-audit-info' to run the self-check.",
+                path.display()
+            )
+            .into())
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    stdout.write_all(json.as_bytes())?;
     Ok(())
 }
 
-fn parse_args() -> Result<(PathBuf, Limits), Box<dyn Error>> {
-    let input = args_os().nth(1).ok_or(USAGE)?;
+#[cfg(feature = "exporter")]
+fn run_exporter(args: Vec<OsString>) -> Result<(), Box<dyn Error>> {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{}", exporter::USAGE);
+        return Ok(());
+    }
+    exporter::main(args)
+}
+
+#[cfg(not(feature = "exporter"))]
+fn run_exporter(_args: Vec<OsString>) -> Result<(), Box<dyn Error>> {
+    Err("rust-audit-info was built without the `exporter` feature".into())
+}
+
+#[cfg(feature = "report")]
+fn run_release_diff(args: Vec<OsString>) -> Result<(), Box<dyn Error>> {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{}", release_diff::USAGE);
+        return Ok(());
+    }
+    release_diff::main(args)
+}
+
+#[cfg(not(feature = "report"))]
+fn run_release_diff(_args: Vec<OsString>) -> Result<(), Box<dyn Error>> {
+    Err("rust-audit-info was built without the `report` feature required by release-diff".into())
+}
+
+#[cfg(feature = "tui")]
+fn run_tui(args: Vec<OsString>) -> Result<(), Box<dyn Error>> {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{}", tui::USAGE);
+        return Ok(());
+    }
+    tui::main(args)
+}
+
+#[cfg(not(feature = "tui"))]
+fn run_tui(_args: Vec<OsString>) -> Result<(), Box<dyn Error>> {
+    Err("rust-audit-info was built without the `tui` feature".into())
+}
+
+#[cfg(feature = "fetch-source")]
+fn run_fetch_source(args: Vec<OsString>) -> Result<(), Box<dyn Error>> {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{}", fetch_source::USAGE);
+        return Ok(());
+    }
+    fetch_source::main(args)
+}
+
+#[cfg(not(feature = "fetch-source"))]
+fn run_fetch_source(_args: Vec<OsString>) -> Result<(), Box<dyn Error>> {
+    Err("rust-audit-info was built without the `fetch-source` feature".into())
+}
+
+fn parse_args() -> Result<Args, Box<dyn Error>> {
+    let mut args: Vec<OsString> = args_os().skip(1).collect();
+    let raw = if let Some(position) = args.iter().position(|arg| arg == "--raw") {
+        args.remove(position);
+        true
+    } else {
+        false
+    };
+    let minidump = if let Some(position) = args.iter().position(|arg| arg == "--minidump") {
+        args.remove(position);
+        true
+    } else {
+        false
+    };
+    if raw && minidump {
+        return Err("--raw cannot be used with --minidump".into());
+    }
+    let store = if let Some(position) = args.iter().position(|arg| arg == "--store") {
+        args.remove(position);
+        if position >= args.len() {
+            return Err("No directory specified for --store".into());
+        }
+        Some(args.remove(position).into())
+    } else {
+        None
+    };
+    let timeout = if let Some(position) = args.iter().position(|arg| arg == "--timeout") {
+        args.remove(position);
+        if position >= args.len() {
+            return Err("No time limit specified for --timeout".into());
+        }
+        let value = args.remove(position);
+        let value = value
+            .to_str()
+            .ok_or("Invalid UTF-8 in --timeout argument")?;
+        Some(timeout::parse_timeout(value)?)
+    } else {
+        None
+    };
+    let format = string_option(&mut args, "--format")?;
+    let spdx_namespace = string_option(&mut args, "--spdx-namespace")?;
+    if let Some(position) = args.iter().position(|arg| arg == "--files") {
+        if format.is_some() {
+            return Err("--format cannot be used with --files".into());
+        }
+        args.remove(position);
+        if args.is_empty() {
+            return Err(USAGE.into());
+        }
+        return Ok(Args {
+            inputs: args.into_iter().map(PathBuf::from).collect(),
+            multiple: true,
+            limits: Default::default(),
+            raw,
+            minidump,
+            store,
+            timeout,
+            format: None,
+            spdx_namespace: None,
+        });
+    }
+    if timeout.is_some() {
+        return Err("--timeout can only be used with --files".into());
+    }
+    let mut args = args.into_iter();
+    let input = args.next().ok_or(USAGE)?;
     let mut limits: Limits = Default::default();
-    if let Some(s) = args_os().nth(2) {
+    if let Some(s) = args.next() {
         let utf8_s = s
             .to_str()
             .ok_or("Invalid UTF-8 in input size limit argument")?;
         limits.input_file_size = utf8_s.parse::<usize>()?
     }
-    if let Some(s) = args_os().nth(3) {
+    if let Some(s) = args.next() {
         let utf8_s = s
             .to_str()
             .ok_or("Invalid UTF-8 in output size limit argument")?;
         limits.decompressed_json_size = utf8_s.parse::<usize>()?
     }
-    Ok((input.into(), limits))
+    Ok(Args {
+        inputs: vec![input.into()],
+        multiple: false,
+        limits,
+        raw,
+        minidump,
+        store,
+        timeout: None,
+        format,
+        spdx_namespace,
+    })
+}
+
+/// Removes an option such as `--format FORMAT` from the arguments and returns its value
+fn string_option(args: &mut Vec<OsString>, name: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let position = match args.iter().position(|arg| arg == name) {
+        Some(position) => position,
+        None => return Ok(None),
+    };
+    args.remove(position);
+    if position >= args.len() {
+        return Err(format!("No value specified for {}", name).into());
+    }
+    match args.remove(position).into_string() {
+        Ok(value) => Ok(Some(value)),
+        Err(_) => Err(format!("Invalid UTF-8 in {} argument", name).into()),
+    }
 }
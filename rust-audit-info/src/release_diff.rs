@@ -0,0 +1,184 @@
+//! Implements `rust-audit-info release-diff`, which summarizes how the dependencies
+//! changed between two releases of a binary, for pasting into release notes.
+
+use auditable_info::{json_from_file, Limits};
+use auditable_serde::{DependencyKind, Package, Source, VersionInfo};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub const USAGE: &'static str = "\
+Usage: rust-audit-info release-diff [--format FORMAT] OLD NEW
+
+Compares the dependencies recorded in the binaries OLD and NEW, e.g. two releases
+of the same program, and prints the crates that were added, removed, upgraded
+or downgraded, with links to crates.io. The binaries' own crates are not listed.
+
+The only FORMAT is `markdown`, the default, which is ready to paste into release notes.
+";
+
+/// Versions of each dependency, by crate name, sorted by version and without duplicates
+type Dependencies<'a> = BTreeMap<&'a str, Vec<&'a Package>>;
+
+pub fn main(mut args: Vec<OsString>) -> Result<(), Box<dyn Error>> {
+    if let Some(position) = args.iter().position(|arg| arg == "--format") {
+        args.remove(position);
+        if position >= args.len() {
+            return Err("No format specified for --format".into());
+        }
+        let format = args.remove(position);
+        if format != "markdown" {
+            return Err(format!("Unknown format '{}'", format.to_string_lossy()).into());
+        }
+    }
+    let (old, new) = match args.as_slice() {
+        [old, new] => (PathBuf::from(old), PathBuf::from(new)),
+        _ => return Err(USAGE.into()),
+    };
+    let (old, new) = (load(&old)?, load(&new)?);
+    let (old, new) = (dependencies(&old), dependencies(&new));
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    stdout.write_all(markdown(&old, &new).as_bytes())?;
+    Ok(())
+}
+
+fn load(path: &Path) -> Result<VersionInfo, Box<dyn Error>> {
+    let json = json_from_file(path, Limits::default())
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(VersionInfo::from_str(&json)?)
+}
+
+fn dependencies(info: &VersionInfo) -> Dependencies<'_> {
+    let mut dependencies = Dependencies::new();
+    for package in info.packages.iter().filter(|package| !package.root) {
+        dependencies.entry(&package.name).or_default().push(package);
+    }
+    for versions in dependencies.values_mut() {
+        versions.sort_by(|a, b| a.version.cmp(&b.version));
+        versions.dedup_by(|a, b| a.version == b.version);
+    }
+    dependencies
+}
+
+/// Renders the changes from `old` to `new` as a Markdown section,
+/// with a list for each kind of change that occurred
+fn markdown(old: &Dependencies<'_>, new: &Dependencies<'_>) -> String {
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    let mut upgraded = Vec::new();
+    let mut downgraded = Vec::new();
+    let empty = Vec::new();
+    let names: BTreeSet<&str> = old.keys().chain(new.keys()).copied().collect();
+    for name in names {
+        let old_versions = old.get(name).unwrap_or(&empty);
+        let new_versions = new.get(name).unwrap_or(&empty);
+        // The common case of a single version being replaced by another
+        if old_versions.len() == 1 && new_versions.len() == 1 {
+            let (old_version, new_version) = (old_versions[0], new_versions[0]);
+            let line = format!(
+                "`{}`: {} → {}{}",
+                name,
+                version_link(old_version),
+                version_link(new_version),
+                build_note(new_version),
+            );
+            if old_version.version < new_version.version {
+                upgraded.push(line);
+            } else if old_version.version > new_version.version {
+                downgraded.push(line);
+            }
+            continue;
+        }
+        let has_version = |versions: &[&Package], package: &Package| {
+            versions
+                .iter()
+                .any(|other| other.version == package.version)
+        };
+        for &package in old_versions {
+            if !has_version(new_versions, package) {
+                removed.push(crate_line(package));
+            }
+        }
+        for &package in new_versions {
+            if !has_version(old_versions, package) {
+                added.push(crate_line(package));
+            }
+        }
+    }
+
+    let mut output = String::from("## Dependency changes\n");
+    let sections = [
+        ("Added", added),
+        ("Removed", removed),
+        ("Upgraded", upgraded),
+        ("Downgraded", downgraded),
+    ];
+    if sections.iter().all(|(_, lines)| lines.is_empty()) {
+        output.push_str("\nNo dependency changes.\n");
+    }
+    for (heading, lines) in sections.iter().filter(|(_, lines)| !lines.is_empty()) {
+        write!(output, "\n### {}\n\n", heading).unwrap();
+        for line in lines {
+            writeln!(output, "- {}", line).unwrap();
+        }
+    }
+    output
+}
+
+fn crate_line(package: &Package) -> String {
+    format!(
+        "`{}` {}{}",
+        package.name,
+        version_link(package),
+        build_note(package)
+    )
+}
+
+/// Links the version to its page on crates.io, if that is where the package came from
+fn version_link(package: &Package) -> String {
+    if package.source == Source::CratesIo {
+        format!(
+            "[{}](https://crates.io/crates/{}/{})",
+            package.version, package.name, package.version
+        )
+    } else {
+        package.version.to_string()
+    }
+}
+
+/// Marks packages that are only used at build time and do not end up in the binary
+fn build_note(package: &Package) -> &'static str {
+    if package.kind == DependencyKind::Build {
+        " (build dependency)"
+    } else {
+        ""
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_crates_io_versions() {
+        let old = VersionInfo::from_str(
+            r#"{"packages":[{"name":"adler","version":"1.0.1","source":"crates.io"},{"name":"hello","version":"0.1.0","source":"local","dependencies":[0],"root":true}]}"#,
+        )
+        .unwrap();
+        let new = VersionInfo::from_str(
+            r#"{"packages":[{"name":"adler","version":"1.0.2","source":"crates.io"},{"name":"cc","version":"1.0.0","source":"registry","kind":"build"},{"name":"hello","version":"0.1.0","source":"local","dependencies":[0,1],"root":true}]}"#,
+        )
+        .unwrap();
+        let output = markdown(&dependencies(&old), &dependencies(&new));
+        assert!(output.contains(
+            "- `adler`: [1.0.1](https://crates.io/crates/adler/1.0.1) → [1.0.2](https://crates.io/crates/adler/1.0.2)\n"
+        ));
+        // Packages from other registries are not linked to crates.io
+        assert!(output.contains("- `cc` 1.0.0 (build dependency)\n"));
+    }
+}
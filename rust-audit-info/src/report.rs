@@ -0,0 +1,45 @@
+//! Renders the audit data in the formats selected with `--format`,
+//! using the same writers that `auditable_serde::report` offers to other tools.
+
+use auditable_serde::report::{Csv, CycloneDx, Json, ReportWriter, Spdx, Table};
+use auditable_serde::{Timestamp, VersionInfo};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::io::Write;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+/// Names accepted by `--format`, except for the default `json`
+pub const FORMATS: &[&str] = &["table", "csv", "cyclonedx", "spdx", "spdx-tag-value"];
+
+/// Parses the audit data and writes it out in the requested format.
+/// SPDX documents need a unique namespace, which is taken from `--spdx-namespace`.
+pub fn write(
+    json: &str,
+    format: &str,
+    spdx_namespace: Option<&str>,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let info = VersionInfo::from_str(json)?;
+    let writer: Box<dyn ReportWriter> = match format {
+        "json" => Box::new(Json),
+        "table" => Box::new(Table),
+        "csv" => Box::new(Csv),
+        "cyclonedx" => Box::new(CycloneDx),
+        "spdx" | "spdx-tag-value" => {
+            let document_namespace = spdx_namespace
+                .ok_or("--spdx-namespace is required for the SPDX formats")?
+                .to_owned();
+            let created = Timestamp::try_from(SystemTime::now())
+                .map_err(|_| "The system clock is set to an unsupported date")?;
+            Box::new(Spdx {
+                document_namespace,
+                created: created.to_string(),
+                tag_value: format == "spdx-tag-value",
+            })
+        }
+        other => return Err(format!("Unknown output format '{}'", other).into()),
+    };
+    writer.write(&info, out)?;
+    Ok(())
+}
@@ -0,0 +1,52 @@
+//! Per-file time limits, so that a single pathological or enormous file,
+//! e.g. on a stalled network mount, cannot hold up a scan of many files.
+
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::Duration;
+
+/// Runs `f` on a separate thread and returns its result, or `None` if it takes longer than `timeout`.
+///
+/// Threads cannot be interrupted, so on timeout the thread is left to finish in the background
+/// and its result is discarded. The memory it holds is bounded by the size limits as usual.
+pub(crate) fn with_timeout<T, F>(timeout: Option<Duration>, f: F) -> Option<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return Some(f()),
+    };
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        // The receiver is gone if the time limit has been exceeded
+        let _ = sender.send(f());
+    });
+    receiver.recv_timeout(timeout).ok()
+}
+
+/// Parses the value of a `--timeout` argument, in seconds
+pub(crate) fn parse_timeout(value: &str) -> Result<Duration, String> {
+    // Values too large for a `Duration` are rejected rather than panicking
+    match value.parse::<f64>().map(Duration::try_from_secs_f64) {
+        Ok(Ok(timeout)) if !timeout.is_zero() => Ok(timeout),
+        _ => Err(format!(
+            "Invalid --timeout value '{}', expected a positive number of seconds",
+            value
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeout_values() {
+        assert_eq!(parse_timeout("1.5").unwrap(), Duration::from_millis(1500));
+        for invalid in ["0", "-1", "NaN", "inf", "1e20", "soon"] {
+            assert!(parse_timeout(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
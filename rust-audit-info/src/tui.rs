@@ -0,0 +1,401 @@
+//! Implements `rust-audit-info tui`, an interactive explorer for the audit data of a binary,
+//! for looking into a dependency tree directly on a server during incident response.
+//!
+//! The state of the explorer is kept apart from drawing it, so that the key handling
+//! is plain data manipulation and the terminal is only touched in [`main`].
+
+use auditable_info::{audit_info_from_file, Limits};
+use auditable_serde::{DependencyKind, Package, VersionInfo};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{Frame, Terminal};
+use std::collections::HashSet;
+use std::error::Error;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+pub const USAGE: &'static str = "\
+Usage: rust-audit-info tui FILE
+
+Opens an interactive view of the dependency tree embedded in FILE.
+
+Keys:
+    Up/Down, k/j    Move the selection
+    Right, l        Expand the selected package in the tree
+    Left, h         Collapse the selected package, or go to the package depending on it
+    /               Search crates by name. Enter keeps the results, Esc cancels the search
+    r               List the packages that depend on the selected one
+    Enter           Show the selected search result or dependent in the tree
+    Esc             Go back to the tree
+    q               Quit
+";
+
+pub fn main(args: Vec<OsString>) -> Result<(), Box<dyn Error>> {
+    let path = match args.as_slice() {
+        [path] => PathBuf::from(path),
+        _ => return Err(USAGE.into()),
+    };
+    let info = audit_info_from_file(&path, Limits::default())?;
+    let mut explorer = Explorer::new(info);
+
+    enable_raw_mode()?;
+    let mut stdout = std::io::stdout();
+    let result = execute!(stdout, EnterAlternateScreen)
+        .and_then(|_| Terminal::new(CrosstermBackend::new(stdout)))
+        .and_then(|mut terminal| run(&mut terminal, &mut explorer));
+    // Restore the terminal even if drawing failed, or the shell would be left unusable
+    disable_raw_mode()?;
+    execute!(std::io::stdout(), LeaveAlternateScreen)?;
+    Ok(result?)
+}
+
+fn run<B: Backend>(terminal: &mut Terminal<B>, explorer: &mut Explorer) -> std::io::Result<()> {
+    loop {
+        terminal.draw(|frame| draw(frame, explorer))?;
+        if let Event::Key(key) = event::read()? {
+            // Windows also reports key releases
+            if key.kind == KeyEventKind::Press && !explorer.handle_key(key.code) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// What the list on the left shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum View {
+    /// The dependency tree, starting from the root packages
+    Tree,
+    /// Packages whose names contain the search query
+    Search,
+    /// Packages that depend directly on the package at this index
+    Dependents(usize),
+}
+
+struct Explorer {
+    info: VersionInfo,
+    view: View,
+    /// Visible rows of the tree, each being the path of package indices from a root package
+    tree: Vec<Vec<usize>>,
+    /// Paths of the tree rows whose dependencies are shown
+    expanded: HashSet<Vec<usize>>,
+    /// Selection in the tree, kept while other views are shown
+    tree_selection: usize,
+    query: String,
+    /// Set while the search query is being typed
+    typing: bool,
+    list: ListState,
+}
+
+impl Explorer {
+    fn new(info: VersionInfo) -> Self {
+        let mut explorer = Explorer {
+            info,
+            view: View::Tree,
+            tree: Vec::new(),
+            expanded: HashSet::new(),
+            tree_selection: 0,
+            query: String::new(),
+            typing: false,
+            list: ListState::default(),
+        };
+        explorer.rebuild_tree();
+        explorer.list.select(Some(0));
+        explorer
+    }
+
+    /// Recomputes the visible rows of the tree after expanding or collapsing a row
+    fn rebuild_tree(&mut self) {
+        let mut pending: Vec<Vec<usize>> = (0..self.info.packages.len())
+            .rev()
+            .filter(|&index| self.info.packages[index].root)
+            .map(|index| vec![index])
+            .collect();
+        self.tree.clear();
+        while let Some(path) = pending.pop() {
+            if self.expanded.contains(&path) {
+                let last = path[path.len() - 1];
+                let dependencies: Vec<usize> = self
+                    .info
+                    .dependencies_of(last)
+                    .map(|(dep, _)| dep)
+                    .collect();
+                for dep in dependencies.into_iter().rev() {
+                    let mut child = path.clone();
+                    child.push(dep);
+                    pending.push(child);
+                }
+            }
+            self.tree.push(path);
+        }
+    }
+
+    /// Indices of the packages listed in the current view
+    fn rows(&self) -> Vec<usize> {
+        match self.view {
+            View::Tree => self.tree.iter().map(|path| path[path.len() - 1]).collect(),
+            View::Search => {
+                let query = self.query.to_lowercase();
+                (0..self.info.packages.len())
+                    .filter(|&index| {
+                        self.info.packages[index]
+                            .name
+                            .to_lowercase()
+                            .contains(&query)
+                    })
+                    .collect()
+            }
+            View::Dependents(index) => self
+                .info
+                .dependents_of(index)
+                .map(|(dependent, _)| dependent)
+                .collect(),
+        }
+    }
+
+    fn selected_package(&self) -> Option<usize> {
+        let selected = self.list.selected()?;
+        self.rows().get(selected).copied()
+    }
+
+    fn show(&mut self, view: View) {
+        if self.view == View::Tree {
+            self.tree_selection = self.list.selected().unwrap_or(0);
+        }
+        self.view = view;
+        let selection = if view == View::Tree {
+            self.tree_selection
+        } else {
+            0
+        };
+        self.list.select(Some(selection));
+    }
+
+    /// Handles a key press. Returns `false` if the explorer should be closed.
+    fn handle_key(&mut self, key: KeyCode) -> bool {
+        if self.typing {
+            match key {
+                KeyCode::Char(c) => self.query.push(c),
+                KeyCode::Backspace => {
+                    self.query.pop();
+                }
+                KeyCode::Enter => self.typing = false,
+                KeyCode::Esc => {
+                    self.typing = false;
+                    self.show(View::Tree);
+                    return true;
+                }
+                _ => {}
+            }
+            self.list.select(Some(0));
+            return true;
+        }
+        let selected = self.list.selected().unwrap_or(0);
+        match key {
+            KeyCode::Char('q') => return false,
+            KeyCode::Down | KeyCode::Char('j') => {
+                if selected + 1 < self.rows().len() {
+                    self.list.select(Some(selected + 1));
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.list.select(Some(selected.saturating_sub(1)));
+            }
+            KeyCode::Right | KeyCode::Char('l') if self.view == View::Tree => {
+                if let Some(path) = self.tree.get(selected) {
+                    self.expanded.insert(path.clone());
+                    self.rebuild_tree();
+                }
+            }
+            KeyCode::Left | KeyCode::Char('h') if self.view == View::Tree => {
+                if let Some(path) = self.tree.get(selected).cloned() {
+                    if self.expanded.remove(&path) {
+                        self.rebuild_tree();
+                    } else if path.len() > 1 {
+                        let parent = &path[..path.len() - 1];
+                        let row = self.tree.iter().position(|row| row.as_slice() == parent);
+                        self.list.select(row);
+                    }
+                }
+            }
+            KeyCode::Enter if self.view != View::Tree => {
+                if let Some(index) = self.selected_package() {
+                    self.reveal(index);
+                }
+            }
+            KeyCode::Char('/') => {
+                self.query.clear();
+                self.typing = true;
+                self.show(View::Search);
+            }
+            KeyCode::Char('r') => {
+                if let Some(index) = self.selected_package() {
+                    self.show(View::Dependents(index));
+                }
+            }
+            KeyCode::Esc => self.show(View::Tree),
+            _ => {}
+        }
+        true
+    }
+
+    /// Expands the tree down to the package at `index` along the first path that reaches it,
+    /// and selects it there
+    fn reveal(&mut self, index: usize) {
+        let path = match self.path_to(index) {
+            Some(path) => path,
+            None => return,
+        };
+        for end in 1..path.len() {
+            self.expanded.insert(path[..end].to_vec());
+        }
+        self.rebuild_tree();
+        self.tree_selection = self.tree.iter().position(|row| *row == path).unwrap_or(0);
+        self.view = View::Tree;
+        self.list.select(Some(self.tree_selection));
+    }
+
+    /// Finds the shortest path from a root package to the package at `index`
+    fn path_to(&self, index: usize) -> Option<Vec<usize>> {
+        let roots = (0..self.info.packages.len()).filter(|&i| self.info.packages[i].root);
+        let mut queue: std::collections::VecDeque<Vec<usize>> = roots.map(|i| vec![i]).collect();
+        let mut visited = HashSet::new();
+        while let Some(path) = queue.pop_front() {
+            let last = path[path.len() - 1];
+            if last == index {
+                return Some(path);
+            }
+            for (dep, _) in self.info.dependencies_of(last) {
+                if visited.insert(dep) {
+                    let mut next = path.clone();
+                    next.push(dep);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    fn title(&self) -> String {
+        match self.view {
+            View::Tree => "Dependency tree".to_owned(),
+            View::Search => format!("Search: {}", self.query),
+            View::Dependents(index) => format!("Used by {}", self.info.packages[index].name),
+        }
+    }
+
+    fn row_label(&self, row: usize, index: usize) -> String {
+        let package = &self.info.packages[index];
+        let name = format!("{} {}", package.name, package.version);
+        if self.view != View::Tree {
+            return name;
+        }
+        let path = &self.tree[row];
+        let marker = if package.dependencies.is_empty() {
+            " "
+        } else if self.expanded.contains(path) {
+            "▾"
+        } else {
+            "▸"
+        };
+        format!("{}{} {}", "  ".repeat(path.len() - 1), marker, name)
+    }
+
+    /// Describes the package at `index` for the details pane
+    fn details(&self, index: usize) -> Vec<Line<'static>> {
+        let package = &self.info.packages[index];
+        let mut lines = vec![
+            Line::from(format!("{} {}", package.name, package.version)),
+            Line::from(""),
+            Line::from(format!("Kind:     {}", kind_name(package.kind))),
+            Line::from(format!(
+                "Source:   {}",
+                String::from(package.source.clone())
+            )),
+            Line::from(format!("Root:     {}", package.root)),
+        ];
+        if let Some(checksum) = &package.checksum {
+            lines.push(Line::from(format!("Checksum: {}", checksum)));
+        }
+        let dependencies: Vec<&Package> = self
+            .info
+            .dependencies_of(index)
+            .map(|(_, dep)| dep)
+            .collect();
+        let dependents: Vec<&Package> =
+            self.info.dependents_of(index).map(|(_, dep)| dep).collect();
+        for (heading, packages) in &[("Dependencies", dependencies), ("Used by", dependents)] {
+            lines.push(Line::from(""));
+            lines.push(Line::from(format!("{} ({}):", heading, packages.len())));
+            for package in packages {
+                lines.push(Line::from(format!(
+                    "  {} {}",
+                    package.name, package.version
+                )));
+            }
+        }
+        lines
+    }
+
+    fn status(&self) -> String {
+        if self.typing {
+            format!("/{}", self.query)
+        } else if self.view == View::Tree {
+            "q quit  ←→ collapse/expand  / search  r used by".to_owned()
+        } else {
+            "q quit  Enter show in tree  Esc back  / search  r used by".to_owned()
+        }
+    }
+}
+
+fn draw<B: Backend>(frame: &mut Frame<B>, explorer: &mut Explorer) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(frame.size());
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(rows[0]);
+
+    let items: Vec<ListItem> = explorer
+        .rows()
+        .into_iter()
+        .enumerate()
+        .map(|(row, index)| ListItem::new(explorer.row_label(row, index)))
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(explorer.title()),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, columns[0], &mut explorer.list);
+
+    let details = match explorer.selected_package() {
+        Some(index) => explorer.details(index),
+        None => vec![Line::from("No matching packages")],
+    };
+    let details = Paragraph::new(details)
+        .block(Block::default().borders(Borders::ALL).title("Details"))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(details, columns[1]);
+    frame.render_widget(Paragraph::new(explorer.status()), rows[1]);
+}
+
+fn kind_name(kind: DependencyKind) -> &'static str {
+    match kind {
+        DependencyKind::Development => "development",
+        DependencyKind::Build => "build",
+        DependencyKind::Runtime => "runtime",
+    }
+}
@@ -0,0 +1,137 @@
+//! Walks the directory trees the exporter scans, with an explicit policy for symlinks,
+//! mount points and special files, so that scanning `/` or a container root is predictable.
+
+use std::collections::HashSet;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+
+pub(crate) struct WalkOptions {
+    /// Follow symlinks found inside directories. Paths to scan given on the command line
+    /// are always followed, the same way `find -H` does.
+    pub follow_symlinks: bool,
+    /// Do not descend into directories on a different filesystem than the path being scanned,
+    /// such as `/proc` or mounted network shares. Only supported on Unix.
+    pub one_file_system: bool,
+}
+
+/// Files and directories that were not scanned, by the reason why
+#[derive(Default)]
+pub(crate) struct WalkStats {
+    /// Could not be read, including dangling symlinks
+    pub errors: usize,
+    /// Symlinks that were not followed
+    pub symlinks: usize,
+    /// Devices, pipes, sockets and other files that are not regular files
+    pub special_files: usize,
+    /// Directories on another filesystem, with `one_file_system` set
+    pub other_filesystems: usize,
+    /// Directories that were already scanned, e.g. because a symlink points to a parent directory
+    pub loops: usize,
+}
+
+/// Calls `on_file` for every regular file found under `roots`
+pub(crate) fn walk(
+    roots: &[PathBuf],
+    options: &WalkOptions,
+    mut on_file: impl FnMut(&Path),
+) -> WalkStats {
+    let mut stats = WalkStats::default();
+    // Shared between the roots, so that overlapping roots are only scanned once
+    let mut visited = HashSet::new();
+    for root in roots {
+        let root_metadata = match fs::metadata(root) {
+            Ok(metadata) => metadata,
+            Err(_) => {
+                stats.errors += 1;
+                continue;
+            }
+        };
+        let root_device = device(&root_metadata);
+        let mut queue = vec![(root.clone(), root_metadata)];
+        while let Some((path, metadata)) = queue.pop() {
+            if metadata.is_dir() {
+                if options.one_file_system && device(&metadata) != root_device {
+                    stats.other_filesystems += 1;
+                    continue;
+                }
+                if !visited.insert(directory_id(&path, &metadata)) {
+                    stats.loops += 1;
+                    continue;
+                }
+                match fs::read_dir(&path) {
+                    Ok(entries) => {
+                        for entry in entries.filter_map(|e| e.ok()) {
+                            let child = entry.path();
+                            if let Some(metadata) = entry_metadata(&child, options, &mut stats) {
+                                queue.push((child, metadata));
+                            }
+                        }
+                    }
+                    Err(_) => stats.errors += 1,
+                }
+            } else if metadata.is_file() {
+                on_file(&path);
+            } else {
+                // Reading devices or pipes may never finish, and they never hold executables
+                stats.special_files += 1;
+            }
+        }
+    }
+    stats
+}
+
+/// Returns the metadata of the entry, or of its target if it is a symlink that should be followed
+fn entry_metadata(path: &Path, options: &WalkOptions, stats: &mut WalkStats) -> Option<Metadata> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => {
+            stats.errors += 1;
+            return None;
+        }
+    };
+    if !metadata.file_type().is_symlink() {
+        return Some(metadata);
+    }
+    if !options.follow_symlinks {
+        stats.symlinks += 1;
+        return None;
+    }
+    match fs::metadata(path) {
+        Ok(metadata) => Some(metadata),
+        Err(_) => {
+            stats.errors += 1;
+            None
+        }
+    }
+}
+
+#[cfg(unix)]
+fn device(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn device(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+/// Identifies a directory regardless of the path it was reached by
+#[derive(PartialEq, Eq, Hash)]
+enum DirectoryId {
+    #[cfg(unix)]
+    Inode(u64, u64),
+    #[cfg(not(unix))]
+    Path(PathBuf),
+}
+
+#[cfg(unix)]
+fn directory_id(_path: &Path, metadata: &Metadata) -> DirectoryId {
+    use std::os::unix::fs::MetadataExt;
+    DirectoryId::Inode(metadata.dev(), metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn directory_id(path: &Path, _metadata: &Metadata) -> DirectoryId {
+    DirectoryId::Path(fs::canonicalize(path).unwrap_or_else(|_| path.to_owned()))
+}